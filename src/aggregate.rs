@@ -0,0 +1,161 @@
+//! Cross-episode statistics.
+//!
+//! Evaluation pipelines summarize many episodes by the distribution of per-episode metrics.
+//! An `Aggregate` combines episodes into a `Summary` of the request rate, the violation rate
+//! and the steps to goal, with means, variances and percentiles.
+//!
+//! Percentiles use the nearest-rank method, and variances are sample variances.
+//! Episodes that did not reach the goal are left out of the steps to goal.
+
+use crate::environment::RunReport;
+use crate::tune::Stats;
+
+/// Stores the metrics of an episode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Episode {
+    /// The number of decisions.
+    pub decisions: usize,
+    /// The number of decisions that requested a model update.
+    pub requests: usize,
+    /// Whether the safety was violated.
+    pub violation: bool,
+    /// The number of steps used to reach the goal, if it was reached.
+    pub steps_to_goal: Option<usize>,
+}
+
+impl From<Stats> for Episode {
+    fn from(stats: Stats) -> Self {
+        Episode {
+            decisions: stats.decisions,
+            requests: stats.requests,
+            violation: stats.violation,
+            steps_to_goal: None,
+        }
+    }
+}
+
+impl From<RunReport> for Episode {
+    fn from(report: RunReport) -> Self {
+        Episode {
+            decisions: report.steps,
+            requests: report.requests,
+            violation: false,
+            steps_to_goal: if report.goal {Some(report.steps)} else {None},
+        }
+    }
+}
+
+/// Stores a summary of the distribution of a metric.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Summary {
+    /// The number of samples.
+    pub count: usize,
+    /// The mean.
+    pub mean: f64,
+    /// The sample variance.
+    pub variance: f64,
+    /// The smallest sample.
+    pub min: f64,
+    /// The median.
+    pub p50: f64,
+    /// The 90th percentile.
+    pub p90: f64,
+    /// The 99th percentile.
+    pub p99: f64,
+    /// The largest sample.
+    pub max: f64,
+}
+
+impl Summary {
+    /// Summarizes samples.
+    ///
+    /// Returns a summary of zeroes when there are no samples.
+    pub fn new(samples: &[f64]) -> Summary {
+        let count = samples.len();
+        if count == 0 {return Summary::default()}
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mean = samples.iter().sum::<f64>() / count as f64;
+        let variance = if count < 2 {0.0} else {
+            samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (count - 1) as f64
+        };
+        let rank = |p: f64| sorted[((p * count as f64).ceil() as usize).clamp(1, count) - 1];
+        Summary {
+            count, mean, variance,
+            min: sorted[0], p50: rank(0.5), p90: rank(0.9), p99: rank(0.99), max: sorted[count - 1],
+        }
+    }
+
+    /// Returns the standard deviation.
+    pub fn std_dev(&self) -> f64 {self.variance.sqrt()}
+
+    /// Returns the summary as JSON.
+    pub fn to_json(&self) -> String {
+        format!("{{\"count\":{},\"mean\":{},\"variance\":{},\"min\":{},\"p50\":{},\"p90\":{},\"p99\":{},\"max\":{}}}",
+                self.count, self.mean, self.variance, self.min, self.p50, self.p90, self.p99, self.max)
+    }
+}
+
+/// Stores the combined statistics of episodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aggregate {
+    /// The number of episodes.
+    pub episodes: usize,
+    /// The fraction of decisions that requested a model update, per episode.
+    pub request_rate: Summary,
+    /// Whether the safety was violated, per episode, where the mean is the violation rate.
+    pub violation_rate: Summary,
+    /// The number of steps used to reach the goal, per episode that reached it.
+    pub steps_to_goal: Summary,
+}
+
+impl Aggregate {
+    /// Combines the metrics of episodes.
+    pub fn new(episodes: &[Episode]) -> Aggregate {
+        let request_rate: Vec<f64> = episodes.iter()
+            .map(|e| e.requests as f64 / e.decisions.max(1) as f64).collect();
+        let violation_rate: Vec<f64> = episodes.iter()
+            .map(|e| if e.violation {1.0} else {0.0}).collect();
+        let steps_to_goal: Vec<f64> = episodes.iter()
+            .filter_map(|e| e.steps_to_goal.map(|n| n as f64)).collect();
+        Aggregate {
+            episodes: episodes.len(),
+            request_rate: Summary::new(&request_rate),
+            violation_rate: Summary::new(&violation_rate),
+            steps_to_goal: Summary::new(&steps_to_goal),
+        }
+    }
+
+    /// Returns the aggregate as JSON.
+    pub fn to_json(&self) -> String {
+        format!("{{\"episodes\":{},\"request_rate\":{},\"violation_rate\":{},\"steps_to_goal\":{}}}",
+                self.episodes, self.request_rate.to_json(), self.violation_rate.to_json(),
+                self.steps_to_goal.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary() {
+        let s = Summary::new(&[3.0, 1.0, 2.0, 4.0]);
+        assert_eq!((s.mean, s.min, s.p50, s.p90, s.max), (2.5, 1.0, 2.0, 4.0, 4.0));
+        assert_eq!(s.variance, 5.0 / 3.0);
+        assert_eq!(Summary::new(&[]), Summary::default());
+    }
+
+    #[test]
+    fn episodes() {
+        let episodes = [
+            Episode {decisions: 4, requests: 1, violation: false, steps_to_goal: Some(4)},
+            Episode {decisions: 2, requests: 2, violation: true, steps_to_goal: None},
+            Episode::from(RunReport {goal: true, halted: false, steps: 6, requests: 0}),
+        ];
+        let a = Aggregate::new(&episodes);
+        assert_eq!((a.request_rate.mean, a.request_rate.max), (1.25 / 3.0, 1.0));
+        assert_eq!((a.violation_rate.mean, a.steps_to_goal.count, a.steps_to_goal.mean), (1.0 / 3.0, 2, 5.0));
+        assert!(a.to_json().starts_with("{\"episodes\":3,\"request_rate\":{\"count\":3,"));
+    }
+}
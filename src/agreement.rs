@@ -0,0 +1,261 @@
+//! Composable agreement policies.
+//!
+//! By default, a safety layer acts by `Agreement`, where every mutation that determines a decision
+//! must agree with core zero. An `AgreementPolicy` replaces this rule for a layer,
+//! when set as `LayerConfig::policy`.
+//!
+//! After every probe, the policy is given the `Votes` of the probes of the layer so far,
+//! and returns a `Verdict`: act, request a model update, or continue probing.
+//! When no more mutations are probed, the policy is asked for a final verdict,
+//! where continuing means that no decision was determined.
+//!
+//! - `Unanimous`: act when all determined probes agree
+//! - `Quorum`: act when a fraction of determined probes agree
+//! - `Weighted`: act when a weighted fraction of determined probes agree, weighted by mutater
+//! - `Approximate`: act when at most a fraction of determined probes disagree,
+//!   such that a few disagreements are tolerated
+//! - `Scoped`: applies a policy to the probes of some mutaters
+//! - `And` and `Or`: combine two policies
+//!
+//! For example, unanimity on goal mutations and a quorum on state mutations:
+//!
+//! ```
+//! use agent_safety_layers::agreement::*;
+//!
+//! static POLICY: And<Scoped<Unanimous>, Scoped<Quorum>> = And(
+//!     Scoped {mutaters: &[0], policy: Unanimous},
+//!     Scoped {mutaters: &[1, 2], policy: Quorum(0.5)},
+//! );
+//! ```
+//!
+//! Policies are referenced as `&'static dyn AgreementPolicy`, such that layer configurations stay `Copy`.
+//! Votes are filtered lazily, such that deciding with a policy does not allocate memory on the heap.
+//! For approximate equality of actions, see `LayerConfig::comparator`.
+
+use std::fmt;
+
+use crate::ProbeOutcome;
+
+/// Stores the outcome of a probe in a safety layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Vote {
+    /// The index of the mutater used by the probe.
+    pub mutater: usize,
+    /// The outcome of the probe.
+    pub outcome: ProbeOutcome,
+}
+
+/// Stores the verdict of an agreement policy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Verdict {
+    /// Act on the decision of core zero.
+    Act,
+    /// Request a model update.
+    Ask,
+    /// Continue probing, or no decision was determined when no more mutations are probed.
+    Pending,
+}
+
+/// Stores the outcomes of the probes so far that a policy applies to.
+#[derive(Clone, Copy)]
+pub struct Votes<'a> {
+    votes: &'a [Vote],
+    filter: &'a dyn Fn(&Vote) -> bool,
+}
+
+fn all(_: &Vote) -> bool {true}
+
+impl<'a> Votes<'a> {
+    /// Creates new votes from the outcomes of probes.
+    pub fn new(votes: &'a [Vote]) -> Self {Votes {votes, filter: &all}}
+
+    /// Returns `true` if the policy applies to a vote.
+    pub fn contains(&self, vote: &Vote) -> bool {(self.filter)(vote)}
+
+    /// Returns the votes that also satisfy a predicate.
+    pub fn filter(&self, filter: &'a dyn Fn(&Vote) -> bool) -> Votes<'a> {Votes {votes: self.votes, filter}}
+
+    /// Returns an iterator over the votes.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Vote> + '_ {
+        self.votes.iter().filter(move |v| (self.filter)(v))
+    }
+}
+
+impl fmt::Debug for Votes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {f.debug_list().entries(self.iter()).finish()}
+}
+
+/// Implemented by rules for agreement between sub-agents.
+pub trait AgreementPolicy: fmt::Debug + Sync {
+    /// Returns the verdict given the outcomes of the probes so far.
+    ///
+    /// When `finished` is `true`, no more mutations are probed.
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict;
+}
+
+/// Returns the numbers of agreeing and determined votes.
+fn tally(votes: Votes<'_>) -> (usize, usize) {
+    votes.iter().fold((0, 0), |(agree, determined), v| match v.outcome {
+        ProbeOutcome::Agree => (agree + 1, determined + 1),
+        ProbeOutcome::Disagree => (agree, determined + 1),
+        ProbeOutcome::RequestModel => (agree, determined),
+    })
+}
+
+/// Returns the final verdict of whether a fraction of determined votes is reached.
+fn fraction(agree: f64, determined: f64, min: f64, finished: bool) -> Verdict {
+    if !finished || determined == 0.0 {Verdict::Pending}
+    else if agree >= min * determined {Verdict::Act}
+    else {Verdict::Ask}
+}
+
+/// Acts when all determined probes agree, and at least one does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Unanimous;
+
+impl AgreementPolicy for Unanimous {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        let (agree, determined) = tally(votes);
+        if agree < determined {Verdict::Ask}
+        else {fraction(agree as f64, determined as f64, 1.0, finished)}
+    }
+}
+
+/// Acts when at least a fraction of the determined probes agree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quorum(pub f64);
+
+impl AgreementPolicy for Quorum {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        let (agree, determined) = tally(votes);
+        fraction(agree as f64, determined as f64, self.0, finished)
+    }
+}
+
+/// Acts when at least a fraction of the weights of determined probes agree.
+///
+/// Mutaters without a weight have weight `1`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weighted {
+    /// The weights by mutater.
+    pub weights: &'static [f64],
+    /// The minimum fraction of agreeing weight.
+    pub min: f64,
+}
+
+impl AgreementPolicy for Weighted {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        let (agree, determined) = votes.iter().fold((0.0, 0.0), |(agree, determined), v| {
+            let w = self.weights.get(v.mutater).cloned().unwrap_or(1.0);
+            match v.outcome {
+                ProbeOutcome::Agree => (agree + w, determined + w),
+                ProbeOutcome::Disagree => (agree, determined + w),
+                ProbeOutcome::RequestModel => (agree, determined),
+            }
+        });
+        fraction(agree, determined, self.min, finished)
+    }
+}
+
+/// Acts when at most a fraction of the determined probes disagree.
+///
+/// Unlike `Quorum`, a model update is requested as soon as the fraction is exceeded
+/// after at least one agreement.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Approximate(pub f64);
+
+impl AgreementPolicy for Approximate {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        let (agree, determined) = tally(votes);
+        let disagree = (determined - agree) as f64;
+        if agree > 0 && disagree > self.0 * determined as f64 && !finished {Verdict::Ask}
+        else {fraction(agree as f64, determined as f64, 1.0 - self.0, finished)}
+    }
+}
+
+/// Applies a policy to the probes of some mutaters.
+///
+/// No decision is determined when none of the mutaters were probed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scoped<P> {
+    /// The indices of the mutaters.
+    pub mutaters: &'static [usize],
+    /// The policy.
+    pub policy: P,
+}
+
+impl<P: AgreementPolicy> AgreementPolicy for Scoped<P> {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        let scoped = |v: &Vote| votes.contains(v) && self.mutaters.contains(&v.mutater);
+        self.policy.verdict(votes.filter(&scoped), finished)
+    }
+}
+
+/// Acts when both policies act, and requests a model update when either does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct And<P, Q>(pub P, pub Q);
+
+impl<P: AgreementPolicy, Q: AgreementPolicy> AgreementPolicy for And<P, Q> {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        match (self.0.verdict(votes, finished), self.1.verdict(votes, finished)) {
+            (Verdict::Ask, _) | (_, Verdict::Ask) => Verdict::Ask,
+            (Verdict::Act, Verdict::Act) => Verdict::Act,
+            _ => Verdict::Pending,
+        }
+    }
+}
+
+/// Acts when either policy acts, and requests a model update when both do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Or<P, Q>(pub P, pub Q);
+
+impl<P: AgreementPolicy, Q: AgreementPolicy> AgreementPolicy for Or<P, Q> {
+    fn verdict(&self, votes: Votes<'_>, finished: bool) -> Verdict {
+        match (self.0.verdict(votes, finished), self.1.verdict(votes, finished)) {
+            (Verdict::Act, _) | (_, Verdict::Act) => Verdict::Act,
+            (Verdict::Ask, Verdict::Ask) => Verdict::Ask,
+            _ => Verdict::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision, Reason};
+
+    fn votes(outcomes: &[ProbeOutcome]) -> Vec<Vote> {
+        outcomes.iter().enumerate().map(|(mutater, &outcome)| Vote {mutater, outcome}).collect()
+    }
+
+    #[test]
+    fn policies() {
+        use ProbeOutcome::*;
+        let v = votes(&[Agree, Disagree, Agree, RequestModel]);
+        assert_eq!(Unanimous.verdict(Votes::new(&v[..1]), false), Verdict::Pending);
+        assert_eq!(Unanimous.verdict(Votes::new(&v), false), Verdict::Ask);
+        assert_eq!(Quorum(0.6).verdict(Votes::new(&v), true), Verdict::Act);
+        assert_eq!(Quorum(0.7).verdict(Votes::new(&v), true), Verdict::Ask);
+        assert_eq!(Weighted {weights: &[1.0, 3.0], min: 0.5}.verdict(Votes::new(&v), true), Verdict::Ask);
+        assert_eq!(Approximate(0.25).verdict(Votes::new(&v[..2]), false), Verdict::Ask);
+        assert_eq!(Approximate(0.5).verdict(Votes::new(&v), true), Verdict::Act);
+        let both = And(Scoped {mutaters: &[0, 2], policy: Unanimous}, Scoped {mutaters: &[1], policy: Quorum(0.0)});
+        assert_eq!(both.verdict(Votes::new(&v), true), Verdict::Act);
+        assert_eq!(Or(Unanimous, Scoped {mutaters: &[3], policy: Unanimous}).verdict(Votes::new(&v), true), Verdict::Pending);
+    }
+
+    #[test]
+    fn layer() {
+        let mut s = crate::tests::four().add(1);
+        // The first mutater does nothing, the second mutates the goal.
+        s.mutaters = vec![|_| 0, crate::tests::four().mutater];
+        s.layers[0].mutation_limit = 2;
+        s.z.model = (4, 3);
+        assert_eq!(s.diagnose().decision, Decision::Action(1));
+        s.layers[0].policy = Some(&Unanimous);
+        assert_eq!(s.diagnose().reason, Reason::Policy {layer: 1});
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.layers[0].policy = Some(&Scoped {mutaters: &[0], policy: Unanimous});
+        assert_eq!(s.diagnose(), crate::Diagnosis {decision: Decision::Action(1), reason: Reason::Policy {layer: 1}});
+    }
+}
@@ -0,0 +1,110 @@
+//! Disagreement-rate alarms.
+//!
+//! When the deployed model drifts from reality,
+//! mutated decisions disagree with core zero more often.
+//! An `Alarmed` agent computes the disagreement rate over a rolling window of decide calls,
+//! as the fraction of probes that disagreed, and fires alarms when the rate crosses thresholds.
+//!
+//! An alarm fires once when the rate rises to its threshold,
+//! and is armed again when the rate falls below it.
+
+use std::collections::VecDeque;
+
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Stores an alarm on the disagreement rate.
+#[derive(Clone, Copy, Debug)]
+pub struct Alarm {
+    /// The rate that fires the alarm.
+    pub threshold: f64,
+    /// Called with the rate when the alarm fires.
+    pub callback: fn(f64),
+    /// Whether the rate is at or above the threshold.
+    pub raised: bool,
+}
+
+/// Stores an agent with disagreement-rate alarms.
+#[derive(Clone, Debug)]
+pub struct Alarmed<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The number of decide calls in the rolling window.
+    pub window: usize,
+    /// The alarms.
+    pub alarms: Vec<Alarm>,
+    history: VecDeque<(u32, u32)>,
+}
+
+impl<M, A, D> Alarmed<M, A, D> {
+    /// Creates a new agent with alarms over a rolling window of decide calls.
+    pub fn new(agent: AgentN<M, A, D>, window: usize) -> Self {
+        Alarmed {agent, window, alarms: vec![], history: VecDeque::new()}
+    }
+
+    /// Registers an alarm that fires when the rate rises to a threshold.
+    pub fn alarm(mut self, threshold: f64, callback: fn(f64)) -> Self {
+        self.alarms.push(Alarm {threshold, callback, raised: false});
+        self
+    }
+
+    /// Returns the disagreement rate over the rolling window.
+    pub fn rate(&self) -> f64 {
+        let (disagreements, probes) = self.history.iter()
+            .fold((0, 0), |(d, p), &(a, b)| (d + a, p + b));
+        if probes == 0 {0.0} else {disagreements as f64 / probes as f64}
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Alarmed<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        let decision = self.agent.decide();
+        let report = self.agent.report;
+        self.history.push_back((report.disagreements, report.probes));
+        while self.history.len() > self.window {self.history.pop_front();}
+        let rate = self.rate();
+        for alarm in &mut self.alarms {
+            let raised = rate >= alarm.threshold;
+            if raised && !alarm.raised {(alarm.callback)(rate)}
+            alarm.raised = raised;
+        }
+        decision
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Alarmed<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FIRED: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn drift() {
+        let mut s = Alarmed::new(crate::tests::four().add(1), 2)
+            .alarm(0.5, |_| {FIRED.fetch_add(1, Ordering::SeqCst);});
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.rate(), 0.0);
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.rate(), 0.5);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.rate(), 1.0);
+        // The alarm fires once while the rate stays above the threshold.
+        assert_eq!(FIRED.load(Ordering::SeqCst), 1);
+        s.update_model((4, 0));
+        s.decide();
+        s.decide();
+        assert!(!s.alarms[0].raised);
+    }
+}
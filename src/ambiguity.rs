@@ -0,0 +1,175 @@
+//! Discovery of goal ambiguity.
+//!
+//! A model where core zero acts, but the layered agent requests a model update,
+//! is in a region where the goal specification is underdetermined:
+//! Small mutations of the model change what the agent should do.
+//!
+//! The function `find_ambiguities` generates models from random bytes,
+//! keeps the ones that are ambiguous, and minimizes each of them
+//! by greedily taking the first smaller candidate that is still ambiguous.
+//! The minimized models are an inventory of where the goal is underdetermined,
+//! and can be written as scenario files with `write_scenarios`,
+//! which reproduce the model request in a single step.
+//!
+//! Requires the `fuzz` feature, or compiling tests of this library.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::fuzz::Unstructured;
+use crate::rng::Rng;
+use crate::scenario::Scenario;
+use crate::{Agent, AgentN, Decision, Diagnosis, Reason};
+
+/// The number of random bytes used to generate each model.
+pub const BYTES: usize = 64;
+
+/// The maximum number of minimization steps of a model.
+pub const MAX_SHRINKS: usize = 1000;
+
+/// Stores a model where core zero acts, but the layered agent requests a model update.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ambiguity<M, A> {
+    /// The minimized model.
+    pub model: M,
+    /// The action of core zero.
+    pub action: A,
+    /// The reason of the model request.
+    pub reason: Reason,
+    /// The number of minimization steps.
+    pub shrinks: usize,
+}
+
+impl<M: Clone, A> Ambiguity<M, A> {
+    /// Returns a scenario of a single step starting from the model.
+    ///
+    /// The goals should not hold on the model, such that the step is taken.
+    pub fn to_scenario(&self, name: impl Into<String>, goals: &[&str]) -> Scenario<M> {
+        Scenario {
+            name: name.into(),
+            initial: self.model.clone(),
+            max_steps: 1,
+            observations: vec![],
+            perturbations: vec![],
+            goals: goals.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+}
+
+fn ambiguous<M, A, D>(agent: &mut AgentN<M, A, D>, model: &M) -> Option<(A, Reason)>
+    where M: Clone, A: PartialEq
+{
+    agent.update_model(model.clone());
+    let Diagnosis {decision, reason} = agent.diagnose();
+    match (decision, agent.z.decide()) {
+        (Decision::RequestModel, Decision::Action(a)) => Some((a, reason)),
+        _ => None,
+    }
+}
+
+/// Searches for ambiguous models, returning them minimized and without duplicates.
+///
+/// Models are generated from random bytes of a generator with some seed.
+/// Smaller candidates of a model are given by `shrink`,
+/// where the first one that is still ambiguous is taken.
+/// The agent is cloned, such that it is not changed.
+pub fn find_ambiguities<M, A, D>(
+    agent: &AgentN<M, A, D>,
+    generate: fn(&mut Unstructured<'_>) -> M,
+    shrink: fn(&M) -> Vec<M>,
+    seed: u64,
+    samples: usize,
+) -> Vec<Ambiguity<M, A>>
+    where M: Clone + PartialEq, A: PartialEq
+{
+    let mut agent = agent.clone();
+    let mut rng = Rng::new(seed);
+    let mut found: Vec<Ambiguity<M, A>> = vec![];
+    for _ in 0..samples {
+        let data: Vec<u8> = (0..BYTES).map(|_| rng.next_u64() as u8).collect();
+        let mut model = generate(&mut Unstructured::new(&data));
+        let (mut action, mut reason) = match ambiguous(&mut agent, &model) {
+            Some(x) => x,
+            None => continue,
+        };
+        let mut shrinks = 0;
+        while shrinks < MAX_SHRINKS {
+            let smaller = shrink(&model).into_iter()
+                .find_map(|m| ambiguous(&mut agent, &m).map(|x| (m, x)));
+            match smaller {
+                Some((m, (a, r))) => {
+                    model = m;
+                    action = a;
+                    reason = r;
+                    shrinks += 1;
+                }
+                None => break,
+            }
+        }
+        if found.iter().all(|x| x.model != model) {
+            found.push(Ambiguity {model, action, reason, shrinks});
+        }
+    }
+    found
+}
+
+/// Writes ambiguities as scenario files in a directory, returning the paths.
+///
+/// The files are named `ambiguity-<index>.json`.
+pub fn write_scenarios<M: Clone, A>(
+    dir: impl AsRef<Path>,
+    ambiguities: &[Ambiguity<M, A>],
+    goals: &[&str],
+    encode: fn(&M) -> String,
+) -> io::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    fs::create_dir_all(dir)?;
+    let mut paths = vec![];
+    for (i, ambiguity) in ambiguities.iter().enumerate() {
+        let path = dir.join(format!("ambiguity-{}.json", i));
+        let scenario = ambiguity.to_scenario(format!("ambiguity #{}", i), goals);
+        fs::write(&path, scenario.to_json(encode))?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{run_scenario, Library};
+
+    fn encode(m: &(u32, u32)) -> String {format!("{},{}", m.0, m.1)}
+    fn decode(s: &str) -> Option<(u32, u32)> {
+        let (a, b) = s.split_once(',')?;
+        Some((a.parse().ok()?, b.parse().ok()?))
+    }
+
+    #[test]
+    fn inventory() {
+        let s = crate::tests::four().add(1);
+        let mut found = find_ambiguities(
+            &s,
+            |u| (u.choose(8) as u32, u.choose(8) as u32),
+            |m| if m.0 > 0 && m.1 > 0 {vec![(m.0 - 1, m.1 - 1)]} else {vec![]},
+            0,
+            64,
+        );
+        found.sort_by_key(|x| x.model);
+        // The goal is underdetermined next to and at the state.
+        let models: Vec<_> = found.iter().map(|x| (x.model, x.action)).collect();
+        assert_eq!(models, vec![((1, 0), 1), ((1, 1), 0)]);
+        assert_eq!(found[0].reason, Reason::Disagree {layer: 1, probe: 0});
+
+        let dir = std::env::temp_dir().join(format!("ambiguity-{}", std::process::id()));
+        let paths = write_scenarios(&dir, &found, &["at goal"], encode).unwrap();
+        let scenario = Scenario::from_json(&fs::read_to_string(&paths[0]).unwrap(), decode).unwrap();
+        let library = Library::new(|m: &mut (u32, u32), a: &i32| m.1 = (m.1 as i32 + a) as u32)
+            .goal("at goal", |m| m.0 == m.1);
+        let mut s = s;
+        let report = run_scenario(&mut s, &scenario, &library).unwrap();
+        assert_eq!((report.run.steps, report.run.requests), (1, 1));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,183 @@
+//! Async approval of high-stakes actions.
+//!
+//! Some actions are too consequential to perform on the agreement of safety layers alone.
+//! An `Approval` agent awaits external approval of high-stakes actions,
+//! e.g. from an operator, with a deadline.
+//!
+//! A rejected action requests a model update, since the agent is missing something an operator knows.
+//! When the deadline passes, the configured safe behavior in `OnTimeout` applies automatically.
+//! Every approval request is logged with its outcome in `Approval::log`.
+//!
+//! The deadline is enforced by a timer thread waking the future,
+//! so it does not depend on a specific executor.
+//!
+//! Requires the `async` feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Agent, Decision, Inspect};
+
+/// Implemented by sources of external approval.
+pub trait Approver<M, A> {
+    /// The future of an approval, which is `true` when the action is approved.
+    type Future: Future<Output = bool> + Unpin;
+    /// Requests approval of an action in some model.
+    fn request(&self, model: &M, action: &A) -> Self::Future;
+}
+
+/// The safe behavior when approval times out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OnTimeout<A> {
+    /// Request a model update.
+    RequestModel,
+    /// Perform a fallback action instead.
+    Fallback(A),
+    /// Halt.
+    Halt,
+}
+
+/// The outcome of an approval request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ApprovalOutcome {
+    /// The action was approved.
+    Approved,
+    /// The action was rejected.
+    Rejected,
+    /// The deadline passed without an answer.
+    TimedOut,
+}
+
+/// Stores a logged approval request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ApprovalRecord<A> {
+    /// The action that awaited approval.
+    pub action: A,
+    /// The outcome.
+    pub outcome: ApprovalOutcome,
+    /// The decision that was made.
+    pub decision: Decision<A>,
+}
+
+/// Stores an agent that awaits approval of high-stakes actions.
+#[derive(Clone, Debug)]
+pub struct Approval<T: Agent, P> {
+    /// The inner agent.
+    pub agent: T,
+    /// The source of approval.
+    pub approver: P,
+    /// Returns `true` if an action in some model requires approval.
+    pub high_stakes: fn(&T::Model, &T::Action) -> bool,
+    /// The maximum time to wait for approval.
+    pub deadline: Duration,
+    /// The safe behavior when approval times out.
+    pub on_timeout: OnTimeout<T::Action>,
+    /// The log of approval requests, in order.
+    pub log: Vec<ApprovalRecord<T::Action>>,
+}
+
+struct Deadline<F> {
+    future: F,
+    at: Instant,
+    timer: bool,
+}
+
+impl<F: Future + Unpin> Future for Deadline<F> {
+    type Output = Option<F::Output>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<F::Output>> {
+        if let Poll::Ready(x) = Pin::new(&mut self.future).poll(cx) {return Poll::Ready(Some(x))}
+        let now = Instant::now();
+        if now >= self.at {return Poll::Ready(None)}
+        if !self.timer {
+            self.timer = true;
+            let waker = cx.waker().clone();
+            let wait = self.at - now;
+            thread::spawn(move || {
+                thread::sleep(wait);
+                waker.wake();
+            });
+        }
+        Poll::Pending
+    }
+}
+
+impl<T, P> Approval<T, P>
+    where T: Inspect, T::Action: Clone, P: Approver<T::Model, T::Action>
+{
+    /// Creates a new agent that awaits approval of high-stakes actions,
+    /// requesting a model update on timeout.
+    pub fn new(agent: T, approver: P, high_stakes: fn(&T::Model, &T::Action) -> bool, deadline: Duration) -> Self {
+        Approval {agent, approver, high_stakes, deadline, on_timeout: OnTimeout::RequestModel, log: vec![]}
+    }
+
+    /// Decide what to do next, awaiting approval of high-stakes actions.
+    pub async fn decide(&mut self) -> Decision<T::Action> {
+        let action = match self.agent.decide() {
+            Decision::Action(a) if (self.high_stakes)(self.agent.model(), &a) => a,
+            decision => return decision,
+        };
+        let future = self.approver.request(self.agent.model(), &action);
+        let at = Instant::now() + self.deadline;
+        let (outcome, decision) = match (Deadline {future, at, timer: false}).await {
+            Some(true) => (ApprovalOutcome::Approved, Decision::Action(action.clone())),
+            Some(false) => (ApprovalOutcome::Rejected, Decision::RequestModel),
+            None => (ApprovalOutcome::TimedOut, match &self.on_timeout {
+                OnTimeout::RequestModel => Decision::RequestModel,
+                OnTimeout::Fallback(a) => Decision::Action(a.clone()),
+                OnTimeout::Halt => Decision::Halt,
+            }),
+        };
+        self.log.push(ApprovalRecord {action, outcome, decision: decision.clone()});
+        decision
+    }
+
+    /// Update internal model.
+    pub fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+
+    /// Perform an action on its internal model.
+    pub fn act(&mut self, action: T::Action) {self.agent.act(action)}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::tests::block_on;
+    use std::future::{pending, ready, Pending, Ready};
+
+    struct Operator;
+
+    impl Approver<(u32, u32), i32> for Operator {
+        type Future = Ready<bool>;
+        fn request(&self, m: &(u32, u32), _: &i32) -> Ready<bool> {ready(m.1 < 2)}
+    }
+
+    struct Away;
+
+    impl Approver<(u32, u32), i32> for Away {
+        type Future = Pending<bool>;
+        fn request(&self, _: &(u32, u32), _: &i32) -> Pending<bool> {pending()}
+    }
+
+    fn moving(_: &(u32, u32), a: &i32) -> bool {*a != 0}
+
+    #[test]
+    fn approve() {
+        let mut s = Approval::new(crate::tests::four().add(1), Operator, moving, Duration::from_secs(1));
+        assert_eq!(block_on(s.decide()), Decision::Action(1));
+        s.update_model((4, 2));
+        assert_eq!(block_on(s.decide()), Decision::RequestModel);
+        assert_eq!(s.log[1].outcome, ApprovalOutcome::Rejected);
+
+        let mut s = Approval::new(crate::tests::four().add(1), Away, moving, Duration::from_millis(5));
+        s.on_timeout = OnTimeout::Fallback(0);
+        assert_eq!(block_on(s.decide()), Decision::Action(0));
+        assert_eq!(s.log, vec![ApprovalRecord {
+            action: 1,
+            outcome: ApprovalOutcome::TimedOut,
+            decision: Decision::Action(0),
+        }]);
+    }
+}
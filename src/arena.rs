@@ -0,0 +1,125 @@
+//! Bump arena for probe scratch space.
+//!
+//! Every safety layer keeps scratch values while probing:
+//! the fingerprints of deltas used to skip duplicate probes,
+//! and the probe outcomes given to an agreement policy.
+//! Instead of allocating per layer, an agent owns a `Scratch` space shared by all its layers,
+//! with an `Arena` for each type of value.
+//! A layer marks the scratch space when it starts probing and resets it to the mark when it is done,
+//! such that the arenas grow to the depth of the safety stack once and are reused afterwards.
+//! Deltas are not kept in the arenas, but on the stack.
+//!
+//! In control loops, `AgentN::reserve_scratch` allocates the arenas up front,
+//! such that decide calls do not allocate scratch space.
+
+use crate::agreement::Vote;
+
+/// Stores scratch values allocated in stack order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Arena<T> {
+    items: Vec<T>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {Arena::new()}
+}
+
+impl<T> Arena<T> {
+    /// Creates a new empty arena.
+    pub fn new() -> Self {Arena {items: vec![]}}
+
+    /// Creates a new empty arena with room for some values.
+    pub fn with_capacity(capacity: usize) -> Self {Arena {items: Vec::with_capacity(capacity)}}
+
+    /// Returns the number of values the arena holds without allocating.
+    pub fn capacity(&self) -> usize {self.items.capacity()}
+
+    /// Reserves room for more values.
+    pub fn reserve(&mut self, additional: usize) {self.items.reserve(additional)}
+
+    /// Returns a mark of the values allocated so far.
+    pub fn mark(&self) -> usize {self.items.len()}
+
+    /// Allocates a value.
+    pub fn push(&mut self, value: T) {self.items.push(value)}
+
+    /// Returns the values allocated since a mark.
+    pub fn since(&self, mark: usize) -> &[T] {&self.items[mark..]}
+
+    /// Frees the values allocated since a mark, keeping the memory for reuse.
+    pub fn reset(&mut self, mark: usize) {self.items.truncate(mark)}
+}
+
+/// Stores a mark of the scratch values allocated so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mark {
+    /// The mark of the fingerprints.
+    pub fingerprints: usize,
+    /// The mark of the votes.
+    pub votes: usize,
+}
+
+/// Stores the scratch space of the safety layers of an agent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scratch {
+    /// The fingerprints of probed deltas.
+    pub fingerprints: Arena<u64>,
+    /// The probe outcomes of layers with an agreement policy.
+    pub votes: Arena<Vote>,
+}
+
+impl Scratch {
+    /// Creates a new empty scratch space.
+    pub fn new() -> Self {Scratch::default()}
+
+    /// Returns a mark of the values allocated so far.
+    pub fn mark(&self) -> Mark {Mark {fingerprints: self.fingerprints.mark(), votes: self.votes.mark()}}
+
+    /// Frees the values allocated since a mark, keeping the memory for reuse.
+    pub fn reset(&mut self, mark: Mark) {
+        self.fingerprints.reset(mark.fingerprints);
+        self.votes.reset(mark.votes);
+    }
+}
+
+impl<M, A, D> crate::AgentN<M, A, D> {
+    /// Allocates scratch space for probing every safety layer up front.
+    pub fn reserve_scratch(&mut self) {
+        let probes = |layer: &crate::LayerConfig<A>|
+            if layer.time_budget.is_some() {u8::MAX} else {layer.mutation_limit} as usize;
+        let fingerprints: usize = self.layers.iter().map(probes).sum();
+        let votes: usize = self.layers.iter().filter(|layer| layer.policy.is_some()).map(probes).sum();
+        self.scratch.fingerprints.reserve(fingerprints);
+        self.scratch.votes.reserve(votes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn stack_order() {
+        let mut arena = Arena::with_capacity(4);
+        arena.push(1);
+        let mark = arena.mark();
+        arena.push(2);
+        arena.push(3);
+        assert_eq!(arena.since(mark), &[2, 3]);
+        arena.reset(mark);
+        assert_eq!((arena.since(0), arena.capacity() >= 4), (&[1][..], true));
+    }
+
+    #[test]
+    fn reused() {
+        let mut s = crate::tests::four().add(3);
+        s.dedup = Some(|d| *d as u64);
+        s.layers[1].policy = Some(&crate::agreement::Unanimous);
+        s.reserve_scratch();
+        let capacity = (s.scratch.fingerprints.capacity(), s.scratch.votes.capacity());
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.scratch.mark(), Mark {fingerprints: 0, votes: 0});
+        assert_eq!((s.scratch.fingerprints.capacity(), s.scratch.votes.capacity()), capacity);
+    }
+}
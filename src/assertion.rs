@@ -0,0 +1,109 @@
+//! Audited goal assertions.
+//!
+//! A model update can also assert that the goal is specified correctly.
+//! With higher confidence in a correct goal, the safety levels can be reduced when needed.
+//!
+//! An `Asserted` agent makes this workflow auditable:
+//! `assert_goal_correct` records who asserted the goal and at which decide call,
+//! and lowers the safety level by some number of levels.
+//! When a probe at the lowered level disagrees with core zero,
+//! the decision depends on the goal after all, which contradicts the assertion.
+//! The assertion is then revoked, and the lowered levels are automatically re-raised.
+
+use crate::{Agent, AgentN, Decision, Inspect, LayerConfig};
+
+/// Stores an assertion that the goal is specified correctly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Assertion {
+    /// Who asserted that the goal is correct.
+    pub provenance: &'static str,
+    /// The number of decide calls before the assertion.
+    pub step: usize,
+    /// The number of safety levels that were lowered.
+    pub levels: usize,
+    /// The decide call that contradicted the assertion, if any.
+    pub revoked: Option<usize>,
+}
+
+/// Stores an agent whose safety level is lowered by audited goal assertions.
+#[derive(Clone, Debug)]
+pub struct Asserted<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The assertions, in order.
+    pub assertions: Vec<Assertion>,
+    steps: usize,
+    lowered: Vec<LayerConfig<A>>,
+}
+
+impl<M, A, D> Asserted<M, A, D> {
+    /// Creates a new agent without assertions.
+    pub fn new(agent: AgentN<M, A, D>) -> Self {
+        Asserted {agent, assertions: vec![], steps: 0, lowered: vec![]}
+    }
+
+    /// Asserts that the goal is correct, lowering the safety level.
+    ///
+    /// The safety level is never lowered below zero.
+    /// Returns the number of levels that were lowered.
+    pub fn assert_goal_correct(&mut self, provenance: &'static str, levels: usize) -> usize {
+        let levels = levels.min(self.agent.layers.len());
+        for _ in 0..levels {self.lowered.extend(self.agent.layers.pop())}
+        self.assertions.push(Assertion {provenance, step: self.steps, levels, revoked: None});
+        levels
+    }
+
+    /// Returns the assertions that still lower the safety level.
+    pub fn active(&self) -> impl Iterator<Item = &Assertion> {
+        self.assertions.iter().filter(|a| a.revoked.is_none())
+    }
+
+    /// Revokes all active assertions, re-raising the lowered safety levels.
+    pub fn revoke(&mut self) {
+        for a in &mut self.assertions {
+            if a.revoked.is_none() {a.revoked = Some(self.steps)}
+        }
+        while let Some(layer) = self.lowered.pop() {self.agent.layers.push(layer)}
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Asserted<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        let decision = self.agent.decide();
+        if self.agent.report.disagreements > 0 && !self.lowered.is_empty() {self.revoke()}
+        self.steps += 1;
+        decision
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Asserted<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contradict() {
+        let mut s = Asserted::new(crate::tests::four().add(2));
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.assert_goal_correct("operator", 1), 1);
+        assert_eq!(s.agent.layers(), 1);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.active().count(), 1);
+        // Mutating the goal changes the decision, so the goal assertion is contradicted.
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.agent.layers(), 2);
+        assert_eq!(s.assertions[0], Assertion {provenance: "operator", step: 1, levels: 1, revoked: Some(2)});
+    }
+}
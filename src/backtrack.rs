@@ -0,0 +1,102 @@
+//! Constraint solvers as core agents.
+//!
+//! A puzzle in the style of `quickbacktrack` has positions that are assigned values.
+//! The function `agent` wraps a backtracking solver as the decider of core zero,
+//! where the model is the puzzle state and the action is the next assignment.
+//!
+//! The decider solves a copy of the puzzle and assigns the first empty position
+//! its value in the solution.
+//! When the puzzle is solved or has no solution, the action is `None`.
+//!
+//! The `Puzzle` trait mirrors the parts of `quickbacktrack` used by its solver,
+//! such that puzzles written for it can implement this trait with little effort.
+
+use crate::AgentZ;
+
+/// Implemented by puzzles that can be solved by backtracking.
+pub trait Puzzle: Clone {
+    /// The position of a value.
+    type Pos: Copy;
+    /// The value at a position.
+    type Val: Copy;
+    /// Sets the value at a position.
+    fn set(&mut self, pos: Self::Pos, val: Self::Val);
+    /// Returns the value at a position.
+    fn get(&self, pos: Self::Pos) -> Self::Val;
+    /// Returns `true` if the puzzle is solved.
+    fn is_solved(&self) -> bool;
+    /// Returns the next empty position to assign, if any.
+    fn empty(&self) -> Option<Self::Pos>;
+    /// Returns the possible values at a position.
+    fn possible(&self, pos: Self::Pos) -> Vec<Self::Val>;
+}
+
+/// Stores an assignment of a value to a position.
+pub type Assignment<P> = (<P as Puzzle>::Pos, <P as Puzzle>::Val);
+
+/// Returns a solution of a puzzle by backtracking, if any.
+pub fn solve<P: Puzzle>(puzzle: &P) -> Option<P> {
+    match puzzle.empty() {
+        None => if puzzle.is_solved() {Some(puzzle.clone())} else {None},
+        Some(pos) => puzzle.possible(pos).into_iter().find_map(|val| {
+            let mut next = puzzle.clone();
+            next.set(pos, val);
+            solve(&next)
+        }),
+    }
+}
+
+/// Returns the next assignment towards a solution, if any.
+pub fn decide<P: Puzzle>(puzzle: &P) -> Option<Assignment<P>> {
+    let pos = puzzle.empty()?;
+    solve(puzzle).map(|solution| (pos, solution.get(pos)))
+}
+
+/// Performs an assignment.
+pub fn act<P: Puzzle>(puzzle: &mut P, action: Option<Assignment<P>>) {
+    if let Some((pos, val)) = action {puzzle.set(pos, val)}
+}
+
+/// Returns a core zero agent that solves a puzzle.
+///
+/// The mutater and undoer describe the uncertainty about the puzzle,
+/// e.g. clearing a given value that might have been misread.
+pub fn agent<P: Puzzle, D>(
+    puzzle: P,
+    mutater: fn(&mut P) -> D,
+    undoer: fn(&mut P, D),
+) -> AgentZ<P, Option<Assignment<P>>, D> {
+    AgentZ {model: puzzle, decider: decide, actor: act, mutater, undoer}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    /// A row of three cells with the distinct values `1`, `2` and `3`, where `0` is empty.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Row([u8; 3]);
+
+    impl Puzzle for Row {
+        type Pos = usize;
+        type Val = u8;
+        fn set(&mut self, pos: usize, val: u8) {self.0[pos] = val}
+        fn get(&self, pos: usize) -> u8 {self.0[pos]}
+        fn is_solved(&self) -> bool {self.empty().is_none()}
+        fn empty(&self) -> Option<usize> {self.0.iter().position(|&v| v == 0)}
+        fn possible(&self, _: usize) -> Vec<u8> {(1..4).filter(|v| !self.0.contains(v)).collect()}
+    }
+
+    #[test]
+    fn solve_row() {
+        // The given value in the last cell might have been misread.
+        let mutater = |p: &mut Row| std::mem::replace(&mut p.0[2], 0);
+        let mut s = agent(Row([1, 0, 0]), mutater, |p, v| p.0[2] = v).add(1);
+        assert_eq!(s.decide(), Decision::Action(Some((1, 2))));
+        s.act(Some((1, 2)));
+        assert_eq!(s.decide(), Decision::Action(Some((2, 3))));
+        s.update_model(Row([0, 0, 1]));
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
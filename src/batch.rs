@@ -0,0 +1,111 @@
+//! Parallel multi-episode simulation.
+//!
+//! Estimating safety empirically, or tuning a configuration, runs many episodes.
+//! The function `run` executes seeded episodes on a number of threads.
+//! Each episode constructs its own agent from the seed, such that episodes are isolated
+//! and the results do not depend on the number of threads.
+//!
+//! `Totals` merges the statistics of episodes, as used by `tune`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::tune::Stats;
+
+/// Runs an episode for every seed, using a number of threads.
+///
+/// When the number of threads is `0`, the available parallelism is used.
+/// Returns the results in the order of the seeds.
+pub fn run<S, F>(seeds: &[u64], threads: usize, episode: F) -> Vec<S>
+    where S: Send, F: Fn(u64) -> S + Sync
+{
+    let threads = match threads {
+        0 => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    }.min(seeds.len()).max(1);
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..seeds.len()).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= seeds.len() {break}
+                let result = episode(seeds[i]);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|result| result.expect("episode finished")).collect()
+}
+
+/// Stores the merged statistics of episodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Totals {
+    /// The number of episodes.
+    pub episodes: usize,
+    /// The number of decisions.
+    pub decisions: usize,
+    /// The number of decisions that requested a model update.
+    pub requests: usize,
+    /// The number of episodes where the safety was violated.
+    pub violations: usize,
+}
+
+impl Totals {
+    /// Merges the statistics of an episode.
+    pub fn add(&mut self, stats: &Stats) {
+        self.episodes += 1;
+        self.decisions += stats.decisions;
+        self.requests += stats.requests;
+        if stats.violation {self.violations += 1}
+    }
+
+    /// Returns the fraction of decisions that requested a model update.
+    pub fn request_rate(&self) -> f64 {self.requests as f64 / self.decisions.max(1) as f64}
+
+    /// Returns the fraction of episodes where the safety was violated.
+    pub fn violation_rate(&self) -> f64 {self.violations as f64 / self.episodes.max(1) as f64}
+}
+
+impl<'a> std::iter::FromIterator<&'a Stats> for Totals {
+    fn from_iter<I: IntoIterator<Item = &'a Stats>>(iter: I) -> Self {
+        let mut totals = Totals::default();
+        for stats in iter {totals.add(stats)}
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn parallel() {
+        // The seed is the true goal, told to the agent when requesting a model update.
+        let episode = |seed: u64| {
+            let mut agent = crate::tests::four().add(1);
+            let mut stats = Stats::default();
+            for _ in 0..10 {
+                stats.decisions += 1;
+                match agent.decide() {
+                    Decision::Action(0) | Decision::Halt => break,
+                    Decision::Action(a) => agent.act(a),
+                    Decision::RequestModel => {
+                        stats.requests += 1;
+                        let state = agent.z.model.1;
+                        agent.update_model((seed as u32, state));
+                    }
+                }
+            }
+            stats
+        };
+        let seeds: Vec<u64> = (1..9).collect();
+        let results = run(&seeds, 3, episode);
+        assert_eq!(results, run(&seeds, 1, episode));
+        assert_eq!(results[3], episode(4));
+        let totals: Totals = results.iter().collect();
+        assert_eq!((totals.episodes, totals.violations), (8, 0));
+    }
+}
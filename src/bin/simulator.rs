@@ -0,0 +1,93 @@
+//! Interactive simulator of a layered agent in a built-in environment.
+//!
+//! Steps an agent wrapped in safety layers through the `doors` environment from the terminal.
+//! When the agent requests a model update, the operator tells which door is the goal,
+//! or accepts the true goal of the environment.
+//! The recorded trace is written as a DOT graph on exit.
+//!
+//! Usage: `simulator [layers] [trace.dot]`
+//!
+//! Requires the `simulator` feature.
+
+use std::io::{self, BufRead, Write};
+
+use agent_safety_layers::environment::Environment;
+use agent_safety_layers::envs::doors::{self, Doors, Model};
+use agent_safety_layers::trace::Trace;
+use agent_safety_layers::{Agent, Decision};
+
+const HELP: &str = "commands: <enter> step, r run until request, q quit";
+
+fn prompt(input: &mut impl BufRead, text: &str) -> Option<String> {
+    print!("{}", text);
+    io::stdout().flush().ok()?;
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let layers = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(1);
+    let path = args.get(2).cloned().unwrap_or_else(|| "trace.dot".into());
+
+    // The agent believes the goal is the door at `5`, but it is the door at `3`.
+    let model = Model {pos: 0, doors: vec![3, 5, 8], goal: 1, candidates: vec![0, 1], opened: false};
+    let mut env = Doors::new(Model {goal: 0, ..model.clone()});
+    let mut agent = doors::agent(model).add(layers);
+    let mut trace = Trace::new();
+
+    println!("doors with {} safety layers", layers);
+    println!("{}", HELP);
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut running = false;
+    while !env.truth.opened {
+        if !running {
+            match prompt(&mut input, "> ").as_deref() {
+                None | Some("q") => break,
+                Some("r") => running = true,
+                Some("") => {}
+                Some(_) => {
+                    println!("{}", HELP);
+                    continue;
+                }
+            }
+        }
+        let m = &agent.z.model;
+        print!("pos {} believed goal {} ({}): ", m.pos, m.goal, m.doors[m.goal]);
+        match trace.record(&mut agent) {
+            Decision::Action(a) => {
+                println!("{:?}", a);
+                env.act(&a);
+                agent.act(a);
+            }
+            Decision::RequestModel => {
+                running = false;
+                println!("request model ({})", trace.steps.last().unwrap().rationale.reason);
+                let mut truth = env.model();
+                let text = format!("goal door index of {:?} [{}]: ", truth.doors, truth.goal);
+                match prompt(&mut input, &text).map(|s| s.parse::<usize>()) {
+                    None => break,
+                    Some(Ok(goal)) if goal < truth.doors.len() => {
+                        truth.goal = goal;
+                        truth.candidates = vec![goal];
+                    }
+                    _ => {}
+                }
+                agent.update_model(truth);
+            }
+            Decision::Halt => {
+                println!("halt");
+                break;
+            }
+        }
+    }
+    println!("solved: {}, violations: {}, steps: {}", env.is_solved(), env.violations, trace.steps.len());
+    match std::fs::write(&path, trace.to_dot()) {
+        Ok(()) => println!("trace written to `{}`", path),
+        Err(err) => eprintln!("could not write trace to `{}`: {}", path, err),
+    }
+}
@@ -0,0 +1,151 @@
+//! Risk-aware utility bounds.
+//!
+//! Mutation probes might all agree on an action whose outcome is still too risky.
+//! `Bounds` gives lower and upper bounds of the utility of an action in some model.
+//! A `Bounded` agent evaluates the bounds of the decided action on the model,
+//! and on every mutation that the outermost safety layer probes,
+//! which gives an interval of the utility that is robust to the same uncertainty.
+//! When the worst case of the interval falls below a threshold,
+//! the action is refused and a model update is requested.
+
+use std::fmt;
+
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Stores an interval of utility.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    /// The worst case.
+    pub lower: f64,
+    /// The best case.
+    pub upper: f64,
+}
+
+impl Interval {
+    /// Returns the width of the interval.
+    pub fn width(&self) -> f64 {self.upper - self.lower}
+
+    /// Returns the smallest interval containing both intervals.
+    pub fn hull(&self, other: &Interval) -> Interval {
+        Interval {lower: self.lower.min(other.lower), upper: self.upper.max(other.upper)}
+    }
+}
+
+/// Stores bounds of the utility of actions.
+pub struct Bounds<M, A> {
+    /// Returns a lower bound of the utility of an action in some model.
+    pub lower: fn(&M, &A) -> f64,
+    /// Returns an upper bound of the utility of an action in some model.
+    pub upper: fn(&M, &A) -> f64,
+    /// The minimum worst case utility for acting.
+    pub threshold: f64,
+}
+
+impl<M, A> Clone for Bounds<M, A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A> Copy for Bounds<M, A> {}
+
+impl<M, A> fmt::Debug for Bounds<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bounds")
+            .field("lower", &self.lower)
+            .field("upper", &self.upper)
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl<M, A> Bounds<M, A> {
+    /// Returns the interval of the utility of an action in some model.
+    pub fn interval(&self, model: &M, action: &A) -> Interval {
+        Interval {lower: (self.lower)(model, action), upper: (self.upper)(model, action)}
+    }
+}
+
+/// Stores a layered agent that refuses actions with a worst case utility below a threshold.
+#[derive(Clone, Debug)]
+pub struct Bounded<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The utility bounds.
+    pub bounds: Bounds<M, A>,
+    /// The interval of the last decided action.
+    pub interval: Option<Interval>,
+    /// Whether the last decided action was refused.
+    pub refused: bool,
+}
+
+impl<M, A: PartialEq, D> Bounded<M, A, D> {
+    /// Creates a new agent with utility bounds.
+    pub fn new(agent: AgentN<M, A, D>, bounds: Bounds<M, A>) -> Self {
+        Bounded {agent, bounds, interval: None, refused: false}
+    }
+
+    /// Returns the interval of the utility of an action,
+    /// over the model and the mutations probed by the outermost safety layer.
+    pub fn robust_interval(&mut self, action: &A) -> Interval {
+        let mut interval = self.bounds.interval(&self.agent.z.model, action);
+        let limit = self.agent.layers.last().map(|config| config.mutation_limit).unwrap_or(0);
+        for probe in 0..limit {
+            let delta = self.agent.mutate_probe(probe);
+            interval = interval.hull(&self.bounds.interval(&self.agent.z.model, action));
+            self.agent.z.undo(delta);
+        }
+        interval
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Bounded<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        self.interval = None;
+        self.refused = false;
+        match self.agent.decide() {
+            Decision::Action(a) => {
+                let interval = self.robust_interval(&a);
+                self.interval = Some(interval);
+                self.refused = interval.lower < self.bounds.threshold;
+                if self.refused {Decision::RequestModel} else {Decision::Action(a)}
+            }
+            decision => decision,
+        }
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Bounded<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The utility of moving is uncertain by one step, and worse the closer the goal is.
+    static BOUNDS: Bounds<(u32, u32), i32> = Bounds {
+        lower: |m, a| if *a == 0 {0.0} else {m.0 as f64 - m.1 as f64 - 2.0},
+        upper: |m, a| if *a == 0 {0.0} else {m.0 as f64 - m.1 as f64},
+        threshold: 0.0,
+    };
+
+    #[test]
+    fn bounded() {
+        let mut s = Bounded::new(crate::tests::four().add(1), BOUNDS);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.interval, Some(Interval {lower: 1.0, upper: 4.0}));
+        // The probes agree, but the worst case of the mutated goal is below the threshold.
+        s.update_model((4, 2));
+        assert_eq!(s.agent.decide(), Decision::Action(1));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert!(s.refused);
+        assert_eq!(s.interval.unwrap().lower, -1.0);
+        assert_eq!(s.model(), &(4, 2));
+    }
+}
@@ -0,0 +1,56 @@
+//! Type-erased agents for heterogeneous collections.
+//!
+//! A `BoxAgent` stores any agent with the same model, action and delta types.
+//! This makes it possible to store layered and custom agents behind one type.
+
+use crate::{Agent, Decision};
+
+/// Stores a type-erased agent.
+pub struct BoxAgent<M, A, D> {
+    /// The boxed agent.
+    pub agent: Box<dyn Agent<Model = M, Action = A, Delta = D>>,
+}
+
+impl<M, A, D> BoxAgent<M, A, D> {
+    /// Creates a new type-erased agent.
+    pub fn new<T>(agent: T) -> Self
+        where T: Agent<Model = M, Action = A, Delta = D> + 'static
+    {
+        BoxAgent {agent: Box::new(agent)}
+    }
+}
+
+impl<M, A, D> Agent for BoxAgent<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {self.agent.decide()}
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AgentS, LayerConfig};
+
+    #[test]
+    fn mixed_collection() {
+        let z = crate::tests::four();
+        let config = LayerConfig {mutation_limit: 1, ..LayerConfig::default()};
+        let mut agents = vec![
+            BoxAgent::new(z.clone()),
+            BoxAgent::new(z.clone().add(2)),
+            BoxAgent::new(AgentS {core: z.add(0), config}),
+        ];
+        for agent in &mut agents {agent.update_model((4, 3))}
+        let decisions: Vec<_> = agents.iter_mut().map(|agent| agent.decide()).collect();
+        assert_eq!(decisions, vec![
+            Decision::Action(1),
+            Decision::RequestModel,
+            Decision::RequestModel,
+        ]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Request budgets with episode-level accounting.
+//!
+//! An agent that keeps requesting model updates might stall an episode forever.
+//! A `Budget` caps the number of model requests per episode.
+//! When the budget is exhausted, the fallback is used instead of requesting again.
+//!
+//! Call `Budget::reset` at the start of every episode.
+
+use std::fmt;
+use std::ptr::fn_addr_eq;
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores the behavior when the request budget is exhausted.
+pub enum Fallback<M, A> {
+    /// Act using a safe default policy.
+    SafeDefault(fn(&M) -> A),
+    /// Halt.
+    Halt,
+}
+
+impl<M, A> Clone for Fallback<M, A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A> Copy for Fallback<M, A> {}
+
+impl<M, A> fmt::Debug for Fallback<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fallback::SafeDefault(policy) => f.debug_tuple("SafeDefault").field(policy).finish(),
+            Fallback::Halt => f.write_str("Halt"),
+        }
+    }
+}
+
+impl<M, A> PartialEq for Fallback<M, A> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Fallback::SafeDefault(a), Fallback::SafeDefault(b)) => fn_addr_eq(*a, *b),
+            (Fallback::Halt, Fallback::Halt) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Stores an agent with a request budget.
+#[derive(Clone, Debug)]
+pub struct Budget<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The maximum number of model requests per episode.
+    pub limit: usize,
+    /// The behavior when the budget is exhausted.
+    pub fallback: Fallback<T::Model, T::Action>,
+    /// The number of model requests in this episode.
+    pub requests: usize,
+    /// The number of decisions in this episode that used the fallback.
+    pub fallbacks: usize,
+}
+
+impl<T: Agent> Budget<T> {
+    /// Creates a new agent with a request budget.
+    pub fn new(agent: T, limit: usize, fallback: Fallback<T::Model, T::Action>) -> Self {
+        Budget {agent, limit, fallback, requests: 0, fallbacks: 0}
+    }
+
+    /// Returns the number of model requests left in this episode.
+    pub fn remaining(&self) -> usize {self.limit.saturating_sub(self.requests)}
+
+    /// Returns `true` if the budget is exhausted.
+    pub fn is_exhausted(&self) -> bool {self.remaining() == 0}
+
+    /// Starts a new episode.
+    pub fn reset(&mut self) {
+        self.requests = 0;
+        self.fallbacks = 0;
+    }
+}
+
+impl<T: Inspect> Agent for Budget<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        match self.agent.decide() {
+            Decision::RequestModel if self.is_exhausted() => {
+                self.fallbacks += 1;
+                match self.fallback {
+                    Fallback::SafeDefault(policy) => Decision::Action(policy(self.agent.model())),
+                    Fallback::Halt => Decision::Halt,
+                }
+            }
+            Decision::RequestModel => {
+                self.requests += 1;
+                Decision::RequestModel
+            }
+            x => x,
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Budget<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhaust() {
+        let mut s = Budget::new(crate::tests::four().add(1), 1, Fallback::SafeDefault(|_| 0));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::Action(0));
+        assert_eq!((s.requests, s.fallbacks), (1, 1));
+        s.fallback = Fallback::Halt;
+        assert_eq!(s.decide(), Decision::Halt);
+        s.reset();
+        assert_eq!(s.remaining(), 1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
@@ -0,0 +1,300 @@
+//! Fluent construction of agents.
+//!
+//! An `AgentBuilder` collects the configuration of an agent,
+//! validates the combination and produces an `AgentN`.
+//!
+//! Typical setups start with `agent`:
+//!
+//! ```text
+//! let agent = agent(model).decide_with(decider).act_with(actor).mutate_with(mutater, undoer).layers(3).build()?;
+//! ```
+
+use std::fmt;
+
+use crate::delta::DeltaMeta;
+use crate::exhausted::Exhausted;
+use crate::rng::{Rng, Stochastic};
+use crate::{AgentN, AgentZ, Event, Incremental, LayerConfig};
+
+/// Stores an error when building an agent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The model is missing.
+    MissingModel,
+    /// The decider is missing.
+    MissingDecider,
+    /// The actor is missing.
+    MissingActor,
+    /// There are no mutaters.
+    MissingMutater,
+    /// The undoer is missing.
+    MissingUndoer,
+    /// A safety layer has a mutation limit of zero, so it can never act.
+    ZeroMutationLimit,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingModel => write!(f, "Missing model"),
+            BuildError::MissingDecider => write!(f, "Missing decider"),
+            BuildError::MissingActor => write!(f, "Missing actor"),
+            BuildError::MissingMutater => write!(f, "Missing mutater"),
+            BuildError::MissingUndoer => write!(f, "Missing undoer"),
+            BuildError::ZeroMutationLimit => write!(f, "Mutation limit of a safety layer is zero"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Collects the configuration of an agent.
+pub struct AgentBuilder<M, A, D> {
+    model: Option<M>,
+    decider: Option<fn(&M) -> A>,
+    actor: Option<fn(&mut M, A)>,
+    mutaters: Vec<fn(&mut M) -> D>,
+    targets: Vec<&'static str>,
+    undoer: Option<fn(&mut M, D)>,
+    layer: LayerConfig<A>,
+    observers: Vec<fn(&Event<A>)>,
+    incremental: Option<Incremental<M, D>>,
+    stochastic: Option<Stochastic<M, D>>,
+    dedup: Option<fn(&D) -> u64>,
+    describe: Option<fn(&D) -> DeltaMeta>,
+    voi: Option<fn(&M, &A, &A) -> bool>,
+    exhausted: Exhausted<M, A>,
+    layers: usize,
+}
+
+/// Creates a new builder with a model.
+pub fn agent<M, A, D>(model: M) -> AgentBuilder<M, A, D> {AgentBuilder::new().model(model)}
+
+impl<M, A, D> Default for AgentBuilder<M, A, D> {
+    fn default() -> Self {
+        AgentBuilder {
+            model: None,
+            decider: None,
+            actor: None,
+            mutaters: vec![],
+            targets: vec![],
+            undoer: None,
+            layer: LayerConfig::default(),
+            observers: vec![],
+            incremental: None,
+            stochastic: None,
+            dedup: None,
+            describe: None,
+            voi: None,
+            exhausted: Exhausted::Request,
+            layers: 0,
+        }
+    }
+}
+
+impl<M, A, D> AgentBuilder<M, A, D> {
+    /// Creates a new empty builder.
+    pub fn new() -> Self {Self::default()}
+
+    /// Sets the model.
+    pub fn model(mut self, model: M) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Sets the decider.
+    pub fn decider(mut self, decider: fn(&M) -> A) -> Self {
+        self.decider = Some(decider);
+        self
+    }
+
+    /// Sets the actor.
+    pub fn actor(mut self, actor: fn(&mut M, A)) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Adds a mutater.
+    ///
+    /// When there are several mutaters, probes cycle through them.
+    pub fn mutater(mut self, mutater: fn(&mut M) -> D) -> Self {
+        self.mutaters.push(mutater);
+        self
+    }
+
+    /// Adds a mutater that targets some named part of the model.
+    ///
+    /// Targets are used to ask targeted questions on disagreement.
+    pub fn mutater_targeting(mut self, target: &'static str, mutater: fn(&mut M) -> D) -> Self {
+        self.targets.resize(self.mutaters.len(), "");
+        self.targets.push(target);
+        self.mutater(mutater)
+    }
+
+    /// Sets a stochastic mutater, seeding its generator.
+    ///
+    /// The stochastic mutater is used for probing instead of other mutaters,
+    /// but a mutater is still required for core zero.
+    pub fn stochastic_mutater(mut self, seed: u64, mutater: fn(&mut M, &mut Rng) -> D) -> Self {
+        self.stochastic = Some(Stochastic::new(seed, mutater));
+        self
+    }
+
+    /// Skips duplicate probes within a decide call, using a fingerprint of deltas.
+    pub fn dedup(mut self, fingerprint: fn(&D) -> u64) -> Self {
+        self.dedup = Some(fingerprint);
+        self
+    }
+
+    /// Describes deltas in explanations and journals.
+    pub fn describe(mut self, describe: fn(&D) -> DeltaMeta) -> Self {
+        self.describe = Some(describe);
+        self
+    }
+
+    /// Sets the undoer.
+    pub fn undoer(mut self, undoer: fn(&mut M, D)) -> Self {
+        self.undoer = Some(undoer);
+        self
+    }
+
+    /// Sets the decider, same as `decider`.
+    pub fn decide_with(self, decider: fn(&M) -> A) -> Self {self.decider(decider)}
+
+    /// Sets the actor, same as `actor`.
+    pub fn act_with(self, actor: fn(&mut M, A)) -> Self {self.actor(actor)}
+
+    /// Adds a mutater and sets the undoer of its deltas.
+    pub fn mutate_with(self, mutater: fn(&mut M) -> D, undoer: fn(&mut M, D)) -> Self {
+        self.mutater(mutater).undoer(undoer)
+    }
+
+    /// Sets the comparator that decides whether two actions agree.
+    pub fn comparator(mut self, comparator: fn(&A, &A) -> bool) -> Self {
+        self.layer.comparator = Some(comparator);
+        self
+    }
+
+    /// Adds an observer.
+    pub fn observer(mut self, observer: fn(&Event<A>)) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Enables incremental deciding with dirty tracking.
+    pub fn incremental(mut self, reads: fn(&M) -> u64, touches: fn(&D) -> u64) -> Self {
+        self.incremental = Some(Incremental {reads, touches});
+        self
+    }
+
+    /// Sets the value-of-information estimate used when sub-agents disagree.
+    pub fn voi(mut self, voi: fn(&M, &A, &A) -> bool) -> Self {
+        self.voi = Some(voi);
+        self
+    }
+
+    /// Sets the behavior when no probe of the outermost safety layer determines a decision.
+    pub fn exhausted(mut self, exhausted: Exhausted<M, A>) -> Self {
+        self.exhausted = exhausted;
+        self
+    }
+
+    /// Sets the number of safety layers.
+    pub fn layers(mut self, layers: usize) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Sets the mutation limit of every safety layer.
+    pub fn mutation_limit(mut self, mutation_limit: u8) -> Self {
+        self.layer.mutation_limit = mutation_limit;
+        self
+    }
+
+    /// Probes every safety layer until a time budget is exhausted, instead of a mutation limit.
+    pub fn time_budget(mut self, budget: std::time::Duration) -> Self {
+        self.layer.time_budget = Some(budget);
+        self
+    }
+
+    /// Sets the configuration of every safety layer.
+    pub fn layer_config(mut self, config: LayerConfig<A>) -> Self {
+        self.layer = config;
+        self
+    }
+
+    /// Validates the configuration and builds the agent.
+    pub fn build(self) -> Result<AgentN<M, A, D>, BuildError> {
+        let model = self.model.ok_or(BuildError::MissingModel)?;
+        let decider = self.decider.ok_or(BuildError::MissingDecider)?;
+        let actor = self.actor.ok_or(BuildError::MissingActor)?;
+        let mutater = *self.mutaters.first().ok_or(BuildError::MissingMutater)?;
+        let undoer = self.undoer.ok_or(BuildError::MissingUndoer)?;
+        if self.layers > 0 && self.layer.mutation_limit == 0 {
+            return Err(BuildError::ZeroMutationLimit);
+        }
+
+        let mut agent = AgentZ {model, decider, actor, mutater, undoer}.add(self.layers);
+        agent.layers = vec![self.layer; self.layers];
+        if !self.targets.is_empty() {
+            let mut targets = self.targets;
+            targets.resize(self.mutaters.len(), "");
+            agent.targets = targets;
+        }
+        if self.mutaters.len() > 1 {agent.mutaters = self.mutaters}
+        agent.observers = self.observers;
+        agent.incremental = self.incremental;
+        agent.stochastic = self.stochastic;
+        agent.dedup = self.dedup;
+        agent.describe = self.describe;
+        agent.voi = self.voi;
+        agent.exhausted = self.exhausted;
+        Ok(agent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    fn builder() -> AgentBuilder<(u32, u32), i32, i32> {
+        let z = crate::tests::four();
+        AgentBuilder::new()
+            .model(z.model)
+            .decider(z.decider)
+            .actor(z.actor)
+            .mutater(z.mutater)
+            .undoer(z.undoer)
+    }
+
+    #[test]
+    fn build() {
+        let mut s = builder().layers(2).build().unwrap();
+        assert_eq!(s.layers(), 2);
+        assert_eq!(s.decide(), Decision::Action(1));
+
+        // Agreeing on approximately equal actions.
+        let mut s = builder().layers(1).comparator(|a, b| (a - b).abs() <= 1).build().unwrap();
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::Action(1));
+    }
+
+    #[test]
+    fn chain() {
+        let z = crate::tests::four();
+        let mut s = agent(z.model).decide_with(z.decider).act_with(z.actor).mutate_with(z.mutater, z.undoer)
+            .layers(1).build().unwrap();
+        assert_eq!(s, z.add(1));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(AgentBuilder::<(u32, u32), i32, i32>::new().build().err(),
+                   Some(BuildError::MissingModel));
+        assert_eq!(builder().layers(1).mutation_limit(0).build().err(),
+                   Some(BuildError::ZeroMutationLimit));
+    }
+}
@@ -0,0 +1,91 @@
+//! Capability-gated actions.
+//!
+//! Actions are tagged with required capabilities,
+//! while the agent holds a set of granted capabilities.
+//! A decision that requires a missing capability is downgraded to a model request,
+//! and the missing capability is recorded.
+//!
+//! This lets operators constrain agents by configuration rather than code changes.
+
+use std::collections::HashSet;
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an agent with capability-gated actions.
+#[derive(Clone, Debug)]
+pub struct Capabilities<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The granted capabilities.
+    pub granted: HashSet<String>,
+    /// Returns the capabilities required by an action.
+    pub requires: fn(&T::Action) -> &'static [&'static str],
+    /// The missing capability of the last decision.
+    pub missing: Option<&'static str>,
+}
+
+impl<T: Agent> Capabilities<T> {
+    /// Creates a new agent without granted capabilities.
+    pub fn new(agent: T, requires: fn(&T::Action) -> &'static [&'static str]) -> Self {
+        Capabilities {agent, granted: HashSet::new(), requires, missing: None}
+    }
+
+    /// Grants a capability.
+    pub fn grant(&mut self, capability: impl Into<String>) {
+        self.granted.insert(capability.into());
+    }
+
+    /// Revokes a capability.
+    pub fn revoke(&mut self, capability: &str) {
+        self.granted.remove(capability);
+    }
+}
+
+impl<T: Agent> Agent for Capabilities<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        self.missing = None;
+        match self.agent.decide() {
+            Decision::Action(a) => {
+                let granted = &self.granted;
+                match (self.requires)(&a).iter().find(|c| !granted.contains(**c)) {
+                    Some(c) => {
+                        self.missing = Some(c);
+                        Decision::RequestModel
+                    }
+                    None => Decision::Action(a),
+                }
+            }
+            Decision::RequestModel => Decision::RequestModel,
+            Decision::Halt => Decision::Halt,
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Capabilities<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_actions() {
+        let requires = |a: &i32| -> &'static [&'static str] {
+            if *a > 0 {&["increment"]} else {&[]}
+        };
+        let mut s = Capabilities::new(crate::tests::four().add(1), requires);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.missing, Some("increment"));
+        s.grant("increment");
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.missing, None);
+    }
+}
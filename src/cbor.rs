@@ -0,0 +1,165 @@
+//! A minimal CBOR encoder and decoder of JSON values, used by the binary formats of this library.
+//!
+//! Integral numbers are encoded as integers, and other numbers as 64-bit floats.
+//! Binary formats are defined by their JSON formats,
+//! such that every message has the same fields in both.
+//! Writers build a JSON value once and encode it as either format.
+
+use std::convert::{TryFrom, TryInto};
+
+use crate::json::{self, Value};
+
+fn write_head(out: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    if n < 24 {out.push(major | n as u8)}
+    else if n <= 0xff {out.extend_from_slice(&[major | 24, n as u8])}
+    else if n <= 0xffff {
+        out.push(major | 25);
+        out.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= 0xffff_ffff {
+        out.push(major | 26);
+        out.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_head(out, 3, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+pub fn encode(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        // Integers up to 2^53 are exact in 64-bit floats.
+        Value::Num(x) if x.fract() == 0.0 && x.abs() < 9007199254740992.0 => {
+            if *x >= 0.0 {write_head(out, 0, *x as u64)} else {write_head(out, 1, (-1.0 - x) as u64)}
+        }
+        Value::Num(x) => {
+            out.push(0xfb);
+            out.extend_from_slice(&x.to_bits().to_be_bytes());
+        }
+        Value::Str(s) => write_str(out, s),
+        Value::Arr(items) => {
+            write_head(out, 4, items.len() as u64);
+            for item in items {encode(item, out)}
+        }
+        Value::Obj(fields) => {
+            write_head(out, 5, fields.len() as u64);
+            for (key, value) in fields {
+                write_str(out, key);
+                encode(value, out);
+            }
+        }
+    }
+}
+
+fn bytes<'a>(src: &'a [u8], i: &mut usize, n: usize) -> Option<&'a [u8]> {
+    let end = i.checked_add(n).filter(|&end| end <= src.len())?;
+    let b = &src[*i..end];
+    *i = end;
+    Some(b)
+}
+
+fn read_head(src: &[u8], i: &mut usize) -> Option<(u8, u8, u64)> {
+    let b = *src.get(*i)?;
+    *i += 1;
+    let (major, info) = (b >> 5, b & 0x1f);
+    let n = match info {
+        0..=23 => info as u64,
+        24 => bytes(src, i, 1)?[0] as u64,
+        25 => u16::from_be_bytes(bytes(src, i, 2)?.try_into().ok()?) as u64,
+        26 => u32::from_be_bytes(bytes(src, i, 4)?.try_into().ok()?) as u64,
+        27 => u64::from_be_bytes(bytes(src, i, 8)?.try_into().ok()?),
+        _ => return None,
+    };
+    Some((major, info, n))
+}
+
+fn read_str(src: &[u8], i: &mut usize, n: u64) -> Option<String> {
+    let b = bytes(src, i, usize::try_from(n).ok()?)?;
+    String::from_utf8(b.to_vec()).ok()
+}
+
+fn decode_value(src: &[u8], i: &mut usize, depth: usize) -> Option<Value> {
+    let (major, info, n) = read_head(src, i)?;
+    // Lengths are bounded by the remaining input, since every item takes at least one byte.
+    let len = |n: u64| usize::try_from(n).ok().filter(|&n| n <= src.len() - *i);
+    Some(match major {
+        0 => Value::Num(n as f64),
+        1 => Value::Num(-1.0 - n as f64),
+        3 => Value::Str(read_str(src, i, n)?),
+        4 => {
+            let depth = depth.checked_sub(1)?;
+            let n = len(n)?;
+            let mut items = Vec::with_capacity(n);
+            for _ in 0..n {items.push(decode_value(src, i, depth)?)}
+            Value::Arr(items)
+        }
+        5 => {
+            let depth = depth.checked_sub(1)?;
+            let n = len(n)?;
+            let mut fields = Vec::with_capacity(n);
+            for _ in 0..n {
+                let key = match read_head(src, i)? {
+                    (3, _, k) => read_str(src, i, k)?,
+                    _ => return None,
+                };
+                fields.push((key, decode_value(src, i, depth)?));
+            }
+            Value::Obj(fields)
+        }
+        7 => match (info, n) {
+            (20, _) => Value::Bool(false),
+            (21, _) => Value::Bool(true),
+            (22, _) => Value::Null,
+            (26, n) => Value::Num(f32::from_bits(n as u32) as f64),
+            (27, n) => Value::Num(f64::from_bits(n)),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+// Limits the nesting depth like `json::parse_limited`.
+pub fn decode(src: &[u8], max_depth: usize) -> Option<Value> {
+    let mut i = 0;
+    let value = decode_value(src, &mut i, max_depth)?;
+    if i == src.len() {Some(value)} else {None}
+}
+
+/// Encodes a JSON value as CBOR.
+pub fn from_value(value: &Value) -> Vec<u8> {
+    let mut out = vec![];
+    encode(value, &mut out);
+    out
+}
+
+/// Decodes CBOR written by this library as JSON, to be read by the JSON readers of this library.
+pub fn to_json(src: &[u8]) -> Option<String> {to_json_limited(src, usize::MAX)}
+
+/// Decodes untrusted CBOR as JSON, rejecting values nested deeper than `max_depth`.
+pub fn to_json_limited(src: &[u8], max_depth: usize) -> Option<String> {
+    decode(src, max_depth).map(|value| json::write(&value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let src = r#"{"a":[0,23,24,-1,-500,2.5,70000,5000000000],"b":"x","c":[true,false,null]}"#;
+        let out = from_value(&json::parse(src).unwrap());
+        assert_eq!(&out[..4], &[0xa3, 0x61, b'a', 0x88]);
+        assert_eq!(json::write(&decode(&out, usize::MAX).unwrap()), src);
+        assert!(decode(&out[..out.len() - 1], usize::MAX).is_none());
+        assert!(decode(&out, 1).is_none());
+        // A huge length is rejected without allocating.
+        assert!(decode(&[0x9b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], usize::MAX).is_none());
+    }
+}
@@ -0,0 +1,272 @@
+//! Machine-checkable safety certificates per action.
+//!
+//! Unlike `certified::Certified`, which proves to actuation code that an action passed a decide,
+//! a `Certificate` is meant for auditors.
+//! It records the fingerprint of the model, every mutation probed in every safety layer,
+//! the outcome of its decision relative to the action of core zero, and the rule that determined the action.
+//!
+//! Certificates are stored as JSON, where actions are encoded as strings by user-supplied codecs,
+//! and the rationale is written by `Rationale::to_json`:
+//!
+//! ```text
+//! {"fingerprint":"4","layers":1,"action":"1","rationale":{"layer":1,"reason":["agree",1,0],
+//!  "checks":[{"probe":0,"second":null,"outcome":"agree","inner":null}]}}
+//! ```
+//!
+//! With the `cbor` feature, certificates can also be stored in a compact binary encoding.
+//!
+//! The fingerprint is computed by a user-supplied function,
+//! such that it is stable across processes and can be recomputed by an auditor.
+//!
+//! `verify_certificate` re-executes the recorded checks on the stated model,
+//! using an agent with the same configuration, such that an auditor can confirm after the fact
+//! that the action really passed the claimed checks.
+//! Stochastic mutaters must use a schedule for the probes to be reproducible.
+
+use crate::rationale::Rationale;
+use crate::{json, Agent, AgentN, Decision, Error, Inspect};
+
+/// Stores a certificate of an action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Certificate<A> {
+    /// The fingerprint of the model that the action was decided for.
+    pub fingerprint: u64,
+    /// The number of safety layers.
+    pub layers: usize,
+    /// The action.
+    pub action: A,
+    /// The probes of each safety layer and the rule that determined the action.
+    pub rationale: Rationale,
+}
+
+impl<A> Certificate<A> {
+    /// Returns the certificate as JSON, encoding the action as a string.
+    pub fn to_json(&self, encode: fn(&A) -> String) -> String {json::write(&self.to_value(encode))}
+
+    /// Returns the certificate as CBOR, encoding the action as a string.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self, encode: fn(&A) -> String) -> Vec<u8> {crate::cbor::from_value(&self.to_value(encode))}
+
+    fn to_value(&self, encode: fn(&A) -> String) -> json::Value {
+        json::Value::Obj(vec![
+            ("fingerprint".into(), json::Value::Str(self.fingerprint.to_string())),
+            ("layers".into(), json::Value::Num(self.layers as f64)),
+            ("action".into(), json::Value::Str(encode(&self.action))),
+            ("rationale".into(), self.rationale.to_value()),
+        ])
+    }
+
+    /// Parses a certificate from CBOR, decoding the action from a string.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(src: &[u8], decode: fn(&str) -> Option<A>) -> Result<Certificate<A>, Error> {
+        let src = crate::cbor::to_json(src).ok_or_else(|| Error::Protocol("Invalid certificate: Expected CBOR".into()))?;
+        Certificate::from_json(&src, decode)
+    }
+
+    /// Parses a certificate from JSON, decoding the action from a string.
+    pub fn from_json(src: &str, decode: fn(&str) -> Option<A>) -> Result<Certificate<A>, Error> {
+        let err = |msg: &str| Error::Protocol(format!("Invalid certificate: {}", msg));
+        let value = json::parse(src).ok_or_else(|| err("Expected JSON"))?;
+        let fingerprint = value.get("fingerprint").and_then(|v| v.str()).and_then(|s| s.parse().ok())
+            .ok_or_else(|| err("Expected fingerprint"))?;
+        let layers = value.get("layers").and_then(|v| v.num()).filter(|x| *x >= 0.0 && x.fract() == 0.0)
+            .ok_or_else(|| err("Expected layers"))? as usize;
+        let action = value.get("action").and_then(|v| v.str()).and_then(decode)
+            .ok_or_else(|| err("Expected action"))?;
+        let rationale = value.get("rationale").and_then(Rationale::from_value)
+            .ok_or_else(|| err("Expected rationale"))?;
+        Ok(Certificate {fingerprint, layers, action, rationale})
+    }
+}
+
+impl<M, A: Clone + PartialEq, D> AgentN<M, A, D> {
+    /// Decide what to do next, together with a certificate when acting.
+    pub fn decide_certificate(&mut self, fingerprint: fn(&M) -> u64) -> (Decision<A>, Option<Certificate<A>>) {
+        let model = fingerprint(&self.z.model);
+        let (decision, rationale) = self.decide_rationale();
+        let certificate = match &decision {
+            Decision::Action(a) => Some(Certificate {
+                fingerprint: model,
+                layers: self.layers(),
+                action: a.clone(),
+                rationale,
+            }),
+            _ => None,
+        };
+        (decision, certificate)
+    }
+}
+
+/// Stores the result of verifying a certificate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VerificationResult<A> {
+    /// The recorded checks were reproduced.
+    Verified,
+    /// The fingerprint of the stated model differs from the certificate.
+    Fingerprint {
+        /// The fingerprint of the stated model.
+        actual: u64,
+    },
+    /// The number of safety layers of the configuration differs from the certificate.
+    Layers {
+        /// The number of safety layers of the configuration.
+        actual: usize,
+    },
+    /// The configuration did not decide the certified action.
+    Action {
+        /// The decision of the configuration.
+        actual: Decision<A>,
+    },
+    /// The configuration decided the action, but the probes or the rule differ.
+    Checks {
+        /// The rationale of the configuration.
+        actual: Rationale,
+    },
+}
+
+impl<A> VerificationResult<A> {
+    /// Returns `true` if the certificate was verified.
+    pub fn is_verified(&self) -> bool {matches!(self, VerificationResult::Verified)}
+}
+
+/// Verifies a certificate by re-executing its checks on a model,
+/// using an agent with the configuration that decided the action.
+///
+/// The agent is not changed. Its history of warm starts, coverage, latency and provenance is not used,
+/// such that verifying gives the same result as for an auditor with a fresh configuration.
+pub fn verify_certificate<M: Clone, A: Clone + PartialEq, D>(
+    cert: &Certificate<A>,
+    model: &M,
+    agent: &AgentN<M, A, D>,
+    fingerprint: fn(&M) -> u64,
+) -> VerificationResult<A> {
+    let actual = fingerprint(model);
+    if actual != cert.fingerprint {return VerificationResult::Fingerprint {actual}}
+    if agent.layers() != cert.layers {return VerificationResult::Layers {actual: agent.layers()}}
+    let mut agent = agent.clone();
+    agent.warm = None;
+    agent.coverage = None;
+    agent.latency = None;
+    agent.provenance = None;
+    agent.update_model(model.clone());
+    match agent.decide_rationale() {
+        (Decision::Action(a), rationale) if a == cert.action => {
+            if rationale == cert.rationale {VerificationResult::Verified}
+            else {VerificationResult::Checks {actual: rationale}}
+        }
+        (actual, _) => VerificationResult::Action {actual},
+    }
+}
+
+/// Stores an agent that emits a certificate for every action.
+#[derive(Clone, Debug)]
+pub struct Certifying<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// Returns the fingerprint of a model.
+    pub fingerprint: fn(&M) -> u64,
+    /// The certificates of the decided actions, in order.
+    pub certificates: Vec<Certificate<A>>,
+}
+
+impl<M, A, D> Certifying<M, A, D> {
+    /// Creates a new agent emitting certificates.
+    pub fn new(agent: AgentN<M, A, D>, fingerprint: fn(&M) -> u64) -> Self {
+        Certifying {agent, fingerprint, certificates: vec![]}
+    }
+}
+
+impl<M, A: Clone + PartialEq, D> Agent for Certifying<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        let (decision, certificate) = self.agent.decide_certificate(self.fingerprint);
+        self.certificates.extend(certificate);
+        decision
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: Clone + PartialEq, D> Inspect for Certifying<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reason;
+
+    fn fingerprint(m: &(u32, u32)) -> u64 {(m.0 as u64) << 32 | m.1 as u64}
+
+    #[test]
+    fn round_trip() {
+        let mut s = Certifying::new(crate::tests::four().add(2), fingerprint);
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.certificates.len(), 1);
+        let cert = &s.certificates[0];
+        assert_eq!((cert.fingerprint, cert.rationale.reason), (4 << 32, Reason::Agree {layer: 2, probe: 0}));
+        let json = cert.to_json(|a| a.to_string());
+        assert!(json.starts_with("{\"fingerprint\":\"17179869184\",\"layers\":2,\"action\":\"1\""));
+        assert_eq!(&Certificate::from_json(&json, |s| s.parse().ok()).unwrap(), cert);
+        assert!(Certificate::<i32>::from_json(&json.replace("agree", "maybe"), |s| s.parse().ok()).is_err());
+        #[cfg(feature = "cbor")]
+        assert_eq!(&Certificate::from_cbor(&cert.to_cbor(|a| a.to_string()), |s| s.parse().ok()).unwrap(), cert);
+    }
+
+    #[test]
+    fn deep() {
+        // Rationales nest with the number of safety layers.
+        let mut s = crate::tests::four().add(32);
+        s.update_model((40, 0));
+        let cert = s.decide_certificate(fingerprint).1.unwrap();
+        let json = cert.to_json(|a| a.to_string());
+        assert_eq!(Certificate::from_json(&json, |s| s.parse().ok()).as_ref(), Ok(&cert));
+        #[cfg(feature = "cbor")]
+        assert_eq!(Certificate::from_cbor(&cert.to_cbor(|a| a.to_string()), |s| s.parse().ok()).as_ref(), Ok(&cert));
+    }
+
+    #[test]
+    fn audit() {
+        let mut s = crate::tests::four().add(1);
+        let cert = s.decide_certificate(fingerprint).1.unwrap();
+        let config = crate::tests::four().add(1);
+        assert!(verify_certificate(&cert, &(4, 0), &config, fingerprint).is_verified());
+        assert_eq!(verify_certificate(&cert, &(4, 3), &config, fingerprint),
+                   VerificationResult::Fingerprint {actual: 4 << 32 | 3});
+        assert_eq!(verify_certificate(&cert, &(4, 0), &crate::tests::four().add(2), fingerprint),
+                   VerificationResult::Layers {actual: 2});
+        // A certificate claiming a different action does not pass.
+        let forged = Certificate {action: -1, ..cert.clone()};
+        assert_eq!(verify_certificate(&forged, &(4, 0), &config, fingerprint),
+                   VerificationResult::Action {actual: Decision::Action(1)});
+        // A certificate claiming more checks than were run does not pass.
+        let mut forged = cert;
+        forged.rationale.checks.push(forged.rationale.checks[0].clone());
+        assert!(matches!(verify_certificate(&forged, &(4, 0), &config, fingerprint),
+                         VerificationResult::Checks {..}));
+    }
+
+    #[test]
+    fn history() {
+        fn config() -> AgentN<(u32, u32), i32, i32> {
+            let mut s = crate::tests::four().add(2);
+            s.mutaters = vec![|m| {m.0 -= 1; -1}, |m| {m.0 -= 2; -2}];
+            s.warm = Some(crate::warm::WarmStart::new(fingerprint));
+            s.coverage = Some(crate::coverage::Coverage::new(|m: &(u32, u32)| m.0 as u64));
+            s
+        }
+        let mut s = config();
+        s.decide();
+        s.decide();
+        let cert = s.decide_certificate(fingerprint).1.unwrap();
+        // An auditor has none of the history of the agent.
+        assert!(verify_certificate(&cert, &(4, 0), &config(), fingerprint).is_verified());
+        assert!(verify_certificate(&cert, &(4, 0), &s, fingerprint).is_verified());
+    }
+}
@@ -0,0 +1,86 @@
+//! Certified actions with provenance.
+//!
+//! A `Certified` action can only be constructed inside the layered decide path.
+//! It carries the number of safety layers and probes that approved it,
+//! so actuation code can statically require certified actions.
+
+use crate::{AgentN, Decision, Event, SafetyReport};
+
+/// Stores an action that was approved by safety layers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Certified<A> {
+    action: A,
+    layers: usize,
+    approvals: u32,
+    probes: u32,
+}
+
+impl<A> Certified<A> {
+    /// Returns the action.
+    pub fn action(&self) -> &A {&self.action}
+
+    /// Returns the number of safety layers that approved the action.
+    pub fn layers(&self) -> usize {self.layers}
+
+    /// Returns the number of probes that agreed with core zero.
+    pub fn approvals(&self) -> u32 {self.approvals}
+
+    /// Returns the total number of probes.
+    pub fn probes(&self) -> u32 {self.probes}
+
+    /// Returns the action.
+    pub fn into_inner(self) -> A {self.action}
+}
+
+impl<M, A, D> AgentN<M, A, D>
+    where A: PartialEq
+{
+    /// Decide what to do next, certifying actions.
+    pub fn decide_certified(&mut self) -> Decision<Certified<A>> {
+        if self.decide_start().is_some() {return Decision::RequestModel}
+        let layers = self.layers();
+        let mut tally = SafetyReport::default();
+        let decision = self.decide_n(layers, layers, &mut tally).0;
+        self.report = tally;
+        self.observe(Event::Decide {layers, decision: &decision});
+        match decision {
+            Decision::Action(action) => Decision::Action(Certified {
+                action,
+                layers,
+                approvals: tally.approvals,
+                probes: tally.probes,
+            }),
+            Decision::RequestModel => Decision::RequestModel,
+            Decision::Halt => Decision::Halt,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provenance() {
+        let mut s = crate::tests::four().add(2);
+        let cert = match s.decide_certified() {Decision::Action(c) => c, _ => panic!()};
+        assert_eq!(*cert.action(), 1);
+        assert_eq!(cert.layers(), 2);
+        assert_eq!(cert.approvals(), 2);
+        assert_eq!(cert.probes(), 2);
+    }
+
+    #[test]
+    fn clock() {
+        use crate::Agent;
+        use std::time::Duration;
+
+        // A stale start of an earlier decide call would trim every probe.
+        let mut s = crate::tests::four().add(2);
+        s.latency = Some(crate::latency::Latency::budget(Duration::from_millis(50)));
+        assert_eq!(s.decide(), Decision::Action(1));
+        std::thread::sleep(Duration::from_millis(60));
+        let cert = match s.decide_certified() {Decision::Action(c) => c, _ => panic!()};
+        assert_eq!((cert.probes(), s.report.trimmed), (2, 0));
+    }
+}
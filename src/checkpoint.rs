@@ -0,0 +1,307 @@
+//! Checkpoint and restore of agent state.
+//!
+//! A long-running agent can save a `Checkpoint` of its state,
+//! and restore it after the process restarts.
+//! The checkpoint contains the model, the configuration of each safety layer,
+//! the request budget and the metrics, when the agent has them.
+//!
+//! Models are encoded as strings by user-supplied codecs,
+//! and checkpoints are stored as JSON, e.g. in a file.
+//! Functions, such as comparators, are not stored,
+//! and are kept from the agent that the checkpoint is restored into.
+//!
+//! Requires the `checkpoint` feature.
+
+use std::time::Duration;
+
+use crate::budget::Budget;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Metered, Metrics, BUCKETS};
+use crate::{json, Agent, AgentN, Agreement, Error, Inspect, LayerConfig, SafetyReport};
+
+/// The version of the checkpoint format.
+pub const VERSION: u32 = 1;
+
+/// Stores the configuration of a safety layer, without functions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerState {
+    /// Limits number of orthogonal mutations.
+    pub mutation_limit: u8,
+    /// The rule for agreement between sub-agents.
+    pub agreement: Agreement,
+    /// The maximum entropy of probe outcomes in this layer for acting.
+    pub max_entropy: Option<f64>,
+    /// Whether to also probe pairs of mutations before acting.
+    pub second_order: bool,
+    /// The time budget of probing, if any.
+    pub time_budget: Option<Duration>,
+}
+
+/// Stores the state of a request budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BudgetState {
+    /// The maximum number of model requests per episode.
+    pub limit: usize,
+    /// The number of model requests in this episode.
+    pub requests: usize,
+    /// The number of decisions in this episode that used the fallback.
+    pub fallbacks: usize,
+}
+
+/// Stores a checkpoint of agent state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Checkpoint {
+    /// The encoded model.
+    pub model: String,
+    /// The configuration of each safety layer, from innermost to outermost.
+    pub layers: Vec<LayerState>,
+    /// Whether the core was replaced without receiving a model update since.
+    pub handoff: bool,
+    /// The safety report of the last decide call.
+    pub report: SafetyReport,
+    /// The request budget, if any.
+    pub budget: Option<BudgetState>,
+    /// The metrics, if any.
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<Metrics>,
+}
+
+impl Checkpoint {
+    /// Returns the checkpoint as JSON.
+    pub fn to_json(&self) -> String {
+        let layers: Vec<String> = self.layers.iter().map(|layer| format!(
+            "{{\"mutation_limit\":{},\"agreement\":\"{}\",\"max_entropy\":{},\"second_order\":{},\"time_budget\":{}}}",
+            layer.mutation_limit,
+            match layer.agreement {Agreement::First => "first", Agreement::All => "all"},
+            layer.max_entropy.map(|x| x.to_string()).unwrap_or_else(|| "null".into()),
+            layer.second_order,
+            layer.time_budget.map(|x| x.as_secs_f64().to_string()).unwrap_or_else(|| "null".into()),
+        )).collect();
+        let r = &self.report;
+        let budget = self.budget.map(|b| format!(
+            "{{\"limit\":{},\"requests\":{},\"fallbacks\":{}}}", b.limit, b.requests, b.fallbacks
+        )).unwrap_or_else(|| "null".into());
+        #[cfg(feature = "metrics")]
+        let metrics = self.metrics.map(|m| format!(
+            ",\"metrics\":{{\"decides\":{},\"requests\":{},\"disagreements\":{},\"buckets\":[{}],\"seconds\":{}}}",
+            m.decides, m.requests, m.disagreements,
+            m.buckets.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(","), m.seconds
+        )).unwrap_or_default();
+        #[cfg(not(feature = "metrics"))]
+        let metrics = "";
+        format!("{{\"version\":{},\"model\":{},\"layers\":[{}],\"handoff\":{},\
+                 \"report\":[{},{},{},{}],\"budget\":{}{}}}",
+                VERSION, json::string(&self.model), layers.join(","), self.handoff,
+                r.probes, r.approvals, r.disagreements, r.requests, budget, metrics)
+    }
+
+    /// Parses a checkpoint from JSON.
+    pub fn from_json(src: &str) -> Result<Checkpoint, Error> {
+        let err = |msg: &str| Error::Protocol(format!("Invalid checkpoint: {}", msg));
+        let value = json::parse(src).ok_or_else(|| err("Expected JSON"))?;
+        let version = value.get("version").and_then(|v| v.num()).ok_or_else(|| err("Expected version"))?;
+        if version > VERSION as f64 {
+            return Err(err(&format!("Unsupported version {}", version)));
+        }
+        let num = |v: &json::Value, key: &str| v.get(key).and_then(|x| x.num())
+            .filter(|x| *x >= 0.0 && x.fract() == 0.0)
+            .ok_or_else(|| err(&format!("Expected {}", key)));
+        let flag = |v: &json::Value, key: &str| v.get(key).and_then(|x| x.bool())
+            .ok_or_else(|| err(&format!("Expected {}", key)));
+        let model = value.get("model").and_then(|v| v.str()).ok_or_else(|| err("Expected model"))?.into();
+        let layers = value.get("layers").and_then(|v| v.array()).ok_or_else(|| err("Expected layers"))?;
+        let layers = layers.iter().map(|layer| Ok(LayerState {
+            mutation_limit: num(layer, "mutation_limit")?.min(u8::MAX as f64) as u8,
+            agreement: match layer.get("agreement").and_then(|v| v.str()) {
+                Some("first") => Agreement::First,
+                Some("all") => Agreement::All,
+                _ => return Err(err("Expected agreement")),
+            },
+            max_entropy: layer.get("max_entropy").and_then(|v| v.num()),
+            second_order: flag(layer, "second_order")?,
+            // Checkpoints without time budgets are still valid.
+            time_budget: layer.get("time_budget").and_then(|v| v.num())
+                .filter(|x| *x >= 0.0).map(Duration::from_secs_f64),
+        })).collect::<Result<_, Error>>()?;
+        let report = value.get("report").and_then(|v| v.array())
+            .filter(|r| r.len() == 4)
+            .and_then(|r| r.iter().map(|x| x.num().map(|x| x as u32)).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| err("Expected report"))?;
+        let budget = match value.get("budget") {
+            Some(b @ json::Value::Obj(_)) => Some(BudgetState {
+                limit: num(b, "limit")? as usize,
+                requests: num(b, "requests")? as usize,
+                fallbacks: num(b, "fallbacks")? as usize,
+            }),
+            _ => None,
+        };
+        #[cfg(feature = "metrics")]
+        let metrics = match value.get("metrics") {
+            Some(m @ json::Value::Obj(_)) => {
+                let mut buckets = [0; BUCKETS.len() + 1];
+                let values = m.get("buckets").and_then(|v| v.array())
+                    .filter(|b| b.len() == buckets.len()).ok_or_else(|| err("Expected buckets"))?;
+                for (n, v) in buckets.iter_mut().zip(values) {
+                    *n = v.num().ok_or_else(|| err("Expected buckets"))? as u64;
+                }
+                Some(Metrics {
+                    decides: num(m, "decides")? as u64,
+                    requests: num(m, "requests")? as u64,
+                    disagreements: num(m, "disagreements")? as u64,
+                    buckets,
+                    seconds: m.get("seconds").and_then(|v| v.num()).ok_or_else(|| err("Expected seconds"))?,
+                })
+            }
+            _ => None,
+        };
+        Ok(Checkpoint {
+            model,
+            layers,
+            handoff: flag(&value, "handoff")?,
+            report: SafetyReport {
+                probes: report[0],
+                approvals: report[1],
+                disagreements: report[2],
+                requests: report[3],
+                ..SafetyReport::default()
+            },
+            budget,
+            #[cfg(feature = "metrics")]
+            metrics,
+        })
+    }
+}
+
+/// Implemented by agents that can be checkpointed.
+pub trait Checkpointed: Agent {
+    /// Saves a checkpoint of the agent state.
+    fn save_checkpoint(&self, encode: fn(&Self::Model) -> String) -> Checkpoint;
+    /// Restores the agent state from a checkpoint.
+    ///
+    /// Returns an error when the model can not be decoded, leaving the agent unchanged.
+    fn restore_checkpoint(
+        &mut self,
+        checkpoint: &Checkpoint,
+        decode: fn(&str) -> Option<Self::Model>,
+    ) -> Result<(), Error>;
+}
+
+impl<M, A: PartialEq, D> Checkpointed for AgentN<M, A, D> {
+    fn save_checkpoint(&self, encode: fn(&M) -> String) -> Checkpoint {
+        Checkpoint {
+            model: encode(self.model()),
+            layers: self.layers.iter().map(|layer| LayerState {
+                mutation_limit: layer.mutation_limit,
+                agreement: layer.agreement,
+                max_entropy: layer.max_entropy,
+                second_order: layer.second_order,
+                time_budget: layer.time_budget,
+            }).collect(),
+            handoff: self.handoff,
+            report: self.report,
+            budget: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint, decode: fn(&str) -> Option<M>) -> Result<(), Error> {
+        let model = decode(&checkpoint.model).ok_or_else(|| Error::Protocol("Invalid checkpoint model".into()))?;
+        self.z.model = model;
+        self.layers.resize(checkpoint.layers.len(), LayerConfig::default());
+        for (layer, state) in self.layers.iter_mut().zip(&checkpoint.layers) {
+            layer.mutation_limit = state.mutation_limit;
+            layer.agreement = state.agreement;
+            layer.max_entropy = state.max_entropy;
+            layer.second_order = state.second_order;
+            layer.time_budget = state.time_budget;
+        }
+        self.handoff = checkpoint.handoff;
+        self.report = checkpoint.report;
+        Ok(())
+    }
+}
+
+impl<T: Checkpointed + Inspect> Checkpointed for Budget<T> {
+    fn save_checkpoint(&self, encode: fn(&T::Model) -> String) -> Checkpoint {
+        let mut checkpoint = self.agent.save_checkpoint(encode);
+        checkpoint.budget = Some(BudgetState {limit: self.limit, requests: self.requests, fallbacks: self.fallbacks});
+        checkpoint
+    }
+
+    fn restore_checkpoint(
+        &mut self,
+        checkpoint: &Checkpoint,
+        decode: fn(&str) -> Option<T::Model>,
+    ) -> Result<(), Error> {
+        self.agent.restore_checkpoint(checkpoint, decode)?;
+        if let Some(budget) = checkpoint.budget {
+            self.limit = budget.limit;
+            self.requests = budget.requests;
+            self.fallbacks = budget.fallbacks;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<M, A: PartialEq, D> Checkpointed for Metered<M, A, D> {
+    fn save_checkpoint(&self, encode: fn(&M) -> String) -> Checkpoint {
+        let mut checkpoint = self.agent.save_checkpoint(encode);
+        checkpoint.metrics = Some(self.metrics);
+        checkpoint
+    }
+
+    fn restore_checkpoint(&mut self, checkpoint: &Checkpoint, decode: fn(&str) -> Option<M>) -> Result<(), Error> {
+        self.agent.restore_checkpoint(checkpoint, decode)?;
+        if let Some(metrics) = checkpoint.metrics {self.metrics = metrics}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::Fallback;
+    use crate::Decision;
+
+    fn encode(m: &(u32, u32)) -> String {format!("{} {}", m.0, m.1)}
+
+    fn decode(s: &str) -> Option<(u32, u32)> {
+        let (a, b) = s.split_once(' ')?;
+        Some((a.parse().ok()?, b.parse().ok()?))
+    }
+
+    #[test]
+    fn restart() {
+        let mut s = Budget::new(crate::tests::four().add(2), 3, Fallback::Halt);
+        s.agent.layers[1].max_entropy = Some(0.5);
+        s.agent.layers[1].agreement = Agreement::All;
+        s.agent.layers[0].time_budget = Some(Duration::from_millis(20));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        let json = s.save_checkpoint(encode).to_json();
+
+        let mut t = Budget::new(crate::tests::four().add(1), 1, Fallback::Halt);
+        t.restore_checkpoint(&Checkpoint::from_json(&json).unwrap(), decode).unwrap();
+        assert_eq!(t.agent, s.agent);
+        assert_eq!((t.limit, t.requests, t.fallbacks), (3, 1, 0));
+        assert_eq!(t.save_checkpoint(encode).to_json(), json);
+
+        let mut bad = Checkpoint::from_json(&json).unwrap();
+        bad.model = "four".into();
+        assert!(t.restore_checkpoint(&bad, decode).is_err());
+        assert!(Checkpoint::from_json(&json.replace("\"version\":1", "\"version\":2")).is_err());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metrics() {
+        let mut s = Metered::new(crate::tests::four().add(1));
+        s.decide();
+        let json = s.save_checkpoint(encode).to_json();
+        let mut t = Metered::new(crate::tests::four().add(1));
+        t.restore_checkpoint(&Checkpoint::from_json(&json).unwrap(), decode).unwrap();
+        assert_eq!(t.metrics, s.metrics);
+    }
+}
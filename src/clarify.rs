@@ -0,0 +1,205 @@
+//! Typed clarification dialogue between agent and oracle.
+//!
+//! A `query::ModelQuery` names the part of the model that caused a model request.
+//! A `Clarification` also says what kind of answer is expected,
+//! such that oracles can answer with a typed `Response` instead of a full model:
+//!
+//! - `Confirm`: whether the model is still current, for model requests without a disagreement
+//! - `Refine`: the value of a field, when a mutation of it disagreed
+//! - `Range`: bounds of a field, when a pair of mutations disagreed,
+//!   where the field is the target of the first mutation
+//!
+//! Fields are numeric parts of the model named by paths, e.g. `"goal"` or `"arm.angle"`,
+//! which are the targets of mutaters, see `AgentN::targets`.
+//! A `Fields` table maps paths to accessors, and applies responses to the model as patches.
+//! A range is applied by clamping the current value into it.
+
+use std::fmt;
+
+use crate::{AgentN, Error, Reason};
+
+/// Stores the kind of a question.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// Is the model still current?
+    Confirm,
+    /// What is the value of the field?
+    Refine,
+    /// Within which bounds is the value of the field?
+    Range,
+}
+
+/// Stores a typed question about the model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Clarification {
+    /// The path of the field, or `None` for the whole model.
+    pub path: Option<&'static str>,
+    /// The kind of question.
+    pub kind: Kind,
+}
+
+impl fmt::Display for Clarification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.path) {
+            (Kind::Confirm, Some(path)) => write!(f, "is `{}` still current?", path),
+            (Kind::Confirm, None) => write!(f, "is the model still current?"),
+            (Kind::Refine, Some(path)) => write!(f, "what is the value of `{}`?", path),
+            (Kind::Range, Some(path)) => write!(f, "within which bounds is `{}`?", path),
+            (_, None) => write!(f, "what is the model?"),
+        }
+    }
+}
+
+/// Stores a typed answer to a clarification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Response {
+    /// Whether the model is still current.
+    Confirm(bool),
+    /// The value of the field.
+    Value(f64),
+    /// The bounds of the field.
+    Range(f64, f64),
+}
+
+/// Stores a numeric field of a model.
+pub struct Field<M> {
+    /// The path of the field.
+    pub path: &'static str,
+    /// Returns the value of the field.
+    pub get: fn(&M) -> f64,
+    /// Sets the value of the field.
+    pub set: fn(&mut M, f64),
+}
+
+impl<M> Clone for Field<M> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M> Copy for Field<M> {}
+
+impl<M> fmt::Debug for Field<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field").field("path", &self.path).finish()
+    }
+}
+
+/// Stores the numeric fields of a model by path.
+#[derive(Debug)]
+pub struct Fields<M> {
+    /// The fields.
+    pub fields: Vec<Field<M>>,
+}
+
+impl<M> Clone for Fields<M> {
+    fn clone(&self) -> Self {Fields {fields: self.fields.clone()}}
+}
+
+impl<M> Default for Fields<M> {
+    fn default() -> Self {Fields {fields: vec![]}}
+}
+
+impl<M> Fields<M> {
+    /// Creates a new empty table of fields.
+    pub fn new() -> Self {Fields::default()}
+
+    /// Adds a field.
+    pub fn field(mut self, path: &'static str, get: fn(&M) -> f64, set: fn(&mut M, f64)) -> Self {
+        self.fields.push(Field {path, get, set});
+        self
+    }
+
+    /// Returns the field with some path.
+    pub fn get(&self, path: &str) -> Option<&Field<M>> {self.fields.iter().find(|f| f.path == path)}
+
+    /// Applies a response to a clarification, returning `true` if the model changed.
+    ///
+    /// Returns an error if the response does not have the expected type,
+    /// or the field is unknown.
+    pub fn apply(&self, model: &mut M, question: &Clarification, response: Response) -> Result<bool, Error> {
+        let invalid = |msg: String| Err(Error::Protocol(format!("Invalid response to `{}`: {}", question, msg)));
+        let field = match (question.kind, response) {
+            (Kind::Confirm, Response::Confirm(_)) => return Ok(false),
+            (Kind::Refine, Response::Value(_)) | (Kind::Range, Response::Range(..)) |
+            (Kind::Range, Response::Value(_)) => match question.path.map(|path| (path, self.get(path))) {
+                Some((_, Some(field))) => field,
+                Some((path, None)) => return invalid(format!("Unknown field `{}`", path)),
+                None => return invalid("Expected field".into()),
+            },
+            (_, response) => return invalid(format!("Unexpected {:?}", response)),
+        };
+        let old = (field.get)(model);
+        let new = match response {
+            Response::Range(lo, hi) if lo <= hi => old.max(lo).min(hi),
+            Response::Range(lo, hi) => return invalid(format!("Empty range {}..{}", lo, hi)),
+            Response::Value(x) => x,
+            Response::Confirm(_) => old,
+        };
+        (field.set)(model, new);
+        Ok(new != old)
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Returns a typed question for the reason of a model request.
+    ///
+    /// Returns `None` when the reason is not a model request.
+    pub fn clarify(&self, reason: Reason) -> Option<Clarification> {
+        let target = |probe: u8| self.targets.get(self.mutater_of(probe)).cloned().filter(|t| !t.is_empty());
+        Some(match reason {
+            Reason::Disagree {probe, ..} => match target(probe) {
+                Some(path) => Clarification {path: Some(path), kind: Kind::Refine},
+                None => Clarification {path: None, kind: Kind::Confirm},
+            },
+            Reason::DisagreePair {probes: (i, _), ..} => match target(i) {
+                Some(path) => Clarification {path: Some(path), kind: Kind::Range},
+                None => Clarification {path: None, kind: Kind::Confirm},
+            },
+            Reason::CoreRequest | Reason::Undetermined {..} | Reason::Handoff |
+            Reason::Divided {..} | Reason::Policy {..} | Reason::Tie =>
+                Clarification {path: None, kind: Kind::Confirm},
+            Reason::Core | Reason::Agree {..} | Reason::AllAgree {..} |
+            Reason::Waived {..} | Reason::LowPriority {..} => return None,
+        })
+    }
+
+    /// Answers a clarification with a typed response, applied as a patch of the model.
+    ///
+    /// Returns `true` if the model changed.
+    pub fn answer_clarification(
+        &mut self,
+        fields: &Fields<M>,
+        question: &Clarification,
+        response: Response
+    ) -> Result<bool, Error> {
+        let changed = fields.apply(&mut self.z.model, question, response)?;
+        self.handoff = false;
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn dialogue() {
+        let mut s = crate::tests::four().add(1);
+        s.targets = vec!["goal"];
+        s.z.model = (4, 3);
+        let diagnosis = s.diagnose();
+        let question = s.clarify(diagnosis.reason).unwrap();
+        assert_eq!(question, Clarification {path: Some("goal"), kind: Kind::Refine});
+        assert_eq!(question.to_string(), "what is the value of `goal`?");
+
+        let fields = Fields::new().field("goal", |m: &(u32, u32)| m.0 as f64, |m, x| m.0 = x as u32);
+        assert!(s.answer_clarification(&fields, &question, Response::Confirm(true)).is_err());
+        assert_eq!(s.answer_clarification(&fields, &question, Response::Value(5.0)), Ok(true));
+        assert_eq!(s.decide(), Decision::Action(1));
+
+        let range = Clarification {path: Some("goal"), kind: Kind::Range};
+        assert_eq!(s.answer_clarification(&fields, &range, Response::Range(0.0, 4.0)), Ok(true));
+        assert_eq!(s.z.model, (4, 3));
+        assert_eq!(s.clarify(crate::Reason::Handoff).unwrap().kind, Kind::Confirm);
+    }
+}
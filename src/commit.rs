@@ -0,0 +1,118 @@
+//! Two-phase commit of irreversible actions.
+//!
+//! An irreversible action can not be undone when it turns out to be wrong.
+//! A `TwoPhase` agent first prepares such actions:
+//! the action is recorded, announced to the environment or operators,
+//! and a model update is requested instead of acting.
+//!
+//! On the next decide, the prepared action is committed only if the model is unchanged,
+//! and the layered agent still agrees on the same action.
+//! Otherwise, the prepared action is aborted cleanly, and the agent decides anew.
+//! This gives the environment a chance to veto an irreversible action by changing the model.
+//!
+//! Irreversible actions are given by a function, or by a classifier using `TwoPhase::classified`.
+
+use crate::reversibility::{self, Irreversibility};
+use crate::{Agent, Decision, Inspect};
+
+/// The phase of an irreversible action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase<A> {
+    /// The action was prepared and announced.
+    Prepared(A),
+    /// The action was committed.
+    Committed(A),
+    /// The action was aborted, because the model or the decision changed.
+    Aborted(A),
+}
+
+/// Stores an agent that commits irreversible actions in two phases.
+#[derive(Clone, Debug)]
+pub struct TwoPhase<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// Returns `true` if an action in some model is irreversible.
+    pub irreversible: fn(&T::Model, &T::Action) -> bool,
+    /// Announces a prepared action in some model, e.g. to operators.
+    pub announce: fn(&T::Model, &T::Action),
+    /// The log of phases, in order.
+    pub log: Vec<Phase<T::Action>>,
+    prepared: Option<(T::Model, T::Action)>,
+}
+
+impl<T: Inspect> TwoPhase<T> where T::Model: Clone + PartialEq, T::Action: Clone + PartialEq {
+    /// Creates a new agent that commits irreversible actions in two phases.
+    pub fn new(agent: T, irreversible: fn(&T::Model, &T::Action) -> bool) -> Self {
+        TwoPhase {agent, irreversible, announce: |_, _| {}, log: vec![], prepared: None}
+    }
+
+    /// Creates a new agent that commits actions in two phases when irreversible by a classifier.
+    pub fn classified<C: Irreversibility<T::Model, T::Action>>(agent: T) -> Self {
+        Self::new(agent, reversibility::irreversible::<T::Model, T::Action, C>)
+    }
+
+    /// Returns the prepared action, if any.
+    pub fn prepared(&self) -> Option<&T::Action> {self.prepared.as_ref().map(|(_, a)| a)}
+}
+
+impl<T: Inspect> Agent for TwoPhase<T> where T::Model: Clone + PartialEq, T::Action: Clone + PartialEq {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        let decision = self.agent.decide();
+        if let Some((model, action)) = self.prepared.take() {
+            // Commit only when nothing changed since preparing.
+            if model == *self.agent.model() && decision == Decision::Action(action.clone()) {
+                self.log.push(Phase::Committed(action));
+                return decision;
+            }
+            self.log.push(Phase::Aborted(action));
+        }
+        match decision {
+            Decision::Action(a) if (self.irreversible)(self.agent.model(), &a) => {
+                (self.announce)(self.agent.model(), &a);
+                self.log.push(Phase::Prepared(a.clone()));
+                self.prepared = Some((self.agent.model().clone(), a));
+                Decision::RequestModel
+            }
+            decision => decision,
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for TwoPhase<T> where T::Model: Clone + PartialEq, T::Action: Clone + PartialEq {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Moving past the state `2` can not be undone.
+    fn irreversible(m: &(u32, u32), a: &i32) -> bool {m.1 < 2 && m.1 as i32 + a >= 2}
+
+    #[test]
+    fn prepare_and_commit() {
+        let mut s = TwoPhase::new(crate::tests::four().add(1), irreversible);
+        s.update_model((4, 1));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.prepared(), Some(&1));
+        s.update_model((4, 1));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.log, vec![Phase::Prepared(1), Phase::Committed(1)]);
+
+        // The environment changes the model, which aborts the action.
+        s.log.clear();
+        s.update_model((4, 1));
+        s.decide();
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.log, vec![Phase::Prepared(1), Phase::Aborted(1)]);
+        assert_eq!(s.prepared(), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Batch certification of recorded episodes.
+//!
+//! For compliance review, every action of every recorded episode should be re-verified
+//! against the recorded model and the configuration of the agent.
+//! Episodes are recorded as files in a directory, one line per certified action:
+//!
+//! ```text
+//! {"model":"[4,0]","certificate":{"fingerprint":"17179869184","layers":1,"action":"1",...}}
+//! ```
+//!
+//! where models are encoded as strings by user-supplied codecs, see `record`.
+//! `certify_dir` verifies every line of every file using `certificate::verify_certificate`,
+//! and summarizes the results in a `ComplianceReport`:
+//!
+//! - verified: the recorded checks were reproduced
+//! - unverifiable: the line could not be parsed, or the certificate has another number of layers
+//!   than the configuration, so it can not be checked
+//! - violated: the certificate does not match the model, or the checks were not reproduced
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::certificate::{verify_certificate, Certificate, VerificationResult};
+use crate::{json, AgentN};
+
+/// Stores the status of a certified action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// The recorded checks were reproduced.
+    Verified,
+    /// The certificate could not be checked, with a description of the problem.
+    Unverifiable(String),
+    /// The certificate was not reproduced, with a description of the problem.
+    Violated(String),
+}
+
+/// Stores the status of a certified action in a recorded episode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// The file of the episode.
+    pub file: PathBuf,
+    /// The line number, starting at `1`.
+    pub line: usize,
+    /// The status.
+    pub status: Status,
+}
+
+/// Stores a summary of certified actions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComplianceReport {
+    /// The number of verified actions.
+    pub verified: usize,
+    /// The number of unverifiable actions.
+    pub unverifiable: usize,
+    /// The number of violated actions.
+    pub violated: usize,
+    /// The actions that were not verified.
+    pub findings: Vec<Finding>,
+}
+
+impl ComplianceReport {
+    /// Returns `true` if all actions were verified.
+    pub fn is_compliant(&self) -> bool {self.unverifiable == 0 && self.violated == 0}
+
+    fn add(&mut self, file: &Path, line: usize, status: Status) {
+        match status {
+            Status::Verified => {self.verified += 1; return}
+            Status::Unverifiable(_) => self.unverifiable += 1,
+            Status::Violated(_) => self.violated += 1,
+        }
+        self.findings.push(Finding {file: file.into(), line, status});
+    }
+}
+
+impl fmt::Display for ComplianceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "verified: {}, unverifiable: {}, violated: {}",
+                 self.verified, self.unverifiable, self.violated)?;
+        for finding in &self.findings {
+            let (kind, msg) = match &finding.status {
+                Status::Verified => continue,
+                Status::Unverifiable(msg) => ("unverifiable", msg),
+                Status::Violated(msg) => ("violated", msg),
+            };
+            writeln!(f, "{}:{}: {}: {}", finding.file.display(), finding.line, kind, msg)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a line of a recorded episode, encoding the model and action as strings.
+pub fn record<M, A>(
+    model: &M,
+    certificate: &Certificate<A>,
+    encode_model: fn(&M) -> String,
+    encode_action: fn(&A) -> String
+) -> String {
+    format!("{{\"model\":{},\"certificate\":{}}}", json::string(&encode_model(model)),
+            certificate.to_json(encode_action))
+}
+
+fn check<M: Clone, A: Clone + PartialEq + fmt::Debug, D>(
+    line: &str,
+    agent: &AgentN<M, A, D>,
+    fingerprint: fn(&M) -> u64,
+    decode_model: fn(&str) -> Option<M>,
+    decode_action: fn(&str) -> Option<A>,
+) -> Status {
+    let value = match json::parse(line) {
+        Some(value) => value,
+        None => return Status::Unverifiable("Expected JSON".into()),
+    };
+    let model = match value.get("model").and_then(|v| v.str()).and_then(decode_model) {
+        Some(model) => model,
+        None => return Status::Unverifiable("Expected model".into()),
+    };
+    let cert = value.get("certificate").ok_or_else(|| "Expected certificate".to_string())
+        .and_then(|v| Certificate::from_json(&json::write(v), decode_action).map_err(|err| err.to_string()));
+    let cert = match cert {
+        Ok(cert) => cert,
+        Err(msg) => return Status::Unverifiable(msg),
+    };
+    match verify_certificate(&cert, &model, agent, fingerprint) {
+        VerificationResult::Verified => Status::Verified,
+        VerificationResult::Layers {actual} =>
+            Status::Unverifiable(format!("Certificate has {} layers, but configuration has {}", cert.layers, actual)),
+        VerificationResult::Fingerprint {actual} =>
+            Status::Violated(format!("Model fingerprint is {}, but certificate has {}", actual, cert.fingerprint)),
+        VerificationResult::Action {actual} =>
+            Status::Violated(format!("Decided {:?}, but certificate has {:?}", actual, cert.action)),
+        VerificationResult::Checks {..} => Status::Violated("Checks were not reproduced".into()),
+    }
+}
+
+/// Verifies every certified action of the recorded episodes in a directory,
+/// using an agent with the configuration that decided the actions.
+///
+/// Files are read in order of their names, and empty lines are skipped.
+pub fn certify_dir<M: Clone, A: Clone + PartialEq + fmt::Debug, D>(
+    dir: impl AsRef<Path>,
+    agent: &AgentN<M, A, D>,
+    fingerprint: fn(&M) -> u64,
+    decode_model: fn(&str) -> Option<M>,
+    decode_action: fn(&str) -> Option<A>,
+) -> io::Result<ComplianceReport> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {files.push(path)}
+    }
+    files.sort();
+    let mut report = ComplianceReport::default();
+    for file in &files {
+        let src = fs::read_to_string(file)?;
+        for (i, line) in src.lines().enumerate() {
+            if line.trim().is_empty() {continue}
+            report.add(file, i + 1, check(line, agent, fingerprint, decode_model, decode_action));
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(m: &(u32, u32)) -> u64 {(m.0 as u64) << 32 | m.1 as u64}
+    fn encode(m: &(u32, u32)) -> String {format!("{},{}", m.0, m.1)}
+    fn decode(s: &str) -> Option<(u32, u32)> {
+        let (a, b) = s.split_once(',')?;
+        Some((a.parse().ok()?, b.parse().ok()?))
+    }
+
+    #[test]
+    fn directory() {
+        let dir = std::env::temp_dir().join(format!("compliance-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut s = crate::tests::four().add(1);
+        let cert = s.decide_certificate(fingerprint).1.unwrap();
+        let line = record(&(4, 0), &cert, encode, |a| a.to_string());
+        let forged = record(&(4, 1), &cert, encode, |a| a.to_string());
+        fs::write(dir.join("a.jsonl"), format!("{}\n\n{}\n", line, forged)).unwrap();
+        fs::write(dir.join("b.jsonl"), "{}\n").unwrap();
+
+        let report = certify_dir(&dir, &s, fingerprint, decode, |a| a.parse().ok()).unwrap();
+        assert_eq!((report.verified, report.unverifiable, report.violated), (1, 1, 1));
+        assert_eq!((report.findings[0].line, report.findings[1].line), (3, 1));
+        assert!(report.to_string().starts_with("verified: 1, unverifiable: 1, violated: 1\n"));
+        assert!(!report.is_compliant());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
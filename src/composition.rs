@@ -0,0 +1,130 @@
+//! Validation of stacked layers.
+//!
+//! Wrapper agents such as `shield::Shield`, `cooldown::Cooldown` or `killswitch::Killable`
+//! can be stacked in any order, but not every order is coherent.
+//! For example, a rate limiter inside the probing loop counts probes instead of actions,
+//! and a kill-switch inside a fallback can be bypassed by the fallback.
+//!
+//! A `Stack` describes the layers of an agent from innermost to outermost,
+//! each with a `Role` that determines where it belongs:
+//!
+//! 1. `Probing`: safety layers probing mutations
+//! 2. `Filter`: layers that downgrade individual decisions, e.g. shields and lookahead
+//! 3. `Limit`: layers that count decisions over time, e.g. cooldowns, budgets and watchdogs
+//! 4. `Timeout`: layers that bound the time of deciding
+//! 5. `Stop`: layers that must stop the agent unconditionally, e.g. kill-switches
+//!
+//! `Stack::validate` rejects a stack with a layer outside of a layer of a later role,
+//! and `Stack::reorder` returns the coherent order, for use when the user consents.
+
+use std::fmt;
+
+/// Stores the role of a layer, in the order from innermost to outermost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// Probes mutations to decide more safely.
+    Probing,
+    /// Downgrades individual decisions.
+    Filter,
+    /// Counts decisions over time.
+    Limit,
+    /// Bounds the time of deciding.
+    Timeout,
+    /// Stops the agent unconditionally.
+    Stop,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Probing => write!(f, "probing"),
+            Role::Filter => write!(f, "filter"),
+            Role::Limit => write!(f, "limit"),
+            Role::Timeout => write!(f, "timeout"),
+            Role::Stop => write!(f, "stop"),
+        }
+    }
+}
+
+/// Stores a description of a layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Layer {
+    /// The name of the layer.
+    pub name: &'static str,
+    /// The role of the layer.
+    pub role: Role,
+}
+
+/// Stores an incoherent ordering of layers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompositionError {
+    /// The layer that is inside of a layer it should wrap.
+    pub inner: Layer,
+    /// The layer that should be inside.
+    pub outer: Layer,
+}
+
+impl fmt::Display for CompositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} layer `{}` is inside of {} layer `{}`, but should wrap it",
+               self.inner.role, self.inner.name, self.outer.role, self.outer.name)
+    }
+}
+
+impl std::error::Error for CompositionError {}
+
+/// Stores a description of stacked layers, from innermost to outermost.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stack {
+    /// The layers.
+    pub layers: Vec<Layer>,
+}
+
+impl Stack {
+    /// Creates a new empty stack.
+    pub fn new() -> Self {Stack::default()}
+
+    /// Adds a layer outside of the current layers.
+    pub fn push(mut self, name: &'static str, role: Role) -> Self {
+        self.layers.push(Layer {name, role});
+        self
+    }
+
+    /// Returns an error for the first layer that is inside of a layer of an earlier role.
+    pub fn validate(&self) -> Result<(), CompositionError> {
+        for (i, &inner) in self.layers.iter().enumerate() {
+            if let Some(&outer) = self.layers[i + 1..].iter().find(|outer| outer.role < inner.role) {
+                return Err(CompositionError {inner, outer});
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the stack in coherent order, keeping the order of layers with the same role.
+    pub fn reorder(&self) -> Stack {
+        let mut layers = self.layers.clone();
+        layers.sort_by_key(|layer| layer.role);
+        Stack {layers}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn order() {
+        let stack = Stack::new()
+            .push("layers", Role::Probing)
+            .push("cooldown", Role::Limit)
+            .push("shield", Role::Filter)
+            .push("killswitch", Role::Stop);
+        let err = stack.validate().unwrap_err();
+        assert_eq!((err.inner.name, err.outer.name), ("cooldown", "shield"));
+        assert_eq!(crate::Error::from(err).to_string(),
+                   "Composition error: limit layer `cooldown` is inside of filter layer `shield`, but should wrap it");
+        let names: Vec<&str> = stack.reorder().layers.iter().map(|l| l.name).collect();
+        assert_eq!(names, ["layers", "shield", "cooldown", "killswitch"]);
+        assert!(stack.reorder().validate().is_ok());
+    }
+}
@@ -0,0 +1,108 @@
+//! A single confidence threshold for acting.
+//!
+//! The agreement rule, mutation limit, maximum entropy and alarms interact in ways
+//! that are hard to tune for operators.
+//! A `Confident` agent combines the signals into one confidence score between `0` and `1`,
+//! and only acts when the score is above `Confident::threshold`:
+//!
+//! - the fraction of probes that agreed with core zero
+//! - the number of probes, as `probes / (probes + 1)`, such that more probes give more confidence
+//! - the disagreement rate over a rolling window of earlier decide calls, as a drift signal
+//!
+//! The score is the product of the agreement fraction, the probe factor and one minus the drift.
+//! Since the score replaces the agreement rule, every safety layer probes all its mutations.
+
+use std::collections::VecDeque;
+
+use crate::{Agent, AgentN, Agreement, Decision, Inspect};
+
+/// Stores an agent that acts above a confidence threshold.
+#[derive(Clone, Debug)]
+pub struct Confident<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The confidence score that must be exceeded for acting.
+    pub threshold: f64,
+    /// The number of earlier decide calls used for the drift signal.
+    pub window: usize,
+    /// The confidence score of the last decide call, if core zero acted.
+    pub score: Option<f64>,
+    history: VecDeque<(u32, u32)>,
+}
+
+impl<M, A: PartialEq, D> Confident<M, A, D> {
+    /// Creates a new agent acting above a confidence threshold,
+    /// with a drift signal over a rolling window of decide calls.
+    pub fn new(mut agent: AgentN<M, A, D>, threshold: f64, window: usize) -> Self {
+        for layer in &mut agent.layers {
+            layer.agreement = Agreement::All;
+            layer.max_entropy = None;
+        }
+        Confident {agent, threshold, window, score: None, history: VecDeque::new()}
+    }
+
+    /// Returns the disagreement rate over the rolling window.
+    pub fn drift(&self) -> f64 {
+        let (probes, disagreements) = self.history.iter()
+            .fold((0, 0), |(p, d), &(probes, disagreements)| (p + probes, d + disagreements));
+        if probes == 0 {0.0} else {disagreements as f64 / probes as f64}
+    }
+
+    fn confidence(&self) -> f64 {
+        let r = &self.agent.report;
+        if r.probes == 0 {return 0.0}
+        let agreement = r.approvals as f64 / r.probes as f64;
+        let probes = r.probes as f64 / (r.probes as f64 + 1.0);
+        agreement * probes * (1.0 - self.drift())
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Confident<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        let core = self.agent.z.decide();
+        let decision = self.agent.decide();
+        self.score = match core {
+            Decision::Action(_) => Some(self.confidence()),
+            _ => None,
+        };
+        let r = self.agent.report;
+        self.history.push_back((r.probes, r.disagreements));
+        while self.history.len() > self.window {self.history.pop_front();}
+        match (decision, self.score) {
+            (Decision::Action(a), Some(score)) if score > self.threshold => Decision::Action(a),
+            // Acting with too little confidence is less safe than requesting a model update.
+            (Decision::Action(_), _) => Decision::RequestModel,
+            (decision, _) => decision,
+        }
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Confident<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dial() {
+        let mut s = Confident::new(crate::tests::four().add(1), 0.7, 4);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.score, Some(0.8));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        // The earlier disagreement lowers confidence below the threshold.
+        s.update_model((4, 0));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert!(s.score.unwrap() < 0.7);
+        assert_eq!(s.drift(), 1.0 / 9.0);
+    }
+}
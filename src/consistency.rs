@@ -0,0 +1,101 @@
+//! Ground-truth consistency checking for tests.
+//!
+//! Decisions are only as good as the internal model of the agent.
+//! When the actor does not match how the environment responds to actions,
+//! the internal model silently drifts away from the environment.
+//!
+//! A `Checked` agent keeps the environment as ground truth,
+//! performs every action in both, and panics when they no longer match.
+//!
+//! Requires the `testing` feature, or compiling tests of this library.
+
+use crate::environment::Environment;
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an agent checked against ground truth.
+pub struct Checked<T: Agent, E> {
+    /// The agent.
+    pub agent: T,
+    /// The ground truth.
+    pub env: E,
+    /// Returns `true` when the internal model matches the ground truth.
+    pub matches: fn(&T::Model, &E) -> bool,
+    /// The number of checked actions.
+    pub checks: usize,
+}
+
+impl<T, E> Checked<T, E>
+    where T: Inspect, E: Environment<Model = T::Model, Action = T::Action>
+{
+    /// Creates a new checked agent, synchronizing its model with the ground truth.
+    pub fn new(mut agent: T, mut env: E, matches: fn(&T::Model, &E) -> bool) -> Self {
+        agent.update_model(env.model());
+        Checked {agent, env, matches, checks: 0}
+    }
+
+    /// Updates the model of the agent from the ground truth.
+    pub fn sync(&mut self) {self.agent.update_model(self.env.model())}
+
+    /// Panics if the internal model does not match the ground truth.
+    pub fn check(&mut self) {
+        self.checks += 1;
+        if !(self.matches)(self.agent.model(), &self.env) {
+            panic!("Internal model does not match ground truth after action #{}", self.checks);
+        }
+    }
+}
+
+impl<T, E> Agent for Checked<T, E>
+    where T: Inspect, E: Environment<Model = T::Model, Action = T::Action>
+{
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {self.agent.decide()}
+    fn act(&mut self, action: T::Action) {
+        self.env.act(&action);
+        self.agent.act(action);
+        self.check();
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T, E> Inspect for Checked<T, E>
+    where T: Inspect, E: Environment<Model = T::Model, Action = T::Action>
+{
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::tests::Three;
+
+    fn drive(s: &mut Checked<crate::AgentN<(u32, u32), i32, i32>, Three>) {
+        for _ in 0..5 {
+            match s.decide() {
+                Decision::Action(a) => s.act(a),
+                Decision::RequestModel => s.sync(),
+                Decision::Halt => break,
+            }
+        }
+    }
+
+    #[test]
+    fn consistent() {
+        let mut s = Checked::new(crate::tests::four().add(1), Three(0), |m, env| m.1 == env.0);
+        drive(&mut s);
+        assert_eq!(s.checks, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Internal model does not match ground truth after action #1")]
+    fn mismatched_actor() {
+        let mut z = crate::tests::four();
+        z.actor = |m, a| m.1 = (m.1 as i32 + 2 * a) as u32;
+        let mut s = Checked::new(z.add(1), Three(0), |m, env| m.1 == env.0);
+        drive(&mut s);
+    }
+}
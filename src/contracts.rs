@@ -0,0 +1,212 @@
+//! Pre- and postconditions on agent methods.
+//!
+//! A `Contracted` agent checks user-specified contracts around each method of the inner agent,
+//! for example that acting must not increase the number of hazards.
+//! A precondition is checked on the model before the method is called.
+//! A postcondition is checked on the model before and after.
+//!
+//! Contracts are only checked in debug builds.
+//! Violations do not panic, but are recorded and sent as `Event::Violation` to the observers,
+//! such that a corrupted model is noticed instead of silently used.
+
+use std::fmt;
+
+use crate::{Agent, Decision, Event, Inspect};
+
+/// Stores a method of an agent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// `Agent::update_model`.
+    UpdateModel,
+    /// `Agent::decide`.
+    Decide,
+    /// `Agent::act`.
+    Act,
+    /// `Agent::mutate`.
+    Mutate,
+    /// `Agent::undo`.
+    Undo,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::UpdateModel => write!(f, "update_model"),
+            Method::Decide => write!(f, "decide"),
+            Method::Act => write!(f, "act"),
+            Method::Mutate => write!(f, "mutate"),
+            Method::Undo => write!(f, "undo"),
+        }
+    }
+}
+
+/// Stores a contract of an agent method.
+pub struct Contract<M> {
+    /// The name of the contract.
+    pub name: &'static str,
+    /// The method.
+    pub method: Method,
+    /// Returns `true` when the model before the call is valid.
+    pub pre: Option<fn(&M) -> bool>,
+    /// Returns `true` when the models before and after the call are valid.
+    pub post: Option<fn(&M, &M) -> bool>,
+}
+
+impl<M> Clone for Contract<M> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M> Copy for Contract<M> {}
+
+impl<M> fmt::Debug for Contract<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Contract")
+            .field("name", &self.name)
+            .field("method", &self.method)
+            .field("pre", &self.pre)
+            .field("post", &self.post)
+            .finish()
+    }
+}
+
+/// Stores a violation of a contract.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ContractViolation {
+    /// The method.
+    pub method: Method,
+    /// The name of the contract.
+    pub contract: &'static str,
+    /// Whether the precondition was violated, instead of the postcondition.
+    pub pre: bool,
+}
+
+/// Stores an agent with contracts on its methods.
+#[derive(Debug)]
+pub struct Contracted<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The contracts.
+    pub contracts: Vec<Contract<T::Model>>,
+    /// Called on violations.
+    pub observers: Vec<fn(&Event<T::Action>)>,
+    /// The violations, in order.
+    pub violations: Vec<ContractViolation>,
+}
+
+impl<T: Inspect> Contracted<T> where T::Model: Clone {
+    /// Creates a new agent without contracts.
+    pub fn new(agent: T) -> Self {
+        Contracted {agent, contracts: vec![], observers: vec![], violations: vec![]}
+    }
+
+    /// Adds a contract.
+    pub fn contract(mut self, contract: Contract<T::Model>) -> Self {
+        self.contracts.push(contract);
+        self
+    }
+
+    fn violate(&mut self, method: Method, contract: &'static str, pre: bool) {
+        self.violations.push(ContractViolation {method, contract, pre});
+        for f in &self.observers {f(&Event::Violation {method, contract})}
+    }
+
+    /// Checks the preconditions, returning the model before the call if needed by postconditions.
+    fn pre(&mut self, method: Method) -> Option<T::Model> {
+        if !cfg!(debug_assertions) {return None}
+        let mut post = false;
+        for i in 0..self.contracts.len() {
+            let c = self.contracts[i];
+            if c.method != method {continue}
+            if let Some(pre) = c.pre {
+                if !pre(self.agent.model()) {self.violate(method, c.name, true)}
+            }
+            post |= c.post.is_some();
+        }
+        if post {Some(self.agent.model().clone())} else {None}
+    }
+
+    fn post(&mut self, method: Method, before: Option<T::Model>) {
+        let before = match before {Some(x) => x, None => return};
+        for i in 0..self.contracts.len() {
+            let c = self.contracts[i];
+            if c.method != method {continue}
+            if let Some(post) = c.post {
+                if !post(&before, self.agent.model()) {self.violate(method, c.name, false)}
+            }
+        }
+    }
+}
+
+impl<T: Inspect> Agent for Contracted<T> where T::Model: Clone {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        let before = self.pre(Method::UpdateModel);
+        self.agent.update_model(model);
+        self.post(Method::UpdateModel, before);
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        let before = self.pre(Method::Decide);
+        let decision = self.agent.decide();
+        self.post(Method::Decide, before);
+        decision
+    }
+    fn act(&mut self, action: T::Action) {
+        let before = self.pre(Method::Act);
+        self.agent.act(action);
+        self.post(Method::Act, before);
+    }
+    fn mutate(&mut self) -> T::Delta {
+        let before = self.pre(Method::Mutate);
+        let delta = self.agent.mutate();
+        self.post(Method::Mutate, before);
+        delta
+    }
+    fn undo(&mut self, delta: T::Delta) {
+        let before = self.pre(Method::Undo);
+        self.agent.undo(delta);
+        self.post(Method::Undo, before);
+    }
+}
+
+impl<T: Inspect> Inspect for Contracted<T> where T::Model: Clone {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static OBSERVED: Cell<usize> = const {Cell::new(0)};
+    }
+
+    #[test]
+    fn violations() {
+        let mut s = Contracted::new(crate::tests::four().add(1))
+            .contract(Contract {
+                name: "state does not pass goal",
+                method: Method::Act,
+                pre: None,
+                post: Some(|_, after| after.1 <= after.0),
+            })
+            .contract(Contract {name: "goal is known", method: Method::Decide, pre: Some(|m| m.0 > 0), post: None});
+        s.observers.push(|event| {
+            assert_eq!(event.to_string(), "contract `state does not pass goal` of `act` violated");
+            OBSERVED.with(|n| n.set(n.get() + 1));
+        });
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert!(s.violations.is_empty());
+        s.update_model((4, 4));
+        s.act(1);
+        assert_eq!(s.violations, vec![ContractViolation {
+            method: Method::Act,
+            contract: "state does not pass goal",
+            pre: false,
+        }]);
+        assert_eq!(OBSERVED.with(|n| n.get()), 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! Cooldowns and repetition limits of actions.
+//!
+//! Agreement checks can not see pathological loops,
+//! e.g. an agent that keeps toggling a switch.
+//! A `Cooldown` agent guards against them with two cheap behavioral limits:
+//!
+//! - a per-action cooldown, the number of decide calls after acting before the action is allowed again
+//! - a maximum number of consecutive identical actions
+//!
+//! A decided action that violates a limit is downgraded to a model request.
+//! Time is counted in decide calls.
+
+use std::fmt;
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an agent with cooldowns and repetition limits of actions.
+pub struct Cooldown<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// Returns the cooldown of an action, in decide calls.
+    pub cooldown: fn(&T::Action) -> usize,
+    /// The maximum number of consecutive identical actions.
+    pub max_repeats: usize,
+    /// The number of decide calls.
+    pub time: usize,
+    /// The time each action was last performed.
+    pub last: Vec<(T::Action, usize)>,
+    /// The last action and the number of consecutive times it was performed.
+    pub streak: Option<(T::Action, usize)>,
+    /// The number of downgraded actions.
+    pub violations: usize,
+}
+
+impl<T: Agent + Clone> Clone for Cooldown<T> where T::Action: Clone {
+    fn clone(&self) -> Self {
+        Cooldown {
+            agent: self.agent.clone(),
+            cooldown: self.cooldown,
+            max_repeats: self.max_repeats,
+            time: self.time,
+            last: self.last.clone(),
+            streak: self.streak.clone(),
+            violations: self.violations,
+        }
+    }
+}
+
+impl<T: Agent + fmt::Debug> fmt::Debug for Cooldown<T> where T::Action: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cooldown")
+            .field("agent", &self.agent)
+            .field("cooldown", &self.cooldown)
+            .field("max_repeats", &self.max_repeats)
+            .field("time", &self.time)
+            .field("last", &self.last)
+            .field("streak", &self.streak)
+            .field("violations", &self.violations)
+            .finish()
+    }
+}
+
+impl<T: Agent> Cooldown<T> where T::Action: PartialEq {
+    /// Creates a new agent with cooldowns and a maximum number of consecutive identical actions.
+    pub fn new(agent: T, cooldown: fn(&T::Action) -> usize, max_repeats: usize) -> Self {
+        Cooldown {agent, cooldown, max_repeats, time: 0, last: vec![], streak: None, violations: 0}
+    }
+
+    /// Returns `true` if an action is allowed at the current time.
+    pub fn allows(&self, action: &T::Action) -> bool {
+        let cooled = self.last.iter().find(|(a, _)| a == action)
+            .map(|(_, t)| self.time - t > (self.cooldown)(action))
+            .unwrap_or(true);
+        let repeats = match &self.streak {
+            Some((a, n)) if a == action => *n,
+            _ => 0,
+        };
+        cooled && repeats < self.max_repeats
+    }
+}
+
+impl<T: Agent> Agent for Cooldown<T> where T::Action: Clone + PartialEq {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        self.time += 1;
+        match self.agent.decide() {
+            Decision::Action(a) if !self.allows(&a) => {
+                self.violations += 1;
+                Decision::RequestModel
+            }
+            x => x,
+        }
+    }
+    fn act(&mut self, action: T::Action) {
+        match self.last.iter_mut().find(|(a, _)| *a == action) {
+            Some(last) => last.1 = self.time,
+            None => self.last.push((action.clone(), self.time)),
+        }
+        self.streak = match self.streak.take() {
+            Some((a, n)) if a == action => Some((a, n + 1)),
+            _ => Some((action.clone(), 1)),
+        };
+        self.agent.act(action)
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Cooldown<T> where T::Action: Clone + PartialEq {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats() {
+        let mut agent = crate::tests::four().add(1);
+        agent.update_model((9, 0));
+        let mut s = Cooldown::new(agent, |_| 0, 3);
+        for _ in 0..3 {
+            assert_eq!(s.decide(), Decision::Action(1));
+            s.act(1);
+        }
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!((s.violations, s.model()), (1, &(9, 3)));
+
+        s.max_repeats = 9;
+        s.cooldown = |_| 1;
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::Action(1));
+    }
+}
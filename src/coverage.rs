@@ -0,0 +1,131 @@
+//! Coverage-guided mutation selection.
+//!
+//! With a fixed number of probes per safety layer, the order of mutaters matters:
+//! A probe into a region of the model space that was probed many times before
+//! says less than a probe into an unexplored region.
+//!
+//! `Coverage` divides the model space into regions using a user-supplied feature extraction,
+//! and counts the visits of each region by probed mutations.
+//! On every decide call, the mutaters of `AgentN::mutaters` are ordered
+//! by the visits of the region that each one mutates into, least visited first,
+//! such that probe `0` explores the least covered region.
+//!
+//! While a rationale is recorded, e.g. for a certificate, the mutaters keep their configured order,
+//! such that the recorded checks can be reproduced by an agent without the same history.
+//!
+//! Counting visits of new regions allocates memory on the heap.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ptr::fn_addr_eq;
+
+use crate::{Agent, AgentN};
+
+/// Stores the coverage of the model space by probed mutations.
+pub struct Coverage<M> {
+    /// Returns the region of a model.
+    pub features: fn(&M) -> u64,
+    /// The number of probed mutations into each region.
+    pub visits: BTreeMap<u64, u32>,
+    /// The order of mutaters for the current decide call.
+    pub(crate) order: Vec<usize>,
+    /// The visits of the region of each mutater, reused between decide calls.
+    keys: Vec<(u32, usize)>,
+}
+
+impl<M> Coverage<M> {
+    /// Creates a new coverage without visits.
+    pub fn new(features: fn(&M) -> u64) -> Self {
+        Coverage {features, visits: BTreeMap::new(), order: vec![], keys: vec![]}
+    }
+
+    /// Returns the number of probed mutations into a region.
+    pub fn visits(&self, region: u64) -> u32 {self.visits.get(&region).cloned().unwrap_or(0)}
+}
+
+impl<M> Clone for Coverage<M> {
+    fn clone(&self) -> Self {
+        Coverage {features: self.features, visits: self.visits.clone(), order: self.order.clone(), keys: vec![]}
+    }
+}
+
+impl<M> fmt::Debug for Coverage<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("features", &self.features)
+            .field("visits", &self.visits)
+            .field("order", &self.order)
+            .finish()
+    }
+}
+
+impl<M> PartialEq for Coverage<M> {
+    fn eq(&self, other: &Self) -> bool {
+        fn_addr_eq(self.features, other.features) && self.visits == other.visits && self.order == other.order
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Orders the mutaters by coverage of the regions they mutate into.
+    pub(crate) fn cover(&mut self) {
+        let mut coverage = match self.coverage.take() {Some(c) => c, None => return};
+        if self.rationale.is_some() {
+            coverage.order.clear();
+            self.coverage = Some(coverage);
+            return;
+        }
+        // The buffers are reused, such that ordering does not allocate after the first decide call.
+        let mut keys = std::mem::take(&mut coverage.keys);
+        keys.clear();
+        keys.extend((0..self.mutaters.len()).map(|i| {
+            let delta = (self.mutaters[i])(&mut self.z.model);
+            let region = (coverage.features)(&self.z.model);
+            self.z.undo(delta);
+            (coverage.visits(region), i)
+        }));
+        keys.sort_unstable();
+        coverage.order.clear();
+        coverage.order.extend(keys.iter().map(|&(_, i)| i));
+        coverage.keys = keys;
+        self.coverage = Some(coverage);
+    }
+
+    /// Counts a visit of the region of the mutated model.
+    pub(crate) fn visit(&mut self) {
+        if let Some(c) = &mut self.coverage {
+            *c.visits.entry((c.features)(&self.z.model)).or_insert(0) += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decision;
+
+    #[test]
+    fn explore() {
+        let mut s = crate::tests::four().add(1);
+        s.mutaters = vec![|m| {m.0 -= 1; -1}, |m| {m.0 -= 2; -2}];
+        s.coverage = Some(Coverage::new(|m: &(u32, u32)| m.0 as u64));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.mutater_of(0), 0);
+        // The region of the first mutater was probed, so the second one is probed first.
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.mutater_of(0), 1);
+        let c = s.coverage.as_ref().unwrap();
+        assert_eq!((c.visits(3), c.visits(2)), (1, 1));
+        assert_eq!(s.z.model, (4, 0));
+    }
+
+    #[test]
+    fn certified() {
+        let mut s = crate::tests::four().add(1);
+        s.mutaters = vec![|m| {m.0 -= 1; -1}, |m| {m.0 -= 2; -2}];
+        s.coverage = Some(Coverage::new(|m: &(u32, u32)| m.0 as u64));
+        assert_eq!(s.decide(), Decision::Action(1));
+        // Certified decisions are ordered by coverage as well.
+        assert!(matches!(s.decide_certified(), Decision::Action(_)));
+        assert_eq!(s.mutater_of(0), 1);
+    }
+}
@@ -0,0 +1,97 @@
+//! Copy-on-write model sharing for large models.
+//!
+//! For large models, copying the whole model on mutation is expensive.
+//! A `Chunked` model is split into chunks that are shared using `Arc`.
+//!
+//! A mutation copies only the chunk that changes,
+//! while the delta keeps the old chunk around.
+//! Undoing a mutation swaps the old chunk back in, which is `O(1)`.
+//!
+//! Cloning a `Chunked` model is cheap, since all chunks are shared.
+
+use std::sync::Arc;
+
+/// Stores a model split into shared chunks.
+#[derive(Debug)]
+pub struct Chunked<T> {
+    /// The shared chunks.
+    pub chunks: Vec<Arc<T>>,
+}
+
+impl<T> Clone for Chunked<T> {
+    fn clone(&self) -> Self {Chunked {chunks: self.chunks.clone()}}
+}
+
+/// Stores a delta that replaced a chunk.
+#[derive(Debug)]
+pub struct ChunkDelta<T> {
+    /// The index of the chunk.
+    pub index: usize,
+    /// The old chunk.
+    pub old: Arc<T>,
+}
+
+impl<T> Chunked<T> {
+    /// Creates a new chunked model.
+    pub fn new(chunks: Vec<T>) -> Self {
+        Chunked {chunks: chunks.into_iter().map(Arc::new).collect()}
+    }
+
+    /// Returns a chunk.
+    pub fn get(&self, index: usize) -> &T {&self.chunks[index]}
+
+    /// Replaces a chunk, returning a delta that undoes the change.
+    pub fn replace(&mut self, index: usize, chunk: T) -> ChunkDelta<T> {
+        let old = std::mem::replace(&mut self.chunks[index], Arc::new(chunk));
+        ChunkDelta {index, old}
+    }
+
+    /// Undoes a delta change by swapping the old chunk back in.
+    pub fn undo(&mut self, delta: ChunkDelta<T>) {
+        self.chunks[delta.index] = delta.old;
+    }
+}
+
+impl<T: Clone> Chunked<T> {
+    /// Mutates a copy of a chunk, returning a delta that undoes the change.
+    pub fn mutate(&mut self, index: usize, f: impl FnOnce(&mut T)) -> ChunkDelta<T> {
+        let mut chunk = (*self.chunks[index]).clone();
+        f(&mut chunk);
+        self.replace(index, chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    #[test]
+    fn chunked_increments() {
+        // The first chunk is the goal, the second chunk is the state.
+        let mut z = AgentZ {
+            model: Chunked::new(vec![4, 0]),
+            decider: |model: &Chunked<u32>| {
+                let (goal, state) = (*model.get(0), *model.get(1));
+                if state < goal {1} else if state > goal {-1} else {0}
+            },
+            actor: |model: &mut Chunked<u32>, action: i32| {
+                model.mutate(1, |state| *state = (*state as i32 + action) as u32);
+            },
+            mutater: |model: &mut Chunked<u32>| {
+                model.mutate(0, |goal| *goal = goal.saturating_sub(1))
+            },
+            undoer: |model: &mut Chunked<u32>, delta| model.undo(delta),
+        };
+        let shared = z.model.clone();
+        let delta = z.mutate();
+        assert_eq!(*z.model.get(0), 3);
+        // The unchanged chunk is still shared.
+        assert!(Arc::ptr_eq(&z.model.chunks[1], &shared.chunks[1]));
+        z.undo(delta);
+        assert!(Arc::ptr_eq(&z.model.chunks[0], &shared.chunks[0]));
+
+        let mut s = z.add(1);
+        assert_eq!(s.decide(), Decision::Action(1));
+    }
+}
@@ -0,0 +1,145 @@
+//! Confidence-based curriculum for layer reduction.
+//!
+//! A model update can assert that the goal is specified correctly.
+//! With higher confidence in a correct goal, the safety levels can be reduced when needed.
+//!
+//! A `Curriculum` starts an agent at a high safety level.
+//! The safety level is decreased only after a sufficient streak of agreements
+//! and confirmed model updates.
+//! On any disagreement, the safety level is increased again.
+//!
+//! A disagreement is detected when the layered agent requests a model update
+//! while the core zero agent decides an action.
+
+use crate::{Agent, AgentN, AgentZ, Decision};
+
+/// Returns the number of consecutive agreements needed for some confidence
+/// that the disagreement rate is below some rate.
+///
+/// This uses the zero-failure binomial bound `(1 - rate)^n <= 1 - confidence`.
+pub fn streak_for(confidence: f64, rate: f64) -> usize {
+    ((1.0 - confidence).ln() / (1.0 - rate).ln()).ceil() as usize
+}
+
+/// Controls the safety level of an agent.
+pub struct Curriculum<M, A, D> {
+    agent: Option<AgentN<M, A, D>>,
+    level: usize,
+    /// The maximum number of safety layers.
+    pub max: usize,
+    /// The minimum number of safety layers.
+    pub min: usize,
+    /// The number of agreements required to decrease safety level.
+    pub streak: usize,
+    /// The number of confirmed model updates required to decrease safety level.
+    pub confirmations: usize,
+    /// The current number of agreements at this safety level.
+    pub agreements: usize,
+    /// The current number of confirmed model updates at this safety level.
+    pub confirmed: usize,
+}
+
+impl<M, A, D> Curriculum<M, A, D> {
+    /// Creates a new curriculum starting at maximum safety level.
+    pub fn new(z: AgentZ<M, A, D>, max: usize, streak: usize, confirmations: usize) -> Self {
+        Curriculum {
+            agent: Some(z.add(max)),
+            level: max,
+            max,
+            min: 0,
+            streak,
+            confirmations,
+            agreements: 0,
+            confirmed: 0,
+        }
+    }
+
+    /// Returns the current number of safety layers.
+    pub fn level(&self) -> usize {self.level}
+
+    /// Returns the controlled agent.
+    pub fn agent(&mut self) -> &mut AgentN<M, A, D> {self.agent.as_mut().unwrap()}
+
+    fn reset(&mut self) {
+        self.agreements = 0;
+        self.confirmed = 0;
+    }
+
+    fn try_dec(&mut self) {
+        if self.level > self.min &&
+           self.agreements >= self.streak &&
+           self.confirmed >= self.confirmations
+        {
+            self.agent = self.agent.take().map(|agent| agent.dec());
+            self.level -= 1;
+            self.reset();
+        }
+    }
+
+    fn inc(&mut self) {
+        if self.level < self.max {
+            self.agent = self.agent.take().map(|agent| agent.inc());
+            self.level += 1;
+        }
+        self.reset();
+    }
+}
+
+impl<M, A, D> Curriculum<M, A, D>
+    where A: PartialEq
+{
+    /// Decide what to do next, adjusting the safety level.
+    pub fn decide(&mut self) -> Decision<A> {
+        let decision = self.agent().decide();
+        match decision {
+            Decision::Action(_) => {
+                self.agreements += 1;
+                self.try_dec();
+            }
+            Decision::RequestModel => {
+                if let Decision::Action(_) = self.agent().z().decide() {
+                    self.inc();
+                }
+            }
+            Decision::Halt => {}
+        }
+        decision
+    }
+
+    /// Perform an action.
+    pub fn act(&mut self, action: A) {self.agent().act(action)}
+
+    /// Update with a model that is confirmed to be correct.
+    pub fn confirm(&mut self, model: M) {
+        self.agent().update_model(model);
+        self.confirmed += 1;
+        self.try_dec();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streak() {
+        assert_eq!(streak_for(0.95, 0.05), 59);
+    }
+
+    #[test]
+    fn reduce_and_raise() {
+        let mut c = Curriculum::new(crate::tests::four(), 2, 2, 1);
+        c.confirm((4, 0));
+        assert_eq!(c.decide(), Decision::Action(1));
+        c.act(1);
+        assert_eq!(c.level(), 2);
+        assert_eq!(c.decide(), Decision::Action(1));
+        c.act(1);
+        assert_eq!(c.level(), 1);
+        assert_eq!(c.decide(), Decision::Action(1));
+        c.act(1);
+        // Disagreement raises the safety level.
+        assert_eq!(c.decide(), Decision::RequestModel);
+        assert_eq!(c.level(), 2);
+    }
+}
@@ -0,0 +1,120 @@
+//! Time-travel debugging over recorded runs.
+//!
+//! A `Debugger` steps forward and backward through a recorded `Trace`,
+//! showing the model, decision and rationale at each step.
+//!
+//! `Debugger::rerun` decides again on the model of the current step
+//! using an agent with modified settings, for example other mutation limits or mutaters,
+//! to see how the decision would have changed.
+//! The agent is cloned, such that it is not changed by rerunning.
+
+use crate::rationale::Rationale;
+use crate::trace::{Trace, TraceStep};
+use crate::{AgentN, Decision};
+
+/// Stores a debugger over a recorded episode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Debugger<M, A> {
+    /// The recorded episode.
+    pub trace: Trace<M, A>,
+    position: usize,
+}
+
+/// Stores the result of deciding again at a recorded step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rerun<A> {
+    /// The recorded decision.
+    pub recorded: Decision<A>,
+    /// The decision with modified settings.
+    pub decision: Decision<A>,
+    /// The rationale of the decision with modified settings.
+    pub rationale: Rationale,
+}
+
+impl<A: PartialEq> Rerun<A> {
+    /// Returns `true` if the decision changed.
+    pub fn changed(&self) -> bool {self.recorded != self.decision}
+}
+
+impl<M, A> Debugger<M, A> {
+    /// Creates a new debugger at the first step of a trace.
+    pub fn new(trace: Trace<M, A>) -> Self {Debugger {trace, position: 0}}
+
+    /// Returns the index of the current step.
+    pub fn position(&self) -> usize {self.position}
+
+    /// Returns the current step, if the trace is not empty.
+    pub fn current(&self) -> Option<&TraceStep<M, A>> {self.trace.steps.get(self.position)}
+
+    /// Returns the model before deciding at the current step.
+    pub fn model(&self) -> Option<&M> {self.current().map(|step| &step.model)}
+
+    /// Steps forward, returning `false` at the last step.
+    pub fn forward(&mut self) -> bool {
+        if self.position + 1 < self.trace.steps.len() {
+            self.position += 1;
+            true
+        } else {false}
+    }
+
+    /// Steps backward, returning `false` at the first step.
+    pub fn backward(&mut self) -> bool {
+        if self.position > 0 {
+            self.position -= 1;
+            true
+        } else {false}
+    }
+
+    /// Jumps to a step, returning `false` if it is out of range.
+    pub fn seek(&mut self, position: usize) -> bool {
+        if position < self.trace.steps.len() {
+            self.position = position;
+            true
+        } else {false}
+    }
+}
+
+impl<M: Clone, A: Clone + PartialEq> Debugger<M, A> {
+    /// Decides again on the model of the current step, after modifying a clone of the agent.
+    ///
+    /// Returns `None` if the trace is empty.
+    pub fn rerun<D>(
+        &self,
+        agent: &AgentN<M, A, D>,
+        modify: impl FnOnce(&mut AgentN<M, A, D>),
+    ) -> Option<Rerun<A>> {
+        let step = self.current()?;
+        let mut agent = agent.clone();
+        agent.z.model = step.model.clone();
+        modify(&mut agent);
+        let (decision, rationale) = agent.decide_rationale();
+        Some(Rerun {recorded: step.decision.clone(), decision, rationale})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_and_rerun() {
+        let mut s = crate::tests::four().add(1);
+        let mut trace = Trace::new();
+        trace.run(&mut s, 10);
+        let mut debugger = Debugger::new(trace);
+        assert!(!debugger.backward());
+        assert!(debugger.forward() && debugger.forward());
+        assert_eq!(debugger.model(), Some(&(4, 2)));
+        assert!(debugger.backward());
+        assert_eq!(debugger.model(), Some(&(4, 1)));
+        assert!(!debugger.seek(4));
+
+        // Without safety layers, the last step would have acted.
+        assert!(debugger.seek(3));
+        let rerun = debugger.rerun(&s, |agent| agent.layers.clear()).unwrap();
+        assert_eq!((rerun.recorded, rerun.decision), (Decision::RequestModel, Decision::Action(1)));
+        assert!(rerun.changed());
+        assert!(!debugger.rerun(&s, |agent| agent.set_mutation_limit(2)).unwrap().changed());
+        assert_eq!(s.layers(), 1);
+    }
+}
@@ -0,0 +1,138 @@
+//! Composable deltas.
+//!
+//! A delta records the change of a mutation, such that it can be undone.
+//! When deltas implement `ComposeDelta`, several mutations can be composed into one delta,
+//! which is undone by a single call to the undoer.
+//! This makes second-order mutations and batch probing cheaper to keep track of.
+//!
+//! Composition must be associative, with `ComposeDelta::identity` as the neutral element,
+//! and undoing a composed delta must undo all of its mutations.
+//! For example, additive deltas compose by addition, and lists of deltas by concatenation,
+//! where the undoer undoes a list in reverse order.
+//!
+//! A `DeltaMeta` describes a delta with a label, the part of the model it targets and its magnitude.
+//! When `AgentN::describe` is set, explanations and journals describe mutations by their metadata
+//! instead of as opaque values.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::AgentZ;
+
+/// Stores a description of a delta.
+///
+/// Magnitudes are compared bitwise.
+#[derive(Clone, Copy, Debug)]
+pub struct DeltaMeta {
+    /// Names the kind of mutation.
+    pub label: &'static str,
+    /// The part of the model targeted by the mutation.
+    pub target: &'static str,
+    /// The size of the change.
+    pub magnitude: f64,
+}
+
+impl DeltaMeta {
+    /// Creates a new description of a delta.
+    pub const fn new(label: &'static str, target: &'static str, magnitude: f64) -> Self {
+        DeltaMeta {label, target, magnitude}
+    }
+}
+
+impl PartialEq for DeltaMeta {
+    fn eq(&self, other: &Self) -> bool {
+        self.label == other.label &&
+        self.target == other.target &&
+        self.magnitude.to_bits() == other.magnitude.to_bits()
+    }
+}
+
+impl Eq for DeltaMeta {}
+
+impl Hash for DeltaMeta {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.label.hash(state);
+        self.target.hash(state);
+        self.magnitude.to_bits().hash(state);
+    }
+}
+
+impl fmt::Display for DeltaMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} by {}", self.label, self.target, self.magnitude)
+    }
+}
+
+/// Implemented by deltas that can be composed into one delta.
+pub trait ComposeDelta: Sized {
+    /// Returns the delta of no change.
+    fn identity() -> Self;
+    /// Returns the delta of applying this delta and then a later one.
+    fn compose(self, later: Self) -> Self;
+}
+
+macro_rules! compose_add {
+    ($($t:ty),*) => {$(
+        impl ComposeDelta for $t {
+            fn identity() -> Self {0}
+            fn compose(self, later: Self) -> Self {self.wrapping_add(later)}
+        }
+    )*}
+}
+
+compose_add!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl ComposeDelta for () {
+    fn identity() -> Self {}
+    fn compose(self, _: Self) -> Self {}
+}
+
+impl<T> ComposeDelta for Vec<T> {
+    fn identity() -> Self {vec![]}
+    fn compose(mut self, later: Self) -> Self {
+        self.extend(later);
+        self
+    }
+}
+
+impl<T: ComposeDelta, U: ComposeDelta> ComposeDelta for (T, U) {
+    fn identity() -> Self {(T::identity(), U::identity())}
+    fn compose(self, later: Self) -> Self {(self.0.compose(later.0), self.1.compose(later.1))}
+}
+
+/// Composes deltas in order of application.
+pub fn compose_all<D: ComposeDelta>(deltas: impl IntoIterator<Item = D>) -> D {
+    deltas.into_iter().fold(D::identity(), D::compose)
+}
+
+impl<M, A, D: ComposeDelta> AgentZ<M, A, D> {
+    /// Applies several mutaters in order, returning the composed delta.
+    pub fn mutate_all(&mut self, mutaters: &[fn(&mut M) -> D]) -> D {
+        mutaters.iter().fold(D::identity(), |d, f| d.compose(f(&mut self.model)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agent;
+
+    #[test]
+    fn compose() {
+        assert_eq!(compose_all(vec![1, -2, 4]), 3);
+        assert_eq!(compose_all(vec![(1, vec!['a']), (2, vec!['b'])]), (3, vec!['a', 'b']));
+        let mut z = crate::tests::four();
+        let delta = z.mutate_all(&[z.mutater, z.mutater, z.mutater]);
+        assert_eq!((delta, z.model), (-3, (1, 0)));
+        z.undo(delta);
+        assert_eq!(z.model, (4, 0));
+    }
+
+    #[test]
+    fn describe() {
+        let meta = DeltaMeta::new("decrement", "goal", 1.0);
+        assert_eq!(meta.to_string(), "decrement of goal by 1");
+        assert_eq!(meta, DeltaMeta::new("decrement", "goal", 1.0));
+        assert_ne!(meta, DeltaMeta::new("decrement", "goal", -1.0));
+    }
+}
@@ -0,0 +1,138 @@
+//! Checking agents for nondeterminism.
+//!
+//! Replay and certification assume that an agent decides the same way
+//! when constructed from the same configuration and seed.
+//! Unseeded randomness, or dependence on hash order or other global state, breaks this assumption.
+//!
+//! `check` constructs an agent twice from the same seed, records a trace of each run,
+//! and returns the first step where the traces diverge.
+
+use std::fmt;
+
+use crate::trace::{Trace, TraceStep};
+use crate::AgentN;
+
+/// The part of a step where two traces diverge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Part {
+    /// One trace ended before the other.
+    Length,
+    /// The models before deciding differ.
+    Model,
+    /// The decisions differ.
+    Decision,
+    /// The rationales differ.
+    Rationale,
+    /// The journaled deltas differ.
+    #[cfg(feature = "replay")]
+    Deltas,
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Part::Length => write!(f, "length"),
+            Part::Model => write!(f, "model"),
+            Part::Decision => write!(f, "decision"),
+            Part::Rationale => write!(f, "rationale"),
+            #[cfg(feature = "replay")]
+            Part::Deltas => write!(f, "deltas"),
+        }
+    }
+}
+
+/// Stores the first divergence between two traces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence<M, A> {
+    /// The index of the step.
+    pub step: usize,
+    /// The part of the step that differs.
+    pub part: Part,
+    /// The step of the first trace, if it has one.
+    pub first: Option<TraceStep<M, A>>,
+    /// The step of the second trace, if it has one.
+    pub second: Option<TraceStep<M, A>>,
+}
+
+impl<M, A> fmt::Display for Divergence<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "traces diverge at step {} in {}", self.step, self.part)
+    }
+}
+
+/// Returns the first divergence between two traces, if any.
+pub fn diff<M, A>(first: &Trace<M, A>, second: &Trace<M, A>) -> Option<Divergence<M, A>>
+    where M: Clone + PartialEq, A: Clone + PartialEq
+{
+    let n = first.steps.len().max(second.steps.len());
+    for step in 0..n {
+        let (a, b) = (first.steps.get(step), second.steps.get(step));
+        let part = match (a, b) {
+            (Some(a), Some(b)) if a.model != b.model => Part::Model,
+            (Some(a), Some(b)) if a.decision != b.decision => Part::Decision,
+            (Some(a), Some(b)) if a.rationale != b.rationale => Part::Rationale,
+            #[cfg(feature = "replay")]
+            (Some(a), Some(b)) if a.deltas != b.deltas => Part::Deltas,
+            (Some(_), Some(_)) => continue,
+            _ => Part::Length,
+        };
+        return Some(Divergence {step, part, first: a.cloned(), second: b.cloned()});
+    }
+    None
+}
+
+/// Runs two agents constructed from the same seed, returning the first divergence of their traces.
+///
+/// Returns the trace when the runs are identical.
+pub fn check<M, A, D, F>(build: F, seed: u64, max_steps: usize) -> Result<Trace<M, A>, Box<Divergence<M, A>>>
+    where M: Clone + PartialEq, A: Clone + PartialEq, F: Fn(u64) -> AgentN<M, A, D>
+{
+    let mut first = Trace::new();
+    first.run(&mut build(seed), max_steps);
+    let mut second = Trace::new();
+    second.run(&mut build(seed), max_steps);
+    match diff(&first, &second) {
+        None => Ok(first),
+        Some(divergence) => Err(Box::new(divergence)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+
+    #[test]
+    fn seeded() {
+        let build = |seed: u64| {
+            let mut s = crate::tests::four().add(1);
+            s.z.undoer = |m, d| m.0 -= d as u32;
+            s.stochastic = Some(crate::rng::Stochastic::new(seed, |m, rng| {
+                let d = rng.below(2) as i32;
+                m.0 += d as u32;
+                d
+            }));
+            s
+        };
+        assert!(check(build, 7, 8).is_ok());
+    }
+
+    #[test]
+    fn global_state() {
+        // The mutater depends on how often it was called before.
+        let build = |_| {
+            let mut s = crate::tests::four().add(1);
+            s.mutaters = vec![|m| {
+                let d = if CALLS.fetch_add(1, Ordering::Relaxed) < 3 {0} else {4};
+                m.0 -= d;
+                d as i32
+            }];
+            s.z.undoer = |m, d| m.0 += d as u32;
+            s
+        };
+        let divergence = check(build, 0, 8).unwrap_err();
+        assert_eq!(divergence.to_string(), "traces diverge at step 0 in decision");
+    }
+}
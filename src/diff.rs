@@ -0,0 +1,138 @@
+//! Deltas produced from two model snapshots.
+//!
+//! Writing a mutater and an undoer by hand requires the delta to record exactly
+//! what the mutater changed.
+//! A model implementing `Diffable` produces such a delta from the model before and after a change,
+//! such that `mutate` turns any change into a mutater, and `undo` is the matching undoer.
+//!
+//! For structs of diffable fields, the macro `diffable!` implements the trait
+//! together with a struct of field deltas:
+//!
+//! ```
+//! use agent_safety_layers::diffable;
+//! use agent_safety_layers::diff::{self, Diffable};
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct Model {goal: u32, state: u32}
+//!
+//! diffable!(Model => ModelDelta {goal: u32, state: u32});
+//!
+//! let mut model = Model {goal: 4, state: 0};
+//! let delta = diff::mutate(&mut model, |m| m.goal -= 1);
+//! assert_eq!(delta.goal, Some((4, 3)));
+//! assert_eq!(delta.state, None);
+//! diff::undo(&mut model, delta);
+//! assert_eq!(model, Model {goal: 4, state: 0});
+//! ```
+
+/// Implemented by models that produce deltas from two snapshots.
+pub trait Diffable {
+    /// The type of deltas.
+    type Delta;
+
+    /// Returns the delta that changes an old model into a new one.
+    fn diff(old: &Self, new: &Self) -> Self::Delta;
+    /// Applies a delta, changing an old model into the new one.
+    fn apply(&mut self, delta: &Self::Delta);
+    /// Reverts a delta, changing a new model back into the old one.
+    fn revert(&mut self, delta: &Self::Delta);
+}
+
+/// Changes a model and returns the delta of the change.
+pub fn mutate<M: Diffable + Clone>(model: &mut M, f: impl FnOnce(&mut M)) -> M::Delta {
+    let old = model.clone();
+    f(model);
+    M::diff(&old, model)
+}
+
+/// Undoes a delta change.
+///
+/// This can be used as a function pointer, e.g. `undo::<M>`.
+pub fn undo<M: Diffable>(model: &mut M, delta: M::Delta) {model.revert(&delta)}
+
+macro_rules! diffable_value {
+    ($($t:ty),*) => {$(
+        /// The delta is the old and new value, or `None` when unchanged.
+        impl Diffable for $t {
+            type Delta = Option<($t, $t)>;
+            fn diff(old: &Self, new: &Self) -> Self::Delta {
+                if old == new {None} else {Some((old.clone(), new.clone()))}
+            }
+            fn apply(&mut self, delta: &Self::Delta) {
+                if let Some((_, new)) = delta {*self = new.clone()}
+            }
+            fn revert(&mut self, delta: &Self::Delta) {
+                if let Some((old, _)) = delta {*self = old.clone()}
+            }
+        }
+    )*}
+}
+
+diffable_value!(bool, char, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, String);
+
+macro_rules! diffable_tuple {
+    ($($t:ident $i:tt),*) => {
+        impl<$($t: Diffable),*> Diffable for ($($t,)*) {
+            type Delta = ($($t::Delta,)*);
+            fn diff(old: &Self, new: &Self) -> Self::Delta {($($t::diff(&old.$i, &new.$i),)*)}
+            fn apply(&mut self, delta: &Self::Delta) {$(self.$i.apply(&delta.$i);)*}
+            fn revert(&mut self, delta: &Self::Delta) {$(self.$i.revert(&delta.$i);)*}
+        }
+    }
+}
+
+diffable_tuple!(A 0, B 1);
+diffable_tuple!(A 0, B 1, C 2);
+diffable_tuple!(A 0, B 1, C 2, D 3);
+
+/// Implements `Diffable` for a struct of diffable fields,
+/// declaring a struct of field deltas.
+#[macro_export]
+macro_rules! diffable {
+    ($t:ty => $delta:ident {$($field:ident: $ft:ty),* $(,)?}) => {
+        /// Stores the delta of each field.
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct $delta {
+            $(
+                /// The delta of a field.
+                pub $field: <$ft as $crate::diff::Diffable>::Delta,
+            )*
+        }
+
+        impl $crate::diff::Diffable for $t {
+            type Delta = $delta;
+            fn diff(old: &Self, new: &Self) -> $delta {
+                $delta {$($field: <$ft as $crate::diff::Diffable>::diff(&old.$field, &new.$field)),*}
+            }
+            fn apply(&mut self, delta: &$delta) {
+                $($crate::diff::Diffable::apply(&mut self.$field, &delta.$field);)*
+            }
+            fn revert(&mut self, delta: &$delta) {
+                $($crate::diff::Diffable::revert(&mut self.$field, &delta.$field);)*
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    #[test]
+    fn mutater_undoer() {
+        let z: AgentZ<(u32, u32), i32, <(u32, u32) as Diffable>::Delta> = AgentZ {
+            model: (4, 0),
+            decider: |m| if m.1 < m.0 {1} else {0},
+            actor: |m, a| m.1 = (m.1 as i32 + a) as u32,
+            mutater: |m| mutate(m, |m| m.0 = 0),
+            undoer: undo::<(u32, u32)>,
+        };
+        let mut s = z.add(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.z.model, (4, 0));
+        let mut m: (u32, u32) = (4, 0);
+        m.apply(&<(u32, u32)>::diff(&(4, 0), &(4, 3)));
+        assert_eq!(m, (4, 3));
+    }
+}
@@ -0,0 +1,117 @@
+//! Differential testing across seeds and configurations.
+//!
+//! The function `difftest` runs two agent configurations side by side
+//! over every combination of seeds and environments,
+//! and reports the steps where their safety-relevant behavior diverges:
+//! One configuration acts, while the other requests a model update or halts.
+//! Different actions are not reported, since they are equally cautious.
+//!
+//! Each run stops at its first divergence.
+//! The reproduction is minimized by trying the model at the diverging step on fresh agents:
+//! When a single decision diverges, the model alone reproduces it,
+//! otherwise the seed, environment and number of steps do.
+//!
+//! Requires the `testing` feature, or compiling tests of this library.
+
+use crate::environment::{step, Environment, StepOutcome};
+use crate::{Decision, Inspect};
+
+/// Stores a divergence between two agent configurations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence<M, A> {
+    /// The seed of the run.
+    pub seed: u64,
+    /// The index of the environment.
+    pub env: usize,
+    /// The index of the diverging step.
+    pub step: usize,
+    /// The outcome of the step for the first configuration.
+    pub first: StepOutcome<A>,
+    /// The outcome of the step for the second configuration.
+    pub second: StepOutcome<A>,
+    /// A model on which fresh agents diverge in a single decision, if found.
+    pub model: Option<M>,
+}
+
+fn acted<A>(outcome: &StepOutcome<A>) -> bool {matches!(outcome, StepOutcome::Acted(_))}
+
+/// Runs two agent configurations over seeds and environments, returning the divergences.
+///
+/// The agents are constructed from the seed of each run,
+/// and every run uses fresh clones of the environment.
+pub fn difftest<T, U, E>(
+    first: impl Fn(u64) -> T,
+    second: impl Fn(u64) -> U,
+    envs: &[E],
+    seeds: impl IntoIterator<Item = u64>,
+    max_steps: usize,
+) -> Vec<Divergence<T::Model, T::Action>>
+    where T: Inspect,
+          U: Inspect<Model = T::Model, Action = T::Action>,
+          E: Environment<Model = T::Model, Action = T::Action> + Clone,
+          T::Model: Clone,
+          T::Action: Clone
+{
+    let mut divergences = vec![];
+    for seed in seeds {
+        for (i, env) in envs.iter().enumerate() {
+            let (mut a, mut b) = (first(seed), second(seed));
+            let (mut env_a, mut env_b) = (env.clone(), env.clone());
+            for k in 0..max_steps {
+                let model = a.model().clone();
+                let (x, y) = (step(&mut a, &mut env_a), step(&mut b, &mut env_b));
+                if acted(&x) != acted(&y) {
+                    let (mut a, mut b) = (first(seed), second(seed));
+                    a.update_model(model.clone());
+                    b.update_model(model.clone());
+                    let acts = |d: Decision<_>| matches!(d, Decision::Action(_));
+                    let single = acts(a.decide()) != acts(b.decide());
+                    divergences.push(Divergence {
+                        seed,
+                        env: i,
+                        step: k,
+                        first: x,
+                        second: y,
+                        model: if single {Some(model)} else {None},
+                    });
+                    break;
+                }
+                if let (StepOutcome::Halted, StepOutcome::Halted) = (x, y) {break}
+            }
+        }
+    }
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::tests::Three;
+
+    #[test]
+    fn safety_divergence() {
+        let divergences = difftest(
+            |_| crate::tests::four().add(1),
+            |_| crate::tests::four().add(0),
+            &[Three(0), Three(3)],
+            0..2,
+            10,
+        );
+        assert_eq!(divergences.len(), 4);
+        assert_eq!(divergences[0], Divergence {
+            seed: 0,
+            env: 0,
+            step: 3,
+            first: StepOutcome::Requested,
+            second: StepOutcome::Acted(1),
+            model: Some((4, 3)),
+        });
+        assert_eq!((divergences[3].seed, divergences[3].env, divergences[3].step), (1, 1, 3));
+        let limited = |_| {
+            let mut s = crate::tests::four().add(1);
+            s.set_mutation_limit(1);
+            s
+        };
+        assert!(difftest(|_| crate::tests::four().add(1), limited, &[Three(0)], 0..1, 10).is_empty());
+    }
+}
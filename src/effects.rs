@@ -0,0 +1,119 @@
+//! Actions described by declared effects.
+//!
+//! Instead of an actor mutating the model directly,
+//! an `Effected` agent describes every action as a list of effects, which it then applies.
+//! Since the effects are known before they happen, guards can inspect exactly
+//! what a decided action will change, and the applied effects are logged for auditing.
+//!
+//! A decided action with an effect rejected by a guard is downgraded to a model request.
+//! The actor of core zero is not used.
+
+use std::fmt;
+
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Stores a layered agent whose actions are applied as effects.
+pub struct Effected<M, A, D, E> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// Returns the effects of an action on a model.
+    pub describe: fn(&M, &A) -> Vec<E>,
+    /// Applies an effect to a model.
+    pub apply: fn(&mut M, &E),
+    /// Returns `true` if an effect is allowed on a model.
+    pub guards: Vec<fn(&M, &E) -> bool>,
+    /// The applied effects of each action.
+    pub log: Vec<Vec<E>>,
+}
+
+impl<M: Clone, A, D, E: Clone> Clone for Effected<M, A, D, E> {
+    fn clone(&self) -> Self {
+        Effected {
+            agent: self.agent.clone(),
+            describe: self.describe,
+            apply: self.apply,
+            guards: self.guards.clone(),
+            log: self.log.clone(),
+        }
+    }
+}
+
+impl<M: fmt::Debug, A: fmt::Debug, D, E: fmt::Debug> fmt::Debug for Effected<M, A, D, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Effected")
+            .field("agent", &self.agent)
+            .field("guards", &self.guards.len())
+            .field("log", &self.log)
+            .finish()
+    }
+}
+
+impl<M, A, D, E> Effected<M, A, D, E> {
+    /// Creates a new agent applying actions as effects.
+    pub fn new(agent: AgentN<M, A, D>, describe: fn(&M, &A) -> Vec<E>, apply: fn(&mut M, &E)) -> Self {
+        Effected {agent, describe, apply, guards: vec![], log: vec![]}
+    }
+
+    /// Adds a guard, returning `true` if an effect is allowed.
+    pub fn guard(mut self, f: fn(&M, &E) -> bool) -> Self {
+        self.guards.push(f);
+        self
+    }
+
+    /// Returns the effects an action would have on the internal model.
+    pub fn effects(&self, action: &A) -> Vec<E> {(self.describe)(&self.agent.z.model, action)}
+
+    /// Returns `true` if all effects of an action are allowed by all guards.
+    pub fn allows(&self, action: &A) -> bool {
+        let model = &self.agent.z.model;
+        self.effects(action).iter().all(|e| self.guards.iter().all(|g| g(model, e)))
+    }
+}
+
+impl<M: Clone, A: Clone + PartialEq, D, E> Agent for Effected<M, A, D, E> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        match self.agent.decide() {
+            Decision::Action(a) if !self.allows(&a) => Decision::RequestModel,
+            x => x,
+        }
+    }
+    fn act(&mut self, action: A) {
+        let effects = self.effects(&action);
+        for e in &effects {(self.apply)(&mut self.agent.z.model, e)}
+        self.log.push(effects);
+    }
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M: Clone, A: Clone + PartialEq, D, E> Inspect for Effected<M, A, D, E> {
+    fn model(&self) -> &M {&self.agent.z.model}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Effect {
+        State(u32),
+    }
+
+    #[test]
+    fn guarded() {
+        let mut s = Effected::new(
+            crate::tests::four().add(1),
+            |m, &a| vec![Effect::State((m.1 as i32 + a) as u32)],
+            |m, e| match *e {Effect::State(x) => m.1 = x},
+        ).guard(|_, e| *e != Effect::State(2));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!((s.model(), &s.log[..]), (&(4, 1), &[vec![Effect::State(1)]][..]));
+        assert_eq!(s.effects(&1), vec![Effect::State(2)]);
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
@@ -0,0 +1,81 @@
+//! Models with multiple environment hypotheses.
+//!
+//! Mutation probing explores alternatives of a single model.
+//! When there are several plausible hypotheses of the environment,
+//! e.g. two different transition dynamics, an `Ensemble` makes this multiplicity explicit:
+//! its model is a list of hypotheses, and it decides on each of them with a layered agent.
+//!
+//! The ensemble acts only when the same action is chosen under all hypotheses,
+//! and halts only when all hypotheses halt.
+//! Otherwise it requests a model update, which replaces the hypotheses.
+//! Acting, mutating and undoing applies to every hypothesis.
+
+use crate::workspace::Workspace;
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Stores a layered agent deciding under multiple hypotheses.
+#[derive(Clone, Debug)]
+pub struct Ensemble<M, A, D> {
+    /// The layered agent, whose internal model is not used.
+    pub agent: AgentN<M, A, D>,
+    /// The hypotheses.
+    pub hypotheses: Vec<M>,
+    /// The decision under each hypothesis in the last decide call.
+    pub decisions: Vec<Decision<A>>,
+    workspace: Workspace<M, A, D>,
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Ensemble<M, A, D> {
+    /// Creates a new ensemble of hypotheses.
+    pub fn new(agent: AgentN<M, A, D>, hypotheses: Vec<M>) -> Self {
+        Ensemble {agent, hypotheses, decisions: vec![], workspace: Workspace::new()}
+    }
+
+    /// Returns `true` if the decisions in the last decide call disagreed.
+    pub fn disagrees(&self) -> bool {self.decisions.windows(2).any(|w| w[0] != w[1])}
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Agent for Ensemble<M, A, D> {
+    type Model = Vec<M>;
+    type Action = A;
+    type Delta = Vec<D>;
+    fn update_model(&mut self, hypotheses: Vec<M>) {self.hypotheses = hypotheses}
+    fn decide(&mut self) -> Decision<A> {
+        let (agent, workspace) = (&self.agent, &mut self.workspace);
+        self.decisions = self.hypotheses.iter().map(|m| agent.decide_in(m, workspace)).collect();
+        if self.disagrees() {return Decision::RequestModel}
+        self.decisions.first().cloned().unwrap_or(Decision::RequestModel)
+    }
+    fn act(&mut self, action: A) {
+        for m in &mut self.hypotheses {(self.agent.z.actor)(m, action.clone())}
+    }
+    fn mutate(&mut self) -> Vec<D> {
+        let mutater = self.agent.z.mutater;
+        self.hypotheses.iter_mut().map(mutater).collect()
+    }
+    fn undo(&mut self, deltas: Vec<D>) {
+        for (m, delta) in self.hypotheses.iter_mut().zip(deltas) {(self.agent.z.undoer)(m, delta)}
+    }
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Inspect for Ensemble<M, A, D> {
+    fn model(&self) -> &Vec<M> {&self.hypotheses}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unanimous() {
+        let mut s = Ensemble::new(crate::tests::four().add(1), vec![(4, 0), (4, 1)]);
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!(s.model(), &vec![(4, 1), (4, 2)]);
+        s.update_model(vec![(4, 1), (0, 1)]);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert!(s.disagrees());
+        s.update_model(vec![]);
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
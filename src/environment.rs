@@ -0,0 +1,237 @@
+//! Environments that agents interact with.
+//!
+//! An agent acts on its internal model and requests a model update when uncertain.
+//! An `Environment` performs the actions of the agent and answers model requests.
+//!
+//! The function `step` drives one decision of an agent in an environment.
+//! For simple simulations, `Simulate::steps` returns an iterator over steps,
+//! and `run_until` drives an agent until a goal is reached.
+//! To measure how much caution costs, `run_measured` also tracks progress towards the goal.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Implemented by environments.
+pub trait Environment {
+    /// The type of models of the environment.
+    type Model;
+    /// The type of actions.
+    type Action;
+
+    /// Returns an updated model of the environment.
+    fn model(&mut self) -> Self::Model;
+    /// Performs an action in the environment.
+    fn act(&mut self, action: &Self::Action);
+}
+
+/// Stores the outcome of a step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StepOutcome<A> {
+    /// The agent acted.
+    Acted(A),
+    /// The agent requested a model update, which was answered by the environment.
+    Requested,
+    /// The agent halted.
+    Halted,
+}
+
+/// Drives one decision of an agent in an environment.
+///
+/// An action is performed both in the environment and on the internal model of the agent.
+pub fn step<T, E>(agent: &mut T, env: &mut E) -> StepOutcome<T::Action>
+    where T: Agent, E: Environment<Model = T::Model, Action = T::Action>, T::Action: Clone
+{
+    let decision = agent.decide();
+    perform(agent, env, &decision);
+    match decision {
+        Decision::Action(a) => StepOutcome::Acted(a),
+        Decision::RequestModel => StepOutcome::Requested,
+        Decision::Halt => StepOutcome::Halted,
+    }
+}
+
+fn perform<T, E>(agent: &mut T, env: &mut E, decision: &Decision<T::Action>)
+    where T: Agent, E: Environment<Model = T::Model, Action = T::Action>, T::Action: Clone
+{
+    match decision {
+        Decision::Action(a) => {
+            env.act(a);
+            agent.act(a.clone());
+        }
+        Decision::RequestModel => agent.update_model(env.model()),
+        Decision::Halt => {}
+    }
+}
+
+/// Stores an iterator over steps of an agent in an environment.
+pub struct Steps<'a, T, E, P> {
+    agent: &'a mut T,
+    env: &'a mut E,
+    until: P,
+    done: bool,
+}
+
+impl<T, E, P> Iterator for Steps<'_, T, E, P>
+    where T: Inspect,
+          E: Environment<Model = T::Model, Action = T::Action>,
+          P: FnMut(&Decision<T::Action>, &T::Model) -> bool,
+          T::Model: Clone,
+          T::Action: Clone
+{
+    type Item = (Decision<T::Action>, T::Model);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {return None}
+        let decision = self.agent.decide();
+        perform(self.agent, self.env, &decision);
+        let model = self.agent.model().clone();
+        self.done = matches!(decision, Decision::Halt) || (self.until)(&decision, &model);
+        Some((decision, model))
+    }
+}
+
+/// Stores a report of running an agent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RunReport {
+    /// Whether the goal was reached.
+    pub goal: bool,
+    /// Whether the agent halted.
+    pub halted: bool,
+    /// The number of steps used.
+    pub steps: usize,
+    /// The number of model requests, answered by the environment.
+    pub requests: usize,
+}
+
+/// Runs an agent until the model satisfies the goal, or the maximum number of steps is used.
+///
+/// Model requests are answered by the environment, which acts as an oracle.
+pub fn run_until<T, E, G>(agent: &mut T, env: &mut E, goal: G, max_steps: usize) -> RunReport
+    where T: Inspect,
+          E: Environment<Model = T::Model, Action = T::Action>,
+          G: FnMut(&T::Model) -> bool,
+          T::Action: Clone
+{
+    run_measured(agent, env, goal, max_steps, |_| 0.0).report
+}
+
+/// Stores a report of running an agent, together with its effectiveness.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Measured {
+    /// The report.
+    pub report: RunReport,
+    /// The effectiveness after the last step.
+    pub effectiveness: f64,
+    /// The mean effectiveness over all steps.
+    ///
+    /// Steps spent waiting for model updates lower the mean without lowering the final effectiveness.
+    pub mean_effectiveness: f64,
+}
+
+/// Runs an agent like `run_until`, evaluating an effectiveness metric after every step.
+///
+/// The metric evaluates progress towards the goal in the environment.
+pub fn run_measured<T, E, G, F>(
+    agent: &mut T,
+    env: &mut E,
+    mut goal: G,
+    max_steps: usize,
+    mut metric: F
+) -> Measured
+    where T: Inspect,
+          E: Environment<Model = T::Model, Action = T::Action>,
+          G: FnMut(&T::Model) -> bool,
+          F: FnMut(&E) -> f64,
+          T::Action: Clone
+{
+    let mut measured = Measured {effectiveness: metric(env), ..Measured::default()};
+    let mut sum = 0.0;
+    let report = &mut measured.report;
+    while !goal(agent.model()) {
+        if report.steps == max_steps {break}
+        report.steps += 1;
+        let outcome = step(agent, env);
+        measured.effectiveness = metric(env);
+        sum += measured.effectiveness;
+        match outcome {
+            StepOutcome::Acted(_) => {}
+            StepOutcome::Requested => report.requests += 1,
+            StepOutcome::Halted => {
+                report.halted = true;
+                break;
+            }
+        }
+    }
+    report.goal = !report.halted && goal(agent.model());
+    measured.mean_effectiveness = if report.steps == 0 {measured.effectiveness}
+        else {sum / report.steps as f64};
+    measured
+}
+
+/// Implemented by agents that can be simulated in an environment.
+pub trait Simulate: Inspect + Sized {
+    /// Returns an iterator over decisions, together with a snapshot of the model after each step.
+    ///
+    /// The iterator ends after the agent halts,
+    /// or after the step where `until` returns `true`.
+    fn steps<'a, E, P>(&'a mut self, env: &'a mut E, until: P) -> Steps<'a, Self, E, P>
+        where E: Environment<Model = Self::Model, Action = Self::Action>,
+              P: FnMut(&Decision<Self::Action>, &Self::Model) -> bool
+    {
+        Steps {agent: self, env, until, done: false}
+    }
+}
+
+impl<T: Inspect> Simulate for T {}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The environment of `crate::tests::four` where the true goal is `3`.
+    #[derive(Clone)]
+    pub struct Three(pub u32);
+
+    impl Environment for Three {
+        type Model = (u32, u32);
+        type Action = i32;
+        fn model(&mut self) -> (u32, u32) {(3, self.0)}
+        fn act(&mut self, action: &i32) {self.0 = (self.0 as i32 + action) as u32}
+    }
+
+    #[test]
+    fn steps() {
+        let mut agent = crate::tests::four().add(1);
+        let mut env = Three(0);
+        assert_eq!(step(&mut agent, &mut env), StepOutcome::Acted(1));
+        assert_eq!(step(&mut agent, &mut env), StepOutcome::Acted(1));
+        assert_eq!(env.0, 2);
+        assert_eq!(step(&mut agent, &mut env), StepOutcome::Acted(1));
+        assert_eq!(step(&mut agent, &mut env), StepOutcome::Requested);
+        assert_eq!(agent.z.model, (3, 3));
+
+        let mut agent = crate::tests::four().add(1);
+        let mut env = Three(2);
+        agent.update_model((4, 2));
+        // Stop after learning the true goal.
+        let steps: Vec<_> = agent.steps(&mut env, |_, m| m.0 == 3).collect();
+        assert_eq!(steps, vec![(Decision::Action(1), (4, 3)), (Decision::RequestModel, (3, 3))]);
+    }
+
+    #[test]
+    fn run() {
+        let mut agent = crate::tests::four().add(1);
+        let report = run_until(&mut agent, &mut Three(0), |m| m.0 == 3 && m.1 == 3, 10);
+        assert_eq!(report, RunReport {goal: true, halted: false, steps: 4, requests: 1});
+
+        // The agent never reaches the state `4` when told the goal is `3`.
+        let mut agent = crate::tests::four().add(1);
+        let report = run_until(&mut agent, &mut Three(0), |m| m.1 == 4, 10);
+        assert_eq!(report, RunReport {goal: false, halted: false, steps: 10, requests: 7});
+
+        // Progress is the fraction of the way to the true goal.
+        let mut agent = crate::tests::four().add(1);
+        let measured = run_measured(&mut agent, &mut Three(0), |m| m.0 == 3, 10, |env| env.0 as f64 / 3.0);
+        assert_eq!(measured.report.steps, 4);
+        assert_eq!(measured.effectiveness, 1.0);
+        assert_eq!(measured.mean_effectiveness, 0.75);
+    }
+}
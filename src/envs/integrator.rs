@@ -0,0 +1,154 @@
+//! A one-dimensional double integrator with continuous actions.
+//!
+//! The agent applies a force to a unit mass, steering it to a target position
+//! without passing a wall.
+//! The agent believes the target is somewhere, but the true target might be closer to the wall.
+//! The mutater moves the believed target towards the origin.
+//!
+//! Actions are floats, so sub-agents rarely decide exactly equal actions.
+//! Use `agree` as comparator, which treats forces within `TOLERANCE` as agreeing.
+//!
+//! While the agent waits for a model update, time passes without applying force.
+//! Passing the wall counts as a safety violation.
+
+use crate::builder::AgentBuilder;
+use crate::environment::Environment;
+use crate::envs::Benchmark;
+use crate::pareto::Outcome;
+use crate::AgentZ;
+
+/// The time step.
+pub const DT: f64 = 0.1;
+/// The maximum difference between forces that agree.
+pub const TOLERANCE: f64 = 0.5;
+
+/// Stores a model of the double integrator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Model {
+    /// The position.
+    pub pos: f64,
+    /// The velocity.
+    pub vel: f64,
+    /// The target position.
+    pub target: f64,
+    /// The position of the wall.
+    pub wall: f64,
+    /// The maximum force.
+    pub max_force: f64,
+}
+
+impl Model {
+    /// Advances time by one step while applying a force.
+    pub fn advance(&mut self, force: f64) {
+        self.vel += force.max(-self.max_force).min(self.max_force) * DT;
+        self.pos += self.vel * DT;
+    }
+}
+
+/// Returns a force using a critically damped controller.
+pub fn decide(model: &Model) -> f64 {
+    let force = (model.target - model.pos) - 2.0 * model.vel;
+    force.max(-model.max_force).min(model.max_force)
+}
+
+/// Applies a force.
+pub fn act(model: &mut Model, force: f64) {model.advance(force)}
+
+/// Moves the target towards the origin, returning the old target.
+pub fn nudge_target(model: &mut Model) -> f64 {
+    let old = model.target;
+    model.target -= 1.0;
+    old
+}
+
+/// Restores the target.
+pub fn undo(model: &mut Model, target: f64) {model.target = target}
+
+/// Returns `true` when two forces agree approximately.
+pub fn agree(a: &f64, b: &f64) -> bool {(a - b).abs() <= TOLERANCE}
+
+/// Returns a core zero agent.
+pub fn agent(model: Model) -> AgentZ<Model, f64, f64> {
+    AgentZ {model, decider: decide, actor: act, mutater: nudge_target, undoer: undo}
+}
+
+/// Returns a builder using approximate agreement.
+pub fn builder(model: Model) -> AgentBuilder<Model, f64, f64> {
+    AgentBuilder::new()
+        .model(model)
+        .decider(decide)
+        .actor(act)
+        .mutater(nudge_target)
+        .undoer(undo)
+        .comparator(agree)
+}
+
+/// Stores the true state of the double integrator.
+#[derive(Clone, Copy, Debug)]
+pub struct DoubleIntegrator {
+    /// The true model.
+    pub truth: Model,
+    /// Whether the wall was passed.
+    pub violation: bool,
+}
+
+impl DoubleIntegrator {
+    /// Creates a new double integrator.
+    pub fn new(truth: Model) -> Self {DoubleIntegrator {truth, violation: false}}
+
+    /// Returns the outcome, where the goal is to rest near the target.
+    pub fn outcome(&self) -> Outcome {
+        let t = &self.truth;
+        Outcome {
+            goal: (t.pos - t.target).abs() < 0.1 && t.vel.abs() < 0.1,
+            violation: self.violation,
+        }
+    }
+
+    fn advance(&mut self, force: f64) {
+        self.truth.advance(force);
+        if self.truth.pos > self.truth.wall {self.violation = true}
+    }
+}
+
+impl Environment for DoubleIntegrator {
+    type Model = Model;
+    type Action = f64;
+    fn model(&mut self) -> Model {
+        self.advance(0.0);
+        self.truth
+    }
+    fn act(&mut self, force: &f64) {self.advance(*force)}
+}
+
+/// Effectiveness decreases with the distance to the target.
+impl Benchmark for DoubleIntegrator {
+    fn effectiveness(&self) -> f64 {1.0 / (1.0 + (self.truth.pos - self.truth.target).abs())}
+    fn violations(&self) -> usize {self.violation as usize}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::step;
+
+    fn model(target: f64) -> Model {
+        Model {pos: 0.0, vel: 0.0, target, wall: 4.5, max_force: 0.5}
+    }
+
+    fn run(mut agent: impl crate::Inspect<Model = Model, Action = f64>) -> Outcome {
+        let mut env = DoubleIntegrator::new(model(4.0));
+        for _ in 0..300 {step(&mut agent, &mut env);}
+        env.outcome()
+    }
+
+    #[test]
+    fn benchmark() {
+        // The agent believes the target is at `5`, but it is at `4`.
+        assert_eq!(run(agent(model(5.0))), Outcome {goal: false, violation: true});
+        assert_eq!(run(builder(model(5.0)).layers(1).build().unwrap()),
+                   Outcome {goal: true, violation: false});
+        // With exact agreement, the agent stalls before reaching the target.
+        assert_eq!(run(agent(model(5.0)).add(1)), Outcome {goal: false, violation: false});
+    }
+}
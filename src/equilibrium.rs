@@ -0,0 +1,97 @@
+//! Equilibrium checks for game-theoretic models.
+//!
+//! When a model embeds the payoffs of other agents,
+//! the chosen action is only safe as long as it is a best response to what they will do.
+//! The assumed payoffs of other agents are uncertain,
+//! so an equilibrium that breaks under small changes to them is fragile.
+//!
+//! An `Equilibrium` wraps an agent and checks that the chosen action remains a best response
+//! when the assumed payoffs of other agents are mutated.
+//! Mutations are applied to a copy of the model.
+//! When the equilibrium is fragile, the decision is downgraded to a model request,
+//! and the mutation that broke it is recorded.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an agent that checks the stability of equilibria.
+#[derive(Clone, Debug)]
+pub struct Equilibrium<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// Mutates the assumed payoffs of other agents.
+    pub mutaters: Vec<fn(&mut T::Model)>,
+    /// Returns `true` if an action is a best response in a model.
+    pub best_response: fn(&T::Model, &T::Action) -> bool,
+    /// The index of the mutater that broke the equilibrium of the last decision.
+    pub fragile: Option<usize>,
+}
+
+impl<T: Inspect> Equilibrium<T> {
+    /// Creates a new agent that checks equilibria.
+    pub fn new(agent: T, best_response: fn(&T::Model, &T::Action) -> bool) -> Self {
+        Equilibrium {agent, mutaters: vec![], best_response, fragile: None}
+    }
+
+    /// Adds a mutater of the assumed payoffs of other agents.
+    pub fn mutater(mut self, mutater: fn(&mut T::Model)) -> Self {
+        self.mutaters.push(mutater);
+        self
+    }
+
+    /// Returns the index of the first mutater under which an action is not a best response.
+    pub fn check(&self, action: &T::Action) -> Option<usize>
+        where T::Model: Clone
+    {
+        self.mutaters.iter().position(|mutater| {
+            let mut model = self.agent.model().clone();
+            mutater(&mut model);
+            !(self.best_response)(&model, action)
+        })
+    }
+}
+
+impl<T: Inspect> Agent for Equilibrium<T>
+    where T::Model: Clone
+{
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        self.fragile = None;
+        match self.agent.decide() {
+            Decision::Action(a) => {
+                self.fragile = self.check(&a);
+                if self.fragile.is_some() {Decision::RequestModel} else {Decision::Action(a)}
+            }
+            Decision::RequestModel => Decision::RequestModel,
+            Decision::Halt => Decision::Halt,
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Equilibrium<T>
+    where T::Model: Clone
+{
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fragile() {
+        // The goal is where the other agent is assumed to meet us.
+        let best_response = |m: &(u32, u32), a: &i32| (m.0 as i32 - m.1 as i32).signum() == *a;
+        let mut s = Equilibrium::new(crate::tests::four().add(0), best_response)
+            .mutater(|m| m.0 = m.0.saturating_sub(1));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.fragile, Some(0));
+    }
+}
@@ -0,0 +1,101 @@
+//! Crate-wide error type.
+//!
+//! Fallible parts of this library report errors using `Error`,
+//! instead of panicking.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::builder::BuildError;
+use crate::composition::CompositionError;
+
+/// Stores an error.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// The decider failed to decide.
+    Decider(String),
+    /// A message violated the protocol between agent and environment.
+    Protocol(String),
+    /// An operation did not finish in time.
+    Timeout(Duration),
+    /// An invariant was violated.
+    Invariant(String),
+    /// An action was decided for an older model generation.
+    StaleAction {
+        /// The model generation that the action was decided for.
+        action: u64,
+        /// The current model generation.
+        model: u64,
+    },
+    /// A text could not be parsed.
+    Parse {
+        /// The line number, starting at `1`.
+        line: usize,
+        /// A description of the problem.
+        message: String,
+    },
+    /// There is no migration between schema versions.
+    Migration {
+        /// The version to migrate from.
+        from: u32,
+        /// The version to migrate to.
+        to: u32,
+    },
+    /// An agent could not be built.
+    Build(BuildError),
+    /// A signed model update was rejected.
+    Signature(String),
+    /// Stacked layers are in an incoherent order.
+    Composition(CompositionError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Decider(msg) => write!(f, "Decider failed: {}", msg),
+            Error::Protocol(msg) => write!(f, "Protocol error: {}", msg),
+            Error::Timeout(duration) => write!(f, "Timeout after {:?}", duration),
+            Error::Invariant(msg) => write!(f, "Invariant violated: {}", msg),
+            Error::StaleAction {action, model} =>
+                write!(f, "Action decided for model generation {}, but current is {}",
+                       action, model),
+            Error::Parse {line, message} => write!(f, "Parse error at line {}: {}", line, message),
+            Error::Migration {from, to} =>
+                write!(f, "No migration from schema version {} to {}", from, to),
+            Error::Build(err) => write!(f, "Build error: {}", err),
+            Error::Signature(msg) => write!(f, "Signature rejected: {}", msg),
+            Error::Composition(err) => write!(f, "Composition error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Build(err) => Some(err),
+            Error::Composition(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(err: BuildError) -> Error {Error::Build(err)}
+}
+
+impl From<CompositionError> for Error {
+    fn from(err: CompositionError) -> Error {Error::Composition(err)}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let err: Error = BuildError::MissingModel.into();
+        assert_eq!(err.to_string(), "Build error: Missing model");
+        assert!(std::error::Error::source(&err).is_some());
+        assert_eq!(Error::Timeout(Duration::from_millis(5)).to_string(), "Timeout after 5ms");
+    }
+}
@@ -0,0 +1,101 @@
+//! Behavior when probes are exhausted.
+//!
+//! When every probe of the outermost safety layer requests a model update,
+//! no mutation determines a decision, and the agent requests a model update.
+//! For deployments where stalling forever is itself a hazard,
+//! `AgentN::exhausted` configures what to do instead:
+//!
+//! - `Request`: request a model update, which is the default
+//! - `ActWithCaution`: act on the decision of core zero, graded as `graded::Grade::ActWithCaution`
+//! - `Fallback`: use the same fallback as `budget::Budget`, i.e. a safe default policy or halting
+//!
+//! The reason of such actions is still `Reason::Undetermined`.
+//! Inner safety layers always request a model update,
+//! since acting there would lead to regression in the layers probing them.
+
+use std::fmt;
+
+use crate::budget::Fallback;
+
+/// Stores the behavior when no probe of the outermost safety layer determines a decision.
+#[derive(Default)]
+pub enum Exhausted<M, A> {
+    /// Request an updated model of the environment.
+    #[default]
+    Request,
+    /// Act on the decision of core zero, unless a pair of mutations disagrees.
+    ActWithCaution,
+    /// Use a fallback.
+    Fallback(Fallback<M, A>),
+}
+
+impl<M, A> Clone for Exhausted<M, A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A> Copy for Exhausted<M, A> {}
+
+impl<M, A> fmt::Debug for Exhausted<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exhausted::Request => f.write_str("Request"),
+            Exhausted::ActWithCaution => f.write_str("ActWithCaution"),
+            Exhausted::Fallback(fallback) => f.debug_tuple("Fallback").field(fallback).finish(),
+        }
+    }
+}
+
+impl<M, A> PartialEq for Exhausted<M, A> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Exhausted::Request, Exhausted::Request) |
+            (Exhausted::ActWithCaution, Exhausted::ActWithCaution) => true,
+            (Exhausted::Fallback(a), Exhausted::Fallback(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graded::Grade;
+    use crate::{Agent, AgentS, Decision, Diagnosis, LayerConfig, Reason};
+
+    #[test]
+    fn exhausted() {
+        // Every mutation of the outer layer disagrees in the inner layer.
+        let mut s = crate::tests::four().add(2);
+        s.z.model = (4, 2);
+        let reason = Reason::Undetermined {layer: 2};
+        assert_eq!(s.diagnose(), Diagnosis {decision: Decision::RequestModel, reason});
+        s.exhausted = Exhausted::ActWithCaution;
+        assert_eq!(s.decide_graded(), Grade::ActWithCaution(1));
+        s.exhausted = Exhausted::Fallback(Fallback::SafeDefault(|_| 0));
+        assert_eq!(s.diagnose(), Diagnosis {decision: Decision::Action(0), reason});
+        s.exhausted = Exhausted::Fallback(Fallback::Halt);
+        assert_eq!(s.diagnose(), Diagnosis {decision: Decision::Halt, reason});
+        // A pending agreement policy uses the same behavior.
+        s.layers[1].policy = Some(&crate::agreement::Unanimous);
+        s.exhausted = Exhausted::ActWithCaution;
+        assert_eq!(s.diagnose(), Diagnosis {decision: Decision::Action(1), reason});
+    }
+
+    #[test]
+    fn successor() {
+        // A successor of two safety layers applies the policy only in its own layer.
+        let mut s = AgentS {core: crate::tests::four().add(2), config: LayerConfig::default()};
+        let mut n = crate::tests::four().add(3);
+        s.core.exhausted = Exhausted::Fallback(Fallback::SafeDefault(|_| 7));
+        n.exhausted = Exhausted::Fallback(Fallback::SafeDefault(|_| 7));
+        for goal in 0..6 {
+            for state in 0..6 {
+                s.update_model((goal, state));
+                n.update_model((goal, state));
+                assert_eq!(s.diagnose(), n.diagnose());
+            }
+        }
+        n.update_model((4, 2));
+        assert_eq!(n.diagnose(), Diagnosis {decision: Decision::Action(7), reason: Reason::Undetermined {layer: 3}});
+    }
+}
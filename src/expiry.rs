@@ -0,0 +1,100 @@
+//! Expiry of stale models.
+//!
+//! The safety argument of the layers assumes that the model reflects the environment reasonably well.
+//! An `Expiring` agent gives the model a time to live,
+//! as a number of decide calls or as a duration since the last model update.
+//! When the model has expired, the agent requests a model update without deciding,
+//! regardless of whether the safety layers would agree.
+//!
+//! The model given to `Expiring::new` counts as updated at creation.
+
+use std::time::{Duration, Instant};
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores the time to live of a model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ttl {
+    /// The number of decide calls since the last model update.
+    Steps(usize),
+    /// The time since the last model update.
+    Duration(Duration),
+}
+
+/// Stores an agent whose model expires.
+#[derive(Clone, Debug)]
+pub struct Expiring<T> {
+    /// The inner agent.
+    pub agent: T,
+    /// The time to live of the model.
+    pub ttl: Ttl,
+    /// The number of decide calls since the last model update.
+    pub steps: usize,
+    /// The time of the last model update.
+    pub updated: Instant,
+    /// The number of decide calls refused because the model expired.
+    pub expirations: usize,
+}
+
+impl<T> Expiring<T> {
+    /// Creates a new agent whose model expires.
+    pub fn new(agent: T, ttl: Ttl) -> Self {
+        Expiring {agent, ttl, steps: 0, updated: Instant::now(), expirations: 0}
+    }
+
+    /// Returns `true` if the model has expired.
+    pub fn is_expired(&self) -> bool {
+        match self.ttl {
+            Ttl::Steps(n) => self.steps >= n,
+            Ttl::Duration(d) => self.updated.elapsed() >= d,
+        }
+    }
+}
+
+impl<T: Agent> Agent for Expiring<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        self.steps = 0;
+        self.updated = Instant::now();
+        self.agent.update_model(model);
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        if self.is_expired() {
+            self.expirations += 1;
+            return Decision::RequestModel;
+        }
+        self.steps += 1;
+        self.agent.decide()
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Expiring<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expire() {
+        let mut s = Expiring::new(crate::tests::four().add(1), Ttl::Steps(2));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!((s.decide(), s.expirations), (Decision::RequestModel, 1));
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::Action(1));
+
+        s.ttl = Ttl::Duration(Duration::from_secs(0));
+        assert!(s.is_expired());
+        s.ttl = Ttl::Duration(Duration::from_secs(3600));
+        assert!(!s.is_expired());
+    }
+}
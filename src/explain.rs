@@ -0,0 +1,148 @@
+//! Human-readable decision explanations.
+//!
+//! An `Explanation` tells what core zero chose,
+//! what the mutation that determined the decision chose, and what the agent does:
+//!
+//! ```text
+//! core chose +1; goal mutation #0 of layer 1 chose +1 (agree); acting
+//! ```
+//!
+//! The mutated decision is found by probing the mutation again after deciding,
+//! so stochastic mutaters without a schedule might explain a different mutation,
+//! and observers are notified of probes in inner layers.
+//!
+//! When the agent describes deltas, the mutation is explained by its metadata:
+//!
+//! ```text
+//! core chose +1; decrement of goal by 1 (mutation #0 of layer 1) chose +0 (disagree); requesting model
+//! ```
+
+use std::fmt;
+
+use crate::delta::DeltaMeta;
+use crate::{Agent, AgentN, Decision, Diagnosis, ProbeOutcome, Reason, SafetyReport};
+
+/// Stores the probe that determined a decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProbeExplanation<A> {
+    /// The safety layer, where `1` is the innermost one.
+    pub layer: usize,
+    /// The index of the probe.
+    pub probe: u8,
+    /// The part of the model targeted by the mutater.
+    pub target: Option<&'static str>,
+    /// The description of the delta, when the agent describes deltas.
+    pub meta: Option<DeltaMeta>,
+    /// The decision on the mutated model.
+    pub decision: Decision<A>,
+    /// The outcome of the probe.
+    pub outcome: ProbeOutcome,
+}
+
+/// Stores an explanation of a decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Explanation<A> {
+    /// The decision of core zero.
+    pub core: Decision<A>,
+    /// The probe that determined the decision, if any.
+    pub probe: Option<ProbeExplanation<A>>,
+    /// The decision and its reason.
+    pub diagnosis: Diagnosis<A>,
+}
+
+fn fmt_choice<A: fmt::Display>(decision: &Decision<A>, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match decision {
+        Decision::Action(a) => a.fmt(f),
+        Decision::RequestModel => f.write_str("to request a model"),
+        Decision::Halt => f.write_str("to halt"),
+    }
+}
+
+impl<A: fmt::Display> fmt::Display for Explanation<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("core chose ")?;
+        fmt_choice(&self.core, f)?;
+        match &self.probe {
+            Some(p) => {
+                f.write_str("; ")?;
+                match (p.meta, p.target) {
+                    (Some(meta), _) => write!(f, "{} (mutation #{} of layer {}) chose ", meta, p.probe, p.layer)?,
+                    (None, target) => {
+                        if let Some(target) = target {write!(f, "{} ", target)?}
+                        write!(f, "mutation #{} of layer {} chose ", p.probe, p.layer)?;
+                    }
+                }
+                fmt_choice(&p.decision, f)?;
+                write!(f, " ({})", p.outcome)?;
+            }
+            None => write!(f, "; {}", self.diagnosis.reason)?,
+        }
+        match self.diagnosis.decision {
+            Decision::Action(_) => f.write_str("; acting"),
+            Decision::RequestModel => f.write_str("; requesting model"),
+            Decision::Halt => f.write_str("; halting"),
+        }
+    }
+}
+
+impl<M, A: Clone + PartialEq, D> AgentN<M, A, D> {
+    /// Decide what to do next, together with an explanation.
+    pub fn explain(&mut self) -> Explanation<A> {
+        let diagnosis = self.diagnose();
+        let report = self.report;
+        let core = self.z.decide();
+        let probe = match diagnosis.reason {
+            Reason::Agree {layer, probe} |
+            Reason::Disagree {layer, probe} |
+            Reason::Waived {layer, probe} => {
+                let delta = self.mutate_probe(probe);
+                let meta = self.describe.map(|describe| describe(&delta));
+                let decision = self.decide_n(layer - 1, self.layers(), &mut SafetyReport::default()).0;
+                self.z.undo(delta);
+                let outcome = match (&core, &decision) {
+                    (Decision::Action(a), Decision::Action(b)) =>
+                        if self.layers[layer - 1].agree(a, b) {ProbeOutcome::Agree}
+                        else {ProbeOutcome::Disagree},
+                    _ => ProbeOutcome::RequestModel,
+                };
+                let target = self.query(Reason::Disagree {layer, probe}).and_then(|q| q.target);
+                Some(ProbeExplanation {layer, probe, target, meta, decision, outcome})
+            }
+            _ => None,
+        };
+        // Probing again is not part of the decision.
+        self.report = report;
+        Explanation {core, probe, diagnosis}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::AgentBuilder;
+    use crate::delta::DeltaMeta;
+
+    #[test]
+    fn explain() {
+        let z = crate::tests::four();
+        let mut s = AgentBuilder::new()
+            .model(z.model)
+            .decider(z.decider)
+            .actor(z.actor)
+            .mutater_targeting("goal", z.mutater)
+            .undoer(z.undoer)
+            .layers(1)
+            .build()
+            .unwrap();
+        assert_eq!(format!("{:+}", s.explain()),
+                   "core chose +1; goal mutation #0 of layer 1 chose +1 (agree); acting");
+        s.z.model = (4, 3);
+        assert_eq!(format!("{:+}", s.explain()),
+                   "core chose +1; goal mutation #0 of layer 1 chose +0 (disagree); requesting model");
+        s.handoff = true;
+        assert_eq!(s.explain().to_string(), "core chose 1; core was replaced; requesting model");
+        s.handoff = false;
+        s.describe = Some(|d| DeltaMeta::new("decrement", "goal", -*d as f64));
+        assert_eq!(format!("{:+}", s.explain()),
+                   "core chose +1; decrement of goal by 1 (mutation #0 of layer 1) chose +0 (disagree); requesting model");
+    }
+}
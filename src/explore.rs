@@ -0,0 +1,179 @@
+//! Budgeted exploration for gathering information.
+//!
+//! An agent that keeps requesting model updates might be stuck,
+//! because the environment can not answer with a model that resolves the ambiguity.
+//! An `Explorer` proposes designated information-gathering actions instead,
+//! taken from a user-supplied set of safe probes, e.g. looking around or sensing,
+//! after the agent requested model updates some number of times in a row.
+//!
+//! Every safe probe has a cost, and exploration stops when the budget is spent.
+//! Safe probes are proposed in turn, skipping those that do not apply to the model
+//! or cost more than the remaining budget.
+//! Every proposed action is recorded in an audit log.
+//!
+//! Call `Explorer::reset` at the start of every episode.
+
+use std::fmt;
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores a designated low-risk action for gathering information.
+pub struct SafeProbe<M, A> {
+    /// The name of the safe probe.
+    pub name: &'static str,
+    /// Returns the action for a model, or `None` if the probe does not apply.
+    pub propose: fn(&M) -> Option<A>,
+    /// The cost of the action.
+    pub cost: usize,
+}
+
+impl<M, A> Clone for SafeProbe<M, A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A> Copy for SafeProbe<M, A> {}
+
+impl<M, A> fmt::Debug for SafeProbe<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SafeProbe")
+            .field("name", &self.name)
+            .field("propose", &self.propose)
+            .field("cost", &self.cost)
+            .finish()
+    }
+}
+
+/// Stores an audited exploration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exploration<A> {
+    /// The index of the decide call, counted over all episodes.
+    pub decision: usize,
+    /// The name of the safe probe.
+    pub probe: &'static str,
+    /// The proposed action.
+    pub action: A,
+    /// The cost of the action.
+    pub cost: usize,
+}
+
+/// Stores an agent that explores when stuck requesting models.
+#[derive(Clone, Debug)]
+pub struct Explorer<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The safe probes.
+    pub probes: Vec<SafeProbe<T::Model, T::Action>>,
+    /// The number of model requests in a row before exploring.
+    pub patience: usize,
+    /// The exploration budget per episode.
+    pub budget: usize,
+    /// The cost spent in this episode.
+    pub spent: usize,
+    /// The number of model requests in a row.
+    pub streak: usize,
+    /// The explorations over all episodes.
+    pub audit: Vec<Exploration<T::Action>>,
+    /// The number of decide calls over all episodes.
+    decisions: usize,
+    /// The next safe probe to try.
+    next: usize,
+}
+
+impl<T: Agent> Explorer<T> {
+    /// Creates a new agent exploring with some safe probes.
+    pub fn new(agent: T, probes: Vec<SafeProbe<T::Model, T::Action>>, patience: usize, budget: usize) -> Self {
+        Explorer {agent, probes, patience, budget, spent: 0, streak: 0, audit: vec![], decisions: 0, next: 0}
+    }
+
+    /// Returns the exploration budget left in this episode.
+    pub fn remaining(&self) -> usize {self.budget.saturating_sub(self.spent)}
+
+    /// Starts a new episode, keeping the audit log.
+    pub fn reset(&mut self) {
+        self.spent = 0;
+        self.streak = 0;
+    }
+}
+
+impl<T: Inspect> Explorer<T> where T::Action: Clone {
+    fn explore(&mut self) -> Option<T::Action> {
+        let n = self.probes.len();
+        for i in 0..n {
+            let probe = self.probes[(self.next + i) % n];
+            if probe.cost > self.remaining() {continue}
+            if let Some(action) = (probe.propose)(self.agent.model()) {
+                self.next = (self.next + i + 1) % n;
+                self.spent += probe.cost;
+                self.audit.push(Exploration {
+                    decision: self.decisions,
+                    probe: probe.name,
+                    action: action.clone(),
+                    cost: probe.cost,
+                });
+                return Some(action);
+            }
+        }
+        None
+    }
+}
+
+impl<T: Inspect> Agent for Explorer<T> where T::Action: Clone {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        self.decisions += 1;
+        match self.agent.decide() {
+            Decision::RequestModel => {
+                self.streak += 1;
+                if self.streak <= self.patience {return Decision::RequestModel}
+                match self.explore() {
+                    Some(action) => {
+                        self.streak = 0;
+                        Decision::Action(action)
+                    }
+                    None => Decision::RequestModel,
+                }
+            }
+            x => {
+                self.streak = 0;
+                x
+            }
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Explorer<T> where T::Action: Clone {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explore() {
+        let probes = vec![
+            SafeProbe {name: "look", propose: |_: &(u32, u32)| Some(0), cost: 1},
+            SafeProbe {name: "listen", propose: |_: &(u32, u32)| None, cost: 1},
+            SafeProbe {name: "step back", propose: |_: &(u32, u32)| Some(-1), cost: 2},
+        ];
+        let mut s = Explorer::new(crate::tests::four().add(1), probes, 1, 3);
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::Action(0));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::Action(-1));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!((s.decide(), s.remaining()), (Decision::RequestModel, 0));
+        assert_eq!(s.audit[1], Exploration {decision: 4, probe: "step back", action: -1, cost: 2});
+        s.reset();
+        s.decide();
+        assert_eq!(s.decide(), Decision::Action(0));
+        assert_eq!(s.audit.len(), 3);
+    }
+}
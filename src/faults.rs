@@ -0,0 +1,148 @@
+//! Mutation testing of user deciders.
+//!
+//! The safety layers are supposed to catch a decider that goes wrong on the actual model.
+//! The function `inject_faults` verifies this by running the layered agent,
+//! and at every step perturbing the decision of core zero on the actual model,
+//! for example by flipping or shifting the action.
+//! Decisions on mutated models are not perturbed,
+//! so the layers catch the fault when they do not act on it.
+//! Faults that slipped through as actions are reported as escapes.
+//!
+//! The faulty core stores the original core zero in its model as `Faulty`,
+//! in the same way as other deciders that need state.
+//! Probes use the mutater of core zero.
+
+use std::ops::{Add, Neg};
+
+use crate::{Agent, AgentN, AgentZ, Decision};
+
+/// Stores a fault that perturbs an action.
+#[derive(Debug)]
+pub struct Fault<A> {
+    /// The name of the fault.
+    pub name: &'static str,
+    /// Perturbs an action.
+    pub perturb: fn(&A) -> A,
+}
+
+impl<A> Clone for Fault<A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<A> Copy for Fault<A> {}
+
+/// Flips the sign of an action.
+pub fn flip<A: Clone + Neg<Output = A>>(a: &A) -> A {-a.clone()}
+
+/// Shifts an action by one.
+pub fn shift<A: Clone + Add<Output = A> + From<u8>>(a: &A) -> A {a.clone() + A::from(1)}
+
+/// Stores core zero together with a fault injected on some model.
+#[derive(Clone, Debug)]
+pub struct Faulty<M, A, D> {
+    /// The original core zero.
+    pub core: AgentZ<M, A, D>,
+    /// The model where the fault is injected.
+    pub trigger: M,
+    /// The fault.
+    pub fault: Fault<A>,
+}
+
+fn decide<M: PartialEq, A, D>(f: &Faulty<M, A, D>) -> A {
+    let a = (f.core.decider)(&f.core.model);
+    if f.core.model == f.trigger {(f.fault.perturb)(&a)} else {a}
+}
+
+fn act<M, A, D>(f: &mut Faulty<M, A, D>, a: A) {(f.core.actor)(&mut f.core.model, a)}
+
+fn mutate<M, A, D>(f: &mut Faulty<M, A, D>) -> D {(f.core.mutater)(&mut f.core.model)}
+
+fn undo<M, A, D>(f: &mut Faulty<M, A, D>, d: D) {(f.core.undoer)(&mut f.core.model, d)}
+
+/// Stores a fault that slipped through the safety layers as an action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Escape<M, A> {
+    /// The index of the step.
+    pub step: usize,
+    /// The name of the fault.
+    pub fault: &'static str,
+    /// The model of the step.
+    pub model: M,
+    /// The faulty action.
+    pub action: A,
+}
+
+/// Stores the result of injecting faults.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaultReport<M, A> {
+    /// The number of injected faults that changed the decision of core zero.
+    pub injected: usize,
+    /// The number of faults caught by the safety layers.
+    pub caught: usize,
+    /// The faults that slipped through as actions.
+    pub escapes: Vec<Escape<M, A>>,
+}
+
+/// Runs the agent for some steps and injects every fault at every step.
+///
+/// The run stops when the agent does not act.
+/// The agent is cloned, such that it is not changed.
+pub fn inject_faults<M, A, D>(
+    agent: &AgentN<M, A, D>,
+    faults: &[Fault<A>],
+    steps: usize,
+) -> FaultReport<M, A>
+    where M: Clone + PartialEq, A: Clone + PartialEq
+{
+    let mut report = FaultReport {injected: 0, caught: 0, escapes: vec![]};
+    let mut reference = agent.clone();
+    for step in 0..steps {
+        let model = reference.z.model.clone();
+        for fault in faults {
+            let a = (agent.z.decider)(&model);
+            if (fault.perturb)(&a) == a {continue}
+            report.injected += 1;
+            let mut core = agent.z.clone();
+            core.model = model.clone();
+            let z = AgentZ {
+                model: Faulty {core, trigger: model.clone(), fault: *fault},
+                decider: decide,
+                actor: act,
+                mutater: mutate,
+                undoer: undo,
+            };
+            let mut faulty = z.add(0);
+            faulty.layers = agent.layers.clone();
+            match faulty.decide() {
+                Decision::Action(action) => report.escapes.push(Escape {
+                    step,
+                    fault: fault.name,
+                    model: model.clone(),
+                    action,
+                }),
+                Decision::RequestModel | Decision::Halt => report.caught += 1,
+            }
+        }
+        match reference.decide() {
+            Decision::Action(a) => reference.act(a),
+            Decision::RequestModel | Decision::Halt => break,
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catch_faults() {
+        let faults = [Fault {name: "flip", perturb: flip}, Fault {name: "shift", perturb: shift}];
+        let report = inject_faults(&crate::tests::four().add(1), &faults, 10);
+        assert_eq!((report.injected, report.caught), (8, 8));
+
+        let report = inject_faults(&crate::tests::four().add(0), &faults, 3);
+        assert_eq!((report.injected, report.caught), (6, 0));
+        assert_eq!(report.escapes[1], Escape {step: 0, fault: "shift", model: (4, 0), action: 2});
+    }
+}
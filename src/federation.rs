@@ -0,0 +1,99 @@
+//! Federated model-update sharing between agents.
+//!
+//! A group of agents working on the same task might stall on the same ambiguity,
+//! each requesting a model update independently.
+//! A `Federation` shares the part of a model update that clarifies the ambiguity,
+//! e.g. the goal, such that one clarification propagates to all peers.
+//!
+//! Every shared update records its provenance: the agent it originated from and a version.
+//! When a new update conflicts with the current one, a resolver decides which one wins.
+//! Accepted updates are applied to the models of all peers and kept in a log.
+
+use crate::Inspect;
+
+/// Stores a shared update with provenance.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shared<U> {
+    /// The shared part of a model.
+    pub update: U,
+    /// The index of the agent the update originated from.
+    pub origin: usize,
+    /// The version, increasing with every published update.
+    pub version: u64,
+}
+
+/// Stores a group of agents that share model updates.
+#[derive(Clone, Debug)]
+pub struct Federation<T: Inspect, U> {
+    /// The agents.
+    pub agents: Vec<T>,
+    /// Extracts the shared part of a model.
+    pub extract: fn(&T::Model) -> U,
+    /// Applies the shared part to a model.
+    pub apply: fn(&mut T::Model, U),
+    /// Returns `true` if an incoming update should replace the current one.
+    ///
+    /// Called with the current and the incoming update.
+    pub resolve: fn(&Shared<U>, &Shared<U>) -> bool,
+    /// The accepted updates, in order.
+    pub log: Vec<Shared<U>>,
+    version: u64,
+}
+
+impl<T, U> Federation<T, U>
+    where T: Inspect, T::Model: Clone, U: Clone + PartialEq
+{
+    /// Creates a new federation where the newest update wins.
+    pub fn new(agents: Vec<T>, extract: fn(&T::Model) -> U, apply: fn(&mut T::Model, U)) -> Self {
+        Federation {agents, extract, apply, resolve: |_, _| true, log: vec![], version: 0}
+    }
+
+    /// Returns the current shared update, if any.
+    pub fn current(&self) -> Option<&Shared<U>> {self.log.last()}
+
+    /// Updates the model of an agent and shares the update with its peers.
+    ///
+    /// Returns `true` if the shared update was accepted.
+    /// A rejected update is still applied to the agent it was sent to.
+    pub fn update_model(&mut self, agent: usize, model: T::Model) -> bool {
+        let update = (self.extract)(&model);
+        self.agents[agent].update_model(model);
+        if self.current().map(|c| c.update == update).unwrap_or(false) {return true}
+        self.version += 1;
+        let shared = Shared {update, origin: agent, version: self.version};
+        if let Some(current) = self.current() {
+            if !(self.resolve)(current, &shared) {return false}
+        }
+        for (i, peer) in self.agents.iter_mut().enumerate() {
+            if i == agent {continue}
+            let mut model = peer.model().clone();
+            (self.apply)(&mut model, shared.update.clone());
+            peer.update_model(model);
+        }
+        self.log.push(shared);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agent;
+
+    #[test]
+    fn propagate_goal() {
+        let agents = vec![crate::tests::four().add(1), crate::tests::four().add(1)];
+        let mut s = Federation::new(agents, |m: &(u32, u32)| m.0, |m, goal| m.0 = goal);
+        s.agents[1].update_model((4, 1));
+        assert!(s.update_model(0, (3, 0)));
+        assert_eq!(s.agents[1].z.model, (3, 1));
+        assert_eq!(s.current(), Some(&Shared {update: 3, origin: 0, version: 1}));
+
+        // Updates from the first agent take priority.
+        s.resolve = |current, incoming| incoming.origin <= current.origin;
+        assert!(!s.update_model(1, (2, 1)));
+        assert_eq!(s.agents[0].z.model, (3, 0));
+        assert_eq!(s.agents[1].z.model, (2, 1));
+        assert_eq!(s.log.len(), 1);
+    }
+}
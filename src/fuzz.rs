@@ -0,0 +1,207 @@
+//! Fuzzing support.
+//!
+//! Fuzzers produce raw bytes, which `Unstructured` turns into structured values
+//! of types implementing `Arbitrary`, in the style of the `arbitrary` crate.
+//! When the bytes run out, values default to zero, such that any input is valid.
+//!
+//! The function `fuzz` is a harness that interprets bytes as a sequence of operations,
+//! interleaving model updates with decide calls, and checks invariants of this library:
+//!
+//! - Deciding restores the model, since every mutation is undone
+//! - Mutating and undoing restores the model
+//! - The agent does not act after disagreement in the outermost safety layer,
+//!   unless the value of information waived it
+//!
+//! Decided actions are performed on the model, such that the agent moves on.
+//!
+//! Requires the `fuzz` feature, or compiling tests of this library.
+
+use crate::{Agent, AgentN, Decision, Error, ProbeOutcome, Reason};
+
+/// Stores raw bytes to take structured values from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Unstructured<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Unstructured<'a> {
+    /// Creates new unstructured data.
+    pub fn new(data: &'a [u8]) -> Self {Unstructured {data}}
+
+    /// Returns `true` if all bytes are taken.
+    pub fn is_empty(&self) -> bool {self.data.is_empty()}
+
+    /// Takes a byte, or `0` when all bytes are taken.
+    pub fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((&b, rest)) => {
+                self.data = rest;
+                b
+            }
+            None => 0,
+        }
+    }
+
+    /// Takes a number in `[0, n)`, where `n` is greater than zero.
+    pub fn choose(&mut self, n: usize) -> usize {
+        let x = if n <= 0x100 {self.byte() as usize} else {u64::arbitrary(self) as usize};
+        x % n
+    }
+
+    /// Takes a value.
+    pub fn arbitrary<T: Arbitrary>(&mut self) -> T {T::arbitrary(self)}
+}
+
+/// Implemented by types that can be taken from unstructured data.
+pub trait Arbitrary: Sized {
+    /// Takes a value from unstructured data.
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self;
+}
+
+macro_rules! arbitrary_int {
+    ($($t:ty),*) => {$(
+        impl Arbitrary for $t {
+            fn arbitrary(u: &mut Unstructured<'_>) -> Self {
+                let mut bytes = [0; std::mem::size_of::<$t>()];
+                for b in &mut bytes {*b = u.byte()}
+                <$t>::from_le_bytes(bytes)
+            }
+        }
+    )*}
+}
+
+arbitrary_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Arbitrary for bool {
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self {u.byte() & 1 == 1}
+}
+
+impl Arbitrary for () {
+    fn arbitrary(_: &mut Unstructured<'_>) -> Self {}
+}
+
+impl<T: Arbitrary, U: Arbitrary> Arbitrary for (T, U) {
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self {(u.arbitrary(), u.arbitrary())}
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self {
+        if u.arbitrary() {Some(u.arbitrary())} else {None}
+    }
+}
+
+/// Takes a sequence of up to 255 values, for example a sequence of deltas.
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self {
+        let n = u.byte();
+        (0..n).map(|_| u.arbitrary()).collect()
+    }
+}
+
+impl<A: Arbitrary> Arbitrary for Decision<A> {
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self {
+        match u.choose(3) {
+            0 => Decision::Action(u.arbitrary()),
+            1 => Decision::RequestModel,
+            _ => Decision::Halt,
+        }
+    }
+}
+
+/// Stores an operation of the fuzz harness.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Op<M> {
+    /// Update the model.
+    Update(M),
+    /// Decide, performing the action if any.
+    Decide,
+    /// Mutate and undo the model.
+    MutateUndo,
+}
+
+impl<M: Arbitrary> Arbitrary for Op<M> {
+    fn arbitrary(u: &mut Unstructured<'_>) -> Self {
+        match u.choose(4) {
+            0 => Op::Update(u.arbitrary()),
+            1 => Op::MutateUndo,
+            _ => Op::Decide,
+        }
+    }
+}
+
+/// Runs operations taken from bytes on an agent, checking invariants.
+///
+/// Returns the number of operations, or the first violated invariant.
+pub fn fuzz<M, A, D>(agent: &mut AgentN<M, A, D>, data: &[u8]) -> Result<usize, Error>
+    where M: Arbitrary + Clone + PartialEq, A: PartialEq
+{
+    let mut u = Unstructured::new(data);
+    let mut ops = 0;
+    while !u.is_empty() {
+        ops += 1;
+        match u.arbitrary() {
+            Op::Update(model) => agent.update_model(model),
+            Op::MutateUndo => {
+                let model = agent.z.model.clone();
+                let delta = agent.mutate();
+                agent.undo(delta);
+                if agent.z.model != model {
+                    return Err(Error::Invariant(format!("Undo did not restore the model at operation {}", ops)));
+                }
+            }
+            Op::Decide => {
+                let model = agent.z.model.clone();
+                let (decision, rationale) = agent.decide_rationale();
+                if agent.z.model != model {
+                    return Err(Error::Invariant(format!("Deciding changed the model at operation {}", ops)));
+                }
+                let disagreed = rationale.checks.iter().any(|c| c.outcome == ProbeOutcome::Disagree);
+                // Waived disagreements and agreement policies might act after a disagreement.
+                let tolerated = matches!(rationale.reason, Reason::Waived {..} | Reason::Policy {..});
+                if let Decision::Action(a) = decision {
+                    if disagreed && !tolerated {
+                        return Err(Error::Invariant(format!("Acted after disagreement at operation {}", ops)));
+                    }
+                    agent.act(a);
+                }
+            }
+        }
+    }
+    Ok(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn invariants() {
+        let mut u = Unstructured::new(&[1, 0, 0, 0, 2, 3]);
+        assert_eq!(u.arbitrary::<u32>(), 1);
+        assert_eq!(u.arbitrary::<Decision<u8>>(), Decision::Halt);
+        assert_eq!(u.arbitrary::<Vec<i32>>(), vec![0, 0, 0]);
+        assert!(u.is_empty());
+
+        let mut rng = Rng::new(0);
+        for _ in 0..100 {
+            let data: Vec<u8> = (0..64).map(|_| rng.next_u64() as u8).collect();
+            let mut s = crate::tests::four().add(2);
+            assert!(fuzz(&mut s, &data).is_ok());
+        }
+
+        // An undoer that forgets to restore the goal is caught.
+        let mut s = crate::tests::four().add(1);
+        s.z.undoer = |_, _| {};
+        assert!(fuzz(&mut s, &[1]).is_err());
+
+        // A quorum acts although the goal mutation disagrees.
+        let mut s = crate::tests::four().add(1);
+        s.mutaters = vec![|_| 0, crate::tests::four().mutater];
+        s.layers[0].mutation_limit = 2;
+        s.layers[0].policy = Some(&crate::agreement::Quorum(0.5));
+        s.update_model((4, 3));
+        assert_eq!(fuzz(&mut s, &[2]), Ok(1));
+        assert_eq!(s.z.model, (4, 4));
+    }
+}
@@ -0,0 +1,112 @@
+//! Model-generation counters for detecting stale decisions.
+//!
+//! A model update might arrive between a decide call and the action,
+//! such that the action was computed against a model that has since changed.
+//! A `Generational` agent counts model generations,
+//! increasing on every model update and action,
+//! and stamps decided actions with the generation they were computed for.
+//!
+//! `Generational::act_stamped` rejects a stale action with `Error::StaleAction`.
+//! As an `Agent`, acting on anything but the last decided action for the current generation
+//! is refused, and the refused action is logged with its generation.
+//! Stamps are plain data, such that runtimes can pass them across threads or processes,
+//! e.g. as the generation of `wire::Message::ModelUpdate`.
+//! For stamps that can not be forged, see `verified::Guarded`.
+
+use crate::{Agent, Decision, Error, Inspect};
+
+/// Stores an action stamped with the model generation it was decided for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Stamped<A> {
+    /// The action.
+    pub action: A,
+    /// The model generation.
+    pub generation: u64,
+}
+
+/// Stores an agent that counts model generations.
+#[derive(Clone, Debug)]
+pub struct Generational<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The current model generation.
+    pub generation: u64,
+    /// The generation of the last decided action, if not yet acted on.
+    pub decided: Option<u64>,
+    /// The refused actions with the generation they were decided for, if any.
+    pub stale: Vec<(T::Action, Option<u64>)>,
+}
+
+impl<T: Agent> Generational<T> {
+    /// Creates a new agent counting model generations from zero.
+    pub fn new(agent: T) -> Self {Generational {agent, generation: 0, decided: None, stale: vec![]}}
+
+    /// Decide what to do next, stamping actions with the current generation.
+    pub fn decide_stamped(&mut self) -> Decision<Stamped<T::Action>> {
+        match self.decide() {
+            Decision::Action(action) => Decision::Action(Stamped {action, generation: self.generation}),
+            Decision::RequestModel => Decision::RequestModel,
+            Decision::Halt => Decision::Halt,
+        }
+    }
+
+    /// Performs a stamped action, returning an error if the action is stale.
+    pub fn act_stamped(&mut self, action: Stamped<T::Action>) -> Result<(), Error> {
+        if action.generation != self.generation {
+            return Err(Error::StaleAction {action: action.generation, model: self.generation});
+        }
+        self.perform(action.action);
+        Ok(())
+    }
+
+    fn perform(&mut self, action: T::Action) {
+        self.generation += 1;
+        self.decided = None;
+        self.agent.act(action);
+    }
+}
+
+impl<T: Agent> Agent for Generational<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        self.generation += 1;
+        self.agent.update_model(model);
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        let decision = self.agent.decide();
+        self.decided = if let Decision::Action(_) = decision {Some(self.generation)} else {None};
+        decision
+    }
+    fn act(&mut self, action: T::Action) {
+        if self.decided == Some(self.generation) {self.perform(action)}
+        else {self.stale.push((action, self.decided))}
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Generational<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale() {
+        let mut s = Generational::new(crate::tests::four().add(1));
+        let a = match s.decide_stamped() {Decision::Action(a) => a, _ => panic!()};
+        s.update_model((4, 1));
+        assert_eq!(s.act_stamped(a), Err(Error::StaleAction {action: 0, model: 1}));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.update_model((4, 2));
+        s.act(1);
+        assert_eq!((s.model(), &s.stale[..]), (&(4, 2), &[(1, Some(1))][..]));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!((s.model(), s.generation), (&(4, 3), 3));
+    }
+}
@@ -0,0 +1,178 @@
+//! Golden-trace regression testing.
+//!
+//! A recorded `Trace` can be snapshot as a golden file,
+//! with one line per step of tab-separated model, decision and rationale:
+//!
+//! ```text
+//! (4, 0) Action(1) {"layer":1,"reason":["agree",1,0],"checks":[...]}
+//! ```
+//!
+//! Later, `check_golden` compares a new trace of a refactored agent on the same seeds
+//! against the golden file, returning the steps that differ.
+//! `assert_golden` panics with the differences,
+//! and writes the golden file when it is missing or when `UPDATE_GOLDEN` is set,
+//! such that intended changes are accepted explicitly.
+//!
+//! A trace with a manifest writes it on the first line, as `manifest` followed by a tab and JSON.
+//! `assert_golden` refuses a golden file with a mismatched manifest,
+//! unless `ALLOW_MISMATCH` is set.
+//!
+//! Requires the `testing` feature, or compiling tests of this library.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::manifest::{self, Manifest, Mismatch};
+use crate::trace::Trace;
+
+/// The environment variable that makes `assert_golden` overwrite golden files.
+pub const UPDATE: &str = "UPDATE_GOLDEN";
+
+/// The environment variable that makes `assert_golden` accept mismatched manifests.
+pub const ALLOW_MISMATCH: &str = "GOLDEN_ALLOW_MISMATCH";
+
+const MANIFEST: &str = "manifest\t";
+
+/// Stores a step where a trace differs from the golden trace.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Diff {
+    /// The index of the step.
+    pub step: usize,
+    /// The golden line, if the golden trace has this step.
+    pub expected: Option<String>,
+    /// The new line, if the new trace has this step.
+    pub actual: Option<String>,
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "step {}:", self.step)?;
+        writeln!(f, "- {}", self.expected.as_deref().unwrap_or("<missing>"))?;
+        write!(f, "+ {}", self.actual.as_deref().unwrap_or("<missing>"))
+    }
+}
+
+impl<M: fmt::Debug, A: fmt::Debug> Trace<M, A> {
+    /// Returns the trace in the golden file format.
+    pub fn to_golden(&self) -> String {
+        let manifest = self.manifest.iter().map(|m| format!("{}{}\n", MANIFEST, m.to_json()));
+        manifest.chain(self.steps.iter().map(|step| format!(
+            "{:?}\t{:?}\t{}\n", step.model, step.decision, step.rationale.to_json()
+        ))).collect()
+    }
+}
+
+fn split_manifest(src: &str) -> (Option<Manifest>, &str) {
+    match src.strip_prefix(MANIFEST) {
+        Some(rest) => {
+            let (line, steps) = rest.split_once('\n').unwrap_or((rest, ""));
+            (Manifest::from_json(line), steps)
+        }
+        None => (None, src),
+    }
+}
+
+/// Returns the steps where a trace differs from a golden trace.
+///
+/// Manifests are not compared.
+pub fn diff(expected: &str, actual: &str) -> Vec<Diff> {
+    let mut expected = split_manifest(expected).1.lines();
+    let mut actual = split_manifest(actual).1.lines();
+    let mut diffs = vec![];
+    for step in 0.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => break,
+            (a, b) if a == b => {}
+            (a, b) => diffs.push(Diff {step, expected: a.map(String::from), actual: b.map(String::from)}),
+        }
+    }
+    diffs
+}
+
+/// Compares a trace against a golden file, returning the steps that differ.
+///
+/// A missing golden file differs at every step.
+pub fn check_golden<M, A>(path: impl AsRef<Path>, trace: &Trace<M, A>) -> Vec<Diff>
+    where M: fmt::Debug, A: fmt::Debug
+{
+    let expected = fs::read_to_string(path).unwrap_or_default();
+    diff(&expected, &trace.to_golden())
+}
+
+/// Returns an error if the manifest of a golden file does not match the manifest of a trace.
+///
+/// A missing golden file, or a trace or golden file without a manifest, matches.
+pub fn check_manifest<M, A>(path: impl AsRef<Path>, trace: &Trace<M, A>) -> Result<(), Mismatch> {
+    let expected = fs::read_to_string(path).unwrap_or_default();
+    manifest::check(split_manifest(&expected).0.as_ref(), trace.manifest.as_ref())
+}
+
+/// Panics if a trace differs from a golden file.
+///
+/// Writes the golden file instead, when it is missing or when `UPDATE_GOLDEN` is set.
+/// Panics on a mismatched manifest, unless `GOLDEN_ALLOW_MISMATCH` is set.
+pub fn assert_golden<M, A>(path: impl AsRef<Path>, trace: &Trace<M, A>)
+    where M: fmt::Debug, A: fmt::Debug
+{
+    let path = path.as_ref();
+    if !path.exists() || std::env::var_os(UPDATE).is_some() {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).expect("Could not create golden file directory");
+        }
+        fs::write(path, trace.to_golden()).expect("Could not write golden file");
+        return;
+    }
+    if let Err(mismatch) = check_manifest(path, trace) {
+        if std::env::var_os(ALLOW_MISMATCH).is_none() {
+            panic!("Golden file `{}` was recorded with a different {}", path.display(), mismatch);
+        }
+    }
+    let diffs = check_golden(path, trace);
+    if !diffs.is_empty() {
+        let diffs: Vec<String> = diffs.iter().map(|d| d.to_string()).collect();
+        panic!("Trace differs from golden file `{}`:\n{}", path.display(), diffs.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regression() {
+        let path = std::env::temp_dir().join(format!("golden-{}.trace", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut trace = Trace::new();
+        trace.run(&mut crate::tests::four().add(1), 10);
+        assert_golden(&path, &trace);
+        assert_golden(&path, &trace);
+        assert!(fs::read_to_string(&path).unwrap().starts_with(
+            "(4, 0)\tAction(1)\t{\"layer\":1,\"reason\":[\"agree\",1,0]"));
+
+        // A refactored agent without safety layers acts at the last step.
+        let mut refactored = Trace::new();
+        refactored.run(&mut crate::tests::four().add(1).dec(), 5);
+        let diffs = check_golden(&path, &refactored);
+        assert_eq!(diffs.iter().map(|d| d.step).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+        assert!(diffs[3].actual.as_ref().unwrap().starts_with("(4, 3)\tAction(1)"));
+        assert_eq!(diffs[4].expected, None);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn manifest() {
+        let path = std::env::temp_dir().join(format!("golden-manifest-{}.trace", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut agent = crate::tests::four().add(1);
+        let mut trace = Trace::new().with_manifest(Manifest::capture(&agent, 1));
+        trace.run(&mut agent, 10);
+        assert_golden(&path, &trace);
+        assert!(fs::read_to_string(&path).unwrap().starts_with("manifest\t{\"version\":"));
+        assert!(check_golden(&path, &trace).is_empty());
+        let replay = trace.clone().with_manifest(Manifest::new(2));
+        assert_eq!(check_manifest(&path, &replay).unwrap_err().fields, vec!["layers", "schema"]);
+        assert!(check_manifest(&path, &Trace::<(u32, u32), i32>::new()).is_ok());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,106 @@
+//! Graded decisions with levels of abstention.
+//!
+//! A `Decision` splits between acting and asking.
+//! Executors might want to handle actions differently depending on how well they were checked,
+//! e.g. with reduced actuator limits for cautious actions.
+//! A `Grade` refines actions into two grades, by the following rules:
+//!
+//! - `Act`: a safety layer found that mutations agreed with core zero,
+//!   and no probe requested a model update or was trimmed by the latency budget
+//! - `ActWithCaution`: the action was decided without the agreement of a safety layer
+//!   (no safety layers, a waived disagreement, an agreement policy, a tie or a low priority objective),
+//!   or some probe requested a model update, or probing was trimmed
+//! - `RequestModel` and `Halt`: as for `Decision`
+//!
+//! Grades are ordered from the least to the most abstaining.
+
+use crate::{AgentN, Decision, Diagnosis, Reason, SafetyReport};
+
+/// Stores a graded decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Grade<A> {
+    /// An action that the safety layers agreed on.
+    Act(A),
+    /// An action that should be performed with caution.
+    ActWithCaution(A),
+    /// Request an updated model of the environment.
+    RequestModel,
+    /// Stop acting until the agent is reset by the environment.
+    Halt,
+}
+
+impl<A> Grade<A> {
+    /// Grades a diagnosis, using the report of the decide call.
+    pub fn new(diagnosis: Diagnosis<A>, report: &SafetyReport) -> Self {
+        match diagnosis.decision {
+            Decision::Action(a) => match diagnosis.reason {
+                Reason::Agree {..} | Reason::AllAgree {..} if report.requests == 0 && report.trimmed == 0 =>
+                    Grade::Act(a),
+                _ => Grade::ActWithCaution(a),
+            },
+            Decision::RequestModel => Grade::RequestModel,
+            Decision::Halt => Grade::Halt,
+        }
+    }
+
+    /// Returns the action, if any.
+    pub fn action(&self) -> Option<&A> {
+        match self {
+            Grade::Act(a) | Grade::ActWithCaution(a) => Some(a),
+            Grade::RequestModel | Grade::Halt => None,
+        }
+    }
+
+    /// Returns `true` if the action should be performed with caution.
+    pub fn is_cautious(&self) -> bool {matches!(self, Grade::ActWithCaution(_))}
+
+    /// Returns the level of abstention, from `0` for `Act` to `3` for `Halt`.
+    pub fn level(&self) -> u8 {
+        match self {
+            Grade::Act(_) => 0,
+            Grade::ActWithCaution(_) => 1,
+            Grade::RequestModel => 2,
+            Grade::Halt => 3,
+        }
+    }
+}
+
+impl<A> From<Grade<A>> for Decision<A> {
+    fn from(grade: Grade<A>) -> Decision<A> {
+        match grade {
+            Grade::Act(a) | Grade::ActWithCaution(a) => Decision::Action(a),
+            Grade::RequestModel => Decision::RequestModel,
+            Grade::Halt => Decision::Halt,
+        }
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D>
+    where A: PartialEq
+{
+    /// Decide what to do next, grading actions.
+    pub fn decide_graded(&mut self) -> Grade<A> {
+        let diagnosis = self.diagnose();
+        Grade::new(diagnosis, &self.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grades() {
+        let mut s = crate::tests::four().add(1);
+        assert_eq!(s.decide_graded(), Grade::Act(1));
+        s.z.model = (4, 3);
+        assert_eq!(s.decide_graded(), Grade::RequestModel);
+        s.voi = Some(|_, _, _| false);
+        assert_eq!(s.decide_graded(), Grade::ActWithCaution(1));
+        assert_eq!(Decision::from(Grade::ActWithCaution(1)), Decision::Action(1));
+
+        let mut s = crate::tests::four().add(0);
+        assert!(s.decide_graded().is_cautious());
+        assert!(Grade::Act(1) < Grade::ActWithCaution(0) && Grade::RequestModel < Grade::<i32>::Halt);
+    }
+}
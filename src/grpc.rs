@@ -0,0 +1,254 @@
+//! gRPC transport for the agent/environment protocol.
+//!
+//! The service definition is in `proto/agent.proto`, also available as `PROTO`,
+//! such that clients in other languages can be generated from it.
+//!
+//! An `AgentService` implements the service for any agent.
+//! It decodes protobuf requests, calls the agent and encodes protobuf replies,
+//! while a gRPC server handles the HTTP/2 transport and calls `AgentService::call`
+//! with the method path and the request message.
+//! Decisions are streamed to subscribers of `AgentService::trace`.
+//!
+//! Models and actions are opaque bytes in the protocol, encoded by user-supplied codecs.
+//!
+//! Requires the `grpc` feature.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::{Agent, Decision};
+
+/// The service definition.
+pub const PROTO: &str = include_str!("../proto/agent.proto");
+
+/// Stores a method of the service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// Decide what to do next.
+    Decide,
+    /// Perform an action on the internal model.
+    Act,
+    /// Update the internal model.
+    UpdateModel,
+    /// Streams every decision made after subscribing.
+    Trace,
+}
+
+impl Method {
+    /// Returns the gRPC path of the method.
+    pub fn path(&self) -> &'static str {
+        match self {
+            Method::Decide => "/agent_safety_layers.Agent/Decide",
+            Method::Act => "/agent_safety_layers.Agent/Act",
+            Method::UpdateModel => "/agent_safety_layers.Agent/UpdateModel",
+            Method::Trace => "/agent_safety_layers.Agent/Trace",
+        }
+    }
+
+    /// Returns the method of a gRPC path, if any.
+    pub fn from_path(path: &str) -> Option<Method> {
+        [Method::Decide, Method::Act, Method::UpdateModel, Method::Trace]
+            .iter().copied().find(|m| m.path() == path)
+    }
+}
+
+/// Stores a gRPC status code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// The request message is invalid.
+    InvalidArgument = 3,
+    /// The method is not implemented as a unary call.
+    Unimplemented = 12,
+}
+
+/// Stores an error status of a call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Status {
+    /// The status code.
+    pub code: Code,
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl Status {
+    fn invalid(message: &str) -> Status {Status {code: Code::InvalidArgument, message: message.into()}}
+}
+
+fn put_varint(out: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        out.push(x as u8 | 0x80);
+        x >>= 7;
+    }
+    out.push(x as u8);
+}
+
+fn get_varint(bytes: &[u8], i: &mut usize) -> Option<u64> {
+    let mut x = 0;
+    for shift in (0..64).step_by(7) {
+        let b = *bytes.get(*i)?;
+        *i += 1;
+        x |= ((b & 0x7f) as u64) << shift;
+        if b < 0x80 {return Some(x)}
+    }
+    None
+}
+
+/// Returns the bytes of a length-delimited field in a protobuf message.
+///
+/// Returns `Some(&[])` when the field is missing, which is its default value.
+fn bytes_field(msg: &[u8], field: u64) -> Option<&[u8]> {
+    let mut i = 0;
+    let mut value: &[u8] = &[];
+    while i < msg.len() {
+        let key = get_varint(msg, &mut i)?;
+        match key & 7 {
+            0 => {get_varint(msg, &mut i)?;}
+            2 => {
+                let len = get_varint(msg, &mut i)? as usize;
+                let end = i.checked_add(len).filter(|&end| end <= msg.len())?;
+                if key >> 3 == field {value = &msg[i..end]}
+                i = end;
+            }
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// Encodes a decision as a protobuf message.
+pub fn encode_decision(decision: &Decision<Vec<u8>>) -> Vec<u8> {
+    let mut out = vec![];
+    let kind = match decision {
+        Decision::Action(_) => 0,
+        Decision::RequestModel => 1,
+        Decision::Halt => 2,
+    };
+    if kind != 0 {
+        put_varint(&mut out, 1 << 3);
+        put_varint(&mut out, kind);
+    }
+    if let Decision::Action(action) = decision {
+        put_varint(&mut out, 2 << 3 | 2);
+        put_varint(&mut out, action.len() as u64);
+        out.extend_from_slice(action);
+    }
+    out
+}
+
+/// Decodes a decision from a protobuf message.
+pub fn decode_decision(msg: &[u8]) -> Option<Decision<Vec<u8>>> {
+    let mut i = 0;
+    let mut kind = 0;
+    while i < msg.len() {
+        let key = get_varint(msg, &mut i)?;
+        match key & 7 {
+            0 => {
+                let x = get_varint(msg, &mut i)?;
+                if key >> 3 == 1 {kind = x}
+            }
+            2 => {
+                let len = get_varint(msg, &mut i)? as usize;
+                i = i.checked_add(len).filter(|&end| end <= msg.len())?;
+            }
+            _ => return None,
+        }
+    }
+    match kind {
+        0 => Some(Decision::Action(bytes_field(msg, 2)?.to_vec())),
+        1 => Some(Decision::RequestModel),
+        2 => Some(Decision::Halt),
+        _ => None,
+    }
+}
+
+/// Stores an agent served over the gRPC protocol.
+#[derive(Debug)]
+pub struct AgentService<T: Agent> {
+    /// The agent.
+    pub agent: T,
+    /// Decodes a model.
+    pub decode_model: fn(&[u8]) -> Option<T::Model>,
+    /// Encodes an action.
+    pub encode_action: fn(&T::Action) -> Vec<u8>,
+    /// Decodes an action.
+    pub decode_action: fn(&[u8]) -> Option<T::Action>,
+    subscribers: Vec<Sender<Vec<u8>>>,
+}
+
+impl<T: Agent> AgentService<T> {
+    /// Creates a new service for an agent.
+    pub fn new(
+        agent: T,
+        decode_model: fn(&[u8]) -> Option<T::Model>,
+        encode_action: fn(&T::Action) -> Vec<u8>,
+        decode_action: fn(&[u8]) -> Option<T::Action>,
+    ) -> Self {
+        AgentService {agent, decode_model, encode_action, decode_action, subscribers: vec![]}
+    }
+
+    /// Calls a unary method with a request message, returning the reply message.
+    pub fn call(&mut self, path: &str, request: &[u8]) -> Result<Vec<u8>, Status> {
+        let unimplemented = || Status {code: Code::Unimplemented, message: format!("Unknown method `{}`", path)};
+        match Method::from_path(path).ok_or_else(unimplemented)? {
+            Method::Decide => {
+                let decision = match self.agent.decide() {
+                    Decision::Action(a) => Decision::Action((self.encode_action)(&a)),
+                    Decision::RequestModel => Decision::RequestModel,
+                    Decision::Halt => Decision::Halt,
+                };
+                let reply = encode_decision(&decision);
+                self.subscribers.retain(|s| s.send(reply.clone()).is_ok());
+                Ok(reply)
+            }
+            Method::Act => {
+                let bytes = bytes_field(request, 1).ok_or_else(|| Status::invalid("Invalid message"))?;
+                let action = (self.decode_action)(bytes).ok_or_else(|| Status::invalid("Invalid action"))?;
+                self.agent.act(action);
+                Ok(vec![])
+            }
+            Method::UpdateModel => {
+                let bytes = bytes_field(request, 1).ok_or_else(|| Status::invalid("Invalid message"))?;
+                let model = (self.decode_model)(bytes).ok_or_else(|| Status::invalid("Invalid model"))?;
+                self.agent.update_model(model);
+                Ok(vec![])
+            }
+            Method::Trace => Err(Status {
+                code: Code::Unimplemented,
+                message: "Trace is a streaming method, use `AgentService::trace`".into(),
+            }),
+        }
+    }
+
+    /// Subscribes to the decision stream, receiving every decision message made after this call.
+    pub fn trace(&mut self) -> Receiver<Vec<u8>> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.push(tx);
+        rx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serve() {
+        let mut s = AgentService::new(
+            crate::tests::four().add(1),
+            |b| if b.len() == 2 {Some((b[0] as u32, b[1] as u32))} else {None},
+            |a| vec![*a as u8],
+            |b| b.first().map(|&a| a as i8 as i32),
+        );
+        let trace = s.trace();
+        let reply = s.call(Method::Decide.path(), &[]).unwrap();
+        assert_eq!(decode_decision(&reply), Some(Decision::Action(vec![1])));
+        assert_eq!(s.call(Method::Act.path(), &[0x0a, 1, 1]), Ok(vec![]));
+        assert_eq!(s.agent.z.model, (4, 1));
+        assert_eq!(s.call(Method::UpdateModel.path(), &[0x0a, 2, 4, 3]), Ok(vec![]));
+        assert_eq!(decode_decision(&s.call(Method::Decide.path(), &[]).unwrap()),
+                   Some(Decision::RequestModel));
+        assert_eq!(trace.try_iter().count(), 2);
+        assert_eq!(s.call(Method::UpdateModel.path(), &[0x0a, 1, 4]).unwrap_err().code, Code::InvalidArgument);
+        assert_eq!(s.call("/Unknown", &[]).unwrap_err().code, Code::Unimplemented);
+        assert_eq!(decode_decision(&encode_decision(&Decision::Halt)), Some(Decision::Halt));
+    }
+}
@@ -0,0 +1,212 @@
+//! Async handles to agents.
+//!
+//! An `AgentHandle` runs an agent on a worker thread and exposes it through futures,
+//! such that async services can integrate layered agents without managing threads.
+//!
+//! The futures do not depend on a specific executor.
+//! At most `bound` calls are queued before callers wait for the agent to catch up,
+//! which applies backpressure on the decision stream.
+//!
+//! Deciders that decide asynchronously, e.g. over a network, implement `AsyncDecider`.
+//!
+//! Requires the `async` feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use crate::{Agent, Decision, Error};
+
+type Call<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+struct Slot<R> {
+    value: Option<R>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+// Fulfils the reply of a call, closing it when dropped without a value.
+pub(crate) struct Promise<R>(Arc<Mutex<Slot<R>>>);
+
+impl<R> Promise<R> {
+    pub(crate) fn fulfil(self, value: R) {
+        self.0.lock().unwrap().value = Some(value);
+    }
+}
+
+impl<R> Drop for Promise<R> {
+    fn drop(&mut self) {
+        let mut slot = self.0.lock().unwrap();
+        slot.closed = true;
+        if let Some(waker) = slot.waker.take() {waker.wake()}
+    }
+}
+
+pub(crate) struct Reply<R>(Arc<Mutex<Slot<R>>>);
+
+/// Returns a promise together with the future of its reply.
+pub(crate) fn promise<R>() -> (Promise<R>, Reply<R>) {
+    let slot = Arc::new(Mutex::new(Slot {value: None, closed: false, waker: None}));
+    (Promise(slot.clone()), Reply(slot))
+}
+
+/// Implemented by deciders that decide asynchronously, e.g. over a network.
+pub trait AsyncDecider<M> {
+    /// The action type.
+    type Action;
+    /// The future of a decision.
+    type Future: Future<Output = Result<Self::Action, Error>>;
+    /// Decides an action for a model.
+    fn decide(&self, model: &M) -> Self::Future;
+}
+
+impl<R> Future for Reply<R> {
+    type Output = Option<R>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<R>> {
+        let mut slot = self.0.lock().unwrap();
+        match slot.value.take() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if slot.closed => Poll::Ready(None),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct Enqueue<'a, T> {
+    calls: &'a SyncSender<Call<T>>,
+    capacity: &'a Mutex<Vec<Waker>>,
+    call: Option<Call<T>>,
+}
+
+impl<T> Unpin for Enqueue<'_, T> {}
+
+impl<T> Future for Enqueue<'_, T> {
+    type Output = bool;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        // Register before trying, such that capacity freed in between is not missed.
+        self.capacity.lock().unwrap().push(cx.waker().clone());
+        let call = self.call.take().expect("Polled after completion");
+        match self.calls.try_send(call) {
+            Ok(()) => Poll::Ready(true),
+            Err(TrySendError::Disconnected(_)) => Poll::Ready(false),
+            Err(TrySendError::Full(call)) => {
+                self.call = Some(call);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Stores an async handle to an agent running on a worker thread.
+pub struct AgentHandle<T> {
+    calls: SyncSender<Call<T>>,
+    capacity: Arc<Mutex<Vec<Waker>>>,
+    thread: JoinHandle<T>,
+}
+
+impl<T: Agent + Send + 'static> AgentHandle<T> {
+    /// Runs an agent on a worker thread.
+    ///
+    /// At most `bound` calls are queued before callers wait.
+    pub fn spawn(mut agent: T, bound: usize) -> Self {
+        let (calls, rx) = mpsc::sync_channel::<Call<T>>(bound);
+        let capacity: Arc<Mutex<Vec<Waker>>> = Arc::new(Mutex::new(vec![]));
+        let waiting = capacity.clone();
+        let thread = thread::spawn(move || {
+            while let Ok(call) = rx.recv() {
+                for waker in waiting.lock().unwrap().drain(..) {waker.wake()}
+                call(&mut agent);
+            }
+            agent
+        });
+        AgentHandle {calls, capacity, thread}
+    }
+
+    async fn call<R, F>(&self, f: F) -> Option<R>
+        where R: Send + 'static, F: FnOnce(&mut T) -> R + Send + 'static
+    {
+        let (promise, reply) = promise();
+        let call: Call<T> = Box::new(move |agent| promise.fulfil(f(agent)));
+        let sent = Enqueue {calls: &self.calls, capacity: &self.capacity, call: Some(call)}.await;
+        if sent {reply.await} else {None}
+    }
+
+    /// Decide what to do next.
+    ///
+    /// Returns `None` if the agent is no longer running.
+    pub async fn decide(&self) -> Option<Decision<T::Action>>
+        where T::Action: Send
+    {
+        self.call(|agent| agent.decide()).await
+    }
+
+    /// Update internal model.
+    ///
+    /// Returns `false` if the agent is no longer running.
+    pub async fn update_model(&self, model: T::Model) -> bool
+        where T::Model: Send
+    {
+        self.call(move |agent| agent.update_model(model)).await.is_some()
+    }
+
+    /// Perform an action on its internal model.
+    ///
+    /// Returns `false` if the agent is no longer running.
+    pub async fn act(&self, action: T::Action) -> bool
+        where T::Action: Send
+    {
+        self.call(move |agent| agent.act(action)).await.is_some()
+    }
+
+    /// Stops the agent after queued calls and returns it.
+    ///
+    /// Returns an error if the worker thread panicked.
+    pub fn shutdown(self) -> thread::Result<T> {
+        drop(self.calls);
+        self.thread.join()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::task::Wake;
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {self.0.unpark()}
+    }
+
+    /// Runs a future to completion on the current thread.
+    pub fn block_on<F: Future>(f: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut f = Box::pin(f);
+        loop {
+            match f.as_mut().poll(&mut cx) {
+                Poll::Ready(x) => return x,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn decide_and_update() {
+        let handle = AgentHandle::spawn(crate::tests::four().add(1), 1);
+        block_on(async {
+            assert!(handle.update_model((4, 3)).await);
+            assert_eq!(handle.decide().await, Some(Decision::RequestModel));
+            assert!(handle.update_model((2, 0)).await);
+            assert_eq!(handle.decide().await, Some(Decision::Action(1)));
+            assert!(handle.act(1).await);
+        });
+        assert_eq!(handle.shutdown().unwrap().z.model, (2, 1));
+    }
+}
@@ -0,0 +1,161 @@
+//! Health checks for long-running agents.
+//!
+//! A `Monitored` agent keeps liveness indicators for readiness probes:
+//! the time since the last decision that acted, the number of consecutive model requests,
+//! the state of a circuit breaker and the remaining request budget.
+//!
+//! The circuit breaker opens after a number of consecutive model requests.
+//! While it is open, the agent requests a model update without deciding.
+//! A model update half-opens the breaker, such that the next decide tries again:
+//! when it acts the breaker closes, otherwise it opens again.
+
+use std::time::{Duration, Instant};
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores the state of a circuit breaker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Breaker {
+    /// Decisions are made as usual.
+    Closed,
+    /// The next decision tries again after a model update.
+    HalfOpen,
+    /// Model updates are requested without deciding.
+    Open,
+}
+
+/// Stores liveness indicators of an agent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Health {
+    /// The time since the last decision that acted, if any.
+    pub since_action: Option<Duration>,
+    /// The number of consecutive model requests.
+    pub consecutive_requests: usize,
+    /// The state of the circuit breaker.
+    pub breaker: Breaker,
+    /// The number of model requests left in the budget, if any.
+    pub budget_remaining: Option<usize>,
+}
+
+impl Health {
+    /// Returns `true` if the agent is ready to make decisions.
+    pub fn is_ready(&self) -> bool {
+        self.breaker != Breaker::Open && self.budget_remaining != Some(0)
+    }
+}
+
+/// Stores an agent with liveness indicators.
+#[derive(Clone, Debug)]
+pub struct Monitored<T> {
+    /// The inner agent.
+    pub agent: T,
+    /// The number of consecutive model requests that opens the circuit breaker.
+    pub trip_after: usize,
+    /// The time of the last decision that acted.
+    pub last_action: Option<Instant>,
+    /// The number of consecutive model requests.
+    pub consecutive_requests: usize,
+    /// The state of the circuit breaker.
+    pub breaker: Breaker,
+    /// Returns the number of model requests left in the budget of the inner agent.
+    pub remaining: Option<fn(&T) -> usize>,
+}
+
+impl<T: Agent> Monitored<T> {
+    /// Creates a new monitored agent.
+    pub fn new(agent: T, trip_after: usize) -> Self {
+        Monitored {
+            agent,
+            trip_after,
+            last_action: None,
+            consecutive_requests: 0,
+            breaker: Breaker::Closed,
+            remaining: None,
+        }
+    }
+
+    /// Reports the remaining request budget of the inner agent, e.g. `Budget::remaining`.
+    pub fn with_budget(mut self, remaining: fn(&T) -> usize) -> Self {
+        self.remaining = Some(remaining);
+        self
+    }
+
+    /// Returns the liveness indicators.
+    pub fn health(&self) -> Health {
+        Health {
+            since_action: self.last_action.map(|t| t.elapsed()),
+            consecutive_requests: self.consecutive_requests,
+            breaker: self.breaker,
+            budget_remaining: self.remaining.map(|f| f(&self.agent)),
+        }
+    }
+}
+
+impl<T: Agent> Agent for Monitored<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        if self.breaker == Breaker::Open {self.breaker = Breaker::HalfOpen}
+        self.agent.update_model(model)
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        if self.breaker == Breaker::Open {
+            self.consecutive_requests += 1;
+            return Decision::RequestModel;
+        }
+        let decision = self.agent.decide();
+        match decision {
+            Decision::Action(_) => {
+                self.last_action = Some(Instant::now());
+                self.consecutive_requests = 0;
+                self.breaker = Breaker::Closed;
+            }
+            Decision::RequestModel => {
+                self.consecutive_requests += 1;
+                if self.breaker == Breaker::HalfOpen || self.consecutive_requests >= self.trip_after {
+                    self.breaker = Breaker::Open;
+                }
+            }
+            Decision::Halt => {}
+        }
+        decision
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Monitored<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{Budget, Fallback};
+
+    #[test]
+    fn trip_breaker() {
+        let budget = Budget::new(crate::tests::four().add(1), 5, Fallback::Halt);
+        let mut s = Monitored::new(budget, 2).with_budget(Budget::remaining);
+        assert!(s.health().since_action.is_none());
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        let health = s.health();
+        assert_eq!((health.consecutive_requests, health.breaker), (3, Breaker::Open));
+        // The open breaker does not spend the budget.
+        assert_eq!(health.budget_remaining, Some(3));
+        assert!(!health.is_ready());
+
+        s.update_model((4, 0));
+        assert_eq!(s.breaker, Breaker::HalfOpen);
+        assert_eq!(s.decide(), Decision::Action(1));
+        let health = s.health();
+        assert_eq!((health.consecutive_requests, health.breaker), (0, Breaker::Closed));
+        assert!(health.since_action.is_some());
+        assert!(health.is_ready());
+    }
+}
@@ -0,0 +1,155 @@
+//! Hibernation and resumption of agents.
+//!
+//! Physical deployments pause agents, e.g. for maintenance or transport, and restart them later.
+//! The model of a paused agent goes stale, so it must not act on it after restarting.
+//!
+//! A `Hibernation` agent follows an explicit protocol:
+//!
+//! - `hibernate`: every decide returns `Decision::Halt`, and every act is refused and logged
+//! - `resume`: with a model update, the agent is awake again,
+//!   otherwise it requests a model update on every decide until it receives one
+//!
+//! With the `checkpoint` feature, `Hibernation::hibernate_to` also saves a checkpoint of the agent,
+//! and `Hibernation::wake_from` restores an agent from it, which requests a model update before acting.
+
+use crate::{Agent, Decision, Inspect};
+#[cfg(feature = "checkpoint")]
+use crate::checkpoint::{Checkpoint, Checkpointed};
+#[cfg(feature = "checkpoint")]
+use crate::Error;
+
+/// Stores the phase of the hibernation protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// The agent decides and acts.
+    Awake,
+    /// The agent rejects decide and act.
+    Hibernating,
+    /// The agent requests a model update before acting.
+    Resuming,
+}
+
+/// Stores an agent that can hibernate and resume.
+#[derive(Clone, Debug)]
+pub struct Hibernation<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The phase of the protocol.
+    pub phase: Phase,
+    /// The actions refused while not awake.
+    pub refused: Vec<T::Action>,
+}
+
+impl<T: Agent> Hibernation<T> {
+    /// Creates a new awake agent.
+    pub fn new(agent: T) -> Self {Hibernation {agent, phase: Phase::Awake, refused: vec![]}}
+
+    /// Starts hibernating.
+    pub fn hibernate(&mut self) {self.phase = Phase::Hibernating}
+
+    /// Resumes from hibernation, with a model update if available.
+    ///
+    /// Without a model update, the agent requests one before acting.
+    pub fn resume(&mut self, model_update: Option<T::Model>) {
+        match model_update {
+            Some(model) => {
+                self.agent.update_model(model);
+                self.phase = Phase::Awake;
+            }
+            None => self.phase = Phase::Resuming,
+        }
+    }
+}
+
+#[cfg(feature = "checkpoint")]
+impl<T: Checkpointed> Hibernation<T> {
+    /// Starts hibernating, returning a checkpoint of the agent.
+    pub fn hibernate_to(&mut self, encode: fn(&T::Model) -> String) -> Checkpoint {
+        self.hibernate();
+        self.agent.save_checkpoint(encode)
+    }
+
+    /// Restores a hibernated agent from a checkpoint,
+    /// which requests a model update before acting.
+    pub fn wake_from(
+        mut agent: T,
+        checkpoint: &Checkpoint,
+        decode: fn(&str) -> Option<T::Model>,
+    ) -> Result<Self, Error> {
+        agent.restore_checkpoint(checkpoint, decode)?;
+        Ok(Hibernation {agent, phase: Phase::Resuming, refused: vec![]})
+    }
+}
+
+impl<T: Agent> Agent for Hibernation<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        // Model updates while hibernating go stale before resuming.
+        if self.phase == Phase::Hibernating {return}
+        self.agent.update_model(model);
+        self.phase = Phase::Awake;
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        match self.phase {
+            Phase::Awake => self.agent.decide(),
+            Phase::Hibernating => Decision::Halt,
+            Phase::Resuming => Decision::RequestModel,
+        }
+    }
+    fn act(&mut self, action: T::Action) {
+        if self.phase == Phase::Awake {self.agent.act(action)} else {self.refused.push(action)}
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Hibernation<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol() {
+        let mut s = Hibernation::new(crate::tests::four().add(1));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.hibernate();
+        assert_eq!(s.decide(), Decision::Halt);
+        s.act(1);
+        s.update_model((4, 2));
+        assert_eq!((s.model(), &s.refused[..]), (&(4, 0), &[1][..]));
+
+        s.resume(None);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.act(1);
+        assert_eq!(s.model(), &(4, 0));
+        s.update_model((4, 1));
+        assert_eq!((s.phase, s.decide()), (Phase::Awake, Decision::Action(1)));
+
+        s.hibernate();
+        s.resume(Some((4, 3)));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.phase, Phase::Awake);
+    }
+
+    #[cfg(feature = "checkpoint")]
+    #[test]
+    fn restart() {
+        let mut s = Hibernation::new(crate::tests::four().add(1));
+        s.update_model((4, 2));
+        let checkpoint = s.hibernate_to(|m| format!("{} {}", m.0, m.1));
+        let decode = |s: &str| {
+            let (a, b) = s.split_once(' ')?;
+            Some((a.parse().ok()?, b.parse().ok()?))
+        };
+        let mut t = Hibernation::wake_from(crate::tests::four().add(1), &checkpoint, decode).unwrap();
+        assert_eq!(t.model(), &(4, 2));
+        assert_eq!(t.decide(), Decision::RequestModel);
+        t.update_model((4, 2));
+        assert_eq!(t.decide(), Decision::Action(1));
+    }
+}
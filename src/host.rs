@@ -0,0 +1,160 @@
+//! Hosting many agents concurrently.
+//!
+//! For fleet-style deployments, a `Host` runs the agents of a `registry::Registry`
+//! on a number of worker threads, each agent with its own model and wrappers,
+//! e.g. `budget::Budget` and `metrics::Metered`.
+//!
+//! Model updates, decide requests and actions are sent over a single channel,
+//! and routed by agent id to the worker that owns the agent.
+//! Requests for the same agent are handled in order,
+//! while agents on different workers decide concurrently.
+//! Replies carry the agent id, and arrive in the order they are produced.
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::registry::{AgentId, Registry};
+use crate::{Agent, Decision};
+
+/// Stores a request to a host.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request<M, A> {
+    /// Updates the model of an agent.
+    Update(AgentId, M),
+    /// Requests a decision of an agent.
+    Decide(AgentId),
+    /// Performs an action of an agent.
+    Act(AgentId, A),
+    /// Stops the host.
+    Shutdown,
+}
+
+/// Stores a reply of a host.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply<A> {
+    /// The decision of an agent.
+    Decision(AgentId, Decision<A>),
+    /// There is no agent with the id.
+    Unknown(AgentId),
+}
+
+type Shard<T> = BTreeMap<usize, (String, T)>;
+
+/// Stores a handle to agents running on worker threads.
+pub struct Host<T: Agent> {
+    requests: Sender<Request<T::Model, T::Action>>,
+    replies: Receiver<Reply<T::Action>>,
+    len: usize,
+    dispatcher: JoinHandle<()>,
+    workers: Vec<JoinHandle<Shard<T>>>,
+}
+
+fn work<T: Agent>(mut shard: Shard<T>, requests: Receiver<Request<T::Model, T::Action>>,
+                  replies: Sender<Reply<T::Action>>) -> Shard<T> {
+    for request in requests {
+        let (id, reply) = match request {
+            Request::Update(id, model) =>
+                (id, shard.get_mut(&id.0).map(|(_, agent)| agent.update_model(model)).map(|_| None)),
+            Request::Decide(id) =>
+                (id, shard.get_mut(&id.0).map(|(_, agent)| Some(Reply::Decision(id, agent.decide())))),
+            Request::Act(id, action) =>
+                (id, shard.get_mut(&id.0).map(|(_, agent)| agent.act(action)).map(|_| None)),
+            Request::Shutdown => break,
+        };
+        let reply = match reply {
+            Some(Some(reply)) => reply,
+            Some(None) => continue,
+            None => Reply::Unknown(id),
+        };
+        if replies.send(reply).is_err() {break}
+    }
+    shard
+}
+
+impl<T> Host<T>
+    where T: Agent + Send + 'static, T::Model: Send + 'static, T::Action: Send + 'static
+{
+    /// Runs the agents of a registry on some worker threads.
+    pub fn spawn(registry: Registry<T>, workers: usize) -> Self {
+        let workers = workers.max(1);
+        let mut entries = registry.into_entries();
+        let len = entries.len();
+        let (replies_tx, replies) = mpsc::channel();
+        let mut shards: Vec<Shard<T>> = (0..workers).map(|_| BTreeMap::new()).collect();
+        for (i, entry) in entries.drain(..).enumerate() {
+            if let Some(entry) = entry {shards[i % workers].insert(i, entry);}
+        }
+        let mut senders = vec![];
+        let workers = shards.into_iter().map(|shard| {
+            let (tx, rx) = mpsc::channel();
+            senders.push(tx);
+            let replies = replies_tx.clone();
+            thread::spawn(move || work(shard, rx, replies))
+        }).collect();
+        let (requests, requests_rx) = mpsc::channel::<Request<T::Model, T::Action>>();
+        let dispatcher = thread::spawn(move || {
+            for request in requests_rx {
+                let id = match &request {
+                    Request::Update(id, _) | Request::Decide(id) | Request::Act(id, _) => *id,
+                    Request::Shutdown => break,
+                };
+                if senders[id.0 % senders.len()].send(request).is_err() {break}
+            }
+        });
+        Host {requests, replies, len, dispatcher, workers}
+    }
+
+    /// Returns a sender of requests, which can be cloned for many clients.
+    pub fn sender(&self) -> Sender<Request<T::Model, T::Action>> {self.requests.clone()}
+
+    /// Sends a request.
+    ///
+    /// Returns `false` if the host is no longer running.
+    pub fn send(&self, request: Request<T::Model, T::Action>) -> bool {self.requests.send(request).is_ok()}
+
+    /// Receives the next reply, blocking until one is available.
+    ///
+    /// Returns `None` if the host is no longer running.
+    pub fn recv(&self) -> Option<Reply<T::Action>> {self.replies.recv().ok()}
+
+    /// Stops the host after handling the requests sent so far, returning the registry.
+    ///
+    /// Returns an error if a worker thread panicked.
+    pub fn shutdown(self) -> thread::Result<Registry<T>> {
+        let _ = self.requests.send(Request::Shutdown);
+        self.dispatcher.join()?;
+        let mut entries: Vec<Option<(String, T)>> = (0..self.len).map(|_| None).collect();
+        for worker in self.workers {
+            for (i, entry) in worker.join()? {entries[i] = Some(entry)}
+        }
+        Ok(Registry::from_entries(entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fleet() {
+        let mut registry = Registry::new();
+        let ids: Vec<AgentId> = (0..4).map(|i| registry.insert(format!("agent {}", i), crate::tests::four().add(1)))
+            .collect();
+        assert!(registry.remove(ids[3]).is_some());
+        let host = Host::spawn(registry, 2);
+        let sender = host.sender();
+        for &id in &ids {assert!(sender.send(Request::Decide(id)).is_ok())}
+        let mut replies: Vec<Reply<i32>> = (0..4).map(|_| host.recv().unwrap()).collect();
+        replies.sort_by_key(|r| match r {Reply::Decision(id, _) | Reply::Unknown(id) => *id});
+        assert_eq!(replies[0], Reply::Decision(ids[0], Decision::Action(1)));
+        assert_eq!(replies[3], Reply::Unknown(ids[3]));
+
+        assert!(host.send(Request::Update(ids[1], (2, 2))));
+        assert!(host.send(Request::Act(ids[2], 1)));
+        let registry = host.shutdown().unwrap();
+        assert_eq!(registry.get(ids[1]).unwrap().z.model, (2, 2));
+        assert_eq!(registry.get(ids[2]).unwrap().z.model, (4, 1));
+        assert_eq!((registry.len(), registry.name(ids[2])), (3, Some("agent 2")));
+    }
+}
@@ -0,0 +1,97 @@
+//! Batched and coalesced model updates.
+//!
+//! When updates arrive faster than the agent decides,
+//! applying them one by one makes the agent go through intermediate models
+//! that might be inconsistent with each other.
+//!
+//! A `ModelInbox` accumulates updates between decide calls,
+//! merging them with a user-supplied function into one coherent update.
+//! The update is a whole model or a patch, see `ApplyPatch`.
+
+use crate::patch::ApplyPatch;
+use crate::Agent;
+
+/// Stores updates received between decide calls.
+#[derive(Clone, Debug)]
+pub struct ModelInbox<U> {
+    pending: Option<U>,
+    count: usize,
+    /// Merges an older update with a newer one.
+    pub merge: fn(U, U) -> U,
+}
+
+impl<U> ModelInbox<U> {
+    /// Creates a new empty inbox.
+    pub fn new(merge: fn(U, U) -> U) -> Self {
+        ModelInbox {pending: None, count: 0, merge}
+    }
+
+    /// Creates a new empty inbox that keeps the latest update.
+    pub fn latest() -> Self {Self::new(|_, new| new)}
+
+    /// Receives an update.
+    pub fn push(&mut self, update: U) {
+        self.pending = Some(match self.pending.take() {
+            Some(old) => (self.merge)(old, update),
+            None => update,
+        });
+        self.count += 1;
+    }
+
+    /// Returns the number of updates received since last take.
+    pub fn len(&self) -> usize {self.count}
+
+    /// Returns `true` if there are no pending updates.
+    pub fn is_empty(&self) -> bool {self.pending.is_none()}
+
+    /// Takes the merged update, leaving the inbox empty.
+    pub fn take(&mut self) -> Option<U> {
+        self.count = 0;
+        self.pending.take()
+    }
+
+    /// Updates the model of an agent, returning `true` if there was a pending update.
+    pub fn deliver<T: Agent<Model = U>>(&mut self, agent: &mut T) -> bool {
+        match self.take() {
+            Some(model) => {
+                agent.update_model(model);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies a patch to an agent, returning `true` if there was a pending patch.
+    pub fn deliver_patch<T: ApplyPatch<Patch = U>>(&mut self, agent: &mut T) -> bool {
+        match self.take() {
+            Some(patch) => {
+                agent.apply_patch(patch);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decision;
+
+    #[test]
+    fn coalesce() {
+        let mut s = crate::tests::four().add(1);
+        // Observations of goal and state arrive separately, where `0` is unknown.
+        let mut inbox = ModelInbox::new(|old: (u32, u32), new: (u32, u32)| {
+            (if new.0 == 0 {old.0} else {new.0}, if new.1 == 0 {old.1} else {new.1})
+        });
+        inbox.push((3, 0));
+        inbox.push((0, 2));
+        assert_eq!(inbox.len(), 2);
+        assert!(inbox.deliver(&mut s));
+        assert_eq!(s.z.model, (3, 2));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert!(inbox.is_empty());
+        assert!(!inbox.deliver(&mut s));
+    }
+}
@@ -0,0 +1,232 @@
+//! Per-mutation disagreement statistics.
+//!
+//! Some mutaters are more informative than others:
+//! A mutation that often disagrees with core zero points at a part of the model
+//! the agent is uncertain about, while a mutation that always agrees mostly costs probes.
+//!
+//! `MutationStats` counts the probes and disagreements of each mutater across a run.
+//! An `Informed` agent records the statistics from the rationale of every decision,
+//! and when prioritizing, orders `AgentN::mutaters` before each decide call
+//! such that the historically most informative mutaters are probed first within the mutation limit.
+//!
+//! When pruning, every `prune_after` decide calls the agent drops the mutaters
+//! whose probes never disagreed, as long as some other mutater did.
+//! Since probes cycle through the mutaters, their probes go to the remaining mutaters.
+//! Every dropped mutater is logged in `Informed::pruned` for audit.
+//!
+//! With the `metrics` feature, `MutationStats::render` exposes the statistics in Prometheus text format.
+
+use crate::rationale::Rationale;
+use crate::{Agent, AgentN, Decision, Inspect, ProbeOutcome};
+
+/// Stores the probes and disagreements of each mutater.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MutationStats {
+    /// The label of each mutater.
+    pub labels: Vec<&'static str>,
+    /// The number of probes of each mutater.
+    pub probes: Vec<u64>,
+    /// The number of probes of each mutater that disagreed with core zero.
+    pub disagreements: Vec<u64>,
+}
+
+impl MutationStats {
+    /// Creates new statistics of labeled mutaters.
+    pub fn new(labels: Vec<&'static str>) -> Self {
+        let n = labels.len();
+        MutationStats {labels, probes: vec![0; n], disagreements: vec![0; n]}
+    }
+
+    /// Records the outcome of a probe of some mutater.
+    pub fn record(&mut self, mutater: usize, outcome: ProbeOutcome) {
+        if mutater >= self.probes.len() {
+            self.labels.resize(mutater + 1, "");
+            self.probes.resize(mutater + 1, 0);
+            self.disagreements.resize(mutater + 1, 0);
+        }
+        self.probes[mutater] += 1;
+        if outcome == ProbeOutcome::Disagree {self.disagreements[mutater] += 1}
+    }
+
+    /// Returns the fraction of probes of some mutater that disagreed.
+    ///
+    /// Returns `0` when the mutater was never probed.
+    pub fn rate(&self, mutater: usize) -> f64 {
+        match self.probes.get(mutater) {
+            Some(&n) if n > 0 => self.disagreements[mutater] as f64 / n as f64,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the number of probes of some mutater that disagreed.
+    pub fn disagreements(&self, mutater: usize) -> u64 {
+        self.disagreements.get(mutater).cloned().unwrap_or(0)
+    }
+}
+
+/// Stores a mutater that was dropped for never disagreeing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pruned {
+    /// The original index of the mutater.
+    pub mutater: usize,
+    /// The label of the mutater.
+    pub label: &'static str,
+    /// The number of decide calls before the mutater was dropped.
+    pub decides: u64,
+    /// The number of probes of the mutater, none of which disagreed.
+    pub probes: u64,
+}
+
+/// Stores an agent that tracks the disagreements of its mutaters.
+#[derive(Clone, Debug)]
+pub struct Informed<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The statistics, by original index of mutaters.
+    pub stats: MutationStats,
+    /// Whether to probe the most informative mutaters first.
+    pub prioritize: bool,
+    /// The original index of the mutater in each slot of `agent.mutaters`.
+    pub order: Vec<usize>,
+    /// The number of decide calls between pruning ineffective mutaters.
+    ///
+    /// When `None`, mutaters are never pruned.
+    pub prune_after: Option<u64>,
+    /// The number of decide calls.
+    pub decides: u64,
+    /// The pruned mutaters, in order.
+    pub pruned: Vec<Pruned>,
+}
+
+impl<M, A: PartialEq, D> Informed<M, A, D> {
+    /// Creates a new agent tracking disagreements, labeled by the targets of mutaters.
+    pub fn new(agent: AgentN<M, A, D>) -> Self {
+        let n = agent.mutaters.len().max(1);
+        let mut labels = agent.targets.clone();
+        labels.resize(n, "");
+        Informed {
+            agent,
+            stats: MutationStats::new(labels),
+            prioritize: false,
+            order: (0..n).collect(),
+            prune_after: None,
+            decides: 0,
+            pruned: vec![],
+        }
+    }
+
+    /// Probes the most informative mutaters first.
+    pub fn prioritize(self) -> Self {Informed {prioritize: true, ..self}}
+
+    /// Prunes ineffective mutaters every `decides` decide calls.
+    pub fn prune_after(self, decides: u64) -> Self {Informed {prune_after: Some(decides), ..self}}
+
+    fn reorder(&mut self) {
+        let n = self.agent.mutaters.len();
+        if n < 2 {return}
+        let targeted = self.agent.targets.len() == n;
+        let mut slots: Vec<_> = (0..n).map(|slot| {
+            let target = if targeted {self.agent.targets[slot]} else {""};
+            (self.order[slot], self.agent.mutaters[slot], target)
+        }).collect();
+        let stats = &self.stats;
+        // Mutaters with equal disagreements keep their original order.
+        slots.sort_by_key(|&(i, _, _)| (std::cmp::Reverse(stats.disagreements(i)), i));
+        self.order = slots.iter().map(|s| s.0).collect();
+        self.agent.mutaters = slots.iter().map(|s| s.1).collect();
+        if targeted {self.agent.targets = slots.iter().map(|s| s.2).collect()}
+    }
+
+    fn prune(&mut self) {
+        let n = self.agent.mutaters.len();
+        if n < 2 || !self.order.iter().any(|&i| self.stats.disagreements(i) > 0) {return}
+        let targeted = self.agent.targets.len() == n;
+        for slot in (0..n).rev() {
+            let mutater = self.order[slot];
+            let probes = self.stats.probes.get(mutater).cloned().unwrap_or(0);
+            if probes == 0 || self.stats.disagreements(mutater) > 0 {continue}
+            let label = self.stats.labels.get(mutater).cloned().unwrap_or("");
+            self.pruned.push(Pruned {mutater, label, decides: self.decides, probes});
+            self.order.remove(slot);
+            self.agent.mutaters.remove(slot);
+            if targeted {self.agent.targets.remove(slot);}
+        }
+    }
+
+    fn tally(&mut self, rationale: &Rationale) {
+        for check in &rationale.checks {
+            for probe in Some(check.probe).into_iter().chain(check.second) {
+                let slot = self.agent.mutater_of(probe);
+                let mutater = self.order.get(slot).cloned().unwrap_or(slot);
+                self.stats.record(mutater, check.outcome);
+            }
+            if let Some(inner) = &check.inner {self.tally(inner)}
+        }
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Informed<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        if self.prioritize {self.reorder()}
+        let (decision, rationale) = self.agent.decide_rationale();
+        self.tally(&rationale);
+        self.decides += 1;
+        if let Some(n) = self.prune_after {
+            if self.decides.is_multiple_of(n) {self.prune()}
+        }
+        decision
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Informed<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agreement;
+
+    #[test]
+    fn prioritize() {
+        let mut s = crate::tests::four().add(1);
+        s.mutaters = vec![|m| {m.0 += 1; 1}, |m| {m.0 -= 1; -1}];
+        s.targets = vec!["raise", "lower"];
+        s.layers[0].agreement = Agreement::All;
+        s.z.model = (4, 3);
+        let mut s = Informed::new(s).prioritize();
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!((s.stats.probes.clone(), s.stats.disagreements.clone()), (vec![1, 1], vec![0, 1]));
+        // Lowering the goal disagreed, so it is probed first.
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.order, vec![1, 0]);
+        assert_eq!(s.agent.targets, vec!["lower", "raise"]);
+        assert_eq!((s.stats.probes.clone(), s.stats.disagreements.clone()), (vec![1, 2], vec![0, 2]));
+        assert_eq!(s.stats.rate(1), 1.0);
+    }
+
+    #[test]
+    fn prune() {
+        let mut s = crate::tests::four().add(1);
+        s.mutaters = vec![|m| {m.0 += 1; 1}, |m| {m.0 += 2; 2}, |m| {m.0 -= 1; -1}];
+        s.layers[0].agreement = Agreement::All;
+        s.z.model = (4, 3);
+        let mut s = Informed::new(s).prune_after(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.pruned, vec![
+            Pruned {mutater: 1, label: "", decides: 1, probes: 1},
+            Pruned {mutater: 0, label: "", decides: 1, probes: 1},
+        ]);
+        assert_eq!((s.order.clone(), s.agent.mutaters.len()), (vec![2], 1));
+        // Every probe now lowers the goal.
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.agent.report.probes, 1);
+    }
+}
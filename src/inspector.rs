@@ -0,0 +1,98 @@
+//! Read-only views of the internals of layered agents.
+//!
+//! Monitoring dashboards need to introspect a live agent without changing it.
+//! An `Inspector` borrows an agent immutably, exposing its model, its safety layers,
+//! the safety report of the last decide call and, for `metrics::Metered` agents, its metrics.
+
+use crate::{AgentN, LayerConfig, SafetyReport};
+
+/// Stores a read-only view of a layered agent.
+#[derive(Debug)]
+pub struct Inspector<'a, M, A, D> {
+    agent: &'a AgentN<M, A, D>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<&'a crate::metrics::Metrics>,
+}
+
+impl<M, A, D> Clone for Inspector<'_, M, A, D> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A, D> Copy for Inspector<'_, M, A, D> {}
+
+impl<'a, M, A, D> Inspector<'a, M, A, D> {
+    /// Creates a new inspector of an agent.
+    pub fn new(agent: &'a AgentN<M, A, D>) -> Self {
+        Inspector {
+            agent,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Returns the internal model.
+    pub fn model(&self) -> &'a M {&self.agent.z.model}
+
+    /// Returns the number of safety layers.
+    pub fn layers(&self) -> usize {self.agent.layers.len()}
+
+    /// Returns the configuration of each safety layer, from innermost to outermost.
+    pub fn configs(&self) -> &'a [LayerConfig<A>] {&self.agent.layers}
+
+    /// Returns the configuration of a safety layer, where `1` is the innermost one.
+    pub fn config(&self, layer: usize) -> Option<&'a LayerConfig<A>> {
+        layer.checked_sub(1).and_then(|i| self.agent.layers.get(i))
+    }
+
+    /// Returns the number of mutaters used for probing.
+    pub fn mutaters(&self) -> usize {self.agent.mutaters.len()}
+
+    /// Returns the safety report of the last decide call.
+    pub fn report(&self) -> SafetyReport {self.agent.report}
+
+    /// Returns `true` if the agent requests model updates after replacing its core.
+    pub fn handoff(&self) -> bool {self.agent.handoff}
+
+    /// Returns the metrics of decide calls, for metered agents.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Option<&'a crate::metrics::Metrics> {self.metrics}
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Returns a read-only view of the agent.
+    pub fn inspector(&self) -> Inspector<'_, M, A, D> {Inspector::new(self)}
+}
+
+#[cfg(feature = "metrics")]
+impl<M, A, D> crate::metrics::Metered<M, A, D> {
+    /// Returns a read-only view of the agent, including its metrics.
+    pub fn inspector(&self) -> Inspector<'_, M, A, D> {
+        Inspector {metrics: Some(&self.metrics), ..Inspector::new(&self.agent)}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn view() {
+        let mut s = crate::tests::four().add(2);
+        assert_eq!(s.decide(), Decision::Action(1));
+        let view = s.inspector();
+        assert_eq!((view.model(), view.layers(), view.mutaters()), (&(4, 0), 2, 0));
+        assert_eq!(view.config(1), Some(&LayerConfig::default()));
+        assert_eq!((view.config(0), view.config(3)), (None, None));
+        assert_eq!(view.report(), s.report);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn metered() {
+        let mut s = crate::metrics::Metered::new(crate::tests::four().add(1));
+        s.decide();
+        assert_eq!(s.inspector().metrics().map(|m| m.decides), Some(1));
+        assert_eq!(s.agent.inspector().metrics(), None);
+    }
+}
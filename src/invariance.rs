@@ -0,0 +1,107 @@
+//! Invariance probability estimates on decisions.
+//!
+//! A safety layer gives a binary answer: act or request a model update.
+//! Callers that want graded safety can use `AgentN::decide_probabilistic`,
+//! which also estimates the probability that the action of core zero is invariant
+//! under the uncertainty modeled by the mutaters.
+//!
+//! The estimate samples mutated models, decides each one using the inner safety layers,
+//! and counts the samples that agree with core zero, with Laplace smoothing:
+//! `(agreed + 1) / (samples + 2)`.
+//! With a stochastic mutater, every sample is drawn from its distribution,
+//! such that many samples estimate the probability over the breadth of the model space.
+//! Sampling is not part of the decision, so it does not change the safety report.
+//!
+//! `sample_size` computes the number of samples needed from a confidence level
+//! and the tolerated error of the estimated disagreement rate,
+//! using the Hoeffding bound `n >= ln(2 / (1 - confidence)) / (2 * tolerance^2)`.
+//! `AgentN::decide_with_confidence` uses it, such that users do not need to guess sampling budgets.
+
+use crate::{Agent, AgentN, Decision, SafetyReport};
+
+/// Returns the number of samples needed to estimate a disagreement rate within a tolerance,
+/// with some confidence level.
+///
+/// Returns `u32::MAX` when the confidence is `1` or more, or the tolerance is `0` or less.
+pub fn sample_size(confidence: f64, tolerance: f64) -> u32 {
+    if confidence >= 1.0 || tolerance.is_nan() || tolerance <= 0.0 {return u32::MAX}
+    let delta = 1.0 - confidence.max(0.0);
+    let n = (2.0 / delta).ln() / (2.0 * tolerance * tolerance);
+    n.ceil().min(u32::MAX as f64) as u32
+}
+
+/// Stores a decision with an estimated probability of invariance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Invariance<A> {
+    /// The decision.
+    pub decision: Decision<A>,
+    /// The estimated probability that the action of core zero is invariant.
+    ///
+    /// This is `0` when core zero does not act.
+    pub probability: f64,
+    /// The number of sampled models.
+    pub samples: u32,
+    /// The number of sampled models that agreed with core zero.
+    pub agreed: u32,
+}
+
+impl<M, A: PartialEq, D> AgentN<M, A, D> {
+    /// Decide what to do next, together with an estimated probability of invariance,
+    /// using a number of sampled models.
+    pub fn decide_probabilistic(&mut self, samples: u32) -> Invariance<A> {
+        let decision = self.decide();
+        let report = self.report;
+        let a = match self.z.decide() {
+            Decision::Action(a) => a,
+            _ => return Invariance {decision, probability: 0.0, samples: 0, agreed: 0},
+        };
+        let n = self.layers().saturating_sub(1);
+        let mut agreed = 0;
+        for i in 0..samples {
+            let delta = self.mutate_probe((i % u8::MAX as u32) as u8);
+            let b = self.decide_n(n, self.layers(), &mut SafetyReport::default()).0;
+            self.z.undo(delta);
+            match (b, self.layers.get(n)) {
+                (Decision::Action(b), Some(config)) if config.agree(&a, &b) => agreed += 1,
+                (Decision::Action(b), None) if a == b => agreed += 1,
+                _ => {}
+            }
+        }
+        self.report = report;
+        let probability = (agreed + 1) as f64 / (samples + 2) as f64;
+        Invariance {decision, probability, samples, agreed}
+    }
+
+    /// Decide what to do next, together with an estimated probability of invariance,
+    /// using enough samples for a confidence level and a tolerated error of the disagreement rate.
+    pub fn decide_with_confidence(&mut self, confidence: f64, tolerance: f64) -> Invariance<A> {
+        self.decide_probabilistic(sample_size(confidence, tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graded() {
+        let mut s = crate::tests::four().add(1);
+        let inv = s.decide_probabilistic(8);
+        assert_eq!((inv.decision, inv.agreed, inv.probability), (Decision::Action(1), 8, 0.9));
+        s.mutaters = vec![|m| {m.0 += 1; 1}, |m| {m.0 -= 1; -1}];
+        s.z.model = (4, 3);
+        // The first mutation agrees, but only half of the samples do.
+        let inv = s.decide_probabilistic(4);
+        assert_eq!((inv.decision, inv.agreed, inv.probability), (Decision::Action(1), 2, 0.5));
+        assert_eq!(s.z.model, (4, 3));
+    }
+
+    #[test]
+    fn samples() {
+        assert_eq!(sample_size(0.95, 0.1), 185);
+        assert_eq!(sample_size(0.95, 0.05), 738);
+        assert_eq!(sample_size(1.0, 0.1), u32::MAX);
+        let mut s = crate::tests::four().add(1);
+        assert_eq!(s.decide_with_confidence(0.95, 0.1).samples, 185);
+    }
+}
@@ -0,0 +1,194 @@
+//! Invariants over traces.
+//!
+//! An invariant is a predicate over a transition:
+//! the model before deciding, the decision and the model before the next decision.
+//! The model before the next decision reflects the action, or the model update that was requested.
+//!
+//! Invariants are registered in `Invariants`, and checked against a recorded `Trace`,
+//! or live by wrapping an agent in `Watched`.
+//! Violations are collected in an `InvariantReport` instead of panicking.
+
+use std::fmt;
+
+use crate::trace::Trace;
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an invariant.
+pub struct Invariant<M, A> {
+    /// The name of the invariant.
+    pub name: &'static str,
+    /// Returns `true` when a transition satisfies the invariant.
+    pub holds: fn(&M, &Decision<A>, &M) -> bool,
+}
+
+impl<M, A> Clone for Invariant<M, A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A> Copy for Invariant<M, A> {}
+
+impl<M, A> fmt::Debug for Invariant<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Invariant").field("name", &self.name).field("holds", &self.holds).finish()
+    }
+}
+
+/// Stores a violation of an invariant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InvariantViolation {
+    /// The name of the invariant.
+    pub invariant: &'static str,
+    /// The index of the step of the transition.
+    pub step: usize,
+}
+
+/// Stores a report of checked transitions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct InvariantReport {
+    /// The number of checked transitions.
+    pub transitions: usize,
+    /// The violations, in order.
+    pub violations: Vec<InvariantViolation>,
+}
+
+impl InvariantReport {
+    /// Returns `true` if no invariant was violated.
+    pub fn is_ok(&self) -> bool {self.violations.is_empty()}
+}
+
+/// Stores registered invariants.
+pub struct Invariants<M, A> {
+    /// The invariants.
+    pub invariants: Vec<Invariant<M, A>>,
+}
+
+impl<M, A> Clone for Invariants<M, A> {
+    fn clone(&self) -> Self {Invariants {invariants: self.invariants.clone()}}
+}
+
+impl<M, A> fmt::Debug for Invariants<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Invariants").field("invariants", &self.invariants).finish()
+    }
+}
+
+impl<M, A> Default for Invariants<M, A> {
+    fn default() -> Self {Invariants {invariants: vec![]}}
+}
+
+impl<M, A> Invariants<M, A> {
+    /// Creates a new empty registry of invariants.
+    pub fn new() -> Self {Self::default()}
+
+    /// Registers an invariant.
+    pub fn register(mut self, name: &'static str, holds: fn(&M, &Decision<A>, &M) -> bool) -> Self {
+        self.invariants.push(Invariant {name, holds});
+        self
+    }
+
+    /// Checks a transition at some step, adding violations to a report.
+    pub fn check(&self, step: usize, prev: &M, decision: &Decision<A>, next: &M, report: &mut InvariantReport) {
+        report.transitions += 1;
+        for inv in &self.invariants {
+            if !(inv.holds)(prev, decision, next) {
+                report.violations.push(InvariantViolation {invariant: inv.name, step});
+            }
+        }
+    }
+
+    /// Checks the transitions of a recorded trace.
+    ///
+    /// The last step has no next model, so it is not checked.
+    pub fn check_trace(&self, trace: &Trace<M, A>) -> InvariantReport {
+        let mut report = InvariantReport::default();
+        for (i, w) in trace.steps.windows(2).enumerate() {
+            self.check(i, &w[0].model, &w[0].decision, &w[1].model, &mut report);
+        }
+        report
+    }
+}
+
+/// Stores an agent whose transitions are checked live.
+pub struct Watched<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The invariants.
+    pub invariants: Invariants<T::Model, T::Action>,
+    /// The report of checked transitions.
+    pub report: InvariantReport,
+    steps: usize,
+    pending: Option<(T::Model, Decision<T::Action>)>,
+}
+
+impl<T: Inspect> Watched<T> where T::Model: Clone, T::Action: Clone {
+    /// Creates a new watched agent.
+    pub fn new(agent: T, invariants: Invariants<T::Model, T::Action>) -> Self {
+        Watched {agent, invariants, report: InvariantReport::default(), steps: 0, pending: None}
+    }
+
+    /// Checks the transition of the last decision, using the current model as the next model.
+    ///
+    /// This is done automatically by the next decide call.
+    pub fn flush(&mut self) {
+        if let Some((prev, decision)) = self.pending.take() {
+            self.invariants.check(self.steps - 1, &prev, &decision, self.agent.model(), &mut self.report);
+        }
+    }
+}
+
+impl<T: Inspect> Agent for Watched<T> where T::Model: Clone, T::Action: Clone {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        self.flush();
+        let model = self.agent.model().clone();
+        let decision = self.agent.decide();
+        self.pending = Some((model, decision.clone()));
+        self.steps += 1;
+        decision
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Watched<T> where T::Model: Clone, T::Action: Clone {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{run_until, tests::Three};
+
+    fn towards(prev: &(u32, u32), decision: &Decision<i32>, next: &(u32, u32)) -> bool {
+        match decision {
+            Decision::Action(a) => *a == 0 || next.1.abs_diff(prev.0) < prev.1.abs_diff(prev.0),
+            _ => true,
+        }
+    }
+
+    fn invariants() -> Invariants<(u32, u32), i32> {
+        Invariants::new()
+            .register("acting moves towards the goal", towards)
+            .register("goal is never 4", |_, _, next| next.0 != 4)
+    }
+
+    #[test]
+    fn check() {
+        let mut trace = Trace::new();
+        trace.run(&mut crate::tests::four().add(1), 10);
+        let report = invariants().check_trace(&trace);
+        assert_eq!(report.transitions, 3);
+        assert_eq!(report.violations.len(), 3);
+        assert_eq!(report.violations[0], InvariantViolation {invariant: "goal is never 4", step: 0});
+
+        let mut s = Watched::new(crate::tests::four().add(1), invariants());
+        run_until(&mut s, &mut Three(0), |m| m.0 == 3 && m.1 == 3, 10);
+        s.flush();
+        assert_eq!(s.report.transitions, 4);
+        assert_eq!(s.report.violations.iter().map(|v| v.step).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+}
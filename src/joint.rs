@@ -0,0 +1,198 @@
+//! Joint-action agreement between two agents.
+//!
+//! Two agents coordinating in a shared environment might each decide safely on their own,
+//! but still take actions that conflict with each other.
+//!
+//! A `Joint` pair exchanges proposed actions before acting.
+//! Each agent checks the proposal of its counterpart against its own model and action.
+//! The pair acts only when both agents decide an action using their own mutation checks,
+//! and both accept the proposal of the counterpart.
+//! Otherwise both agents request a model update, or halt when one of them halts.
+//!
+//! When the proposals conflict, the pair can negotiate over ranked lists of actions.
+//! The agents take turns conceding to their next ranked action, starting with the first agent,
+//! until both accept a pair of actions.
+//! Negotiation ends after a maximum number of rounds or when neither agent can concede,
+//! falling back to both agents requesting a model update.
+//! Ranked actions are not checked by mutations, so they should be safe alternatives.
+
+use std::fmt;
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores why a joint decision did not act.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Refusal {
+    /// The first agent did not decide an action.
+    First,
+    /// The second agent did not decide an action.
+    Second,
+    /// The first agent did not accept the proposal of the second.
+    RejectedByFirst,
+    /// The second agent did not accept the proposal of the first.
+    RejectedBySecond,
+    /// Negotiation did not find a pair of actions that both agents accept.
+    Deadlock,
+}
+
+/// Stores how two agents negotiate over conflicting proposals.
+pub struct Negotiation<T: Agent, U: Agent> {
+    /// Returns the actions of the first agent, from most to least preferred.
+    pub rank_first: fn(&T::Model) -> Vec<T::Action>,
+    /// Returns the actions of the second agent, from most to least preferred.
+    pub rank_second: fn(&U::Model) -> Vec<U::Action>,
+    /// The maximum number of rounds.
+    pub max_rounds: usize,
+}
+
+impl<T: Agent, U: Agent> Clone for Negotiation<T, U> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<T: Agent, U: Agent> Copy for Negotiation<T, U> {}
+
+impl<T: Agent, U: Agent> fmt::Debug for Negotiation<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Negotiation")
+            .field("rank_first", &self.rank_first)
+            .field("rank_second", &self.rank_second)
+            .field("max_rounds", &self.max_rounds)
+            .finish()
+    }
+}
+
+/// Stores two agents that act jointly.
+#[derive(Clone, Debug)]
+pub struct Joint<T: Agent, U: Agent> {
+    /// The first agent.
+    pub first: T,
+    /// The second agent.
+    pub second: U,
+    /// Returns `true` if the first agent accepts the action of the second.
+    ///
+    /// Called with the model and the action of the first agent.
+    pub accept_first: fn(&T::Model, &T::Action, &U::Action) -> bool,
+    /// Returns `true` if the second agent accepts the action of the first.
+    ///
+    /// Called with the model and the action of the second agent.
+    pub accept_second: fn(&U::Model, &U::Action, &T::Action) -> bool,
+    /// Why the last joint decision did not act, if it did not.
+    pub refusal: Option<Refusal>,
+    /// How to negotiate over conflicting proposals, if at all.
+    pub negotiation: Option<Negotiation<T, U>>,
+}
+
+impl<T: Inspect, U: Inspect> Joint<T, U> {
+    /// Creates a new pair of agents that act jointly.
+    pub fn new(
+        first: T,
+        second: U,
+        accept_first: fn(&T::Model, &T::Action, &U::Action) -> bool,
+        accept_second: fn(&U::Model, &U::Action, &T::Action) -> bool,
+    ) -> Self {
+        Joint {first, second, accept_first, accept_second, refusal: None, negotiation: None}
+    }
+
+    /// Negotiates over ranked lists of actions when proposals conflict.
+    pub fn with_negotiation(mut self, negotiation: Negotiation<T, U>) -> Self {
+        self.negotiation = Some(negotiation);
+        self
+    }
+
+    /// Decide what to do next, jointly.
+    pub fn decide(&mut self) -> Decision<(T::Action, U::Action)> {
+        let (a, b) = match (self.first.decide(), self.second.decide()) {
+            (Decision::Halt, _) | (_, Decision::Halt) => {
+                self.refusal = None;
+                return Decision::Halt;
+            }
+            (Decision::Action(a), Decision::Action(b)) => (a, b),
+            (Decision::Action(_), _) => return self.refuse(Refusal::Second),
+            _ => return self.refuse(Refusal::First),
+        };
+        if self.accepts(&a, &b) {
+            self.refusal = None;
+            return Decision::Action((a, b));
+        }
+        if let Some(negotiation) = self.negotiation {return self.negotiate(negotiation)}
+        if !(self.accept_first)(self.first.model(), &a, &b) {self.refuse(Refusal::RejectedByFirst)}
+        else {self.refuse(Refusal::RejectedBySecond)}
+    }
+
+    fn accepts(&self, a: &T::Action, b: &U::Action) -> bool {
+        (self.accept_first)(self.first.model(), a, b) &&
+        (self.accept_second)(self.second.model(), b, a)
+    }
+
+    fn negotiate(&mut self, negotiation: Negotiation<T, U>) -> Decision<(T::Action, U::Action)> {
+        let mut ranked_first = (negotiation.rank_first)(self.first.model());
+        let mut ranked_second = (negotiation.rank_second)(self.second.model());
+        let (mut i, mut j) = (0, 0);
+        // Every round concedes one action, so it terminates within the total number of actions.
+        for round in 0..negotiation.max_rounds {
+            let (a, b) = match (ranked_first.get(i), ranked_second.get(j)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => break,
+            };
+            if self.accepts(a, b) {
+                self.refusal = None;
+                return Decision::Action((ranked_first.swap_remove(i), ranked_second.swap_remove(j)));
+            }
+            let first_turn = round % 2 == 0;
+            if first_turn && i + 1 < ranked_first.len() || j + 1 >= ranked_second.len() {i += 1}
+            else {j += 1}
+        }
+        self.refuse(Refusal::Deadlock)
+    }
+
+    fn refuse(&mut self, refusal: Refusal) -> Decision<(T::Action, U::Action)> {
+        self.refusal = Some(refusal);
+        Decision::RequestModel
+    }
+
+    /// Performs a joint action on the internal models.
+    pub fn act(&mut self, (a, b): (T::Action, U::Action)) {
+        self.first.act(a);
+        self.second.act(b);
+    }
+
+    /// Updates the internal models of both agents.
+    pub fn update_models(&mut self, first: T::Model, second: U::Model) {
+        self.first.update_model(first);
+        self.second.update_model(second);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agree_jointly() {
+        // Two agents move towards the same goal, but must not move in opposite directions.
+        let accept = |_: &(u32, u32), a: &i32, b: &i32| a * b >= 0;
+        let mut s = Joint::new(crate::tests::four().add(1), crate::tests::four().add(1), accept, accept);
+        assert_eq!(s.decide(), Decision::Action((1, 1)));
+        s.update_models((4, 0), (0, 2));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.refusal, Some(Refusal::RejectedByFirst));
+        s.update_models((4, 3), (4, 0));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.refusal, Some(Refusal::First));
+    }
+
+    #[test]
+    fn negotiate() {
+        let accept = |_: &(u32, u32), a: &i32, b: &i32| a * b >= 0;
+        let rank = |m: &(u32, u32)| if m.1 < m.0 {vec![1, 0]} else {vec![-1, 0]};
+        let negotiation = Negotiation {rank_first: rank, rank_second: rank, max_rounds: 4};
+        let mut s = Joint::new(crate::tests::four().add(1), crate::tests::four().add(1), accept, accept)
+            .with_negotiation(negotiation);
+        s.update_models((4, 0), (0, 2));
+        // The first agent concedes to waiting.
+        assert_eq!(s.decide(), Decision::Action((0, -1)));
+        s.negotiation = Some(Negotiation {max_rounds: 0, ..negotiation});
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.refusal, Some(Refusal::Deadlock));
+    }
+}
@@ -0,0 +1,209 @@
+//! A minimal JSON reader and writer, used by the wire formats of this library.
+
+// Some readers are only used by feature-gated modules.
+#![allow(dead_code)]
+
+pub enum Value {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Value>),
+    Obj(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Obj(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn array(&self) -> Option<&[Value]> {
+        if let Value::Arr(items) = self {Some(items)} else {None}
+    }
+
+    pub fn str(&self) -> Option<&str> {
+        if let Value::Str(s) = self {Some(s)} else {None}
+    }
+
+    pub fn bool(&self) -> Option<bool> {
+        if let Value::Bool(b) = self {Some(*b)} else {None}
+    }
+
+    pub fn num(&self) -> Option<f64> {
+        if let Value::Num(x) = self {Some(*x)} else {None}
+    }
+}
+
+pub fn string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn write(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(value, &mut out);
+    out
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b {"true"} else {"false"}),
+        Value::Num(x) if x.is_finite() => out.push_str(&x.to_string()),
+        Value::Num(_) => out.push_str("null"),
+        Value::Str(s) => out.push_str(&string(s)),
+        Value::Arr(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {out.push(',')}
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Obj(fields) => {
+            out.push('{');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {out.push(',')}
+                out.push_str(&string(key));
+                out.push(':');
+                write_value(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+// Data written by this library nests with the number of safety layers, so it is read without a limit.
+pub fn parse(src: &str) -> Option<Value> {parse_limited(src, usize::MAX)}
+
+// Untrusted input is read with a limit, such that deep nesting does not overflow the stack.
+pub fn parse_limited(src: &str, max_depth: usize) -> Option<Value> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+    let value = parse_value(&chars, &mut i, max_depth)?;
+    skip(&chars, &mut i);
+    if i == chars.len() {Some(value)} else {None}
+}
+
+fn skip(chars: &[char], i: &mut usize) {
+    while *i < chars.len() && chars[*i].is_whitespace() {*i += 1}
+}
+
+fn expect(chars: &[char], i: &mut usize, c: char) -> Option<()> {
+    skip(chars, i);
+    if chars.get(*i) == Some(&c) {*i += 1; Some(())} else {None}
+}
+
+fn parse_value(chars: &[char], i: &mut usize, depth: usize) -> Option<Value> {
+    skip(chars, i);
+    let word = |i: &mut usize, w: &str, v: Value| {
+        let end = *i + w.len();
+        if end <= chars.len() && chars[*i..end].iter().copied().eq(w.chars()) {*i = end; Some(v)}
+        else {None}
+    };
+    match *chars.get(*i)? {
+        'n' => word(i, "null", Value::Null),
+        't' => word(i, "true", Value::Bool(true)),
+        'f' => word(i, "false", Value::Bool(false)),
+        '"' => parse_string(chars, i).map(Value::Str),
+        '[' => {
+            let depth = depth.checked_sub(1)?;
+            *i += 1;
+            let mut items = vec![];
+            if expect(chars, i, ']').is_some() {return Some(Value::Arr(items))}
+            loop {
+                items.push(parse_value(chars, i, depth)?);
+                if expect(chars, i, ']').is_some() {return Some(Value::Arr(items))}
+                expect(chars, i, ',')?;
+            }
+        }
+        '{' => {
+            let depth = depth.checked_sub(1)?;
+            *i += 1;
+            let mut fields = vec![];
+            if expect(chars, i, '}').is_some() {return Some(Value::Obj(fields))}
+            loop {
+                skip(chars, i);
+                let key = parse_string(chars, i)?;
+                expect(chars, i, ':')?;
+                fields.push((key, parse_value(chars, i, depth)?));
+                if expect(chars, i, '}').is_some() {return Some(Value::Obj(fields))}
+                expect(chars, i, ',')?;
+            }
+        }
+        c if c == '-' || c.is_ascii_digit() => {
+            let start = *i;
+            while *i < chars.len() && "+-.eE0123456789".contains(chars[*i]) {*i += 1}
+            chars[start..*i].iter().collect::<String>().parse().ok().map(Value::Num)
+        }
+        _ => None,
+    }
+}
+
+fn parse_string(chars: &[char], i: &mut usize) -> Option<String> {
+    if chars.get(*i) != Some(&'"') {return None}
+    *i += 1;
+    let mut out = String::new();
+    loop {
+        let c = *chars.get(*i)?;
+        *i += 1;
+        match c {
+            '"' => return Some(out),
+            '\\' => {
+                let e = *chars.get(*i)?;
+                *i += 1;
+                out.push(match e {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'u' => {
+                        let hex: String = chars.get(*i..*i + 4)?.iter().collect();
+                        *i += 4;
+                        std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?).unwrap_or('\u{fffd}')
+                    }
+                    e => e,
+                });
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let value = parse(r#" {"a": [1, -2.5e1, true, null], "b": "x\"\n\u0041"} "#).unwrap();
+        assert_eq!(value.get("a").and_then(|a| a.array()).map(|a| a.len()), Some(4));
+        assert_eq!(value.get("a").and_then(|a| a.array()).and_then(|a| a[1].num()), Some(-25.0));
+        assert_eq!(value.get("b").and_then(|b| b.str()), Some("x\"\nA"));
+        assert_eq!(string("x\"\n\u{1}"), r#""x\"\n\u0001""#);
+        assert!(parse("{\"a\":1,}").is_none());
+    }
+
+    #[test]
+    fn nested() {
+        let nested = |n| "[".repeat(n) + &"]".repeat(n);
+        assert!(parse(&nested(1000)).is_some());
+        assert!(parse_limited(&nested(64), 64).is_some());
+        assert!(parse_limited(&nested(65), 64).is_none());
+        assert!(parse_limited(&nested(1_000_000), 64).is_none());
+    }
+}
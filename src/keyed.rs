@@ -0,0 +1,137 @@
+//! Layering actions that do not implement `PartialEq`.
+//!
+//! Layered agents compare actions of sub-agents, which requires `A: PartialEq`.
+//! This excludes actions containing closures or handles,
+//! and floats where `NaN` never equals itself, such that sub-agents never agree.
+//!
+//! Such actions can still be layered by wrapping them:
+//!
+//! - `ByKey`: compares actions by an extracted key, e.g. an id or `f64::to_bits`
+//! - `Compared`: compares actions by an injected comparator
+//!
+//! Deciders return the wrapped action, and actors unwrap it with `into_inner` or by dereferencing.
+//! For approximate agreement of actions that are `PartialEq`, see `LayerConfig::comparator`.
+
+use std::fmt;
+use std::ops::Deref;
+use std::ptr::fn_addr_eq;
+
+/// Stores an action that is compared by an extracted key.
+pub struct ByKey<A, K> {
+    /// The action.
+    pub action: A,
+    /// Returns the key of the action.
+    pub key: fn(&A) -> K,
+}
+
+impl<A, K> ByKey<A, K> {
+    /// Creates a new action compared by key.
+    pub fn new(action: A, key: fn(&A) -> K) -> Self {ByKey {action, key}}
+
+    /// Returns the key of the action.
+    pub fn key(&self) -> K {(self.key)(&self.action)}
+
+    /// Returns the action.
+    pub fn into_inner(self) -> A {self.action}
+}
+
+impl<A: Clone, K> Clone for ByKey<A, K> {
+    fn clone(&self) -> Self {ByKey {action: self.action.clone(), key: self.key}}
+}
+
+impl<A: Copy, K> Copy for ByKey<A, K> {}
+
+impl<A: fmt::Debug, K> fmt::Debug for ByKey<A, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ByKey").field(&self.action).finish()
+    }
+}
+
+impl<A, K: PartialEq> PartialEq for ByKey<A, K> {
+    fn eq(&self, other: &Self) -> bool {self.key() == other.key()}
+}
+
+impl<A, K> Deref for ByKey<A, K> {
+    type Target = A;
+    fn deref(&self) -> &A {&self.action}
+}
+
+/// Stores an action that is compared by an injected comparator.
+///
+/// Two actions are equal when they have the same comparator and it returns `true`.
+pub struct Compared<A> {
+    /// The action.
+    pub action: A,
+    /// Returns `true` when two actions agree.
+    pub comparator: fn(&A, &A) -> bool,
+}
+
+impl<A> Compared<A> {
+    /// Creates a new action compared by a comparator.
+    pub fn new(action: A, comparator: fn(&A, &A) -> bool) -> Self {Compared {action, comparator}}
+
+    /// Returns the action.
+    pub fn into_inner(self) -> A {self.action}
+}
+
+impl<A: Clone> Clone for Compared<A> {
+    fn clone(&self) -> Self {Compared {action: self.action.clone(), comparator: self.comparator}}
+}
+
+impl<A: Copy> Copy for Compared<A> {}
+
+impl<A: fmt::Debug> fmt::Debug for Compared<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Compared").field(&self.action).finish()
+    }
+}
+
+impl<A> PartialEq for Compared<A> {
+    fn eq(&self, other: &Self) -> bool {
+        fn_addr_eq(self.comparator, other.comparator) && (self.comparator)(&self.action, &other.action)
+    }
+}
+
+impl<A> Deref for Compared<A> {
+    type Target = A;
+    fn deref(&self) -> &A {&self.action}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    /// An action without `PartialEq`.
+    #[derive(Clone, Debug)]
+    struct Step(u32);
+
+    #[test]
+    fn by_key() {
+        let z = AgentZ {
+            model: (4, 0),
+            decider: |m: &(u32, u32)| ByKey::new(Step(if m.1 < m.0 {1} else {0}), |s: &Step| s.0),
+            actor: |m: &mut (u32, u32), a: ByKey<Step, u32>| m.1 += a.0,
+            mutater: |m: &mut (u32, u32)| m.0 -= 1,
+            undoer: |m: &mut (u32, u32), _| m.0 += 1,
+        };
+        let mut s = z.add(1);
+        match s.decide() {
+            Decision::Action(a) => {
+                assert_eq!(a.key(), 1);
+                s.act(a);
+            }
+            _ => panic!("expected action"),
+        }
+        s.update_model((4, 3));
+        assert!(matches!(s.decide(), Decision::RequestModel));
+    }
+
+    #[test]
+    fn compared() {
+        let agree = |a: &f64, b: &f64| a.to_bits() == b.to_bits();
+        assert_eq!(Compared::new(f64::NAN, agree), Compared::new(f64::NAN, agree));
+        assert_ne!(Compared::new(0.0, agree), Compared::new(-0.0, agree));
+        assert_eq!(*Compared::new(2.0, agree), 2.0);
+    }
+}
@@ -0,0 +1,89 @@
+//! External kill-switch.
+//!
+//! A `KillSwitch` is a shared flag owned outside the agent, e.g. by an operator console
+//! or a monitoring thread, and cloned into a `Killable` agent.
+//! When the switch is engaged, every decide returns `Decision::Halt`
+//! and every act is refused and logged, without calling the inner agent.
+//!
+//! Since the switch is checked outside of the inner agent,
+//! no configuration of safety layers can bypass it.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores a shared flag that stops agents when engaged.
+#[derive(Clone, Debug, Default)]
+pub struct KillSwitch(Arc<AtomicBool>);
+
+impl KillSwitch {
+    /// Creates a new disengaged kill-switch.
+    pub fn new() -> Self {KillSwitch::default()}
+
+    /// Engages the kill-switch.
+    pub fn engage(&self) {self.0.store(true, Ordering::SeqCst)}
+
+    /// Disengages the kill-switch.
+    pub fn reset(&self) {self.0.store(false, Ordering::SeqCst)}
+
+    /// Returns `true` if the kill-switch is engaged.
+    pub fn is_engaged(&self) -> bool {self.0.load(Ordering::SeqCst)}
+}
+
+/// Stores an agent that is stopped by a kill-switch.
+#[derive(Clone, Debug)]
+pub struct Killable<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The kill-switch.
+    pub switch: KillSwitch,
+    /// The actions refused while the kill-switch was engaged.
+    pub refused: Vec<T::Action>,
+}
+
+impl<T: Agent> Killable<T> {
+    /// Creates a new agent stopped by a kill-switch.
+    pub fn new(agent: T, switch: KillSwitch) -> Self {Killable {agent, switch, refused: vec![]}}
+}
+
+impl<T: Agent> Agent for Killable<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        if self.switch.is_engaged() {return Decision::Halt}
+        let decision = self.agent.decide();
+        // The switch might be engaged while deciding.
+        if self.switch.is_engaged() {Decision::Halt} else {decision}
+    }
+    fn act(&mut self, action: T::Action) {
+        if self.switch.is_engaged() {self.refused.push(action)} else {self.agent.act(action)}
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Killable<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stop() {
+        let switch = KillSwitch::new();
+        let mut s = Killable::new(crate::tests::four().add(1), switch.clone());
+        assert_eq!(s.decide(), Decision::Action(1));
+        std::thread::spawn(move || switch.engage()).join().unwrap();
+        assert_eq!(s.decide(), Decision::Halt);
+        s.act(1);
+        assert_eq!((s.model(), &s.refused[..]), (&(4, 0), &[1][..]));
+        s.switch.reset();
+        s.act(1);
+        assert_eq!(s.model(), &(4, 1));
+    }
+}
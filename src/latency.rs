@@ -0,0 +1,90 @@
+//! Decision latency reporting and budgets.
+//!
+//! When `AgentN::latency` is set, every decide call measures the time of each phase,
+//! recorded in the safety report:
+//!
+//! - `core_time`: deciding with core zero
+//! - `probe_time`: mutating and undoing models for probes
+//! - `compare_time`: comparing actions
+//!
+//! With a budget, probing is trimmed when the time since the start of the decide call
+//! would exceed the budget after the next probe, estimated by the mean time per probe so far.
+//! A layer that is trimmed before a decision is determined requests a model update,
+//! so trimming trades effectiveness for latency, not safety.
+//! The number of trimmed layers is recorded in `SafetyReport::trimmed`.
+//!
+//! Measuring makes safety reports depend on timing, so it is disabled by default.
+
+use std::time::{Duration, Instant};
+
+use crate::AgentN;
+
+/// Stores the latency settings of an agent.
+#[derive(Clone, Copy, Debug)]
+pub struct Latency {
+    /// The latency budget of a decide call, if enforced.
+    pub budget: Option<Duration>,
+    /// The start of the current decide call.
+    pub(crate) start: Option<Instant>,
+}
+
+impl PartialEq for Latency {
+    fn eq(&self, other: &Self) -> bool {self.budget == other.budget}
+}
+
+impl Latency {
+    /// Creates new latency settings that only measure.
+    pub fn measure() -> Self {Latency {budget: None, start: None}}
+
+    /// Creates new latency settings that enforce a budget.
+    pub fn budget(budget: Duration) -> Self {Latency {budget: Some(budget), start: None}}
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Starts measuring a decide call.
+    pub(crate) fn clock_start(&mut self) {
+        if let Some(latency) = &mut self.latency {latency.start = Some(Instant::now())}
+    }
+
+    /// Returns the current time, when measuring.
+    pub(crate) fn clock(&self) -> Option<Instant> {self.latency.map(|_| Instant::now())}
+
+    /// Returns `true` if the next probe would exceed the latency budget, given the probes so far.
+    pub(crate) fn over_budget(&self, probes: u32) -> bool {
+        match self.latency {
+            Some(Latency {budget: Some(budget), start: Some(start)}) => {
+                let elapsed = start.elapsed();
+                elapsed + elapsed / (probes + 1) > budget
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Adds the time since a measurement to a total.
+pub(crate) fn lap(start: Option<Instant>, total: &mut Duration) {
+    if let Some(start) = start {*total += start.elapsed()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision, Reason, SafetyReport};
+
+    #[test]
+    fn budget() {
+        let mut s = crate::tests::four().add(2);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.report.core_time, Duration::from_secs(0));
+
+        s.latency = Some(Latency::measure());
+        assert_eq!(s.decide(), Decision::Action(1));
+        let SafetyReport {core_time, trimmed, ..} = s.report;
+        assert!(core_time > Duration::from_secs(0));
+        assert_eq!(trimmed, 0);
+
+        s.latency = Some(Latency::budget(Duration::from_secs(0)));
+        assert_eq!(s.diagnose().reason, Reason::Undetermined {layer: 2});
+        assert_eq!((s.report.probes, s.report.trimmed), (0, 1));
+    }
+}
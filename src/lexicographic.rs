@@ -0,0 +1,137 @@
+//! Lexicographic multi-objective deciders.
+//!
+//! A decider can be built from an ordered list of objectives, where higher scores are better.
+//! The first objective picks the best candidate actions,
+//! and ties are resolved by successive objectives.
+//!
+//! The function `decide` returns a `Choice`, which remembers the objective that determined it.
+//! Choices agree only when the same objective determined the same action,
+//! such that a mutation that changes which objective is decisive counts as a disagreement.
+//!
+//! A decision that is determined only by a low-priority objective
+//! is fragile, because it depends on small differences in objectives that matter less.
+//! A `Prioritized` agent requests a model update instead of acting on such decisions,
+//! with the reason `Reason::LowPriority`.
+
+use crate::{Agent, AgentN, Decision, Diagnosis, Inspect, Reason};
+
+/// Stores an action chosen by lexicographic objectives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Choice<A> {
+    /// The action.
+    pub action: A,
+    /// The index of the objective that determined the action.
+    ///
+    /// This equals the number of objectives when the action was tied in all objectives.
+    pub objective: usize,
+}
+
+/// Returns the score of an action in some model, where higher is better.
+pub type Objective<M, A> = fn(&M, &A) -> f64;
+
+/// Chooses among candidate actions by lexicographic objectives.
+///
+/// Among tied actions, the first candidate is chosen.
+/// Panics if there are no candidates.
+pub fn decide<M, A: Clone>(model: &M, candidates: &[A], objectives: &[Objective<M, A>]) -> Choice<A> {
+    let (best, objective) = rank(model, candidates, objectives);
+    Choice {action: candidates[best[0]].clone(), objective}
+}
+
+/// Returns the indices of the best candidates, with the index of the objective that determined them.
+fn rank<M, A>(model: &M, candidates: &[A], objectives: &[Objective<M, A>]) -> (Vec<usize>, usize) {
+    let mut best: Vec<usize> = (0..candidates.len()).collect();
+    for (i, f) in objectives.iter().enumerate() {
+        let scores: Vec<f64> = best.iter().map(|&j| f(model, &candidates[j])).collect();
+        let max = scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let next: Vec<usize> = best.iter().zip(&scores).filter(|(_, &s)| s == max).map(|(&j, _)| j).collect();
+        if next.len() == 1 {return (next, i)}
+        best = next;
+    }
+    (best, objectives.len())
+}
+
+/// Returns the candidate actions tied in all objectives, in the order of candidates.
+///
+/// Ties can be resolved reproducibly using `tiebreak::TieBreaker`.
+pub fn ties<M, A: Clone>(model: &M, candidates: &[A], objectives: &[Objective<M, A>]) -> Vec<A> {
+    rank(model, candidates, objectives).0.into_iter().map(|j| candidates[j].clone()).collect()
+}
+
+/// Stores an agent that requests a model update on decisions determined by low-priority objectives.
+#[derive(Clone, Debug)]
+pub struct Prioritized<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, Choice<A>, D>,
+    /// The index of the first objective of low priority.
+    pub low: usize,
+}
+
+impl<M, A: PartialEq, D> Prioritized<M, A, D> {
+    /// Creates a new agent where objectives from index `low` have low priority.
+    pub fn new(agent: AgentN<M, Choice<A>, D>, low: usize) -> Self {Prioritized {agent, low}}
+
+    /// Decide what to do next, together with the reason.
+    pub fn diagnose(&mut self) -> Diagnosis<Choice<A>> {
+        let diagnosis = self.agent.diagnose();
+        match &diagnosis.decision {
+            Decision::Action(c) if c.objective >= self.low => Diagnosis {
+                decision: Decision::RequestModel,
+                reason: Reason::LowPriority {objective: c.objective},
+            },
+            _ => diagnosis,
+        }
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Prioritized<M, A, D> {
+    type Model = M;
+    type Action = Choice<A>;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<Choice<A>> {self.diagnose().decision}
+    fn act(&mut self, action: Choice<A>) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Prioritized<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AgentZ;
+
+    static OBJECTIVES: [Objective<(u32, u32), i32>; 2] = [
+        // Move towards the goal.
+        |m, a| -((m.1 as i32 + a) - m.0 as i32).abs() as f64,
+        // Prefer standing still.
+        |_, a| -a.abs() as f64,
+    ];
+
+    fn decider(m: &(u32, u32)) -> Choice<i32> {decide(m, &[2, 0, -2], &OBJECTIVES)}
+
+    #[test]
+    fn low_priority() {
+        assert_eq!(decider(&(4, 0)), Choice {action: 2, objective: 0});
+        assert_eq!(decide(&(4, 0), &[1, 0], &OBJECTIVES[1..]), Choice {action: 0, objective: 0});
+        assert_eq!(decide(&(4, 4), &[1, 1], &OBJECTIVES), Choice {action: 1, objective: 2});
+
+        let z = AgentZ {
+            model: (4, 0),
+            decider,
+            actor: |m: &mut (u32, u32), c: Choice<i32>| m.1 = (m.1 as i32 + c.action) as u32,
+            mutater: |m| {m.0 -= 1; -1},
+            undoer: |m, d| m.0 = (m.0 as i32 - d) as u32,
+        };
+        let mut s = Prioritized::new(z.add(0), 1);
+        assert_eq!(s.decide(), Decision::Action(Choice {action: 2, objective: 0}));
+        // Moving by `2` or standing still are equally close to the goal.
+        s.update_model((4, 3));
+        let diagnosis = s.diagnose();
+        assert_eq!(diagnosis.decision, Decision::RequestModel);
+        assert_eq!(diagnosis.reason, Reason::LowPriority {objective: 1});
+    }
+}
@@ -8,7 +8,7 @@
 //! (also called "Higher Order Utilitarianism").
 //! This is an extension of Instrumental Rationality with higher order reasoning about goals.
 //!
-//! For informal proof of correctness, see comments in code of `AgentS::decide`.
+//! For informal proof of correctness, see comments in code of `AgentN::decide_s`.
 //!
 //! ### Design
 //!
@@ -32,6 +32,8 @@
 //! This library does not include fixed algorithms for interactions between agents and environment.
 //! There are many ways to construct such algorithms using this library.
 //!
+//! Commonly used types, and `builder::agent` for constructing agents, are exported in `prelude`.
+//!
 //! ### Definition of "Safer"
 //!
 //! An agent simulates consequences of its actions using a model of the environment.
@@ -82,6 +84,21 @@
 //! A single model is used for all safety layers.
 //! Each safety layer adds a delta for keeping track of mutations.
 //!
+//! Deciding does not allocate memory on the heap.
+//! Deltas are kept on the stack while probing mutations,
+//! so agents can be used inside real-time control loops.
+//! This assumes that the decider, mutater and undoer do not allocate,
+//! and that scratch space is reserved up front, see `AgentN::reserve_scratch`.
+//!
+//! Opt-in features that record history might allocate while deciding:
+//! coverage visits of new regions, warm-start outcomes of new probes,
+//! rationales, provenance and journaled deltas.
+//!
+//! ### Thread Safety
+//!
+//! Agents are `Send` or `Sync` when the model is.
+//! To drive an agent from one thread and inspect it from another, use `SharedAgent`.
+//!
 //! ### Time Complexity
 //!
 //! The time complexity is linear `O(N)` where `N` is safety layers.
@@ -95,13 +112,294 @@
 //! ...
 //! ```
 
+pub mod aggregate;
+pub mod agreement;
+pub mod alarm;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod ambiguity;
+pub mod arena;
+#[cfg(feature = "async")]
+pub mod approval;
+pub mod assertion;
+#[cfg(feature = "quickbacktrack")]
+pub mod backtrack;
+pub mod batch;
+pub mod bounds;
+pub mod boxed;
+pub mod budget;
+pub mod builder;
+pub mod capability;
+#[cfg(feature = "cbor")]
+mod cbor;
+pub mod certificate;
+pub mod certified;
+#[cfg(feature = "checkpoint")]
+pub mod checkpoint;
+pub mod clarify;
+pub mod commit;
+pub mod compliance;
+pub mod composition;
+pub mod confidence;
+#[cfg(any(test, feature = "testing"))]
+pub mod consistency;
+pub mod contracts;
+pub mod cooldown;
+pub mod coverage;
+pub mod cow;
+pub mod curriculum;
+pub mod debugger;
+pub mod delta;
+pub mod determinism;
+pub mod diff;
+#[cfg(any(test, feature = "testing"))]
+pub mod difftest;
+pub mod effects;
+pub mod ensemble;
+pub mod environment;
+#[cfg(feature = "envs")]
+pub mod envs;
+pub mod equilibrium;
+pub mod error;
+pub mod exhausted;
+pub mod expiry;
+pub mod explain;
+pub mod explore;
+pub mod faults;
+pub mod federation;
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
+pub mod generation;
+#[cfg(any(test, feature = "testing"))]
+pub mod golden;
+pub mod graded;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "async")]
+pub mod handle;
+pub mod health;
+pub mod hibernate;
+pub mod host;
+pub mod inbox;
+pub mod informative;
+pub mod inspector;
+pub mod invariance;
+pub mod invariants;
+pub mod joint;
+pub mod keyed;
+pub mod killswitch;
+pub mod latency;
+pub mod lexicographic;
+pub mod lookahead;
+mod json;
+#[cfg(feature = "llm")]
+pub mod llm;
+pub mod manifest;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod migrate;
+pub mod oracle;
+pub mod pareto;
+pub mod patch;
+#[cfg(feature = "persistent")]
+pub mod persistent;
+pub mod planner;
+pub mod posterior;
+pub mod preference;
+pub mod prelude;
+pub mod provenance;
+#[cfg(any(test, feature = "prover"))]
+pub mod prover;
+pub mod query;
+pub mod rationale;
+pub mod recovery;
+pub mod registry;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod reversibility;
+pub mod reward;
+pub mod rng;
+pub mod robustness;
+pub mod rollback;
+pub mod runtime;
+pub mod sandbox;
+pub mod savepoint;
+pub mod scenario;
+pub mod schema;
+pub mod series;
+pub mod shadow;
+pub mod shared;
+pub mod shield;
+#[cfg(feature = "crypto")]
+pub mod signed;
+pub mod sourcing;
+pub mod stepper;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod surprise;
+pub mod swap;
+pub mod tiebreak;
+pub mod tom;
+pub mod trace;
+pub mod transition;
+pub mod trust;
+pub mod tune;
+pub mod utility;
+pub mod verified;
+pub mod voting;
+pub mod warm;
+pub mod watchdog;
+pub mod wire;
+pub mod workspace;
+
+use std::fmt;
+use std::ptr::fn_addr_eq;
+use std::time::{Duration, Instant};
+
+pub use error::Error;
+
+use agreement::{AgreementPolicy, Verdict, Vote, Votes};
+use budget::Fallback;
+use exhausted::Exhausted;
+
 /// Stores agent decision.
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Decision<A> {
     /// An action to perform.
     Action(A),
     /// Request an updated model of the environment.
     RequestModel,
+    /// Stop acting until the agent is reset by the environment.
+    Halt,
+}
+
+impl<A: fmt::Display> fmt::Display for Decision<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Action(a) => {
+                f.write_str("Action(")?;
+                a.fmt(f)?;
+                f.write_str(")")
+            }
+            Decision::RequestModel => f.write_str("RequestModel"),
+            Decision::Halt => f.write_str("Halt"),
+        }
+    }
+}
+
+/// Stores the reason for a decision.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Reason {
+    /// Core zero decided without safety layers.
+    Core,
+    /// Core zero requested a model update.
+    CoreRequest,
+    /// A mutation agreed with core zero.
+    Agree {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+        /// The index of the probe.
+        probe: u8,
+    },
+    /// All mutations that determined a decision agreed with core zero.
+    AllAgree {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+    },
+    /// A mutation disagreed with core zero.
+    Disagree {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+        /// The index of the probe.
+        probe: u8,
+    },
+    /// No mutation determined a decision.
+    Undetermined {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+    },
+    /// The core was replaced without receiving a model update since.
+    Handoff,
+    /// The probe outcomes of a layer were too divided.
+    Divided {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+    },
+    /// A pair of mutations disagreed with core zero, while single mutations agreed.
+    DisagreePair {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+        /// The indices of the probes.
+        probes: (u8, u8),
+    },
+    /// A mutation disagreed with core zero, but new information was not worth waiting for.
+    Waived {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+        /// The index of the probe.
+        probe: u8,
+    },
+    /// The decision was determined only by an objective of low priority.
+    LowPriority {
+        /// The index of the objective.
+        objective: usize,
+    },
+    /// Core zero found a tie between actions.
+    Tie,
+    /// The agreement policy of a layer decided.
+    Policy {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+    },
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Core => write!(f, "core zero decided"),
+            Reason::CoreRequest => write!(f, "core zero requested a model"),
+            Reason::Agree {layer, probe} =>
+                write!(f, "mutation #{} of layer {} agreed", probe, layer),
+            Reason::AllAgree {layer} => write!(f, "all mutations of layer {} agreed", layer),
+            Reason::Disagree {layer, probe} =>
+                write!(f, "mutation #{} of layer {} disagreed", probe, layer),
+            Reason::Undetermined {layer} =>
+                write!(f, "no mutation of layer {} determined a decision", layer),
+            Reason::Handoff => write!(f, "core was replaced"),
+            Reason::Divided {layer} =>
+                write!(f, "mutations of layer {} were too divided", layer),
+            Reason::DisagreePair {layer, probes: (i, j)} =>
+                write!(f, "mutations #{} and #{} of layer {} disagreed together", i, j, layer),
+            Reason::Waived {layer, probe} =>
+                write!(f, "mutation #{} of layer {} disagreed, but asking was not worth it", probe, layer),
+            Reason::LowPriority {objective} =>
+                write!(f, "only objective #{} of low priority determined a decision", objective),
+            Reason::Tie => write!(f, "core zero found a tie between actions"),
+            Reason::Policy {layer} => write!(f, "the agreement policy of layer {} decided", layer),
+        }
+    }
+}
+
+/// Stores a decision together with its reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Diagnosis<A> {
+    /// The decision.
+    pub decision: Decision<A>,
+    /// The reason for the decision.
+    pub reason: Reason,
+}
+
+impl<A: fmt::Display> fmt::Display for Diagnosis<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.decision {
+            Decision::Action(a) => {
+                f.write_str("Action(")?;
+                a.fmt(f)?;
+                write!(f, ", reason: {})", self.reason)
+            }
+            Decision::RequestModel => write!(f, "RequestModel(reason: {})", self.reason),
+            Decision::Halt => write!(f, "Halt(reason: {})", self.reason),
+        }
+    }
 }
 
 /// Implemented by agents.
@@ -125,8 +423,15 @@ pub trait Agent {
     fn undo(&mut self, delta: Self::Delta);
 }
 
+/// Implemented by agents that give read access to their internal model.
+pub trait Inspect: Agent {
+    /// Returns the internal model.
+    fn model(&self) -> &Self::Model;
+}
+
 /// Stores an agent that only acts, assuming its model is perfect.
-#[derive(Clone)]
+///
+/// Agents are compared by model and by the addresses of their functions.
 pub struct AgentZ<M, A, D> {
     /// Stores the model.
     pub model: M,
@@ -140,14 +445,75 @@ pub struct AgentZ<M, A, D> {
     pub undoer: fn(&mut M, D),
 }
 
+impl<M: Clone, A, D> Clone for AgentZ<M, A, D> {
+    fn clone(&self) -> Self {
+        AgentZ {
+            model: self.model.clone(),
+            decider: self.decider,
+            actor: self.actor,
+            mutater: self.mutater,
+            undoer: self.undoer,
+        }
+    }
+}
+
+impl<M: fmt::Debug, A, D> fmt::Debug for AgentZ<M, A, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentZ")
+            .field("model", &self.model)
+            .field("decider", &self.decider)
+            .field("actor", &self.actor)
+            .field("mutater", &self.mutater)
+            .field("undoer", &self.undoer)
+            .finish()
+    }
+}
+
+impl<M: PartialEq, A, D> PartialEq for AgentZ<M, A, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.model == other.model &&
+        fn_addr_eq(self.decider, other.decider) &&
+        fn_addr_eq(self.actor, other.actor) &&
+        fn_addr_eq(self.mutater, other.mutater) &&
+        fn_addr_eq(self.undoer, other.undoer)
+    }
+}
+
 impl<M, A, D> AgentZ<M, A, D> {
     /// Add extra layers of safety.
+    #[allow(clippy::should_implement_trait)]
     pub fn add(self, n: usize) -> AgentN<M, A, D> {
-        match n {
-            0 => AgentN::Z(self),
-            _ => AgentN::S(Box::new(AgentS {core: self.add(n-1)})),
+        AgentN {
+            z: self,
+            layers: vec![LayerConfig::default(); n],
+            mutaters: vec![],
+            targets: vec![],
+            observers: vec![],
+            incremental: None,
+            stochastic: None,
+            coverage: None,
+            dedup: None,
+            describe: None,
+            voi: None,
+            exhausted: exhausted::Exhausted::Request,
+            report: SafetyReport::default(),
+            handoff: false,
+            rationale: None,
+            scratch: arena::Scratch::new(),
+            latency: None,
+            warm: None,
+            provenance: None,
+            #[cfg(feature = "replay")]
+            journal: None,
         }
     }
+
+    /// Returns the model after performing an action, without changing the agent.
+    pub fn simulate(&self, action: A) -> M where M: Clone {
+        let mut model = self.model.clone();
+        (self.actor)(&mut model, action);
+        model
+    }
 }
 
 impl<M, A, D> Agent for AgentZ<M, A, D> {
@@ -161,102 +527,577 @@ impl<M, A, D> Agent for AgentZ<M, A, D> {
     fn undo(&mut self, delta: D) {(self.undoer)(&mut self.model, delta)}
 }
 
+impl<M, A, D> Inspect for AgentZ<M, A, D> {
+    fn model(&self) -> &M {&self.model}
+}
+
 /// Stores a agent with N added safety layers.
-pub enum AgentN<M, A, D> {
+///
+/// The core zero agent is stored separately from the safety layers,
+/// such that it can be reached in constant time.
+///
+/// Agents are compared by model and configuration,
+/// where functions are compared by address.
+pub struct AgentN<M, A, D> {
     /// Core zero agent.
-    Z(AgentZ<M, A, D>),
-    /// Successor agent.
-    S(Box<AgentS<M, A, D>>),
+    pub z: AgentZ<M, A, D>,
+    /// The configuration of each safety layer, from innermost to outermost.
+    pub layers: Vec<LayerConfig<A>>,
+    /// Mutaters used for probing, where probe `i` uses mutater `i` modulo their number,
+    /// unless they are ordered by `coverage`.
+    ///
+    /// When empty, the mutater of core zero is used.
+    /// Deltas are undone by the undoer of core zero.
+    pub mutaters: Vec<fn(&mut M) -> D>,
+    /// Names the part of the model targeted by each mutater.
+    ///
+    /// The index of the mutater of core zero is `0`.
+    pub targets: Vec<&'static str>,
+    /// Called on events while deciding.
+    pub observers: Vec<fn(&Event<A>)>,
+    /// Enables incremental deciding with dirty tracking.
+    pub incremental: Option<Incremental<M, D>>,
+    /// A stochastic mutater used for probing instead of `mutaters`.
+    ///
+    /// Deltas are undone by the undoer of core zero.
+    pub stochastic: Option<rng::Stochastic<M, D>>,
+    /// Orders `mutaters` on every decide call, probing unexplored regions of the model space first.
+    pub coverage: Option<coverage::Coverage<M>>,
+    /// Returns a fingerprint of a delta, used to skip duplicate probes within a decide call.
+    ///
+    /// Deltas with equal fingerprints are assumed to be the same mutation.
+    pub dedup: Option<fn(&D) -> u64>,
+    /// Describes deltas in explanations and journals.
+    pub describe: Option<fn(&D) -> delta::DeltaMeta>,
+    /// Estimates the value of information when sub-agents disagree.
+    ///
+    /// Called with the model, the action of core zero and the conflicting action.
+    /// Returns `true` when the expected benefit of a model update outweighs the cost of waiting.
+    /// When it does not, the agent acts on the decision of core zero.
+    /// When `None`, the agent always requests a model update.
+    pub voi: Option<fn(&M, &A, &A) -> bool>,
+    /// The behavior when no probe of the outermost safety layer determines a decision.
+    pub exhausted: exhausted::Exhausted<M, A>,
+    /// The safety report of the last decide call.
+    pub report: SafetyReport,
+    /// Whether the core was replaced without receiving a model update since.
+    ///
+    /// While this is `true`, the agent requests a model update on every decide.
+    pub handoff: bool,
+    /// The stack of rationales being recorded, when recording.
+    pub(crate) rationale: Option<Vec<rationale::Rationale>>,
+    /// The scratch space of safety layers while probing.
+    pub(crate) scratch: arena::Scratch,
+    /// Measures the latency of decide calls and enforces a budget, when set.
+    pub latency: Option<latency::Latency>,
+    /// Reuses the probes of previous decide calls, when set.
+    pub warm: Option<warm::WarmStart<M>>,
+    /// Tracks which layer and probe applied each delta, when set.
+    pub provenance: Option<provenance::Provenance<M>>,
+    /// The journal of deltas, when journaling.
+    #[cfg(feature = "replay")]
+    pub(crate) journal: Option<replay::Journal<D>>,
 }
 
-impl<M, A, D> AgentN<M, A, D> {
-    /// Returns the core zero agent.
-    pub fn z(&mut self) -> &mut AgentZ<M, A, D> {
-        match self {
-            AgentN::Z(agent) => agent,
-            AgentN::S(agent) => agent.core.z(),
+impl<M: Clone, A, D> Clone for AgentN<M, A, D> {
+    fn clone(&self) -> Self {
+        AgentN {
+            z: self.z.clone(),
+            layers: self.layers.clone(),
+            mutaters: self.mutaters.clone(),
+            targets: self.targets.clone(),
+            observers: self.observers.clone(),
+            incremental: self.incremental,
+            stochastic: self.stochastic.clone(),
+            coverage: self.coverage.clone(),
+            dedup: self.dedup,
+            describe: self.describe,
+            voi: self.voi,
+            exhausted: self.exhausted,
+            report: self.report,
+            handoff: self.handoff,
+            rationale: None,
+            scratch: arena::Scratch::new(),
+            latency: self.latency,
+            warm: self.warm.clone(),
+            provenance: self.provenance.clone(),
+            #[cfg(feature = "replay")]
+            journal: self.journal.clone(),
         }
     }
+}
 
-    /// Decreases one safety level.
-    pub fn dec(self) -> AgentN<M, A, D> {
-        match self {
-            AgentN::Z(_) => self,
-            AgentN::S(agent) => agent.core,
-        }
+impl<M: fmt::Debug, A, D> fmt::Debug for AgentN<M, A, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentN")
+            .field("z", &self.z)
+            .field("layers", &self.layers)
+            .field("mutaters", &self.mutaters)
+            .field("targets", &self.targets)
+            .field("observers", &self.observers)
+            .field("incremental", &self.incremental)
+            .field("stochastic", &self.stochastic)
+            .field("coverage", &self.coverage)
+            .field("dedup", &self.dedup)
+            .field("describe", &self.describe)
+            .field("voi", &self.voi)
+            .field("exhausted", &self.exhausted)
+            .field("report", &self.report)
+            .field("handoff", &self.handoff)
+            .field("latency", &self.latency)
+            .field("warm", &self.warm)
+            .field("provenance", &self.provenance)
+            .finish()
     }
+}
 
-    /// Increase one safety level.
-    pub fn inc(self) -> AgentN<M, A, D> {
-        AgentN::S(Box::new(AgentS {core: self}))
+impl<M: PartialEq, A, D> PartialEq for AgentN<M, A, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.z == other.z &&
+        self.layers == other.layers &&
+        fns_eq(&self.mutaters, &other.mutaters, |a, b| fn_addr_eq(a, b)) &&
+        self.targets == other.targets &&
+        fns_eq(&self.observers, &other.observers, |a, b| fn_addr_eq(a, b)) &&
+        self.incremental == other.incremental &&
+        self.stochastic == other.stochastic &&
+        self.coverage == other.coverage &&
+        match (self.dedup, other.dedup) {
+            (Some(a), Some(b)) => fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        } &&
+        match (self.describe, other.describe) {
+            (Some(a), Some(b)) => fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        } &&
+        match (self.voi, other.voi) {
+            (Some(a), Some(b)) => fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        } &&
+        self.exhausted == other.exhausted &&
+        self.report == other.report &&
+        self.handoff == other.handoff &&
+        self.latency == other.latency &&
+        self.warm == other.warm &&
+        self.provenance == other.provenance
     }
 }
 
-impl<M, A, D> Agent for AgentN<M, A, D>
-    where A: PartialEq
-{
-    type Model = M;
-    type Action = A;
-    type Delta = D;
-    fn update_model(&mut self, model: M) {
-        match self {
-            AgentN::Z(agent) => agent.update_model(model),
-            AgentN::S(agent) => agent.update_model(model),
+fn fns_eq<F: Copy>(a: &[F], b: &[F], eq: fn(F, F) -> bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| eq(a, b))
+}
+
+/// Stores the rule for agreement between sub-agents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Agreement {
+    /// Act when the first mutation that determines a decision agrees.
+    First,
+    /// Act when all mutations that determine a decision agree.
+    All,
+}
+
+/// Stores the configuration of a safety layer.
+pub struct LayerConfig<A> {
+    /// Limits number of orthogonal mutations.
+    pub mutation_limit: u8,
+    /// The rule for agreement between sub-agents.
+    pub agreement: Agreement,
+    /// Returns `true` when two actions agree.
+    ///
+    /// When `None`, actions agree when they are equal.
+    pub comparator: Option<fn(&A, &A) -> bool>,
+    /// The maximum entropy of probe outcomes in this layer for acting.
+    ///
+    /// When exceeded, a model update is requested. When `None`, there is no maximum.
+    pub max_entropy: Option<f64>,
+    /// Whether to also probe pairs of mutations before acting.
+    ///
+    /// Single mutations might agree while their composition disagrees,
+    /// for example when uncertainty about goal and state interact.
+    pub second_order: bool,
+    /// Probes mutations until the time budget of the layer is exhausted, instead of `mutation_limit`.
+    ///
+    /// At least one and at most `u8::MAX` mutations are probed,
+    /// where the number of probes that fit is recorded in the safety report and the rationale.
+    /// Pairs of mutations are still limited by `mutation_limit`.
+    pub time_budget: Option<Duration>,
+    /// The policy for agreement between sub-agents, instead of `agreement`.
+    ///
+    /// When `None`, `agreement` is used.
+    pub policy: Option<&'static dyn AgreementPolicy>,
+    /// Called on events of this safety layer only.
+    ///
+    /// Receives the probes of the layer, and its decision as `Event::Decide` at the safety level of the layer.
+    pub observer: Option<fn(&Event<A>)>,
+}
+
+impl<A> Clone for LayerConfig<A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<A> Copy for LayerConfig<A> {}
+
+impl<A> fmt::Debug for LayerConfig<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LayerConfig")
+            .field("mutation_limit", &self.mutation_limit)
+            .field("agreement", &self.agreement)
+            .field("comparator", &self.comparator)
+            .field("max_entropy", &self.max_entropy)
+            .field("second_order", &self.second_order)
+            .field("time_budget", &self.time_budget)
+            .field("policy", &self.policy)
+            .field("observer", &self.observer)
+            .finish()
+    }
+}
+
+impl<A> PartialEq for LayerConfig<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.mutation_limit == other.mutation_limit &&
+        self.agreement == other.agreement &&
+        match (self.comparator, other.comparator) {
+            (Some(a), Some(b)) => fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        } &&
+        self.max_entropy == other.max_entropy &&
+        self.second_order == other.second_order &&
+        self.time_budget == other.time_budget &&
+        match (self.policy, other.policy) {
+            (Some(a), Some(b)) => std::ptr::addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        } &&
+        match (self.observer, other.observer) {
+            (Some(a), Some(b)) => fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
         }
     }
-    fn decide(&mut self) -> Decision<A> {
-        match self {
-            AgentN::Z(agent) => agent.decide(),
-            AgentN::S(agent) => agent.decide(),
+}
+
+impl<A> Default for LayerConfig<A> {
+    fn default() -> Self {
+        LayerConfig {
+            mutation_limit: MUTATION_LIMIT,
+            agreement: Agreement::First,
+            comparator: None,
+            max_entropy: None,
+            second_order: false,
+            time_budget: None,
+            policy: None,
+            observer: None,
         }
     }
-    fn act(&mut self, action: A) {
-        match self {
-            AgentN::Z(agent) => agent.act(action),
-            AgentN::S(agent) => agent.act(action),
+}
+
+impl<A: PartialEq> LayerConfig<A> {
+    /// Returns `true` when two actions agree.
+    pub fn agree(&self, a: &A, b: &A) -> bool {
+        match self.comparator {
+            Some(f) => f(a, b),
+            None => a == b,
         }
     }
-    fn mutate(&mut self) -> D {
+}
+
+impl<A> LayerConfig<A> {
+    /// Returns `true` when counts of probe outcomes exceed the maximum entropy.
+    pub fn divided(&self, counts: &[u32]) -> bool {
+        self.max_entropy.map(|max| entropy(counts) > max).unwrap_or(false)
+    }
+}
+
+/// Stores the outcome of probing a mutation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProbeOutcome {
+    /// The mutated decision agrees with core zero.
+    Agree,
+    /// The mutated decision disagrees with core zero.
+    Disagree,
+    /// The mutated decision requests a model update.
+    RequestModel,
+}
+
+impl fmt::Display for ProbeOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AgentN::Z(agent) => agent.mutate(),
-            AgentN::S(agent) => agent.mutate(),
+            ProbeOutcome::Agree => write!(f, "agree"),
+            ProbeOutcome::Disagree => write!(f, "disagree"),
+            ProbeOutcome::RequestModel => write!(f, "request model"),
         }
     }
-    fn undo(&mut self, delta: D) {
+}
+
+/// Stores a report of the probes of a decide call, over all safety layers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SafetyReport {
+    /// The number of probes.
+    pub probes: u32,
+    /// The number of probes that agreed with core zero.
+    pub approvals: u32,
+    /// The number of probes that disagreed with core zero.
+    pub disagreements: u32,
+    /// The number of probes that requested a model update.
+    pub requests: u32,
+    /// The time spent deciding with core zero, when measuring latency.
+    pub core_time: Duration,
+    /// The time spent mutating and undoing models for probes, when measuring latency.
+    pub probe_time: Duration,
+    /// The time spent comparing actions, when measuring latency.
+    pub compare_time: Duration,
+    /// The number of safety layers whose probing was trimmed to fit the latency budget.
+    pub trimmed: u32,
+    /// The number of undos that did not restore the model, when verifying provenance.
+    pub undo_errors: u32,
+}
+
+impl SafetyReport {
+    /// Returns the entropy of probe outcomes in bits, as a safety index.
+    ///
+    /// This is `0` when all probes had the same outcome,
+    /// and increases the more divided the mutated decisions were.
+    pub fn entropy(&self) -> f64 {entropy(&[self.approvals, self.disagreements, self.requests])}
+}
+
+/// Returns the entropy in bits of a distribution given by counts.
+pub fn entropy(counts: &[u32]) -> f64 {
+    let total: u32 = counts.iter().sum();
+    counts.iter().filter(|&&c| c > 0).map(|&c| {
+        let p = c as f64 / total as f64;
+        -p * p.log2()
+    }).sum()
+}
+
+/// Stores an event observed while deciding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a, A> {
+    /// A safety layer probed a mutation.
+    Probe {
+        /// The safety layer, where `1` is the innermost one.
+        layer: usize,
+        /// The index of the probe.
+        probe: u8,
+        /// The outcome of the probe.
+        outcome: ProbeOutcome,
+    },
+    /// The agent made a decision.
+    Decide {
+        /// The number of safety layers.
+        layers: usize,
+        /// The decision.
+        decision: &'a Decision<A>,
+    },
+    /// A contract of an agent method was violated.
+    Violation {
+        /// The method.
+        method: contracts::Method,
+        /// The name of the contract.
+        contract: &'static str,
+    },
+}
+
+impl<'a, A: fmt::Display> fmt::Display for Event<'a, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            AgentN::Z(agent) => agent.undo(delta),
-            AgentN::S(agent) => agent.undo(delta),
+            Event::Probe {layer, probe, outcome} =>
+                write!(f, "mutation #{} of layer {}: {}", probe, layer, outcome),
+            Event::Decide {layers, decision} => {
+                write!(f, "decided at safety level {}: ", layers)?;
+                decision.fmt(f)
+            }
+            Event::Violation {method, contract} =>
+                write!(f, "contract `{}` of `{}` violated", contract, method),
         }
     }
 }
 
-/// Stores a successor agent.
-pub struct AgentS<M, A, D> {
-    /// The core sub-agent.
-    pub core: AgentN<M, A, D>,
+/// Stores functions for incremental deciding.
+///
+/// Regions of the model are represented as bits.
+/// When a mutation does not touch any region read by the decider,
+/// core zero would decide the same action,
+/// so probing the mutation is skipped.
+pub struct Incremental<M, D> {
+    /// Returns the regions of the model that the decider reads.
+    pub reads: fn(&M) -> u64,
+    /// Returns the regions of the model that a delta touches.
+    pub touches: fn(&D) -> u64,
 }
 
-/// A constant that limits number of orthogonal mutations.
-pub const MUTATION_LIMIT: u8 = 4;
+impl<M, D> Clone for Incremental<M, D> {
+    fn clone(&self) -> Self {*self}
+}
 
-impl<M, A, D> Agent for AgentS<M, A, D>
+impl<M, D> Copy for Incremental<M, D> {}
+
+impl<M, D> fmt::Debug for Incremental<M, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Incremental")
+            .field("reads", &self.reads)
+            .field("touches", &self.touches)
+            .finish()
+    }
+}
+
+impl<M, D> PartialEq for Incremental<M, D> {
+    fn eq(&self, other: &Self) -> bool {
+        fn_addr_eq(self.reads, other.reads) && fn_addr_eq(self.touches, other.touches)
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Returns the core zero agent.
+    pub fn z(&mut self) -> &mut AgentZ<M, A, D> {&mut self.z}
+
+    /// Returns the number of safety layers.
+    pub fn layers(&self) -> usize {self.layers.len()}
+
+    /// Decreases one safety level.
+    pub fn dec(mut self) -> AgentN<M, A, D> {
+        self.layers.pop();
+        self
+    }
+
+    /// Increase one safety level.
+    pub fn inc(mut self) -> AgentN<M, A, D> {
+        self.layers.push(LayerConfig::default());
+        self
+    }
+
+    /// Replaces the core zero agent, returning the old one.
+    ///
+    /// The agent requests a model update on every decide,
+    /// until the new core receives a model update.
+    pub fn replace_core(&mut self, z: AgentZ<M, A, D>) -> AgentZ<M, A, D> {
+        self.handoff = true;
+        std::mem::replace(&mut self.z, z)
+    }
+
+    /// Returns the model after performing an action, without changing the agent.
+    pub fn simulate(&self, action: A) -> M where M: Clone {self.z.simulate(action)}
+
+    /// Sets the mutation limit of all safety layers.
+    pub fn set_mutation_limit(&mut self, limit: u8) {
+        for layer in &mut self.layers {layer.mutation_limit = limit}
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D>
     where A: PartialEq
 {
-    type Model = M;
-    type Action = A;
-    type Delta = D;
-    fn update_model(&mut self, model: M) {self.core.z().update_model(model)}
-    fn decide(&mut self) -> Decision<A> {
+    fn observe(&self, event: Event<A>) {
+        for f in &self.observers {f(&event)}
+    }
+
+    fn observe_layer(&self, config: &LayerConfig<A>, event: Event<A>) {
+        if let Some(f) = config.observer {f(&event)}
+        self.observe(event);
+    }
+
+    /// Reseeds the stochastic mutater before deciding, when it follows a schedule.
+    pub(crate) fn schedule(&mut self) {
+        if let Some(s) = &mut self.stochastic {
+            if let Some(schedule) = &s.schedule {s.rng = schedule.rng(&self.z.model)}
+        }
+    }
+
+    fn mutate_probe(&mut self, probe: u8) -> D {
+        if let Some(s) = &mut self.stochastic {return (s.mutater)(&mut self.z.model, &mut s.rng)}
+        match self.mutaters.len() {
+            0 => self.z.mutate(),
+            _ => (self.mutaters[self.mutater_of(probe)])(&mut self.z.model),
+        }
+    }
+
+    /// Prepares a decide call, returning a diagnosis when handing off.
+    ///
+    /// Every decide path calls this before probing.
+    pub(crate) fn decide_start(&mut self) -> Option<Diagnosis<A>> {
+        // A new core might use a model that does not reflect the environment.
+        self.report = SafetyReport::default();
+        #[cfg(feature = "replay")]
+        self.take_deltas();
+        if self.handoff {
+            return Some(Diagnosis {decision: Decision::RequestModel, reason: Reason::Handoff});
+        }
+        self.schedule();
+        self.cover();
+        self.warm_up();
+        self.provenance_start();
+        self.clock_start();
+        None
+    }
+
+    /// Decide what to do next, together with the reason.
+    pub fn diagnose(&mut self) -> Diagnosis<A> {
+        if let Some(diagnosis) = self.decide_start() {return diagnosis}
+        let mut report = SafetyReport::default();
+        let (decision, reason) = self.decide_n(self.layers(), self.layers(), &mut report);
+        self.report = report;
+        self.observe(Event::Decide {layers: self.layers(), decision: &decision});
+        Diagnosis {decision, reason}
+    }
+
+    /// Decides using the `n` innermost safety layers,
+    /// where `outer` is the outermost safety layer of the decide call.
+    pub(crate) fn decide_n(&mut self, n: usize, outer: usize, tally: &mut SafetyReport) -> (Decision<A>, Reason) {
+        match n {
+            0 => {
+                let start = self.clock();
+                let decision = self.z.decide();
+                latency::lap(start, &mut tally.core_time);
+                (decision, Reason::Core)
+            }
+            _ => {
+                self.rationale_enter(n);
+                let config = self.layers[n-1];
+                let (decision, reason) = self.decide_s(config, n-1, outer, tally);
+                self.rationale_exit(reason);
+                if let Some(f) = config.observer {f(&Event::Decide {layers: n, decision: &decision})}
+                (decision, reason)
+            }
+        }
+    }
+
+    /// Decides as a successor agent of the `n` innermost safety layers,
+    /// where `outer` is the outermost safety layer of the decide call.
+    pub(crate) fn decide_s(
+        &mut self,
+        config: LayerConfig<A>,
+        n: usize,
+        outer: usize,
+        tally: &mut SafetyReport
+    ) -> (Decision<A>, Reason) {
+        let mark = self.scratch.mark();
+        let result = self.probe_layer(config, n, outer, tally, mark);
+        self.scratch.reset(mark);
+        result
+    }
+
+    fn probe_layer(
+        &mut self,
+        config: LayerConfig<A>,
+        n: usize,
+        outer: usize,
+        tally: &mut SafetyReport,
+        mark: arena::Mark
+    ) -> (Decision<A>, Reason) {
+        let layer = n + 1;
+        let base = self.warm_fingerprint();
         // Each case of this algorithm has a corresponding informal proof of safer level
         // described in comments. Given that these proofs are correct,
         // it follows that this algorithm constructs a safer level.
         //
         // Use the core zero to keep linear complexity.
-        match self.core.z().decide() {
+        let start = self.clock();
+        let decision = self.z.decide();
+        latency::lap(start, &mut tally.core_time);
+        match decision {
             // If core zero requests model update,
             // then it is just as safe to request a model update.
-            Decision::RequestModel => Decision::RequestModel,
+            Decision::RequestModel => (Decision::RequestModel, Reason::CoreRequest),
+            // If core zero halts, then it is just as safe to halt.
+            Decision::Halt => (Decision::Halt, Reason::Core),
             Decision::Action(a) => {
+                let reads = self.incremental.as_ref().map(|inc| (inc.reads)(&self.z.model));
                 // Mutate model and compare decisions.
                 //
                 // When a mutated decision is found,
@@ -266,44 +1107,344 @@ impl<M, A, D> Agent for AgentS<M, A, D>
                 // This is sufficient to prove better safety in this case.
                 //
                 // Give up after reaching mutation limit.
-                for _ in 0..MUTATION_LIMIT {
-                    let delta = self.core.mutate();
-                    let b = self.core.decide();
-                    self.core.undo(delta);
-                    match b {
-                        Decision::RequestModel => continue,
-                        Decision::Action(b) => {
-                            // If both sub-agents agree,
-                            // then it is more safe than just relying on core zero.
-                            if a == b {return Decision::Action(a)}
-                            // If sub-agents disagree,
-                            // then it is more safe to request a model update.
-                            else {return Decision::RequestModel}
+                let mut agreed = false;
+                // Counts of probe outcomes in this layer.
+                let mut counts = [0; 3];
+                // Fast models get deeper checking within a time budget.
+                let deadline = config.time_budget.map(|budget| Instant::now() + budget);
+                let limit = if deadline.is_some() {u8::MAX} else {config.mutation_limit};
+                for probe in 0..limit {
+                    if let Some(deadline) = deadline {
+                        if probe > 0 && Instant::now() >= deadline {break}
+                    }
+                    // Trimming the probes makes the decision undetermined, unless all agreed so far.
+                    if self.over_budget(tally.probes) {
+                        tally.trimmed += 1;
+                        break;
+                    }
+                    let start = self.clock();
+                    let before = self.provenance_mark();
+                    let delta = self.mutate_probe(probe);
+                    self.provenance_apply(layer, probe, before);
+                    latency::lap(start, &mut tally.probe_time);
+                    let key = base.zip(self.warm_fingerprint()).map(|(base, mutated)| (n, base, mutated));
+                    #[cfg(feature = "replay")]
+                    self.journal(layer, probe, replay::DeltaOp::Apply, &delta);
+                    self.visit();
+                    // A duplicate mutation has the same outcome as when it was first probed.
+                    if let Some(dedup) = self.dedup {
+                        // Fingerprints of probed deltas are kept in the scratch space.
+                        let fingerprint = dedup(&delta);
+                        let duplicate = self.scratch.fingerprints.since(mark.fingerprints).contains(&fingerprint);
+                        self.scratch.fingerprints.push(fingerprint);
+                        if duplicate {
+                            #[cfg(feature = "replay")]
+                            self.journal(layer, probe, replay::DeltaOp::Undo, &delta);
+                            let start = self.clock();
+                            self.z.undo(delta);
+                            self.provenance_undo(tally);
+                            latency::lap(start, &mut tally.probe_time);
+                            continue;
+                        }
+                    }
+                    // When core zero would decide on a model that the mutation did not touch,
+                    // it decides the same action, so there is no need to decide again.
+                    let skip = match (n, reads, &self.incremental) {
+                        (0, Some(reads), Some(inc)) => (inc.touches)(&delta) & reads == 0,
+                        _ => false,
+                    };
+                    // An unchanged probe of a previous decide call has the same outcome.
+                    let reused = if skip {None} else {key.and_then(|key| self.warm_outcome(key))};
+                    let b = if skip || reused.is_some() {None} else {Some(self.decide_n(n, outer, tally).0)};
+                    #[cfg(feature = "replay")]
+                    self.journal(layer, probe, replay::DeltaOp::Undo, &delta);
+                    let start = self.clock();
+                    self.z.undo(delta);
+                    self.provenance_undo(tally);
+                    latency::lap(start, &mut tally.probe_time);
+                    let outcome = match &b {
+                        None => reused.unwrap_or(ProbeOutcome::Agree),
+                        Some(Decision::RequestModel) | Some(Decision::Halt) => ProbeOutcome::RequestModel,
+                        Some(Decision::Action(b)) => {
+                            let start = self.clock();
+                            let agree = config.agree(&a, b);
+                            latency::lap(start, &mut tally.compare_time);
+                            if agree {ProbeOutcome::Agree}
+                            else {ProbeOutcome::Disagree}
+                        }
+                    };
+                    if reused.is_none() {self.warm_record(key, self.mutater_of(probe), outcome)}
+                    self.observe_layer(&config, Event::Probe {layer, probe, outcome});
+                    self.rationale_check(probe, None, outcome, n > 0 && b.is_some());
+                    tally.probes += 1;
+                    match outcome {
+                        ProbeOutcome::Agree => tally.approvals += 1,
+                        ProbeOutcome::Disagree => tally.disagreements += 1,
+                        ProbeOutcome::RequestModel => tally.requests += 1,
+                    }
+                    counts[outcome as usize] += 1;
+                    if let Some(policy) = config.policy {
+                        // The probe outcomes of this layer are kept in the scratch space.
+                        self.scratch.votes.push(Vote {mutater: self.mutater_of(probe), outcome});
+                        match policy.verdict(Votes::new(self.scratch.votes.since(mark.votes)), false) {
+                            Verdict::Pending => continue,
+                            Verdict::Act => return self.act_policy(config, a, n, outer, tally, &counts),
+                            Verdict::Ask => return (Decision::RequestModel, Reason::Policy {layer}),
                         }
                     }
+                    match outcome {
+                        ProbeOutcome::RequestModel => continue,
+                        // If both sub-agents agree,
+                        // then it is more safe than just relying on core zero.
+                        ProbeOutcome::Agree => match config.agreement {
+                            // If mutations are too divided,
+                            // then it is more safe to request a model update.
+                            Agreement::First if config.divided(&counts) =>
+                                return (Decision::RequestModel, Reason::Divided {layer}),
+                            Agreement::First =>
+                                return self.act_second_order(config, a, n, outer, tally, Reason::Agree {layer, probe}),
+                            Agreement::All => agreed = true,
+                        },
+                        // If sub-agents disagree,
+                        // then it is more safe to request a model update.
+                        //
+                        // Acting when new information is not worth waiting for
+                        // trades safety for effectiveness, as decided by the user.
+                        ProbeOutcome::Disagree => match (self.voi, &b) {
+                            (Some(voi), Some(Decision::Action(b))) if !voi(&self.z.model, &a, b) =>
+                                return (Decision::Action(a), Reason::Waived {layer, probe}),
+                            _ => return (Decision::RequestModel, Reason::Disagree {layer, probe}),
+                        },
+                    }
+                }
+
+                // If the agreement policy is satisfied by all probes,
+                // then it is as safe as the policy makes it.
+                // A pending policy determines no decision.
+                if let Some(policy) = config.policy {
+                    match policy.verdict(Votes::new(self.scratch.votes.since(mark.votes)), true) {
+                        Verdict::Act => return self.act_policy(config, a, n, outer, tally, &counts),
+                        Verdict::Ask => return (Decision::RequestModel, Reason::Policy {layer}),
+                        Verdict::Pending => {}
+                    }
+                }
+
+                // If all mutations that determine a decision agree,
+                // then it is at least as safe as acting on the first agreement.
+                if agreed && config.divided(&counts) {
+                    return (Decision::RequestModel, Reason::Divided {layer});
                 }
+                if agreed {return self.act_second_order(config, a, n, outer, tally, Reason::AllAgree {layer})}
 
                 // If no mutation can be found that determines a decision,
                 // then it is more safe to request a model update.
                 //
                 // If action was returned, then it would lead to regression in higher safety levels.
-                Decision::RequestModel
+                // In the outermost layer, the user might trade safety for not stalling.
+                let reason = Reason::Undetermined {layer};
+                match self.exhausted {
+                    _ if layer < outer => (Decision::RequestModel, reason),
+                    Exhausted::Request => (Decision::RequestModel, reason),
+                    Exhausted::ActWithCaution => self.act_second_order(config, a, n, outer, tally, reason),
+                    Exhausted::Fallback(Fallback::SafeDefault(policy)) =>
+                        (Decision::Action(policy(&self.z.model)), reason),
+                    Exhausted::Fallback(Fallback::Halt) => (Decision::Halt, reason),
+                }
+            }
+        }
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D>
+    where A: PartialEq
+{
+    /// Acts on the decision of core zero when the agreement policy is satisfied,
+    /// unless the probe outcomes are too divided.
+    fn act_policy(
+        &mut self,
+        config: LayerConfig<A>,
+        a: A,
+        n: usize,
+        outer: usize,
+        tally: &mut SafetyReport,
+        counts: &[u32; 3]
+    ) -> (Decision<A>, Reason) {
+        let layer = n + 1;
+        if config.divided(counts) {return (Decision::RequestModel, Reason::Divided {layer})}
+        self.act_second_order(config, a, n, outer, tally, Reason::Policy {layer})
+    }
+
+    /// Acts on the decision of core zero, unless a pair of mutations disagrees.
+    fn act_second_order(
+        &mut self,
+        config: LayerConfig<A>,
+        a: A,
+        n: usize,
+        outer: usize,
+        tally: &mut SafetyReport,
+        reason: Reason
+    ) -> (Decision<A>, Reason) {
+        if !config.second_order {return (Decision::Action(a), reason)}
+        let layer = n + 1;
+        for i in 0..config.mutation_limit {
+            for j in i + 1..config.mutation_limit {
+                let before = self.provenance_mark();
+                let first = self.mutate_probe(i);
+                self.provenance_apply(layer, i, before);
+                #[cfg(feature = "replay")]
+                self.journal(layer, i, replay::DeltaOp::Apply, &first);
+                let before = self.provenance_mark();
+                let second = self.mutate_probe(j);
+                self.provenance_apply(layer, j, before);
+                #[cfg(feature = "replay")]
+                self.journal(layer, j, replay::DeltaOp::Apply, &second);
+                let b = self.decide_n(n, outer, tally).0;
+                #[cfg(feature = "replay")]
+                self.journal(layer, j, replay::DeltaOp::Undo, &second);
+                self.z.undo(second);
+                self.provenance_undo(tally);
+                #[cfg(feature = "replay")]
+                self.journal(layer, i, replay::DeltaOp::Undo, &first);
+                self.z.undo(first);
+                self.provenance_undo(tally);
+                let outcome = match b {
+                    Decision::Action(b) if config.agree(&a, &b) => ProbeOutcome::Agree,
+                    Decision::Action(_) => ProbeOutcome::Disagree,
+                    Decision::RequestModel | Decision::Halt => ProbeOutcome::RequestModel,
+                };
+                self.rationale_check(i, Some(j), outcome, n > 0);
+                tally.probes += 1;
+                match outcome {
+                    ProbeOutcome::Agree => tally.approvals += 1,
+                    // If a composition of mutations disagrees,
+                    // then it is more safe to request a model update.
+                    ProbeOutcome::Disagree => {
+                        tally.disagreements += 1;
+                        return (Decision::RequestModel, Reason::DisagreePair {layer, probes: (i, j)});
+                    }
+                    ProbeOutcome::RequestModel => tally.requests += 1,
+                }
             }
         }
+        (Decision::Action(a), reason)
+    }
+}
+
+impl<M, A, D> Agent for AgentN<M, A, D>
+    where A: PartialEq
+{
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {
+        self.handoff = false;
+        self.z.update_model(model)
+    }
+    fn decide(&mut self) -> Decision<A> {self.diagnose().decision}
+    fn act(&mut self, action: A) {self.z.act(action)}
+    fn mutate(&mut self) -> D {self.z.mutate()}
+    fn undo(&mut self, delta: D) {self.z.undo(delta)}
+}
+
+impl<M, A, D> Inspect for AgentN<M, A, D>
+    where A: PartialEq
+{
+    fn model(&self) -> &M {&self.z.model}
+}
+
+/// Stores a successor agent.
+pub struct AgentS<M, A, D> {
+    /// The core sub-agent.
+    pub core: AgentN<M, A, D>,
+    /// The configuration of the safety layer.
+    pub config: LayerConfig<A>,
+}
+
+impl<M: Clone, A, D> Clone for AgentS<M, A, D> {
+    fn clone(&self) -> Self {AgentS {core: self.core.clone(), config: self.config}}
+}
+
+impl<M: fmt::Debug, A, D> fmt::Debug for AgentS<M, A, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AgentS")
+            .field("core", &self.core)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl<M: PartialEq, A, D> PartialEq for AgentS<M, A, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.core == other.core && self.config == other.config
+    }
+}
+
+/// The default limit of orthogonal mutations.
+pub const MUTATION_LIMIT: u8 = 4;
+
+impl<M, A, D> AgentS<M, A, D>
+    where A: PartialEq
+{
+    /// Decide what to do next, together with the reason.
+    pub fn diagnose(&mut self) -> Diagnosis<A> {
+        if let Some(diagnosis) = self.core.decide_start() {return diagnosis}
+        let n = self.core.layers();
+        let mut report = SafetyReport::default();
+        let (decision, reason) = self.core.decide_s(self.config, n, n + 1, &mut report);
+        self.core.report = report;
+        self.core.observe(Event::Decide {layers: n + 1, decision: &decision});
+        Diagnosis {decision, reason}
     }
-    fn act(&mut self, action: A) {self.core.z().act(action)}
+}
+
+impl<M, A, D> Agent for AgentS<M, A, D>
+    where A: PartialEq
+{
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.core.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {self.diagnose().decision}
+    fn act(&mut self, action: A) {self.core.z.act(action)}
     fn mutate(&mut self) -> D {self.core.mutate()}
-    fn undo(&mut self, delta: D) {self.core.z().undo(delta)}
+    fn undo(&mut self, delta: D) {self.core.z.undo(delta)}
+}
+
+impl<M, A, D> Inspect for AgentS<M, A, D>
+    where A: PartialEq
+{
+    fn model(&self) -> &M {&self.core.z.model}
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
 
-    #[test]
-    fn it_works() {
-        // A simple problem of reaching `4` by increments.
-        let mut z = AgentZ {
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const {Cell::new(0)};
+    }
+
+    /// Counts heap allocations per thread.
+    struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|n| n.set(n.get() + 1));
+            unsafe {System.alloc(layout)}
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe {System.dealloc(ptr, layout)}
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+
+    /// A simple problem of reaching `4` by increments.
+    pub fn four() -> AgentZ<(u32, u32), i32, i32> {
+        AgentZ {
             model: (4, 0),
             decider: |model: &(u32, u32)| {
                 if model.1 < model.0 {1}
@@ -322,8 +1463,12 @@ mod tests {
             undoer: |model: &mut (u32, u32), delta: i32| {
                 model.0 = (model.0 as i32 - delta) as u32;
             }
-        };
+        }
+    }
 
+    #[test]
+    fn it_works() {
+        let mut z = four();
         assert_eq!(z.decide(), Decision::Action(1));
         if let Decision::Action(a) = z.decide() {
             z.act(a);
@@ -376,4 +1521,234 @@ mod tests {
         // Reached goal.
         assert_eq!(s.decide(), Decision::Action(0));
     }
+
+    #[test]
+    fn successor_agent() {
+        // A successor of one safety layer behaves like two safety layers.
+        let mut s = AgentS {core: four().add(1), config: LayerConfig::default()};
+        let mut n = four().add(2);
+        assert_eq!(n.layers(), 2);
+        for _ in 0..3 {
+            let a = s.decide();
+            assert_eq!(a, n.decide());
+            if let Decision::Action(a) = a {
+                s.act(a);
+                n.act(a);
+            }
+        }
+        assert_eq!(s.core.z.model, n.z.model);
+    }
+
+    #[test]
+    fn decide_does_not_allocate() {
+        use agreement::{And, Quorum, Scoped, Unanimous};
+
+        static POLICY: And<Scoped<Unanimous>, Scoped<Quorum>> = And(
+            Scoped {mutaters: &[0], policy: Unanimous},
+            Scoped {mutaters: &[0, 1], policy: Quorum(0.5)},
+        );
+        let mut s = four().add(3);
+        let mut p = four().add(3);
+        p.layers[2].policy = Some(&POLICY);
+        p.reserve_scratch();
+        let before = ALLOCATIONS.with(|n| n.get());
+        let mut acts = 0;
+        for _ in 0..10 {
+            if let Decision::Action(a) = s.decide() {s.act(a)}
+            if let Decision::Action(a) = p.decide() {
+                p.act(a);
+                acts += 1;
+            }
+        }
+        assert_eq!(ALLOCATIONS.with(|n| n.get()), before);
+        assert!(acts > 0);
+    }
+
+    #[test]
+    fn coverage_does_not_allocate() {
+        let mut s = four().add(1);
+        s.mutaters = vec![|m| {m.0 -= 1; -1}, |m| {m.0 -= 2; -2}];
+        s.coverage = Some(coverage::Coverage::new(|m: &(u32, u32)| m.0 as u64));
+        s.decide();
+        // Once the regions were visited, ordering the mutaters reuses its buffers.
+        let before = ALLOCATIONS.with(|n| n.get());
+        for _ in 0..10 {s.decide();}
+        assert_eq!(ALLOCATIONS.with(|n| n.get()), before);
+    }
+
+    #[test]
+    fn incremental() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DECISIONS: AtomicUsize = AtomicUsize::new(0);
+
+        // The model is `(goal, state, weather)`, where the mutation changes the weather.
+        let z = AgentZ {
+            model: (4, 0, 0),
+            decider: |model: &(u32, u32, u32)| {
+                DECISIONS.fetch_add(1, Ordering::SeqCst);
+                if model.1 < model.0 {1} else {0}
+            },
+            actor: |model: &mut (u32, u32, u32), action: u32| model.1 += action,
+            mutater: |model: &mut (u32, u32, u32)| {
+                model.2 += 1;
+                0b100
+            },
+            undoer: |model: &mut (u32, u32, u32), _| model.2 -= 1,
+        };
+        let mut s = z.add(1);
+        s.incremental = Some(Incremental {reads: |_| 0b011, touches: |delta| *delta});
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(DECISIONS.load(Ordering::SeqCst), 1);
+        assert_eq!(s.z.model, (4, 0, 0));
+    }
+
+    #[test]
+    fn layer_observer() {
+        thread_local! {
+            static EVENTS: Cell<(usize, usize)> = const {Cell::new((0, 0))};
+        }
+
+        let mut s = four().add(2);
+        s.z.model = (4, 2);
+        s.layers[0].observer = Some(|event| EVENTS.with(|n| {
+            let (probes, decides) = n.get();
+            match event {
+                Event::Probe {layer: 1, ..} => n.set((probes + 1, decides)),
+                Event::Decide {layers: 1, ..} => n.set((probes, decides + 1)),
+                _ => panic!("unexpected event: {}", event),
+            }
+        }));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(EVENTS.with(|n| n.get()), (4, 4));
+    }
+
+    #[test]
+    fn replace_core() {
+        let mut s = four().add(1);
+        let mut z = four();
+        z.decider = |_| 0;
+        let old = s.replace_core(z);
+        assert_eq!(old.model, (4, 0));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.update_model((4, 0));
+        assert_eq!(s.decide(), Decision::Action(0));
+    }
+
+    #[test]
+    fn agreement() {
+        let mut s = four().add(1);
+        s.update_model((4, 3));
+        // The first mutater does nothing, the second mutates the goal.
+        s.mutaters = vec![|_| 0, four().mutater];
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.layers[0].agreement = Agreement::All;
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+
+    #[test]
+    fn value_of_information() {
+        let mut s = four().add(1);
+        s.update_model((4, 3));
+        // Asking is only worth it when the actions are far apart.
+        s.voi = Some(|_, a, b| (a - b).abs() > 1);
+        let diagnosis = s.diagnose();
+        assert_eq!(diagnosis.decision, Decision::Action(1));
+        assert_eq!(diagnosis.reason, Reason::Waived {layer: 1, probe: 0});
+        s.voi = Some(|_, _, _| true);
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+
+    #[test]
+    fn dedup() {
+        // At goal zero, the mutater saturates and produces the same delta.
+        let mut s = four().add(1);
+        s.update_model((0, 0));
+        s.layers[0].agreement = Agreement::All;
+        let probes = |s: &mut AgentN<(u32, u32), i32, i32>| match s.decide_certified() {
+            Decision::Action(c) => c.probes(),
+            _ => 0,
+        };
+        assert_eq!(probes(&mut s), 4);
+        s.dedup = Some(|d| *d as u64);
+        assert_eq!(probes(&mut s), 1);
+    }
+
+    #[test]
+    fn time_budget() {
+        let mut s = four().add(1);
+        s.layers[0].agreement = Agreement::All;
+        s.layers[0].time_budget = Some(Duration::from_secs(1));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.report.probes, u8::MAX as u32);
+        // A slow core only fits a few probes.
+        s.z.decider = |m| {
+            std::thread::sleep(Duration::from_millis(10));
+            if m.1 < m.0 {1} else {0}
+        };
+        s.layers[0].time_budget = Some(Duration::from_millis(25));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert!((1..10).contains(&s.report.probes));
+    }
+
+    #[test]
+    fn safety_index() {
+        let mut s = four().add(2);
+        s.update_model((4, 2));
+        // The first mutater mutates the goal, the second does nothing.
+        s.mutaters = vec![four().mutater, |_| 0];
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.report, SafetyReport {
+            probes: 4, approvals: 2, disagreements: 1, requests: 1, ..SafetyReport::default()
+        });
+        assert_eq!(s.report.entropy(), 1.5);
+        // The outer layer had one request and one agreement.
+        s.layers[1].max_entropy = Some(0.5);
+        assert_eq!(s.diagnose().reason, Reason::Divided {layer: 2});
+    }
+
+    #[test]
+    fn second_order() {
+        let mut s = four().add(1);
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::Action(1));
+        // Decrementing the goal twice disagrees.
+        s.layers[0].second_order = true;
+        assert_eq!(s.diagnose().reason, Reason::DisagreePair {layer: 1, probes: (0, 1)});
+        assert_eq!(s.z.model, (4, 2));
+    }
+
+    #[test]
+    fn clone_debug_eq() {
+        let s = four().add(2);
+        let mut t = s.clone();
+        assert_eq!(s, t);
+        t.layers[1].mutation_limit = 1;
+        assert_ne!(s, t);
+        let s = AgentS {core: s, config: LayerConfig::default()};
+        assert_eq!(s.clone(), s);
+        assert!(format!("{:?}", s).starts_with("AgentS { core: AgentN { z: AgentZ { model: (4, 0)"));
+    }
+
+    #[test]
+    fn display() {
+        let mut s = four().add(1);
+        assert_eq!(format!("{:+}", s.decide()), "Action(+1)");
+        assert_eq!(format!("{:+}", s.diagnose()), "Action(+1, reason: mutation #0 of layer 1 agreed)");
+        s.update_model((4, 3));
+        assert_eq!(format!("{}", s.diagnose()),
+                   "RequestModel(reason: mutation #0 of layer 1 disagreed)");
+        let decision: Decision<i32> = Decision::RequestModel;
+        assert_eq!(format!("{}", Event::Decide {layers: 1, decision: &decision}),
+                   "decided at safety level 1: RequestModel");
+    }
+
+    #[test]
+    fn simulate() {
+        let s = four().add(1);
+        assert_eq!(s.simulate(1), (4, 1));
+        assert_eq!(s.z.simulate(0), (4, 0));
+        assert_eq!(s.z.model, (4, 0));
+    }
 }
@@ -0,0 +1,411 @@
+//! Deciders backed by large language models.
+//!
+//! An `LlmDecider` asks a chat completions endpoint for an action.
+//! The model is turned into a prompt, and the reply is validated by parsing it into an action.
+//! Invalid replies and failed requests are retried, and every request has a timeout.
+//!
+//! `LlmDecider` implements `AsyncDecider`, running requests on their own thread.
+//! To put the decider inside safety layers, store it together with the model in `Prompted`,
+//! and use `decide` as the decider of core zero.
+//! A failed decision is `None`, and failed probes agree with a failed core zero,
+//! so wrap the layered agent in `utility::Tied`, which requests a model update instead.
+//!
+//! Requests are sent as plain HTTP, e.g. to a local gateway,
+//! and are written and parsed without dependencies.
+//! The key of an endpoint is only sent to a loopback host, unless `Endpoint::insecure` is set.
+//!
+//! Requires the `llm` feature.
+
+use std::future::Future;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::handle::{promise, AsyncDecider, Reply};
+use crate::{json, Error};
+
+/// Stores a chat message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatMessage {
+    /// The role, e.g. `system`, `user` or `assistant`.
+    pub role: String,
+    /// The content.
+    pub content: String,
+}
+
+/// Stores a chat completions request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatRequest {
+    /// The name of the language model.
+    pub model: String,
+    /// The messages.
+    pub messages: Vec<ChatMessage>,
+    /// The sampling temperature.
+    pub temperature: f64,
+}
+
+/// Stores a chat completions response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChatResponse {
+    /// The message of each choice.
+    pub choices: Vec<ChatMessage>,
+}
+
+impl ChatRequest {
+    /// Returns the request as JSON.
+    ///
+    /// The temperature must be finite, see `ChatRequest::check`.
+    pub fn to_json(&self) -> String {
+        let messages: Vec<String> = self.messages.iter().map(|m| format!(
+            "{{\"role\":{},\"content\":{}}}", json::string(&m.role), json::string(&m.content)
+        )).collect();
+        format!("{{\"model\":{},\"messages\":[{}],\"temperature\":{}}}",
+                json::string(&self.model), messages.join(","), self.temperature)
+    }
+
+    /// Returns an error when the request can not be written as JSON.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.temperature.is_finite() {Ok(())}
+        else {Err(Error::Decider(format!("Invalid temperature {}", self.temperature)))}
+    }
+}
+
+impl ChatResponse {
+    /// Parses a response from JSON.
+    pub fn from_json(src: &str) -> Result<ChatResponse, Error> {
+        let err = |msg: &str| Error::Protocol(format!("Invalid chat response: {}", msg));
+        let value = json::parse_limited(src, crate::wire::MAX_DEPTH).ok_or_else(|| err("Expected JSON"))?;
+        let choices = value.get("choices").and_then(|c| c.array()).ok_or_else(|| err("Expected choices"))?;
+        let choices = choices.iter().map(|choice| {
+            let message = choice.get("message").ok_or_else(|| err("Expected message"))?;
+            let field = |key: &str| message.get(key).and_then(|v| v.str())
+                .map(String::from).ok_or_else(|| err(&format!("Expected {}", key)));
+            Ok(ChatMessage {role: field("role")?, content: field("content")?})
+        }).collect::<Result<_, Error>>()?;
+        Ok(ChatResponse {choices})
+    }
+}
+
+/// Stores the address of a chat completions endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoint {
+    /// The host name.
+    pub host: String,
+    /// The port.
+    pub port: u16,
+    /// The path, e.g. `/v1/chat/completions`.
+    pub path: String,
+    /// The key sent as a bearer token, if any.
+    pub api_key: Option<String>,
+    /// Allows sending the key over plain HTTP to a host that is not loopback.
+    pub insecure: bool,
+}
+
+/// Stores a decider that asks a language model.
+#[derive(Debug)]
+pub struct LlmDecider<M, A> {
+    /// The endpoint.
+    pub endpoint: Endpoint,
+    /// The name of the language model.
+    pub model: String,
+    /// The system message describing the task and the format of actions.
+    pub system: String,
+    /// Returns the prompt for a model.
+    pub prompt: fn(&M) -> String,
+    /// Parses an action from a reply, returning `None` if the reply is invalid.
+    pub parse: fn(&str) -> Option<A>,
+    /// The sampling temperature.
+    pub temperature: f64,
+    /// The number of retries after failed requests or invalid replies.
+    pub retries: usize,
+    /// The timeout of each request, covering connect, write and read.
+    pub timeout: Duration,
+}
+
+impl<M, A> Clone for LlmDecider<M, A> {
+    fn clone(&self) -> Self {
+        LlmDecider {
+            endpoint: self.endpoint.clone(),
+            model: self.model.clone(),
+            system: self.system.clone(),
+            prompt: self.prompt,
+            parse: self.parse,
+            temperature: self.temperature,
+            retries: self.retries,
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<M, A> LlmDecider<M, A> {
+    /// Creates a new decider with 2 retries and a timeout of 30 seconds.
+    pub fn new(
+        endpoint: Endpoint,
+        model: impl Into<String>,
+        system: impl Into<String>,
+        prompt: fn(&M) -> String,
+        parse: fn(&str) -> Option<A>,
+    ) -> Self {
+        LlmDecider {
+            endpoint,
+            model: model.into(),
+            system: system.into(),
+            prompt,
+            parse,
+            temperature: 0.0,
+            retries: 2,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Returns the request for a model.
+    pub fn request(&self, model: &M) -> ChatRequest {
+        ChatRequest {
+            model: self.model.clone(),
+            messages: vec![
+                ChatMessage {role: "system".into(), content: self.system.clone()},
+                ChatMessage {role: "user".into(), content: (self.prompt)(model)},
+            ],
+            temperature: self.temperature,
+        }
+    }
+
+    /// Returns the action of a response, when it is valid.
+    pub fn validate(&self, response: &ChatResponse) -> Result<A, Error> {
+        let reply = &response.choices.first()
+            .ok_or_else(|| Error::Decider("No choices in reply".into()))?.content;
+        (self.parse)(reply.trim()).ok_or_else(|| Error::Decider(format!("Invalid reply `{}`", reply)))
+    }
+
+    /// Sends a request, retrying on failure, blocking until an action is decided.
+    ///
+    /// Returns the last error when all retries fail,
+    /// or an error without sending when the request is invalid.
+    pub fn send(&self, request: &ChatRequest) -> Result<A, Error> {
+        request.check()?;
+        let body = request.to_json();
+        let mut result = Err(Error::Decider("No attempts".into()));
+        for _ in 0..=self.retries {
+            result = post(&self.endpoint, &body, self.timeout)
+                .and_then(|text| ChatResponse::from_json(&text))
+                .and_then(|response| self.validate(&response));
+            if result.is_ok() {break}
+        }
+        result
+    }
+
+    /// Decides an action for a model, blocking until it is decided.
+    pub fn decide_blocking(&self, model: &M) -> Result<A, Error> {self.send(&self.request(model))}
+}
+
+/// Stores the future of a decision by a language model.
+pub struct LlmFuture<A>(Reply<Result<A, Error>>);
+
+impl<A> Future for LlmFuture<A> {
+    type Output = Result<A, Error>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|reply| {
+            reply.unwrap_or_else(|| Err(Error::Decider("Request thread stopped".into())))
+        })
+    }
+}
+
+impl<M: 'static, A: Send + 'static> AsyncDecider<M> for LlmDecider<M, A> {
+    type Action = A;
+    type Future = LlmFuture<A>;
+    fn decide(&self, model: &M) -> LlmFuture<A> {
+        let request = self.request(model);
+        let decider = self.clone();
+        let (promise, reply) = promise();
+        thread::spawn(move || promise.fulfil(decider.send(&request)));
+        LlmFuture(reply)
+    }
+}
+
+/// Stores a model together with the decider that decides on it.
+#[derive(Clone, Debug)]
+pub struct Prompted<M, A> {
+    /// The model.
+    pub model: M,
+    /// The decider.
+    pub decider: LlmDecider<M, A>,
+}
+
+/// Decides by asking the language model, returning `None` if it fails.
+///
+/// Use as the decider of core zero, where the model is `Prompted`,
+/// and wrap the layered agent in `utility::Tied`.
+pub fn decide<M, A>(prompted: &Prompted<M, A>) -> Option<A> {
+    prompted.decider.decide_blocking(&prompted.model).ok()
+}
+
+fn post(endpoint: &Endpoint, body: &str, timeout: Duration) -> Result<String, Error> {
+    let io = |err: std::io::Error| match err.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => Error::Timeout(timeout),
+        _ => Error::Protocol(err.to_string()),
+    };
+    let deadline = Instant::now() + timeout;
+    // The time left of the request, such that a slow server can not extend it by trickling bytes.
+    let left = || Some(deadline.saturating_duration_since(Instant::now()))
+        .filter(|left| !left.is_zero()).ok_or(Error::Timeout(timeout));
+    let addr = (endpoint.host.as_str(), endpoint.port).to_socket_addrs().map_err(io)?
+        .next().ok_or_else(|| Error::Protocol(format!("Unknown host `{}`", endpoint.host)))?;
+    if endpoint.api_key.is_some() && !endpoint.insecure && !addr.ip().is_loopback() {
+        return Err(Error::Protocol(format!("Refusing to send key over plain HTTP to `{}`", endpoint.host)));
+    }
+    let mut stream = TcpStream::connect_timeout(&addr, left()?).map_err(io)?;
+    let auth = endpoint.api_key.as_ref()
+        .map(|key| format!("Authorization: Bearer {}\r\n", key)).unwrap_or_default();
+    // HTTP/1.0 avoids chunked responses, so the body is the rest of the stream.
+    let request = format!("POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\n\
+                           Content-Length: {}\r\n{}\r\n{}",
+                          endpoint.path, endpoint.host, body.len(), auth, body);
+    let mut written = 0;
+    while written < request.len() {
+        stream.set_write_timeout(Some(left()?)).map_err(io)?;
+        match stream.write(&request.as_bytes()[written..]).map_err(io)? {
+            0 => return Err(Error::Protocol("Connection closed".into())),
+            n => written += n,
+        }
+    }
+    let mut response = vec![];
+    let mut buf = [0; 4096];
+    loop {
+        stream.set_read_timeout(Some(left()?)).map_err(io)?;
+        match stream.read(&mut buf).map_err(io)? {
+            0 => break,
+            n => response.extend_from_slice(&buf[..n]),
+        }
+    }
+    let response = String::from_utf8(response).map_err(|err| Error::Protocol(err.to_string()))?;
+    let (head, body) = response.split_once("\r\n\r\n")
+        .ok_or_else(|| Error::Protocol("Expected HTTP headers".into()))?;
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if status != "200" {return Err(Error::Protocol(format!("HTTP status {}", status)))}
+    Ok(body.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // Serves canned replies, one per connection.
+    fn serve(replies: Vec<&'static str>) -> Endpoint {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for reply in replies {
+                let (mut stream, _) = listener.accept().unwrap();
+                // Reads the whole request, such that closing does not reset the connection.
+                let mut request = vec![];
+                let mut buf = [0; 4096];
+                while let Ok(n) = stream.read(&mut buf) {
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if n == 0 || text.split_once("\r\n\r\n").map(|(_, body)| body.ends_with('}')).unwrap_or(false) {
+                        break;
+                    }
+                }
+                let body = format!("{{\"choices\":[{{\"message\":{{\"role\":\"assistant\",\"content\":{}}}}}]}}",
+                                   json::string(reply));
+                let _ = write!(stream, "HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+            }
+        });
+        Endpoint {host: "127.0.0.1".into(), port, path: "/v1/chat/completions".into(),
+                  api_key: None, insecure: false}
+    }
+
+    fn decider(endpoint: Endpoint) -> LlmDecider<(u32, u32), i32> {
+        LlmDecider::new(endpoint, "test", "Reply with -1, 0 or 1.",
+                        |m| format!("goal {}, state {}", m.0, m.1),
+                        |reply| reply.parse().ok().filter(|a: &i32| a.abs() <= 1))
+    }
+
+    #[test]
+    fn retry_invalid() {
+        let s = decider(serve(vec!["move right", "1"]));
+        assert_eq!(s.request(&(4, 0)).to_json(),
+                   "{\"model\":\"test\",\"messages\":[{\"role\":\"system\",\"content\":\"Reply with -1, 0 or 1.\"},\
+                    {\"role\":\"user\",\"content\":\"goal 4, state 0\"}],\"temperature\":0}");
+        assert_eq!(s.decide_blocking(&(4, 0)), Ok(1));
+
+        let mut s = decider(serve(vec!["2"]));
+        s.retries = 0;
+        let prompted = Prompted {model: (4, 0), decider: s};
+        assert_eq!(decide(&prompted), None);
+    }
+
+    #[test]
+    fn outage() {
+        use crate::utility::Tied;
+        use crate::{AgentZ, Decision, Diagnosis, Reason};
+
+        let mut s = decider(serve(vec!["2"]));
+        s.retries = 0;
+        let z = AgentZ {
+            model: Prompted {model: (4, 0), decider: s},
+            decider: decide,
+            actor: |p: &mut Prompted<(u32, u32), i32>, a: Option<i32>| if let Some(a) = a {
+                p.model.1 = (p.model.1 as i32 + a) as u32
+            },
+            mutater: |p| {p.model.0 -= 1; -1},
+            undoer: |p, d| p.model.0 = (p.model.0 as i32 - d) as u32,
+        };
+        let mut s = Tied::new(z.add(1));
+        assert_eq!(s.diagnose(), Diagnosis {decision: Decision::RequestModel, reason: Reason::Tie});
+    }
+
+    #[test]
+    fn temperature() {
+        let mut s = decider(serve(vec![]));
+        s.temperature = f64::NAN;
+        assert_eq!(s.decide_blocking(&(4, 0)), Err(Error::Decider("Invalid temperature NaN".into())));
+        s.temperature = f64::INFINITY;
+        assert!(s.request(&(4, 0)).check().is_err());
+    }
+
+    #[test]
+    fn deadline() {
+        // Trickles the headers, such that no single read times out.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in 0..100 {
+                if stream.write_all(b" ").is_err() {break}
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+        let endpoint = Endpoint {host: "127.0.0.1".into(), port, path: "/".into(), api_key: None, insecure: false};
+        let mut s = decider(endpoint);
+        s.retries = 0;
+        s.timeout = Duration::from_millis(100);
+        let start = Instant::now();
+        assert_eq!(s.decide_blocking(&(4, 0)), Err(Error::Timeout(s.timeout)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn key_loopback() {
+        let mut endpoint = serve(vec!["1"]);
+        endpoint.api_key = Some("secret".into());
+        assert_eq!(decider(endpoint.clone()).decide_blocking(&(4, 0)), Ok(1));
+
+        // Refused before connecting, so the unroutable address is never reached.
+        endpoint.host = "192.0.2.1".into();
+        let mut s = decider(endpoint);
+        s.retries = 0;
+        assert_eq!(s.decide_blocking(&(4, 0)),
+                   Err(Error::Protocol("Refusing to send key over plain HTTP to `192.0.2.1`".into())));
+    }
+
+    #[test]
+    fn decide_async() {
+        let s = decider(serve(vec![" -1 "]));
+        assert_eq!(crate::handle::tests::block_on(AsyncDecider::decide(&s, &(0, 1))), Ok(-1));
+    }
+}
@@ -0,0 +1,119 @@
+//! Speculative lookahead before acting.
+//!
+//! The safety layers check whether a decision holds under mutation of the model,
+//! which only looks one step ahead.
+//! A `Lookahead` agent rolls an agreed action forward on a clone of the agent,
+//! deciding and acting up to a number of steps.
+//! When the clone ends up requesting a model update, or in a model that violates a constraint,
+//! the action is downgraded to a model request.
+//!
+//! Halting during the rollout is considered safe.
+
+use std::fmt;
+
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Stores the outcome of rolling an action forward.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Foresight {
+    /// No problems were found.
+    Clear,
+    /// The agent requested a model update, at some step after acting.
+    Request(usize),
+    /// The model violated the constraint, at some step after acting.
+    Violation(usize),
+}
+
+/// Stores a layered agent that rolls actions forward before acting.
+pub struct Lookahead<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The number of steps to roll forward after acting.
+    pub depth: usize,
+    /// Returns `true` if a model violates the constraint.
+    pub violates: fn(&M) -> bool,
+    /// The outcome of the last rollout.
+    pub foresight: Option<Foresight>,
+}
+
+impl<M: Clone, A: Clone, D> Clone for Lookahead<M, A, D> {
+    fn clone(&self) -> Self {
+        Lookahead {agent: self.agent.clone(), depth: self.depth, violates: self.violates, foresight: self.foresight}
+    }
+}
+
+impl<M: fmt::Debug, A: fmt::Debug, D> fmt::Debug for Lookahead<M, A, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lookahead")
+            .field("agent", &self.agent)
+            .field("depth", &self.depth)
+            .field("violates", &self.violates)
+            .field("foresight", &self.foresight)
+            .finish()
+    }
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Lookahead<M, A, D> {
+    /// Creates a new agent rolling actions forward some steps.
+    pub fn new(agent: AgentN<M, A, D>, depth: usize, violates: fn(&M) -> bool) -> Self {
+        Lookahead {agent, depth, violates, foresight: None}
+    }
+
+    /// Rolls an action forward on a clone of the agent.
+    pub fn rollout(&self, action: A) -> Foresight {
+        let mut agent = self.agent.clone();
+        agent.act(action);
+        if (self.violates)(&agent.z.model) {return Foresight::Violation(0)}
+        for step in 1..=self.depth {
+            match agent.decide() {
+                Decision::Action(a) => agent.act(a),
+                Decision::RequestModel => return Foresight::Request(step),
+                Decision::Halt => break,
+            }
+            if (self.violates)(&agent.z.model) {return Foresight::Violation(step)}
+        }
+        Foresight::Clear
+    }
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Agent for Lookahead<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        let decision = self.agent.decide();
+        self.foresight = None;
+        if let Decision::Action(a) = &decision {
+            let foresight = self.rollout(a.clone());
+            self.foresight = Some(foresight);
+            if foresight != Foresight::Clear {return Decision::RequestModel}
+        }
+        decision
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Inspect for Lookahead<M, A, D> {
+    fn model(&self) -> &M {&self.agent.z.model}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollout() {
+        let mut s = Lookahead::new(crate::tests::four().add(1), 2, |_| false);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.foresight, Some(Foresight::Clear));
+        s.depth = 3;
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.foresight, Some(Foresight::Request(3)));
+        s.violates = |m| m.1 > 2;
+        assert_eq!(s.rollout(1), Foresight::Violation(2));
+        assert_eq!(s.model(), &(4, 0));
+    }
+}
@@ -0,0 +1,171 @@
+//! Reproducibility manifests of recorded traces.
+//!
+//! A recorded trace is only meaningful as safety evidence
+//! when it is replayed with the same library, configuration and seeds.
+//! A `Manifest` captures the crate version, the enabled features,
+//! the configuration of every safety layer, the seeds and the version of the model schema.
+//!
+//! A trace with a manifest embeds it in its golden file,
+//! and `golden::assert_golden` refuses golden files with a mismatched manifest,
+//! unless the environment variable `golden::ALLOW_MISMATCH` is set.
+
+use std::fmt;
+
+use crate::json;
+use crate::trace::Trace;
+use crate::AgentN;
+
+/// The features enabled when compiling this library.
+pub const FEATURES: &[(&str, bool)] = &[
+    ("async", cfg!(feature = "async")),
+    ("cbor", cfg!(feature = "cbor")),
+    ("checkpoint", cfg!(feature = "checkpoint")),
+    ("crypto", cfg!(feature = "crypto")),
+    ("envs", cfg!(feature = "envs")),
+    ("fuzz", cfg!(feature = "fuzz")),
+    ("grpc", cfg!(feature = "grpc")),
+    ("llm", cfg!(feature = "llm")),
+    ("metrics", cfg!(feature = "metrics")),
+    ("persistent", cfg!(feature = "persistent")),
+    ("prover", cfg!(feature = "prover")),
+    ("quickbacktrack", cfg!(feature = "quickbacktrack")),
+    ("replay", cfg!(feature = "replay")),
+    ("testing", cfg!(feature = "testing")),
+];
+
+/// Stores what is needed to reproduce a recorded trace.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Manifest {
+    /// The version of this library.
+    pub version: String,
+    /// The enabled features.
+    pub features: Vec<String>,
+    /// The configuration of each safety layer, from innermost to outermost.
+    pub layers: Vec<String>,
+    /// The seeds used by the agent and the environment.
+    pub seeds: Vec<u64>,
+    /// The version of the model schema.
+    pub schema: u32,
+}
+
+impl Manifest {
+    /// Creates a new manifest of this library, for some version of the model schema.
+    pub fn new(schema: u32) -> Manifest {
+        Manifest {
+            version: env!("CARGO_PKG_VERSION").into(),
+            features: FEATURES.iter().filter(|f| f.1).map(|f| f.0.into()).collect(),
+            layers: vec![],
+            seeds: vec![],
+            schema,
+        }
+    }
+
+    /// Creates a new manifest of an agent, including the seed of its stochastic mutater.
+    pub fn capture<M, A, D>(agent: &AgentN<M, A, D>, schema: u32) -> Manifest {
+        let mut manifest = Manifest::new(schema);
+        manifest.layers = agent.layers.iter().map(|c| format!(
+            "mutation_limit={} agreement={:?} comparator={} max_entropy={:?} second_order={} time_budget={:?}",
+            c.mutation_limit, c.agreement, c.comparator.is_some(), c.max_entropy, c.second_order, c.time_budget
+        )).collect();
+        manifest.seeds.extend(agent.stochastic.as_ref().map(|s| s.seed));
+        manifest
+    }
+
+    /// Adds a seed, e.g. of the environment.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seeds.push(seed);
+        self
+    }
+
+    /// Returns the names of the fields that differ from another manifest.
+    pub fn mismatches(&self, other: &Manifest) -> Vec<&'static str> {
+        let mut fields = vec![];
+        if self.version != other.version {fields.push("version")}
+        if self.features != other.features {fields.push("features")}
+        if self.layers != other.layers {fields.push("layers")}
+        if self.seeds != other.seeds {fields.push("seeds")}
+        if self.schema != other.schema {fields.push("schema")}
+        fields
+    }
+
+    /// Returns the manifest as JSON.
+    ///
+    /// Seeds are written as strings, since JSON numbers do not represent every `u64`.
+    pub fn to_json(&self) -> String {
+        let strings = |items: &[String]| items.iter().map(|s| json::string(s)).collect::<Vec<_>>().join(",");
+        let seeds: Vec<String> = self.seeds.iter().map(|s| format!("\"{}\"", s)).collect();
+        format!("{{\"version\":{},\"features\":[{}],\"layers\":[{}],\"seeds\":[{}],\"schema\":{}}}",
+                json::string(&self.version), strings(&self.features), strings(&self.layers),
+                seeds.join(","), self.schema)
+    }
+
+    /// Reads a manifest from JSON.
+    pub fn from_json(src: &str) -> Option<Manifest> {
+        let value = json::parse(src)?;
+        let strings = |key: &str| -> Option<Vec<String>> {
+            value.get(key)?.array()?.iter().map(|v| v.str().map(String::from)).collect()
+        };
+        let seeds = value.get("seeds")?.array()?.iter()
+            .map(|v| v.str()?.parse().ok()).collect::<Option<Vec<u64>>>()?;
+        let schema = value.get("schema").and_then(|v| v.num()).filter(|n| n.fract() == 0.0 && *n >= 0.0)?;
+        Some(Manifest {
+            version: value.get("version")?.str()?.into(),
+            features: strings("features")?,
+            layers: strings("layers")?,
+            seeds,
+            schema: schema as u32,
+        })
+    }
+}
+
+/// Stores a mismatch between the manifest of a recording and of a replay.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Mismatch {
+    /// The names of the fields that differ.
+    pub fields: Vec<&'static str>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "manifest mismatch in {}", self.fields.join(", "))
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+/// Returns an error if a recorded manifest does not match the manifest of a replay.
+///
+/// Traces without a manifest match any manifest.
+pub fn check(recorded: Option<&Manifest>, replay: Option<&Manifest>) -> Result<(), Mismatch> {
+    match (recorded, replay) {
+        (Some(a), Some(b)) => {
+            let fields = a.mismatches(b);
+            if fields.is_empty() {Ok(())} else {Err(Mismatch {fields})}
+        }
+        _ => Ok(()),
+    }
+}
+
+impl<M, A> Trace<M, A> {
+    /// Attaches a manifest to the trace.
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let manifest = Manifest::capture(&crate::tests::four().add(2), 3).seed(u64::MAX);
+        assert_eq!(manifest.layers.len(), 2);
+        assert_eq!(Manifest::from_json(&manifest.to_json()), Some(manifest.clone()));
+        let other = Manifest::capture(&crate::tests::four().add(1), 4).seed(u64::MAX);
+        assert_eq!(check(Some(&manifest), Some(&other)).unwrap_err().to_string(),
+                   "manifest mismatch in layers, schema");
+        assert!(check(Some(&manifest), None).is_ok());
+    }
+}
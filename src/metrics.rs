@@ -0,0 +1,241 @@
+//! Prometheus-compatible metrics exposition.
+//!
+//! A `Metered` agent measures every decide call,
+//! for users running agents as long-lived services.
+//! `Metered::render_metrics` returns the metrics in Prometheus text format:
+//!
+//! - `agent_decide_seconds`: Histogram of decide latency
+//! - `agent_requests_total`: Counter of decisions that requested a model update
+//! - `agent_disagreements_total`: Counter of probes that disagreed with core zero
+//! - `agent_safety_level`: Gauge of the current number of safety layers
+//!
+//! With `Metered::scope`, disagreements are only counted in a single safety layer,
+//! using the rationale of every decision.
+//!
+//! `informative::MutationStats::render` returns the probes and disagreements of each mutater:
+//!
+//! - `agent_mutation_probes_total`: Counter of probes, labeled by mutater
+//! - `agent_mutation_disagreements_total`: Counter of probes that disagreed, labeled by mutater
+//!
+//! `budget::Budget::render_metrics` returns the request budget of the current episode:
+//!
+//! - `agent_budget_remaining`: Gauge of model requests left
+//! - `agent_budget_requests`: Gauge of model requests
+//! - `agent_budget_fallbacks`: Gauge of decisions that used the fallback
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::budget::Budget;
+use crate::informative::MutationStats;
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// The upper bounds of the latency histogram buckets, in seconds.
+pub const BUCKETS: [f64; 6] = [0.00001, 0.0001, 0.001, 0.01, 0.1, 1.0];
+
+/// Stores metrics of decide calls.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// The number of decide calls.
+    pub decides: u64,
+    /// The number of decisions that requested a model update.
+    pub requests: u64,
+    /// The number of probes that disagreed with core zero.
+    pub disagreements: u64,
+    /// The number of decide calls per latency bucket, where the last one has no upper bound.
+    pub buckets: [u64; BUCKETS.len() + 1],
+    /// The total time spent deciding, in seconds.
+    pub seconds: f64,
+}
+
+impl Metrics {
+    /// Records a decide call.
+    pub fn record<A>(&mut self, latency: Duration, decision: &Decision<A>, disagreements: u32) {
+        let seconds = latency.as_secs_f64();
+        self.decides += 1;
+        if let Decision::RequestModel = decision {self.requests += 1}
+        self.disagreements += disagreements as u64;
+        let bucket = BUCKETS.iter().position(|&le| seconds <= le).unwrap_or(BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.seconds += seconds;
+    }
+
+    /// Returns the metrics in Prometheus text format.
+    pub fn render(&self, safety_level: usize) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP agent_decide_seconds Time spent deciding.");
+        let _ = writeln!(out, "# TYPE agent_decide_seconds histogram");
+        let mut count = 0;
+        for (le, n) in BUCKETS.iter().zip(&self.buckets) {
+            count += n;
+            let _ = writeln!(out, "agent_decide_seconds_bucket{{le=\"{}\"}} {}", le, count);
+        }
+        let _ = writeln!(out, "agent_decide_seconds_bucket{{le=\"+Inf\"}} {}", self.decides);
+        let _ = writeln!(out, "agent_decide_seconds_sum {}", self.seconds);
+        let _ = writeln!(out, "agent_decide_seconds_count {}", self.decides);
+        let _ = writeln!(out, "# HELP agent_requests_total Decisions that requested a model update.");
+        let _ = writeln!(out, "# TYPE agent_requests_total counter");
+        let _ = writeln!(out, "agent_requests_total {}", self.requests);
+        let _ = writeln!(out, "# HELP agent_disagreements_total Probes that disagreed with core zero.");
+        let _ = writeln!(out, "# TYPE agent_disagreements_total counter");
+        let _ = writeln!(out, "agent_disagreements_total {}", self.disagreements);
+        let _ = writeln!(out, "# HELP agent_safety_level Number of safety layers.");
+        let _ = writeln!(out, "# TYPE agent_safety_level gauge");
+        let _ = writeln!(out, "agent_safety_level {}", safety_level);
+        out
+    }
+}
+
+impl MutationStats {
+    /// Returns the statistics in Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("agent_mutation_probes_total", "Probes of each mutater.", &self.probes),
+            ("agent_mutation_disagreements_total", "Probes of each mutater that disagreed with core zero.",
+             &self.disagreements),
+        ];
+        for (name, help, counts) in counters.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            for (i, n) in counts.iter().enumerate() {
+                let label = escape(self.labels.get(i).cloned().unwrap_or(""));
+                let _ = writeln!(out, "{}{{mutater=\"{}\",label=\"{}\"}} {}", name, i, label, n);
+            }
+        }
+        out
+    }
+}
+
+/// Escapes a label value as required by the Prometheus text format.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl<T: Agent> Budget<T> {
+    /// Returns the request budget of the current episode in Prometheus text format.
+    pub fn render_metrics(&self) -> String {
+        let mut out = String::new();
+        let gauges = [
+            ("agent_budget_remaining", "Model requests left in this episode.", self.remaining()),
+            ("agent_budget_requests", "Model requests in this episode.", self.requests),
+            ("agent_budget_fallbacks", "Decisions in this episode that used the fallback.", self.fallbacks),
+        ];
+        for (name, help, n) in gauges.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, n);
+        }
+        out
+    }
+}
+
+/// Stores an agent that measures its decide calls.
+#[derive(Clone, Debug)]
+pub struct Metered<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The metrics.
+    pub metrics: Metrics,
+    /// The safety layer to count disagreements in, where `1` is the innermost one.
+    ///
+    /// When `None`, disagreements are counted in all safety layers.
+    pub scope: Option<usize>,
+}
+
+impl<M, A, D> Metered<M, A, D> {
+    /// Creates a new metered agent.
+    pub fn new(agent: AgentN<M, A, D>) -> Self {
+        Metered {agent, metrics: Metrics::default(), scope: None}
+    }
+
+    /// Creates a new metered agent that counts disagreements in a single safety layer.
+    pub fn scoped(agent: AgentN<M, A, D>, layer: usize) -> Self {
+        Metered {scope: Some(layer), ..Metered::new(agent)}
+    }
+
+    /// Returns the metrics in Prometheus text format.
+    pub fn render_metrics(&self) -> String {self.metrics.render(self.agent.layers())}
+}
+
+impl<M, A: PartialEq, D> Agent for Metered<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {
+        let start = Instant::now();
+        let (decision, disagreements) = match self.scope {
+            Some(layer) => {
+                let (decision, rationale) = self.agent.decide_rationale();
+                (decision, rationale.disagreements(layer))
+            }
+            None => (self.agent.decide(), self.agent.report.disagreements),
+        };
+        self.metrics.record(start.elapsed(), &decision, disagreements);
+        decision
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Metered<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let mut s = Metered::new(crate::tests::four().add(1));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.update_model((4, 0));
+        assert_eq!(s.decide(), Decision::Action(1));
+        let text = s.render_metrics();
+        assert!(text.contains("agent_decide_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("agent_decide_seconds_count 2\n"));
+        assert!(text.contains("agent_requests_total 1\n"));
+        assert!(text.contains("agent_disagreements_total 1\n"));
+        assert!(text.contains("# TYPE agent_safety_level gauge\nagent_safety_level 1\n"));
+    }
+
+    #[test]
+    fn scoped() {
+        // Only the innermost layer disagrees, on the model mutated by the outer layer.
+        let mut s = Metered::scoped(crate::tests::four().add(2), 2);
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.metrics.disagreements, 0);
+        s.scope = Some(1);
+        s.update_model((4, 2));
+        s.decide();
+        assert_eq!(s.metrics.disagreements, 4);
+    }
+
+    #[test]
+    fn render_mutations() {
+        let mut stats = MutationStats::new(vec!["goal"]);
+        stats.record(0, crate::ProbeOutcome::Disagree);
+        let text = stats.render();
+        assert!(text.contains("agent_mutation_probes_total{mutater=\"0\",label=\"goal\"} 1\n"));
+        assert!(text.contains("agent_mutation_disagreements_total{mutater=\"0\",label=\"goal\"} 1\n"));
+        let text = MutationStats::new(vec!["a\"b\\c\nd"]).render();
+        assert!(text.contains("{mutater=\"0\",label=\"a\\\"b\\\\c\\nd\"} 0\n"));
+    }
+
+    #[test]
+    fn render_budget() {
+        let mut s = Budget::new(crate::tests::four().add(1), 1, crate::budget::Fallback::Halt);
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.decide(), Decision::Halt);
+        let text = s.render_metrics();
+        assert!(text.contains("# TYPE agent_budget_remaining gauge\nagent_budget_remaining 0\n"));
+        assert!(text.contains("agent_budget_requests 1\n"));
+        assert!(text.contains("agent_budget_fallbacks 1\n"));
+    }
+}
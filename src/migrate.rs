@@ -0,0 +1,104 @@
+//! Versioned model schemas and migrations.
+//!
+//! A model recorded by an older version of an agent is stored as `Versioned` data,
+//! tagged with the schema version.
+//! The data can be of any representation, for example text or bytes.
+//!
+//! `Migrations` stores a migration step from each version to the next.
+//! Upgrading applies the steps in order until the current version is reached,
+//! such that recorded models can be replayed against newer deciders.
+
+use std::collections::BTreeMap;
+
+use crate::Error;
+
+/// Implemented by models with a versioned schema.
+pub trait ModelVersion {
+    /// The current schema version.
+    const VERSION: u32;
+}
+
+/// Stores model data tagged with the schema version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Versioned<R> {
+    /// The schema version.
+    pub version: u32,
+    /// The model data.
+    pub data: R,
+}
+
+/// Migrates data from some version to the next.
+pub type Step<R> = fn(R) -> Result<R, Error>;
+
+/// Stores migrations between schema versions.
+pub struct Migrations<R> {
+    steps: BTreeMap<u32, Step<R>>,
+}
+
+impl<R> Default for Migrations<R> {
+    fn default() -> Self {Migrations {steps: BTreeMap::new()}}
+}
+
+impl<R> Migrations<R> {
+    /// Creates a new empty registry of migrations.
+    pub fn new() -> Self {Self::default()}
+
+    /// Registers a migration from some version to the next.
+    pub fn register(&mut self, from: u32, step: Step<R>) {
+        self.steps.insert(from, step);
+    }
+
+    /// Upgrades data to some version.
+    pub fn upgrade(&self, mut v: Versioned<R>, to: u32) -> Result<Versioned<R>, Error> {
+        if v.version > to {return Err(Error::Migration {from: v.version, to})}
+        while v.version < to {
+            let step = self.steps.get(&v.version).ok_or(Error::Migration {from: v.version, to})?;
+            v = Versioned {version: v.version + 1, data: step(v.data)?};
+        }
+        Ok(v)
+    }
+
+    /// Upgrades data to the current version of a model and decodes it.
+    pub fn load<M: ModelVersion>(
+        &self,
+        v: Versioned<R>,
+        decode: impl FnOnce(R) -> Result<M, Error>
+    ) -> Result<M, Error> {
+        decode(self.upgrade(v, M::VERSION)?.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Model {goal: u32, state: u32, weather: u32}
+
+    impl ModelVersion for Model {
+        const VERSION: u32 = 2;
+    }
+
+    fn decode(data: String) -> Result<Model, Error> {
+        let v: Vec<u32> = data.split(',').map(|x| x.parse().unwrap()).collect();
+        Ok(Model {goal: v[0], state: v[1], weather: v[2]})
+    }
+
+    #[test]
+    fn upgrade() {
+        let mut migrations = Migrations::new();
+        // Version 1 added weather.
+        migrations.register(0, |data| Ok(format!("{},0", data)));
+        // Version 2 counts state from one.
+        migrations.register(1, |data: String| {
+            let mut v: Vec<u32> = data.split(',').map(|x| x.parse().unwrap()).collect();
+            v[1] += 1;
+            Ok(format!("{},{},{}", v[0], v[1], v[2]))
+        });
+        let old = Versioned {version: 0, data: "4,0".to_string()};
+        let m: Model = migrations.load(old, decode).unwrap();
+        assert_eq!((m.goal, m.state, m.weather), (4, 1, 0));
+
+        let newer = Versioned {version: 3, data: String::new()};
+        assert_eq!(migrations.load::<Model>(newer, decode).err(), Some(Error::Migration {from: 3, to: 2}));
+    }
+}
@@ -0,0 +1,189 @@
+//! Interactive oracle loops.
+//!
+//! When a layered agent requests a model update, a human can often answer it,
+//! e.g. from a console or a user interface.
+//! The function `resolve_requests_with` drives an agent in an environment,
+//! asking a closure for every model request.
+//! The closure may block while waiting for an answer, and returns `None` when not answered.
+//!
+//! An `Oracle` configures how many times a question is retried,
+//! a timeout for each attempt, and a `Fallback` when no answer arrives.
+//! A blocking closure is not interrupted, so an answer that arrives after the timeout is discarded.
+
+use std::time::{Duration, Instant};
+
+use crate::environment::Environment;
+use crate::query::ModelQuery;
+use crate::{Agent, AgentN, Decision, Reason};
+
+/// Stores a question about a model request.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Question<'a, M> {
+    /// The internal model of the agent.
+    pub model: &'a M,
+    /// The reason for the model request.
+    pub reason: Reason,
+    /// The part of the model the agent is uncertain about, if known.
+    pub query: Option<ModelQuery>,
+    /// The attempt of asking, where `0` is the first one.
+    pub attempt: u32,
+}
+
+/// The behavior when a question is not answered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fallback {
+    /// Update the model from the environment.
+    Environment,
+    /// Keep the model, such that the agent will likely request again.
+    Keep,
+    /// Stop driving the agent.
+    Halt,
+}
+
+/// Stores the configuration of an interactive oracle loop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Oracle {
+    /// The number of times an unanswered question is asked again.
+    pub retries: u32,
+    /// The maximum time for an answer of each attempt.
+    pub timeout: Option<Duration>,
+    /// The behavior when a question is not answered.
+    pub fallback: Fallback,
+}
+
+impl Default for Oracle {
+    fn default() -> Self {Oracle {retries: 0, timeout: None, fallback: Fallback::Environment}}
+}
+
+/// Stores a report of an interactive oracle loop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OracleReport {
+    /// The number of steps used.
+    pub steps: usize,
+    /// The number of model requests.
+    pub requests: usize,
+    /// The number of model requests that were answered.
+    pub answered: usize,
+    /// The number of times a question was not answered in time.
+    pub timeouts: usize,
+    /// The number of model requests resolved by the fallback.
+    pub fallbacks: usize,
+    /// Whether the agent halted, or the loop stopped by the fallback.
+    pub halted: bool,
+}
+
+impl Oracle {
+    /// Sets the number of times an unanswered question is asked again.
+    pub fn retries(self, retries: u32) -> Self {Oracle {retries, ..self}}
+
+    /// Sets the maximum time for an answer of each attempt.
+    pub fn timeout(self, timeout: Duration) -> Self {Oracle {timeout: Some(timeout), ..self}}
+
+    /// Sets the behavior when a question is not answered.
+    pub fn fallback(self, fallback: Fallback) -> Self {Oracle {fallback, ..self}}
+
+    /// Drives an agent in an environment for a maximum number of steps,
+    /// asking for answers to model requests.
+    pub fn resolve<M, A, D, E, F>(&self, agent: &mut AgentN<M, A, D>, env: &mut E, max_steps: usize, mut ask: F)
+        -> OracleReport
+        where A: Clone + PartialEq,
+              E: Environment<Model = M, Action = A>,
+              F: FnMut(&Question<M>) -> Option<M>
+    {
+        let mut report = OracleReport::default();
+        while report.steps < max_steps {
+            report.steps += 1;
+            let diagnosis = agent.diagnose();
+            match diagnosis.decision {
+                Decision::Action(a) => {
+                    env.act(&a);
+                    agent.act(a);
+                    continue;
+                }
+                Decision::Halt => {
+                    report.halted = true;
+                    break;
+                }
+                Decision::RequestModel => report.requests += 1,
+            }
+            let query = agent.query(diagnosis.reason);
+            let mut answer = None;
+            for attempt in 0..=self.retries {
+                let start = Instant::now();
+                let question = Question {model: &agent.z.model, reason: diagnosis.reason, query, attempt};
+                let a = ask(&question);
+                if a.is_some() && self.timeout.is_some_and(|t| start.elapsed() > t) {
+                    report.timeouts += 1;
+                    continue;
+                }
+                answer = a;
+                if answer.is_some() {break}
+            }
+            match answer {
+                Some(m) => {
+                    report.answered += 1;
+                    agent.update_model(m);
+                }
+                None => {
+                    report.fallbacks += 1;
+                    match self.fallback {
+                        Fallback::Environment => agent.update_model(env.model()),
+                        Fallback::Keep => {}
+                        Fallback::Halt => {
+                            report.halted = true;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Drives an agent in an environment like `Oracle::resolve`, using the default configuration.
+///
+/// Unanswered questions are not asked again, and fall back to updating the model from the environment.
+pub fn resolve_requests_with<M, A, D, E, F>(agent: &mut AgentN<M, A, D>, env: &mut E, max_steps: usize, ask: F)
+    -> OracleReport
+    where A: Clone + PartialEq,
+          E: Environment<Model = M, Action = A>,
+          F: FnMut(&Question<M>) -> Option<M>
+{
+    Oracle::default().resolve(agent, env, max_steps, ask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::tests::Three;
+
+    #[test]
+    fn ask() {
+        let mut s = crate::tests::four().add(1);
+        let oracle = Oracle::default().retries(1).fallback(Fallback::Halt);
+        // The operator answers on the second attempt.
+        let report = oracle.resolve(&mut s, &mut Three(0), 5, |q| {
+            if q.attempt == 0 {None} else {Some((q.model.1, q.model.1))}
+        });
+        assert_eq!(report, OracleReport {steps: 5, requests: 2, answered: 2, ..Default::default()});
+        assert_eq!(s.z.model, (3, 3));
+
+        let mut s = crate::tests::four().add(1);
+        let report = oracle.resolve(&mut s, &mut Three(0), 10, |_| None);
+        assert_eq!(report, OracleReport {steps: 4, requests: 1, fallbacks: 1, halted: true, ..Default::default()});
+
+        let mut s = crate::tests::four().add(1);
+        let mut env = Three(0);
+        let report = resolve_requests_with(&mut s, &mut env, 4, |q| Some((q.model.1, q.model.1)));
+        assert_eq!(report.answered, 1);
+        // The answer arrives too late, so the model is updated from the environment.
+        let timeout = Oracle::default().timeout(Duration::from_millis(1));
+        let report = timeout.resolve(&mut s, &mut env, 1, |_| {
+            std::thread::sleep(Duration::from_millis(2));
+            Some((5, 3))
+        });
+        assert_eq!((report.timeouts, report.fallbacks), (1, 1));
+        assert_eq!(s.z.model, (3, 3));
+    }
+}
@@ -0,0 +1,183 @@
+//! Safety-versus-effectiveness Pareto analysis.
+//!
+//! Adding safety layers makes an agent request model updates more often.
+//! This reduces safety violations, but might also reduce how often the goal is achieved.
+//!
+//! The function `sweep` runs a number of episodes for each safety level,
+//! measuring the goal-achievement rate and safety-violation rate.
+//! The function `frontier` picks out the points that are not dominated by any other point.
+//!
+//! The same notion of domination applies to actions with vector-valued utilities.
+//! Instead of forcing such agents into an arbitrary scalarization,
+//! a decider can return the Pareto-optimal set of actions using `pareto_set`.
+//! Sets are compared regardless of order, such that mutated decisions agree
+//! when the Pareto-optimal set is stable under the mutation.
+
+use crate::{AgentN, AgentZ};
+
+/// Stores the outcome of an episode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outcome {
+    /// Whether the goal was achieved.
+    pub goal: bool,
+    /// Whether the safety was violated.
+    pub violation: bool,
+}
+
+/// Stores measured rates for a safety level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    /// The number of safety layers.
+    pub layers: usize,
+    /// The fraction of episodes where the goal was achieved.
+    pub goal_rate: f64,
+    /// The fraction of episodes where the safety was violated.
+    pub violation_rate: f64,
+}
+
+impl Point {
+    /// Returns `true` if this point dominates another.
+    ///
+    /// A point dominates another when it is at least as good in both rates,
+    /// and strictly better in one of them.
+    pub fn dominates(&self, other: &Point) -> bool {
+        self.goal_rate >= other.goal_rate &&
+        self.violation_rate <= other.violation_rate &&
+        (self.goal_rate > other.goal_rate || self.violation_rate < other.violation_rate)
+    }
+}
+
+/// Returns `true` if utilities `a` dominate utilities `b`, where higher is better.
+///
+/// Utilities dominate others when they are at least as good in all objectives,
+/// and strictly better in one of them.
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+/// Stores a set of Pareto-optimal actions.
+///
+/// Sets are equal when they contain the same actions, regardless of order.
+#[derive(Clone, Debug)]
+pub struct ParetoSet<A> {
+    /// The actions, in the order of candidates.
+    pub actions: Vec<A>,
+}
+
+impl<A: PartialEq> ParetoSet<A> {
+    /// Returns `true` if the set contains an action.
+    pub fn contains(&self, action: &A) -> bool {self.actions.contains(action)}
+}
+
+impl<A: PartialEq> PartialEq for ParetoSet<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.actions.len() == other.actions.len() && self.actions.iter().all(|a| other.contains(a))
+    }
+}
+
+/// Returns the candidate actions whose utilities are not dominated by any other candidate.
+pub fn pareto_set<M, A: Clone + PartialEq>(
+    model: &M,
+    candidates: &[A],
+    utility: fn(&M, &A) -> Vec<f64>
+) -> ParetoSet<A> {
+    let utilities: Vec<Vec<f64>> = candidates.iter().map(|a| utility(model, a)).collect();
+    let mut actions = vec![];
+    for (a, u) in candidates.iter().zip(&utilities) {
+        if utilities.iter().any(|v| dominates(v, u)) || actions.contains(a) {continue}
+        actions.push(a.clone());
+    }
+    ParetoSet {actions}
+}
+
+/// Runs episodes for each safety level and measures rates.
+///
+/// The episode function gets a fresh agent with the safety layers added,
+/// plus the index of the episode.
+pub fn sweep<M, A, D, F>(
+    z: &AgentZ<M, A, D>,
+    layers: impl IntoIterator<Item = usize>,
+    episodes: usize,
+    mut f: F
+) -> Vec<Point>
+    where M: Clone, F: FnMut(&mut AgentN<M, A, D>, usize) -> Outcome
+{
+    let mut points = vec![];
+    for n in layers {
+        let mut goals = 0;
+        let mut violations = 0;
+        for i in 0..episodes {
+            let mut agent = z.clone().add(n);
+            let outcome = f(&mut agent, i);
+            if outcome.goal {goals += 1}
+            if outcome.violation {violations += 1}
+        }
+        let episodes = episodes.max(1) as f64;
+        points.push(Point {
+            layers: n,
+            goal_rate: goals as f64 / episodes,
+            violation_rate: violations as f64 / episodes,
+        });
+    }
+    points
+}
+
+/// Returns the points that are not dominated by any other point.
+pub fn frontier(points: &[Point]) -> Vec<Point> {
+    points.iter()
+        .filter(|p| !points.iter().any(|q| q.dominates(p)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    #[test]
+    fn frontier_of_increments() {
+        // The true goal is either `3` or `4`, but the agent believes it is `4`.
+        // When requesting a model update, the agent is told the true goal.
+        let points = sweep(&crate::tests::four(), 0..3, 2, |agent, i| {
+            let goal = 3 + i as u32 % 2;
+            let mut violation = false;
+            for _ in 0..10 {
+                match agent.decide() {
+                    Decision::Action(0) | Decision::Halt => break,
+                    Decision::Action(a) => agent.act(a),
+                    Decision::RequestModel => {
+                        let state = agent.z().model.1;
+                        agent.update_model((goal, state));
+                    }
+                }
+                if agent.z().model.1 > goal {violation = true}
+            }
+            Outcome {goal: agent.z().model.1 == goal, violation}
+        });
+        assert_eq!(points[0], Point {layers: 0, goal_rate: 0.5, violation_rate: 0.5});
+        assert_eq!(points[1], Point {layers: 1, goal_rate: 0.5, violation_rate: 0.0});
+        assert_eq!(points[2], Point {layers: 2, goal_rate: 0.0, violation_rate: 0.0});
+        // One safety layer avoids the violation without losing effectiveness.
+        assert_eq!(frontier(&points), vec![points[1]]);
+    }
+
+    #[test]
+    fn stable_set() {
+        fn utility(m: &(u32, u32), a: &i32) -> Vec<f64> {
+            vec![-((m.1 as i32 + a) - m.0 as i32).abs() as f64, -a.abs() as f64]
+        }
+        let z = AgentZ {
+            model: (4, 0),
+            decider: |m| pareto_set(m, &[2, 0, -2], utility),
+            actor: |m: &mut (u32, u32), s: ParetoSet<i32>| m.1 = (m.1 as i32 + s.actions[0]) as u32,
+            mutater: |m| {m.0 -= 1; -1},
+            undoer: |m, d| m.0 = (m.0 as i32 - d) as u32,
+        };
+        let mut s = z.add(1);
+        assert_eq!(s.decide(), Decision::Action(ParetoSet {actions: vec![0, 2]}));
+        // With a lower goal, moving by `2` would no longer be Pareto-optimal.
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
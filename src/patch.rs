@@ -0,0 +1,162 @@
+//! Partial model updates.
+//!
+//! Instead of shipping a whole model every time an agent asks a narrow question,
+//! the environment can send a patch that changes only some parts of the model.
+//!
+//! A model implements `Patchable` to describe its patches.
+//! Agents implement `ApplyPatch` alongside `Agent::update_model`.
+//!
+//! Agents implement `UpdateFrom` to update from a borrowed model,
+//! cloning into the existing model such that its allocations are reused.
+
+use std::borrow::Cow;
+
+use crate::capability::Capabilities;
+use crate::cow::Chunked;
+use crate::shield::Shield;
+use crate::{Agent, AgentN, AgentS, AgentZ, Inspect};
+
+/// Implemented by models that can be partially updated.
+pub trait Patchable {
+    /// The type of patches.
+    type Patch;
+
+    /// Applies a patch.
+    fn apply(&mut self, patch: Self::Patch);
+}
+
+/// Implemented by agents that accept partial model updates.
+pub trait ApplyPatch {
+    /// The type of patches.
+    type Patch;
+
+    /// Update parts of internal model.
+    fn apply_patch(&mut self, patch: Self::Patch);
+}
+
+impl<M: Patchable, A, D> ApplyPatch for AgentZ<M, A, D> {
+    type Patch = M::Patch;
+    fn apply_patch(&mut self, patch: M::Patch) {self.model.apply(patch)}
+}
+
+impl<M: Patchable, A, D> ApplyPatch for AgentN<M, A, D> {
+    type Patch = M::Patch;
+    fn apply_patch(&mut self, patch: M::Patch) {
+        self.handoff = false;
+        self.z.apply_patch(patch)
+    }
+}
+
+impl<M: Patchable, A, D> ApplyPatch for AgentS<M, A, D> {
+    type Patch = M::Patch;
+    fn apply_patch(&mut self, patch: M::Patch) {self.core.apply_patch(patch)}
+}
+
+impl<T: Agent + ApplyPatch> ApplyPatch for Capabilities<T> {
+    type Patch = T::Patch;
+    fn apply_patch(&mut self, patch: T::Patch) {self.agent.apply_patch(patch)}
+}
+
+impl<T: Agent + ApplyPatch> ApplyPatch for Shield<T> {
+    type Patch = T::Patch;
+    fn apply_patch(&mut self, patch: T::Patch) {self.agent.apply_patch(patch)}
+}
+
+/// Implemented by agents that update from a borrowed model.
+pub trait UpdateFrom: Agent where Self::Model: Clone {
+    /// Update internal model from a borrowed model.
+    fn update_model_from(&mut self, model: &Self::Model);
+
+    /// Update internal model, cloning only when the model is borrowed.
+    fn update_model_cow(&mut self, model: Cow<'_, Self::Model>) {
+        match model {
+            Cow::Borrowed(model) => self.update_model_from(model),
+            Cow::Owned(model) => self.update_model(model),
+        }
+    }
+}
+
+impl<M: Clone, A, D> UpdateFrom for AgentZ<M, A, D> {
+    fn update_model_from(&mut self, model: &M) {self.model.clone_from(model)}
+}
+
+impl<M: Clone, A: PartialEq, D> UpdateFrom for AgentN<M, A, D> {
+    fn update_model_from(&mut self, model: &M) {
+        self.handoff = false;
+        self.z.update_model_from(model)
+    }
+}
+
+impl<M: Clone, A: PartialEq, D> UpdateFrom for AgentS<M, A, D> {
+    fn update_model_from(&mut self, model: &M) {self.core.update_model_from(model)}
+}
+
+impl<T: UpdateFrom> UpdateFrom for Capabilities<T> where T::Model: Clone {
+    fn update_model_from(&mut self, model: &T::Model) {self.agent.update_model_from(model)}
+}
+
+impl<T: Inspect + UpdateFrom> UpdateFrom for Shield<T> where T::Model: Clone {
+    fn update_model_from(&mut self, model: &T::Model) {self.agent.update_model_from(model)}
+}
+
+/// Replaces a chunk.
+impl<T> Patchable for Chunked<T> {
+    type Patch = (usize, T);
+    fn apply(&mut self, (index, chunk): (usize, T)) {
+        self.replace(index, chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decision;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Model {goal: u32, state: u32}
+
+    enum Patch {Goal(u32), State(u32)}
+
+    impl Patchable for Model {
+        type Patch = Patch;
+        fn apply(&mut self, patch: Patch) {
+            match patch {
+                Patch::Goal(goal) => self.goal = goal,
+                Patch::State(state) => self.state = state,
+            }
+        }
+    }
+
+    #[test]
+    fn patch_goal() {
+        let z = AgentZ {
+            model: Model {goal: 4, state: 3},
+            decider: |m: &Model| if m.state < m.goal {1} else {0},
+            actor: |m: &mut Model, a: u32| m.state += a,
+            mutater: |m: &mut Model| {
+                m.goal -= 1;
+                1
+            },
+            undoer: |m: &mut Model, d: u32| m.goal += d,
+        };
+        let mut s = z.add(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.apply_patch(Patch::Goal(3));
+        s.apply_patch(Patch::State(0));
+        assert_eq!(s.z.model, Model {goal: 3, state: 0});
+        assert_eq!(s.decide(), Decision::Action(1));
+    }
+
+    #[test]
+    fn borrowed() {
+        let mut s = crate::tests::four().add(1);
+        s.handoff = true;
+        let model = (4, 3);
+        s.update_model_from(&model);
+        assert_eq!((s.z.model, s.handoff), (model, false));
+        s.update_model_cow(Cow::Owned((4, 0)));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.update_model_cow(Cow::Borrowed(&model));
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
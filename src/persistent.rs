@@ -0,0 +1,344 @@
+//! Persistent collections as model backing stores.
+//!
+//! Deep safety stacks mutate and undo the model many times per decide call.
+//! For models with thousands of entries, copying or diffing the model is expensive.
+//!
+//! A `Vector` or `Map` shares its structure using `Arc`.
+//! A change copies only the path from the root to the changed entry,
+//! and returns a `Snapshot` of the old root.
+//! Undoing a change swaps the old root back in, which is `O(1)`.
+//! Cloning a collection is `O(1)`, since all nodes are shared.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+const BITS: usize = 5;
+const WIDTH: usize = 1 << BITS;
+const MASK: usize = WIDTH - 1;
+
+#[derive(Clone)]
+enum Node<T> {
+    Leaf(Vec<T>),
+    Branch(Vec<Arc<Node<T>>>),
+}
+
+/// Stores a snapshot of a persistent collection that undoes a change.
+#[derive(Clone, Debug)]
+pub struct Snapshot<C>(C);
+
+/// Stores a persistent vector.
+pub struct Vector<T> {
+    root: Arc<Node<T>>,
+    len: usize,
+    shift: usize,
+}
+
+impl<T> Clone for Vector<T> {
+    fn clone(&self) -> Self {Vector {root: self.root.clone(), len: self.len, shift: self.shift}}
+}
+
+impl<T> Default for Vector<T> {
+    fn default() -> Self {Vector {root: Arc::new(Node::Leaf(vec![])), len: 0, shift: 0}}
+}
+
+impl<T> Vector<T> {
+    /// Creates a new empty vector.
+    pub fn new() -> Self {Vector::default()}
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {self.len}
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {self.len == 0}
+
+    /// Returns an entry.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {return None}
+        let (mut node, mut level) = (&*self.root, self.shift);
+        loop {
+            match node {
+                Node::Leaf(items) => return items.get(index & MASK),
+                Node::Branch(children) => {
+                    node = &children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the entries.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.len).filter_map(move |i| self.get(i))
+    }
+
+    /// Returns `true` if two vectors share the same root.
+    pub fn ptr_eq(&self, other: &Self) -> bool {Arc::ptr_eq(&self.root, &other.root)}
+
+    /// Returns a snapshot of the vector.
+    pub fn snapshot(&self) -> Snapshot<Self> {Snapshot(self.clone())}
+
+    /// Undoes changes by swapping a snapshot back in.
+    pub fn undo(&mut self, snapshot: Snapshot<Self>) {*self = snapshot.0}
+}
+
+impl<T: Clone> Vector<T> {
+    /// Replaces an entry, returning a snapshot that undoes the change.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> Snapshot<Self> {
+        assert!(index < self.len, "index out of bounds");
+        let snapshot = self.snapshot();
+        let (mut node, mut level) = (&mut self.root, self.shift);
+        loop {
+            match Arc::make_mut(node) {
+                Node::Leaf(items) => {
+                    items[index & MASK] = value;
+                    return snapshot;
+                }
+                Node::Branch(children) => {
+                    node = &mut children[(index >> level) & MASK];
+                    level -= BITS;
+                }
+            }
+        }
+    }
+
+    /// Appends an entry, returning a snapshot that undoes the change.
+    pub fn push(&mut self, value: T) -> Snapshot<Self> {
+        let snapshot = self.snapshot();
+        if self.len == WIDTH << self.shift {
+            let old = self.root.clone();
+            self.root = Arc::new(Node::Branch(vec![old]));
+            self.shift += BITS;
+        }
+        let (index, mut node, mut level) = (self.len, &mut self.root, self.shift);
+        loop {
+            match Arc::make_mut(node) {
+                Node::Leaf(items) => {
+                    items.push(value);
+                    break;
+                }
+                Node::Branch(children) => {
+                    let j = (index >> level) & MASK;
+                    level -= BITS;
+                    if j == children.len() {
+                        children.push(Arc::new(if level == 0 {Node::Leaf(vec![])} else {Node::Branch(vec![])}));
+                    }
+                    node = &mut children[j];
+                }
+            }
+        }
+        self.len += 1;
+        snapshot
+    }
+}
+
+impl<T: Clone> std::iter::FromIterator<T> for Vector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vector = Vector::new();
+        for value in iter {vector.push(value);}
+        vector
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Vector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {f.debug_list().entries(self.iter()).finish()}
+}
+
+impl<T: PartialEq> PartialEq for Vector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other) || self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+const MAP_BITS: usize = 4;
+const MAP_WIDTH: usize = 1 << MAP_BITS;
+// Buckets are split until the hash bits run out.
+const BUCKET: usize = 8;
+
+#[derive(Clone)]
+enum Entries<K, V> {
+    Bucket(Vec<(u64, K, V)>),
+    Branch(Vec<Option<Arc<Entries<K, V>>>>),
+}
+
+/// Stores a persistent hash map.
+pub struct Map<K, V> {
+    root: Arc<Entries<K, V>>,
+    len: usize,
+}
+
+impl<K, V> Clone for Map<K, V> {
+    fn clone(&self) -> Self {Map {root: self.root.clone(), len: self.len}}
+}
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {Map {root: Arc::new(Entries::Bucket(vec![])), len: 0}}
+}
+
+fn hash<K: Hash>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn slot(hash: u64, level: usize) -> usize {(hash >> level) as usize & (MAP_WIDTH - 1)}
+
+impl<K, V> Map<K, V> {
+    /// Creates a new empty map.
+    pub fn new() -> Self {Map::default()}
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {self.len}
+
+    /// Returns `true` if there are no entries.
+    pub fn is_empty(&self) -> bool {self.len == 0}
+
+    /// Returns `true` if two maps share the same root.
+    pub fn ptr_eq(&self, other: &Self) -> bool {Arc::ptr_eq(&self.root, &other.root)}
+
+    /// Returns the entries, in no particular order.
+    pub fn entries(&self) -> Vec<(&K, &V)> {
+        fn walk<'a, K, V>(node: &'a Entries<K, V>, out: &mut Vec<(&'a K, &'a V)>) {
+            match node {
+                Entries::Bucket(items) => out.extend(items.iter().map(|(_, k, v)| (k, v))),
+                Entries::Branch(children) => for child in children.iter().flatten() {walk(child, out)},
+            }
+        }
+        let mut out = vec![];
+        walk(&self.root, &mut out);
+        out
+    }
+
+    /// Returns a snapshot of the map.
+    pub fn snapshot(&self) -> Snapshot<Self> {Snapshot(self.clone())}
+
+    /// Undoes changes by swapping a snapshot back in.
+    pub fn undo(&mut self, snapshot: Snapshot<Self>) {*self = snapshot.0}
+}
+
+impl<K: Hash + Eq, V> Map<K, V> {
+    /// Returns the value of a key.
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let h = hash(key);
+        let (mut node, mut level) = (&*self.root, 0);
+        loop {
+            match node {
+                Entries::Bucket(items) => return items.iter().find(|(_, k, _)| k == key).map(|(_, _, v)| v),
+                Entries::Branch(children) => {
+                    node = children[slot(h, level)].as_ref()?;
+                    level += MAP_BITS;
+                }
+            }
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Map<K, V> {
+    /// Inserts a value, returning a snapshot that undoes the change.
+    pub fn insert(&mut self, key: K, value: V) -> Snapshot<Self> {
+        let snapshot = self.snapshot();
+        if insert(&mut self.root, hash(&key), key, value, 0) {self.len += 1}
+        snapshot
+    }
+
+    /// Removes a key, returning a snapshot that undoes the change.
+    pub fn remove(&mut self, key: &K) -> Snapshot<Self> {
+        let snapshot = self.snapshot();
+        if self.get(key).is_none() {return snapshot}
+        let h = hash(key);
+        let (mut node, mut level) = (&mut self.root, 0);
+        loop {
+            match Arc::make_mut(node) {
+                Entries::Bucket(items) => {
+                    items.retain(|(_, k, _)| k != key);
+                    self.len -= 1;
+                    return snapshot;
+                }
+                Entries::Branch(children) => {
+                    node = children[slot(h, level)].as_mut().expect("key exists");
+                    level += MAP_BITS;
+                }
+            }
+        }
+    }
+}
+
+fn insert<K: Eq + Clone, V: Clone>(node: &mut Arc<Entries<K, V>>, h: u64, key: K, value: V, level: usize) -> bool {
+    let entries = Arc::make_mut(node);
+    let items = match entries {
+        Entries::Bucket(items) => items,
+        Entries::Branch(children) => {
+            let child = children[slot(h, level)].get_or_insert_with(|| Arc::new(Entries::Bucket(vec![])));
+            return insert(child, h, key, value, level + MAP_BITS);
+        }
+    };
+    if let Some(entry) = items.iter_mut().find(|(_, k, _)| *k == key) {
+        entry.2 = value;
+        return false;
+    }
+    items.push((h, key, value));
+    if items.len() > BUCKET && level < 64 {
+        let mut children = vec![None; MAP_WIDTH];
+        for (h, k, v) in std::mem::take(items) {
+            let child = children[slot(h, level)].get_or_insert_with(|| Arc::new(Entries::Bucket(vec![])));
+            if let Entries::Bucket(bucket) = Arc::make_mut(child) {bucket.push((h, k, v))}
+        }
+        *entries = Entries::Branch(children);
+    }
+    true
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Map<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {f.debug_map().entries(self.entries()).finish()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    #[test]
+    fn vector() {
+        let mut v: Vector<u32> = (0..2000).collect();
+        assert_eq!((v.len(), v.get(1500)), (2000, Some(&1500)));
+        let old = v.clone();
+        let snapshot = v.set(1500, 7);
+        assert_eq!((v.get(1500), old.get(1500)), (Some(&7), Some(&1500)));
+        v.undo(snapshot);
+        assert!(v.ptr_eq(&old));
+        assert_eq!(v.iter().copied().sum::<u32>(), 1999 * 1000);
+    }
+
+    #[test]
+    fn map() {
+        let mut m = Map::new();
+        for i in 0..1000 {m.insert(i, i * 2);}
+        let snapshot = m.remove(&500);
+        m.insert(1, 0);
+        assert_eq!((m.len(), m.get(&500), m.get(&1), m.get(&999)), (999, None, Some(&0), Some(&1998)));
+        m.undo(snapshot);
+        assert_eq!((m.len(), m.get(&500), m.get(&1)), (1000, Some(&1000), Some(&2)));
+    }
+
+    #[test]
+    fn deep_stack() {
+        let z: AgentZ<Vector<u32>, u32, Snapshot<Vector<u32>>> = AgentZ {
+            model: (0..5000).map(|_| 1).collect(),
+            decider: |m| *m.get(4999).unwrap(),
+            actor: |m, a| {m.push(a);},
+            mutater: |m| {
+                let i = m.len() - 1;
+                let x = *m.get(i).unwrap();
+                m.set(i, x)
+            },
+            undoer: |m, d| m.undo(d),
+        };
+        let model = z.model.clone();
+        let mut s = z.add(8);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert!(s.z.model.ptr_eq(&model));
+    }
+}
@@ -0,0 +1,111 @@
+//! Read-side planners and write-side executors.
+//!
+//! The `Agent` trait combines deciding with updating and acting on the internal model,
+//! which requires mutable access for every part.
+//! A `Planner` decides from a model it does not own, using shared access,
+//! such that planners can be shared, cached and probed in parallel.
+//! An `Executor` updates the internal model and acts on it.
+//!
+//! Every agent is an executor.
+//! Since `Agent` and `Executor` share method names, import only one of them in a scope.
+//! A `Composed` agent combines a planner with an executor,
+//! where the planner decides on the internal model of the executor.
+
+use crate::{Agent, AgentZ, Decision, Inspect};
+
+/// Implemented by planners that decide from a model.
+pub trait Planner {
+    /// The type of the model.
+    type Model;
+    /// The type of actions.
+    type Action;
+
+    /// Decide what to do next in some model.
+    fn plan(&self, model: &Self::Model) -> Decision<Self::Action>;
+}
+
+/// Implemented by executors that update and act on an internal model.
+pub trait Executor {
+    /// The type of the model.
+    type Model;
+    /// The type of actions.
+    type Action;
+
+    /// Update internal model.
+    fn update_model(&mut self, model: Self::Model);
+    /// Perform an action on its internal model.
+    fn act(&mut self, action: Self::Action);
+}
+
+impl<T: Agent> Executor for T {
+    type Model = T::Model;
+    type Action = T::Action;
+    fn update_model(&mut self, model: T::Model) {Agent::update_model(self, model)}
+    fn act(&mut self, action: T::Action) {Agent::act(self, action)}
+}
+
+impl<M, A, D> Planner for AgentZ<M, A, D> {
+    type Model = M;
+    type Action = A;
+    fn plan(&self, model: &M) -> Decision<A> {Decision::Action((self.decider)(model))}
+}
+
+impl<P: Planner> Planner for &P {
+    type Model = P::Model;
+    type Action = P::Action;
+    fn plan(&self, model: &P::Model) -> Decision<P::Action> {(**self).plan(model)}
+}
+
+/// Stores an agent that combines a planner with an executor.
+#[derive(Clone, Debug)]
+pub struct Composed<P, E> {
+    /// The planner.
+    pub planner: P,
+    /// The executor.
+    pub executor: E,
+}
+
+impl<P, E> Composed<P, E>
+    where E: Inspect, P: Planner<Model = E::Model, Action = E::Action>
+{
+    /// Creates a new agent from a planner and an executor.
+    pub fn new(planner: P, executor: E) -> Self {Composed {planner, executor}}
+}
+
+impl<P, E> Agent for Composed<P, E>
+    where E: Inspect, P: Planner<Model = E::Model, Action = E::Action>
+{
+    type Model = E::Model;
+    type Action = E::Action;
+    type Delta = E::Delta;
+    fn update_model(&mut self, model: E::Model) {self.executor.update_model(model)}
+    fn decide(&mut self) -> Decision<E::Action> {self.planner.plan(self.executor.model())}
+    fn act(&mut self, action: E::Action) {self.executor.act(action)}
+    fn mutate(&mut self) -> E::Delta {self.executor.mutate()}
+    fn undo(&mut self, delta: E::Delta) {self.executor.undo(delta)}
+}
+
+impl<P, E> Inspect for Composed<P, E>
+    where E: Inspect, P: Planner<Model = E::Model, Action = E::Action>
+{
+    fn model(&self) -> &E::Model {self.executor.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Composed, Planner};
+    use crate::{Agent, Decision, Inspect};
+
+    #[test]
+    fn share() {
+        let planner = crate::tests::four();
+        assert_eq!(planner.plan(&(4, 4)), Decision::Action(0));
+        // Two executors share one planner.
+        let mut a = Composed::new(&planner, crate::tests::four());
+        let mut b = Composed::new(&planner, crate::tests::four());
+        b.update_model((4, 5));
+        assert_eq!((a.decide(), b.decide()), (Decision::Action(1), Decision::Action(-1)));
+        a.act(1);
+        assert_eq!(a.model(), &(4, 1));
+    }
+}
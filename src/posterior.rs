@@ -0,0 +1,178 @@
+//! Bayesian goal uncertainty.
+//!
+//! Safety layers mutate goals to find out whether a decision depends on which goal is correct.
+//! A `GoalPosterior` makes this uncertainty explicit as a distribution over candidate goals.
+//! Model updates that confirm or contradict a goal update the distribution by Bayes' rule.
+//!
+//! Models that keep a posterior implement `GoalModel`.
+//! The mutation distribution `GoalSampler` replaces the goal with an alternative goal
+//! sampled from the posterior, such that probes focus on goals that are still plausible:
+//!
+//! ```text
+//! agent.stochastic = Some(Stochastic::sampled::<GoalSampler>(seed));
+//! agent.z.undoer = posterior::undo;
+//! ```
+
+use crate::rng::{MutationDistribution, Rng};
+
+/// Stores a distribution over candidate goals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoalPosterior<G> {
+    /// The candidate goals with their probabilities, summing to `1`.
+    pub candidates: Vec<(G, f64)>,
+}
+
+impl<G: PartialEq> GoalPosterior<G> {
+    /// Creates a new uniform distribution over candidate goals.
+    pub fn uniform(goals: Vec<G>) -> Self {
+        let p = 1.0 / goals.len().max(1) as f64;
+        GoalPosterior {candidates: goals.into_iter().map(|g| (g, p)).collect()}
+    }
+
+    /// Creates a new distribution from prior weights, which are normalized.
+    pub fn with_prior(candidates: Vec<(G, f64)>) -> Self {
+        let mut posterior = GoalPosterior {candidates};
+        posterior.normalize();
+        posterior
+    }
+
+    fn normalize(&mut self) -> bool {
+        let total: f64 = self.candidates.iter().map(|(_, p)| p).sum();
+        if total.is_nan() || total <= 0.0 {return false}
+        for (_, p) in &mut self.candidates {*p /= total}
+        true
+    }
+
+    /// Returns the probability of a goal.
+    pub fn probability(&self, goal: &G) -> f64 {
+        self.candidates.iter().filter(|(g, _)| g == goal).map(|(_, p)| p).sum()
+    }
+
+    /// Returns the most probable goal, if any.
+    pub fn most_probable(&self) -> Option<&G> {
+        self.candidates.iter()
+            .fold(None, |best: Option<&(G, f64)>, c| match best {
+                Some(b) if b.1 >= c.1 => Some(b),
+                _ => Some(c),
+            })
+            .map(|(g, _)| g)
+    }
+
+    /// Returns the entropy of the distribution in bits.
+    pub fn entropy(&self) -> f64 {
+        self.candidates.iter().filter(|(_, p)| *p > 0.0).map(|(_, p)| -p * p.log2()).sum()
+    }
+
+    /// Updates the distribution with the likelihood of an observation under each goal.
+    ///
+    /// Returns `false` when the observation is impossible under every goal,
+    /// leaving the distribution unchanged.
+    pub fn observe(&mut self, likelihood: impl Fn(&G) -> f64) -> bool {
+        let old = self.candidates.iter().map(|(_, p)| *p).collect::<Vec<_>>();
+        for (g, p) in &mut self.candidates {*p *= likelihood(g)}
+        if self.normalize() {return true}
+        for ((_, p), q) in self.candidates.iter_mut().zip(old) {*p = q}
+        false
+    }
+
+    /// Updates the distribution when a model update confirms a goal with some confidence in `[0, 1]`.
+    pub fn confirm(&mut self, goal: &G, confidence: f64) -> bool {
+        self.observe(|g| if g == goal {confidence} else {1.0 - confidence})
+    }
+
+    /// Updates the distribution when a model update contradicts a goal.
+    pub fn contradict(&mut self, goal: &G) -> bool {self.observe(|g| if g == goal {0.0} else {1.0})}
+
+    /// Samples a goal other than `current`, returning `None` if no alternative is plausible.
+    pub fn sample_alternative(&self, current: &G, rng: &mut Rng) -> Option<&G> {
+        let total: f64 = self.candidates.iter().filter(|(g, _)| g != current).map(|(_, p)| p).sum();
+        if total.is_nan() || total <= 0.0 {return None}
+        let mut x = rng.next_f64() * total;
+        let mut last = None;
+        for (g, p) in self.candidates.iter().filter(|(g, p)| g != current && *p > 0.0) {
+            if x < *p {return Some(g)}
+            x -= p;
+            last = Some(g);
+        }
+        last
+    }
+}
+
+/// Implemented by models that keep a distribution over their goal.
+pub trait GoalModel {
+    /// The type of goals.
+    type Goal: Clone + PartialEq;
+
+    /// Returns the current goal.
+    fn goal(&self) -> &Self::Goal;
+    /// Sets the current goal.
+    fn set_goal(&mut self, goal: Self::Goal);
+    /// Returns the distribution over goals.
+    fn posterior(&self) -> &GoalPosterior<Self::Goal>;
+}
+
+/// Samples alternative goals from the posterior of a `GoalModel`.
+///
+/// The delta is the previous goal, which is restored by `undo`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GoalSampler;
+
+impl<M: GoalModel> MutationDistribution<M> for GoalSampler {
+    type Delta = M::Goal;
+
+    fn mutate(model: &mut M, rng: &mut Rng) -> M::Goal {
+        let old = model.goal().clone();
+        if let Some(goal) = model.posterior().sample_alternative(&old, rng).cloned() {model.set_goal(goal)}
+        old
+    }
+}
+
+/// Restores the goal before a mutation by `GoalSampler`.
+pub fn undo<M: GoalModel>(model: &mut M, goal: M::Goal) {model.set_goal(goal)}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Stochastic;
+    use crate::{Agent, AgentZ, Decision};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Belief {
+        goal: u32,
+        state: u32,
+        posterior: GoalPosterior<u32>,
+    }
+
+    impl GoalModel for Belief {
+        type Goal = u32;
+        fn goal(&self) -> &u32 {&self.goal}
+        fn set_goal(&mut self, goal: u32) {self.goal = goal}
+        fn posterior(&self) -> &GoalPosterior<u32> {&self.posterior}
+    }
+
+    #[test]
+    fn sample_goals() {
+        let mut posterior = GoalPosterior::uniform(vec![4, 3, 2]);
+        assert!(posterior.confirm(&4, 0.8));
+        assert!((posterior.probability(&4) - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(posterior.most_probable(), Some(&4));
+        assert!(posterior.contradict(&2));
+        assert!(!posterior.observe(|_| 0.0));
+        assert_eq!(posterior.probability(&2), 0.0);
+
+        let z = AgentZ {
+            model: Belief {goal: 4, state: 3, posterior},
+            decider: |m: &Belief| (m.goal as i32 - m.state as i32).signum(),
+            actor: |m: &mut Belief, a: i32| m.state = (m.state as i32 + a) as u32,
+            mutater: |m: &mut Belief| m.goal,
+            undoer: undo,
+        };
+        let mut s = z.add(1);
+        s.stochastic = Some(Stochastic::sampled::<GoalSampler>(0));
+        // The goal `3` is still plausible, and would not move further.
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.z.model.posterior.contradict(&3);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.z.model.goal, 4);
+    }
+}
@@ -0,0 +1,107 @@
+//! Human preference feedback.
+//!
+//! A model request is often answered by a human, who can also judge decisions:
+//! in some model, one action was preferred over another.
+//! A `Preferring` agent records such judgments in an audit trail,
+//! together with who gave them and whether they answered a model request.
+//!
+//! An optional updater consumes each judgment, e.g. by adjusting reward weights of the model,
+//! or by replacing the decider of core zero.
+//! This closes the loop after an episode that ended in a model request.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores a judgment that one action is preferred over another in some model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preference<M, A> {
+    /// Who gave the judgment.
+    pub source: &'static str,
+    /// The number of decide calls before the judgment.
+    pub step: usize,
+    /// Whether the last decision requested a model update.
+    pub requested: bool,
+    /// The model of the judgment.
+    pub model: M,
+    /// The preferred action.
+    pub chosen: A,
+    /// The rejected action.
+    pub rejected: A,
+}
+
+/// Consumes a judgment for an agent.
+pub type Updater<T> = fn(&mut T, &Preference<<T as Agent>::Model, <T as Agent>::Action>);
+
+/// Stores an agent that receives preference feedback.
+#[derive(Clone, Debug)]
+pub struct Preferring<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The audit trail of judgments, in order.
+    pub preferences: Vec<Preference<T::Model, T::Action>>,
+    /// Consumes a judgment, e.g. by updating the decider.
+    pub updater: Option<Updater<T>>,
+    steps: usize,
+    requested: bool,
+}
+
+impl<T: Agent> Preferring<T> {
+    /// Creates a new agent that records preference feedback.
+    pub fn new(agent: T) -> Self {
+        Preferring {agent, preferences: vec![], updater: None, steps: 0, requested: false}
+    }
+
+    /// Sets the updater that consumes judgments.
+    pub fn updater(mut self, updater: Updater<T>) -> Self {
+        self.updater = Some(updater);
+        self
+    }
+
+    /// Records a judgment that an action is preferred over another in some model.
+    pub fn prefer(&mut self, source: &'static str, model: T::Model, chosen: T::Action, rejected: T::Action) {
+        let preference = Preference {source, step: self.steps, requested: self.requested, model, chosen, rejected};
+        if let Some(f) = self.updater {f(&mut self.agent, &preference)}
+        self.preferences.push(preference);
+    }
+}
+
+impl<T: Agent> Agent for Preferring<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        let decision = self.agent.decide();
+        self.requested = matches!(decision, Decision::RequestModel);
+        self.steps += 1;
+        decision
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Preferring<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AgentN;
+
+    #[test]
+    fn feedback() {
+        // Moving on is preferred, so the goal is further away.
+        fn updater(s: &mut AgentN<(u32, u32), i32, i32>, p: &Preference<(u32, u32), i32>) {
+            if p.chosen > p.rejected {s.update_model((p.model.0 + 1, p.model.1))}
+        }
+        let mut s = Preferring::new(crate::tests::four().add(1)).updater(updater);
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.prefer("operator", (4, 3), 1, 0);
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.preferences[0].source, "operator");
+        assert!(s.preferences[0].requested);
+        assert_eq!(s.preferences[0].step, 1);
+    }
+}
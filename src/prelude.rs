@@ -0,0 +1,38 @@
+//! Commonly used types and functions.
+//!
+//! Typical setups only need a single import:
+//!
+//! ```text
+//! use agent_safety_layers::prelude::*;
+//!
+//! let mut agent = agent(model).decide_with(decider).act_with(actor).mutate_with(mutater, undoer).layers(3).build()?;
+//! let report = run_until(&mut agent, &mut env, goal, 100);
+//! ```
+
+pub use crate::{
+    Agent, AgentN, AgentS, AgentZ, Agreement, Decision, Diagnosis, Error, Event, Inspect,
+    LayerConfig, ProbeOutcome, Reason, SafetyReport,
+};
+pub use crate::agreement::{AgreementPolicy, Quorum, Unanimous, Votes};
+pub use crate::budget::Fallback;
+pub use crate::builder::{agent, AgentBuilder, BuildError};
+pub use crate::environment::{run_until, step, Environment, RunReport, StepOutcome};
+pub use crate::exhausted::Exhausted;
+pub use crate::graded::Grade;
+pub use crate::oracle::Oracle;
+pub use crate::runtime::AgentThread;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prelude() {
+        let z = crate::tests::four();
+        let mut s = agent(z.model).decide_with(z.decider).act_with(z.actor).mutate_with(z.mutater, z.undoer)
+            .layers(1).build().unwrap();
+        let report = run_until(&mut s, &mut crate::environment::tests::Three(0), |m| m.1 == 3, 10);
+        assert!(report.goal);
+        assert_eq!(s.decide_graded(), Grade::RequestModel);
+    }
+}
@@ -0,0 +1,213 @@
+//! Provenance of deltas across safety layers.
+//!
+//! In deep stacks of safety layers, a model corrupted by an incorrect undo is hard to debug,
+//! since many deltas are applied and undone while deciding.
+//! When `AgentN::provenance` is set, every delta applied by a probe is recorded with its `Origin`:
+//! the safety layer, the probe and the mutater that produced it.
+//!
+//! With a fingerprint of the model, see `Provenance::verified`,
+//! every undo is also checked to restore the model from before the delta was applied.
+//! An undo that does not is recorded as an `UndoError` with the origin of the delta,
+//! and counted in `SafetyReport::undo_errors`.
+//!
+//! The record is cleared at the start of every call to `AgentN::diagnose`.
+
+use std::fmt;
+use std::ptr::fn_addr_eq;
+
+use crate::{AgentN, Error, SafetyReport};
+
+/// Stores the origin of a delta.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Origin {
+    /// The safety layer probing, where `1` is the innermost one.
+    pub layer: usize,
+    /// The index of the probe.
+    pub probe: u8,
+    /// The index of the mutater, which is `0` for the mutater of core zero or a stochastic mutater.
+    pub mutater: usize,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "layer {} probe #{} (mutater {})", self.layer, self.probe, self.mutater)
+    }
+}
+
+/// Stores an undo that did not restore the model.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UndoError {
+    /// The origin of the delta.
+    pub origin: Origin,
+    /// The origins of the deltas that were applied when the delta was undone, from outermost to innermost.
+    pub stack: Vec<Origin>,
+    /// The fingerprint of the model before the delta was applied.
+    pub expected: u64,
+    /// The fingerprint of the model after the delta was undone.
+    pub actual: u64,
+}
+
+impl fmt::Display for UndoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "undo of delta from {} did not restore the model (fingerprint {}, expected {})",
+               self.origin, self.actual, self.expected)?;
+        for origin in self.stack.iter().rev() {write!(f, ", within delta from {}", origin)?}
+        Ok(())
+    }
+}
+
+impl From<UndoError> for Error {
+    fn from(err: UndoError) -> Error {Error::Invariant(err.to_string())}
+}
+
+/// Stores the provenance of deltas.
+pub struct Provenance<M> {
+    /// Returns the fingerprint of a model, used to verify undos.
+    pub fingerprint: Option<fn(&M) -> u64>,
+    /// The deltas applied in the last decide call, in order.
+    pub applied: Vec<Origin>,
+    /// The undos that did not restore the model in the last decide call.
+    pub errors: Vec<UndoError>,
+    /// The deltas that are applied but not yet undone, with the fingerprint from before.
+    pub(crate) live: Vec<(Origin, Option<u64>)>,
+}
+
+impl<M> Clone for Provenance<M> {
+    fn clone(&self) -> Self {
+        Provenance {
+            fingerprint: self.fingerprint,
+            applied: self.applied.clone(),
+            errors: self.errors.clone(),
+            live: self.live.clone(),
+        }
+    }
+}
+
+impl<M> fmt::Debug for Provenance<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Provenance")
+            .field("fingerprint", &self.fingerprint)
+            .field("applied", &self.applied)
+            .field("errors", &self.errors)
+            .finish()
+    }
+}
+
+impl<M> PartialEq for Provenance<M> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.fingerprint, other.fingerprint) {
+            (Some(a), Some(b)) => fn_addr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+impl<M> Default for Provenance<M> {
+    fn default() -> Self {Provenance {fingerprint: None, applied: vec![], errors: vec![], live: vec![]}}
+}
+
+impl<M> Provenance<M> {
+    /// Creates a new provenance that records origins of deltas.
+    pub fn new() -> Self {Provenance::default()}
+
+    /// Creates a new provenance that also verifies undos using a fingerprint of the model.
+    pub fn verified(fingerprint: fn(&M) -> u64) -> Self {
+        Provenance {fingerprint: Some(fingerprint), ..Provenance::default()}
+    }
+
+    /// Returns the origins of the deltas that were applied but not undone, from outermost to innermost.
+    pub fn live(&self) -> Vec<Origin> {self.live.iter().map(|(origin, _)| *origin).collect()}
+
+    /// Returns the first undo error of the last decide call, if any.
+    pub fn check(&self) -> Result<(), Error> {
+        match self.errors.first() {
+            Some(err) => Err(err.clone().into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Clears the provenance for a decide call.
+    pub(crate) fn provenance_start(&mut self) {
+        if let Some(p) = &mut self.provenance {
+            p.applied.clear();
+            p.errors.clear();
+            p.live.clear();
+        }
+    }
+
+    /// Returns the fingerprint of the model before applying a delta, when verifying undos.
+    pub(crate) fn provenance_mark(&self) -> Option<u64> {
+        self.provenance.as_ref().and_then(|p| p.fingerprint).map(|f| f(&self.z.model))
+    }
+
+    /// Records a delta applied by a probe.
+    pub(crate) fn provenance_apply(&mut self, layer: usize, probe: u8, before: Option<u64>) {
+        if self.provenance.is_none() {return}
+        let mutater = if self.stochastic.is_some() {0} else {self.mutater_of(probe)};
+        let origin = Origin {layer, probe, mutater};
+        if let Some(p) = &mut self.provenance {
+            p.applied.push(origin);
+            p.live.push((origin, before));
+        }
+    }
+
+    /// Records that the last applied delta was undone, verifying the model.
+    pub(crate) fn provenance_undo(&mut self, tally: &mut SafetyReport) {
+        let actual = self.provenance_mark();
+        let p = match &mut self.provenance {
+            Some(p) => p,
+            None => return,
+        };
+        if let Some((origin, before)) = p.live.pop() {
+            if let Some((expected, actual)) = before.zip(actual).filter(|(a, b)| a != b) {
+                let stack = p.live.iter().map(|(origin, _)| *origin).collect();
+                p.errors.push(UndoError {origin, stack, expected, actual});
+                tally.undo_errors += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    fn fingerprint(m: &(u32, u32)) -> u64 {(m.0 as u64) << 32 | m.1 as u64}
+
+    #[test]
+    fn provenance() {
+        let mut s = crate::tests::four().add(2);
+        s.provenance = Some(Provenance::verified(fingerprint));
+        assert_eq!(s.decide(), Decision::Action(1));
+        let p = s.provenance.as_ref().unwrap();
+        assert_eq!(p.applied, vec![
+            Origin {layer: 2, probe: 0, mutater: 0},
+            Origin {layer: 1, probe: 0, mutater: 0},
+        ]);
+        assert!(p.check().is_ok() && p.live().is_empty());
+
+        // An undoer that forgets to restore the goal is traced to its delta.
+        s.z.undoer = |_, _| {};
+        s.z.model = (4, 0);
+        s.decide();
+        let p = s.provenance.as_ref().unwrap();
+        assert_eq!(s.report.undo_errors, 2);
+        assert_eq!(p.errors[0].origin, Origin {layer: 1, probe: 0, mutater: 0});
+        assert_eq!(p.errors[0].stack, vec![Origin {layer: 2, probe: 0, mutater: 0}]);
+        assert!(p.check().unwrap_err().to_string()
+            .ends_with("within delta from layer 2 probe #0 (mutater 0)"));
+    }
+
+    #[test]
+    fn certified() {
+        let mut s = crate::tests::four().add(2);
+        s.provenance = Some(Provenance::verified(fingerprint));
+        assert_eq!(s.decide(), Decision::Action(1));
+        // Certified decisions only trace their own deltas.
+        assert!(matches!(s.decide_certified(), Decision::Action(_)));
+        assert_eq!(s.provenance.as_ref().unwrap().applied.len(), 2);
+    }
+}
@@ -0,0 +1,128 @@
+//! Propositional verification of the agreement rule.
+//!
+//! The algorithm in `AgentN::decide_s` has an informal proof of a safer level for every case.
+//! This module encodes the case analysis as propositional formulas
+//! and checks the claimed implications using truth tables,
+//! in the style of `pocket_prover`:
+//! Every variable is a bit pattern over all assignments of the variables,
+//! such that one bitwise evaluation of a formula checks all of them at once.
+//!
+//! The variables are:
+//!
+//! - `core`: The model of core zero is correct
+//! - `mutated`: The mutated model is correct
+//! - `agree`: The decision on the mutated model agrees with core zero
+//!
+//! An action is safe when it was decided on a correct model,
+//! and requesting a model update is always safe.
+//! Acting on an agreed action is safe when either model is correct.
+//! Acting on core zero despite disagreements, e.g. when waived, is only as safe as core zero.
+//!
+//! A case is at least as safe as core zero when the safety of core zero implies the safety of the case.
+//! It is more safe when it is also safe in some assignment where core zero is not.
+
+/// The bit pattern of variable `0`.
+pub const P0: u64 = 0xaaaa_aaaa_aaaa_aaaa;
+/// The bit pattern of variable `1`.
+pub const P1: u64 = 0xcccc_cccc_cccc_cccc;
+/// The bit pattern of variable `2`.
+pub const P2: u64 = 0xf0f0_f0f0_f0f0_f0f0;
+/// True in all assignments.
+pub const T: u64 = !0;
+/// False in all assignments.
+pub const F: u64 = 0;
+
+/// Returns the bit pattern of an implication.
+pub fn imply(a: u64, b: u64) -> u64 {!a | b}
+
+/// Returns `true` if a formula holds for all assignments.
+pub fn prove(f: fn(u64, u64, u64) -> u64) -> bool {f(P0, P1, P2) == T}
+
+/// Returns `true` if a formula holds for some assignment.
+pub fn satisfiable(f: fn(u64, u64, u64) -> u64) -> bool {f(P0, P1, P2) != F}
+
+/// Stores a case of the agreement rule.
+#[derive(Clone, Copy, Debug)]
+pub struct Case {
+    /// The name of the case.
+    pub name: &'static str,
+    /// Returns whether the decision of core zero is safe.
+    pub core: fn(u64, u64, u64) -> u64,
+    /// Returns whether the decision of the case is safe,
+    /// from the variables `core`, `mutated` and `agree`.
+    pub safe: fn(u64, u64, u64) -> u64,
+    /// Whether the informal proof claims the case is more safe than core zero,
+    /// instead of just as safe.
+    pub more_safe: bool,
+}
+
+impl Case {
+    /// Returns `true` if the case is at least as safe as core zero.
+    pub fn at_least_as_safe(&self) -> bool {
+        imply((self.core)(P0, P1, P2), (self.safe)(P0, P1, P2)) == T
+    }
+
+    /// Returns `true` if the case is safe in some assignment where core zero is not.
+    pub fn exceeds_core(&self) -> bool {
+        !(self.core)(P0, P1, P2) & (self.safe)(P0, P1, P2) != F
+    }
+
+    /// Returns `true` if the informal proof of the case holds.
+    pub fn holds(&self) -> bool {
+        self.at_least_as_safe() && self.exceeds_core() == self.more_safe
+    }
+}
+
+/// Returns the cases of the agreement rule, in the order of `AgentN::decide_s`.
+pub fn cases() -> Vec<Case> {
+    vec![
+        // Core zero requests a model update or halts, and so does the case.
+        Case {name: "core request", core: |_, _, _| T, safe: |_, _, _| T, more_safe: false},
+        Case {name: "core halt", core: |_, _, _| T, safe: |_, _, _| T, more_safe: false},
+        // Core zero acts, which is safe when its model is correct.
+        // An agreement policy acts on core zero, possibly despite disagreements.
+        Case {name: "policy act", core: |core, _, _| core, safe: |core, _, _| core, more_safe: false},
+        Case {name: "policy ask", core: |core, _, _| core, safe: |_, _, _| T, more_safe: true},
+        Case {name: "divided", core: |core, _, _| core, safe: |_, _, _| T, more_safe: true},
+        Case {
+            name: "agree",
+            core: |core, _, _| core,
+            safe: |core, mutated, agree| core | agree & mutated,
+            more_safe: true,
+        },
+        Case {name: "disagree", core: |core, _, _| core, safe: |_, _, _| T, more_safe: true},
+        // Acting when new information is not worth waiting for is only as safe as core zero.
+        Case {name: "waived", core: |core, _, _| core, safe: |core, _, _| core, more_safe: false},
+        Case {name: "undetermined", core: |core, _, _| core, safe: |_, _, _| T, more_safe: true},
+        // The outermost layer might use `AgentN::exhausted` when no probe determines a decision.
+        Case {name: "exhausted caution", core: |core, _, _| core, safe: |core, _, _| core, more_safe: false},
+        // The default policy is assumed to be safe in every model, as the user claims by choosing it.
+        Case {name: "exhausted default", core: |core, _, _| core, safe: |_, _, _| T, more_safe: true},
+        Case {name: "exhausted halt", core: |core, _, _| core, safe: |_, _, _| T, more_safe: true},
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agreement_rule() {
+        assert!(prove(|a, b, _| imply(a & b, a)));
+        assert!(!prove(|a, b, _| imply(a | b, a)));
+        assert!(satisfiable(|a, b, c| a & !b & c));
+        for case in cases() {
+            assert!(case.holds(), "{}", case.name);
+        }
+        // Acting on core zero without checking is not more safe.
+        let unchecked = Case {
+            name: "unchecked",
+            core: |core, _, _| core,
+            safe: |core, _, _| core,
+            more_safe: true,
+        };
+        assert!(unchecked.at_least_as_safe() && !unchecked.holds());
+        let waived = cases().into_iter().find(|case| case.name == "waived").unwrap();
+        assert!(!Case {more_safe: true, ..waived}.holds());
+    }
+}
@@ -0,0 +1,93 @@
+//! Targeted information requests.
+//!
+//! When a mutation disagrees with core zero,
+//! the agent is uncertain about the part of the model targeted by the mutater.
+//! A `ModelQuery` names this part, such that the environment or an oracle
+//! can answer with a partial update rather than a full model.
+
+use std::fmt;
+
+use crate::{AgentN, Reason};
+
+/// Stores a question about a part of the model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ModelQuery {
+    /// The safety layer, where `1` is the innermost one.
+    pub layer: usize,
+    /// The index of the probe that disagreed.
+    pub probe: u8,
+    /// The index of the mutater that caused disagreement.
+    pub mutater: usize,
+    /// The part of the model targeted by the mutater.
+    pub target: Option<&'static str>,
+}
+
+impl fmt::Display for ModelQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.target {
+            Some(target) => write!(f, "uncertain about {}", target)?,
+            None => write!(f, "uncertain about mutater {}", self.mutater)?,
+        }
+        write!(f, " (mutation #{} of layer {} disagreed)", self.probe, self.layer)
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Returns the index of the mutater used by some probe.
+    pub fn mutater_of(&self, probe: u8) -> usize {
+        let i = probe as usize % self.mutaters.len().max(1);
+        let order = |i| match &self.coverage {
+            Some(c) => c.order.get(i).cloned().unwrap_or(i),
+            None => i,
+        };
+        self.warm_order(i, order(i), order(0))
+    }
+
+    /// Returns a query about the part of the model that caused disagreement.
+    ///
+    /// Returns `None` when the reason is not a disagreement.
+    pub fn query(&self, reason: Reason) -> Option<ModelQuery> {
+        match reason {
+            Reason::Disagree {layer, probe} => {
+                let mutater = self.mutater_of(probe);
+                let target = self.targets.get(mutater).cloned().filter(|t| !t.is_empty());
+                Some(ModelQuery {layer, probe, mutater, target})
+            }
+            _ => None,
+        }
+    }
+
+    /// Answers a query with a partial update of the model.
+    pub fn answer(&mut self, update: impl FnOnce(&mut M)) {
+        update(&mut self.z.model);
+        self.handoff = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::AgentBuilder;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn ask_about_goal() {
+        let z = crate::tests::four();
+        let mut s = AgentBuilder::new()
+            .model((4, 3))
+            .decider(z.decider)
+            .actor(z.actor)
+            .mutater_targeting("goal", z.mutater)
+            .undoer(z.undoer)
+            .layers(1)
+            .build()
+            .unwrap();
+        let diagnosis = s.diagnose();
+        assert_eq!(diagnosis.decision, Decision::RequestModel);
+        let query = s.query(diagnosis.reason).unwrap();
+        assert_eq!(query.target, Some("goal"));
+        assert_eq!(query.to_string(), "uncertain about goal (mutation #0 of layer 1 disagreed)");
+        // The true goal turns out to be `3`.
+        s.answer(|model| model.0 = 3);
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
@@ -0,0 +1,213 @@
+//! Structured rationale attached to decisions.
+//!
+//! A `Rationale` is a tree that records which probes ran in each safety layer,
+//! their outcomes and which reason determined the decision.
+//! A probe that decided using inner safety layers has the rationale of those layers attached.
+//!
+//! The tree can be written as JSON with `Rationale::to_json` and read with `Rationale::from_json`,
+//! for external review tooling or incident reports.
+//! Reasons are tagged arrays of their variant and fields:
+//!
+//! ```text
+//! {"layer":1,"reason":["agree",1,0],"checks":[{"probe":0,"second":null,"outcome":"agree","inner":null}]}
+//! ```
+
+use crate::json::{self, Value};
+use crate::{AgentN, Decision, Error, ProbeOutcome, Reason};
+
+/// Stores a probe that ran in a safety layer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Check {
+    /// The index of the probe.
+    pub probe: u8,
+    /// The index of the second probe, when a pair of mutations was probed.
+    pub second: Option<u8>,
+    /// The outcome of the probe.
+    pub outcome: ProbeOutcome,
+    /// The rationale of the inner safety layers on the mutated model.
+    pub inner: Option<Box<Rationale>>,
+}
+
+/// Stores the rationale of a decision in a safety layer.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rationale {
+    /// The safety layer, where `1` is the innermost one and `0` is core zero.
+    pub layer: usize,
+    /// The probes that ran, in order.
+    pub checks: Vec<Check>,
+    /// The reason that determined the decision.
+    pub reason: Reason,
+}
+
+fn reason_value(reason: Reason) -> Value {
+    let tag = |t: &str, fields: &[usize]| Value::Arr(
+        std::iter::once(Value::Str(t.into())).chain(fields.iter().map(|&x| Value::Num(x as f64))).collect()
+    );
+    match reason {
+        Reason::Core => tag("core", &[]),
+        Reason::CoreRequest => tag("core_request", &[]),
+        Reason::Agree {layer, probe} => tag("agree", &[layer, probe as usize]),
+        Reason::AllAgree {layer} => tag("all_agree", &[layer]),
+        Reason::Disagree {layer, probe} => tag("disagree", &[layer, probe as usize]),
+        Reason::Undetermined {layer} => tag("undetermined", &[layer]),
+        Reason::Handoff => tag("handoff", &[]),
+        Reason::Divided {layer} => tag("divided", &[layer]),
+        Reason::DisagreePair {layer, probes: (i, j)} => tag("disagree_pair", &[layer, i as usize, j as usize]),
+        Reason::Waived {layer, probe} => tag("waived", &[layer, probe as usize]),
+        Reason::LowPriority {objective} => tag("low_priority", &[objective]),
+        Reason::Tie => tag("tie", &[]),
+        Reason::Policy {layer} => tag("policy", &[layer]),
+    }
+}
+
+fn parse_reason(value: &Value) -> Option<Reason> {
+    let items = value.array()?;
+    let n = |k: usize| items.get(k).and_then(|x| x.num()).filter(|x| *x >= 0.0 && x.fract() == 0.0);
+    let layer = || n(1).map(|x| x as usize);
+    let probe = |k: usize| n(k).filter(|x| *x <= u8::MAX as f64).map(|x| x as u8);
+    Some(match items.first()?.str()? {
+        "core" => Reason::Core,
+        "core_request" => Reason::CoreRequest,
+        "agree" => Reason::Agree {layer: layer()?, probe: probe(2)?},
+        "all_agree" => Reason::AllAgree {layer: layer()?},
+        "disagree" => Reason::Disagree {layer: layer()?, probe: probe(2)?},
+        "undetermined" => Reason::Undetermined {layer: layer()?},
+        "handoff" => Reason::Handoff,
+        "divided" => Reason::Divided {layer: layer()?},
+        "disagree_pair" => Reason::DisagreePair {layer: layer()?, probes: (probe(2)?, probe(3)?)},
+        "waived" => Reason::Waived {layer: layer()?, probe: probe(2)?},
+        "low_priority" => Reason::LowPriority {objective: layer()?},
+        "tie" => Reason::Tie,
+        "policy" => Reason::Policy {layer: layer()?},
+        _ => return None,
+    })
+}
+
+fn probe_value(value: &Value) -> Option<u8> {
+    value.num().filter(|x| *x >= 0.0 && *x <= u8::MAX as f64 && x.fract() == 0.0).map(|x| x as u8)
+}
+
+impl Rationale {
+    /// Returns the number of probes in safety layer `layer` that disagreed with core zero.
+    pub fn disagreements(&self, layer: usize) -> u32 {
+        let own = if self.layer == layer {
+            self.checks.iter().filter(|c| c.outcome == ProbeOutcome::Disagree).count() as u32
+        } else {0};
+        own + self.checks.iter().filter_map(|c| c.inner.as_ref()).map(|r| r.disagreements(layer)).sum::<u32>()
+    }
+
+    /// Returns the rationale as a JSON value.
+    pub(crate) fn to_value(&self) -> Value {
+        let checks = self.checks.iter().map(|check| Value::Obj(vec![
+            ("probe".into(), Value::Num(check.probe as f64)),
+            ("second".into(), check.second.map(|j| Value::Num(j as f64)).unwrap_or(Value::Null)),
+            ("outcome".into(), Value::Str(check.outcome.to_string())),
+            ("inner".into(), check.inner.as_ref().map(|inner| inner.to_value()).unwrap_or(Value::Null)),
+        ])).collect();
+        Value::Obj(vec![
+            ("layer".into(), Value::Num(self.layer as f64)),
+            ("reason".into(), reason_value(self.reason)),
+            ("checks".into(), Value::Arr(checks)),
+        ])
+    }
+
+    /// Reads a rationale from a JSON value.
+    pub(crate) fn from_value(value: &Value) -> Option<Rationale> {
+        let checks = value.get("checks")?.array()?.iter().map(|check| {
+            let second = match check.get("second")? {
+                Value::Null => None,
+                x => Some(probe_value(x)?),
+            };
+            let outcome = match check.get("outcome")?.str()? {
+                "agree" => ProbeOutcome::Agree,
+                "disagree" => ProbeOutcome::Disagree,
+                "request model" => ProbeOutcome::RequestModel,
+                _ => return None,
+            };
+            let inner = match check.get("inner")? {
+                Value::Null => None,
+                x => Some(Box::new(Rationale::from_value(x)?)),
+            };
+            Some(Check {probe: probe_value(check.get("probe")?)?, second, outcome, inner})
+        }).collect::<Option<_>>()?;
+        Some(Rationale {
+            layer: value.get("layer")?.num().filter(|x| *x >= 0.0 && x.fract() == 0.0)? as usize,
+            reason: parse_reason(value.get("reason")?)?,
+            checks,
+        })
+    }
+
+    /// Returns the rationale as JSON.
+    pub fn to_json(&self) -> String {json::write(&self.to_value())}
+
+    /// Parses a rationale from JSON.
+    pub fn from_json(src: &str) -> Result<Rationale, Error> {
+        json::parse(src).as_ref().and_then(Rationale::from_value)
+            .ok_or_else(|| Error::Protocol("Invalid rationale".into()))
+    }
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Starts recording the rationale of safety layer `layer`.
+    pub(crate) fn rationale_enter(&mut self, layer: usize) {
+        if let Some(stack) = &mut self.rationale {
+            stack.push(Rationale {layer, checks: vec![], reason: Reason::Undetermined {layer}});
+        }
+    }
+
+    /// Sets the reason of the safety layer being recorded.
+    pub(crate) fn rationale_exit(&mut self, reason: Reason) {
+        if let Some(node) = self.rationale.as_mut().and_then(|stack| stack.last_mut()) {
+            node.reason = reason;
+        }
+    }
+
+    /// Records a probe, attaching the rationale of inner layers when they decided.
+    pub(crate) fn rationale_check(&mut self, probe: u8, second: Option<u8>, outcome: ProbeOutcome, inner: bool) {
+        if let Some(stack) = &mut self.rationale {
+            let inner = if inner {stack.pop().map(Box::new)} else {None};
+            if let Some(node) = stack.last_mut() {
+                node.checks.push(Check {probe, second, outcome, inner});
+            }
+        }
+    }
+}
+
+impl<M, A: PartialEq, D> AgentN<M, A, D> {
+    /// Decide what to do next, together with a structured rationale.
+    pub fn decide_rationale(&mut self) -> (Decision<A>, Rationale) {
+        self.rationale = Some(vec![]);
+        let diagnosis = self.diagnose();
+        let root = self.rationale.take().and_then(|mut stack| stack.pop());
+        let rationale = root.unwrap_or(Rationale {
+            layer: self.layers(),
+            checks: vec![],
+            reason: diagnosis.reason,
+        });
+        (diagnosis.decision, rationale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rationale_tree() {
+        let mut s = crate::tests::four().add(2);
+        let (decision, rationale) = s.decide_rationale();
+        assert_eq!(decision, Decision::Action(1));
+        assert_eq!(rationale.reason, Reason::Agree {layer: 2, probe: 0});
+        let inner = rationale.checks[0].inner.as_ref().unwrap();
+        assert_eq!(inner.layer, 1);
+        assert_eq!(inner.checks[0], Check {probe: 0, second: None, outcome: ProbeOutcome::Agree, inner: None});
+        assert_eq!(s.rationale, None);
+
+        let mut s = crate::tests::four().add(1);
+        let json = s.decide_rationale().1.to_json();
+        assert_eq!(json, "{\"layer\":1,\"reason\":[\"agree\",1,0],\
+             \"checks\":[{\"probe\":0,\"second\":null,\"outcome\":\"agree\",\"inner\":null}]}");
+        assert_eq!(Rationale::from_json(&json).unwrap().to_json(), json);
+        assert!(Rationale::from_json(&json.replace("agree", "maybe")).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Recovery from missed undos.
+//!
+//! A mutation that is not undone leaves the model in a mutated state,
+//! and every later decision is silently made on the wrong model.
+//! A `SafetyNet` keeps a checkpoint of the last known-good model,
+//! taken after every model update and action.
+//! At the start of every decide call, the fingerprint of the model is compared with the checkpoint.
+//! On a mismatch, the checkpoint is restored before deciding, and the incident is logged.
+//!
+//! Like contracts, the model is only checked in debug builds.
+
+use std::fmt;
+
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Stores an incident where the model was restored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Incident {
+    /// The index of the decide call.
+    pub decision: usize,
+    /// The fingerprint of the checkpoint.
+    pub expected: u64,
+    /// The fingerprint of the model found at the start of the decide call.
+    pub actual: u64,
+}
+
+impl fmt::Display for Incident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "decide call #{} found model with fingerprint {}, restored checkpoint with fingerprint {}",
+               self.decision, self.actual, self.expected)
+    }
+}
+
+/// Stores a layered agent that restores its model after missed undos.
+pub struct SafetyNet<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// Returns the fingerprint of a model.
+    pub fingerprint: fn(&M) -> u64,
+    /// The last known-good model with its fingerprint, in debug builds.
+    pub checkpoint: Option<(M, u64)>,
+    /// The incidents.
+    pub incidents: Vec<Incident>,
+    /// The number of decide calls.
+    pub decisions: usize,
+}
+
+impl<M: Clone, A, D> Clone for SafetyNet<M, A, D> {
+    fn clone(&self) -> Self {
+        SafetyNet {
+            agent: self.agent.clone(),
+            fingerprint: self.fingerprint,
+            checkpoint: self.checkpoint.clone(),
+            incidents: self.incidents.clone(),
+            decisions: self.decisions,
+        }
+    }
+}
+
+impl<M: fmt::Debug, A, D> fmt::Debug for SafetyNet<M, A, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SafetyNet")
+            .field("agent", &self.agent)
+            .field("fingerprint", &self.fingerprint)
+            .field("checkpoint", &self.checkpoint)
+            .field("incidents", &self.incidents)
+            .field("decisions", &self.decisions)
+            .finish()
+    }
+}
+
+impl<M: Clone, A, D> SafetyNet<M, A, D> {
+    /// Creates a new agent, taking a checkpoint of its model.
+    pub fn new(agent: AgentN<M, A, D>, fingerprint: fn(&M) -> u64) -> Self {
+        let mut net = SafetyNet {agent, fingerprint, checkpoint: None, incidents: vec![], decisions: 0};
+        net.save();
+        net
+    }
+
+    /// Takes a checkpoint of the model, in debug builds.
+    pub fn save(&mut self) {
+        if cfg!(debug_assertions) {
+            let model = self.agent.z.model.clone();
+            let fingerprint = (self.fingerprint)(&model);
+            self.checkpoint = Some((model, fingerprint));
+        }
+    }
+
+    /// Restores the checkpoint if the model does not match it, returning the incident.
+    pub fn recover(&mut self) -> Option<Incident> {
+        let (model, expected) = self.checkpoint.as_ref()?;
+        let actual = (self.fingerprint)(&self.agent.z.model);
+        if actual == *expected {return None}
+        self.agent.z.model.clone_from(model);
+        let incident = Incident {decision: self.decisions, expected: *expected, actual};
+        self.incidents.push(incident);
+        Some(incident)
+    }
+}
+
+impl<M: Clone, A: PartialEq, D> Agent for SafetyNet<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {
+        self.agent.update_model(model);
+        self.save();
+    }
+    fn decide(&mut self) -> Decision<A> {
+        self.recover();
+        self.decisions += 1;
+        self.agent.decide()
+    }
+    fn act(&mut self, action: A) {
+        self.agent.act(action);
+        self.save();
+    }
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M: Clone, A: PartialEq, D> Inspect for SafetyNet<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recover() {
+        let mut s = SafetyNet::new(crate::tests::four().add(1), |m| (m.0 as u64) << 32 | m.1 as u64);
+        s.update_model((4, 2));
+        // A mutation that is never undone moves the goal.
+        s.mutate();
+        assert_eq!(s.model(), &(3, 2));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!((s.model(), s.incidents.len()), (&(4, 2), 1));
+        assert_eq!(s.incidents[0], Incident {decision: 0, expected: 4 << 32 | 2, actual: 3 << 32 | 2});
+        s.act(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.incidents.len(), 1);
+    }
+}
@@ -0,0 +1,125 @@
+//! Agent registry with identities.
+//!
+//! A `Registry` assigns stable ids and names to agents.
+//! Ids are never reused, even after an agent is removed.
+//!
+//! Model updates, decisions and actions are routed by id.
+//! Routing to an unknown id returns `None`.
+
+use crate::{Agent, Decision};
+
+/// Identifies an agent in a registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AgentId(pub usize);
+
+/// Stores agents with identities.
+pub struct Registry<T> {
+    entries: Vec<Option<(String, T)>>,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {Registry {entries: vec![]}}
+}
+
+impl<T> Registry<T> {
+    /// Creates a new empty registry.
+    pub fn new() -> Self {Self::default()}
+
+    /// Adds a named agent, returning its id.
+    pub fn insert(&mut self, name: impl Into<String>, agent: T) -> AgentId {
+        self.entries.push(Some((name.into(), agent)));
+        AgentId(self.entries.len() - 1)
+    }
+
+    /// Removes an agent.
+    pub fn remove(&mut self, id: AgentId) -> Option<T> {
+        self.entries.get_mut(id.0)?.take().map(|(_, agent)| agent)
+    }
+
+    /// Returns the number of agents.
+    pub fn len(&self) -> usize {self.iter().count()}
+
+    /// Returns `true` if there are no agents.
+    pub fn is_empty(&self) -> bool {self.len() == 0}
+
+    /// Returns the id of the first agent with some name.
+    pub fn find(&self, name: &str) -> Option<AgentId> {
+        self.iter().find(|(_, n, _)| *n == name).map(|(id, _, _)| id)
+    }
+
+    /// Returns the name of an agent.
+    pub fn name(&self, id: AgentId) -> Option<&str> {
+        self.entries.get(id.0)?.as_ref().map(|(name, _)| &**name)
+    }
+
+    /// Returns an agent.
+    pub fn get(&self, id: AgentId) -> Option<&T> {
+        self.entries.get(id.0)?.as_ref().map(|(_, agent)| agent)
+    }
+
+    /// Returns a mutable agent.
+    pub fn get_mut(&mut self, id: AgentId) -> Option<&mut T> {
+        self.entries.get_mut(id.0)?.as_mut().map(|(_, agent)| agent)
+    }
+
+    /// Iterates over ids, names and agents.
+    pub fn iter(&self) -> impl Iterator<Item = (AgentId, &str, &T)> {
+        self.entries.iter().enumerate().filter_map(|(i, entry)| {
+            entry.as_ref().map(|(name, agent)| (AgentId(i), &**name, agent))
+        })
+    }
+
+    /// Returns the entries by id, where removed agents are `None`.
+    pub(crate) fn into_entries(self) -> Vec<Option<(String, T)>> {self.entries}
+
+    /// Creates a registry from entries by id.
+    pub(crate) fn from_entries(entries: Vec<Option<(String, T)>>) -> Self {Registry {entries}}
+
+    /// Iterates over ids, names and mutable agents.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (AgentId, &str, &mut T)> {
+        self.entries.iter_mut().enumerate().filter_map(|(i, entry)| {
+            entry.as_mut().map(|(name, agent)| (AgentId(i), &**name, agent))
+        })
+    }
+}
+
+impl<T: Agent> Registry<T> {
+    /// Updates the model of an agent.
+    pub fn update_model(&mut self, id: AgentId, model: T::Model) -> Option<()> {
+        self.get_mut(id).map(|agent| agent.update_model(model))
+    }
+
+    /// Decides what an agent does next.
+    pub fn decide(&mut self, id: AgentId) -> Option<Decision<T::Action>> {
+        self.get_mut(id).map(|agent| agent.decide())
+    }
+
+    /// Performs an action of an agent.
+    pub fn act(&mut self, id: AgentId, action: T::Action) -> Option<()> {
+        self.get_mut(id).map(|agent| agent.act(action))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_by_id() {
+        let z = crate::tests::four();
+        let mut registry = Registry::new();
+        let alice = registry.insert("alice", z.clone().add(0));
+        let bob = registry.insert("bob", z.add(1));
+        assert_eq!(registry.find("bob"), Some(bob));
+        assert_eq!(registry.decide(alice), Some(Decision::Action(1)));
+        registry.act(alice, 1);
+        assert_eq!(registry.get(alice).unwrap().z.model, (4, 1));
+
+        assert!(registry.remove(alice).is_some());
+        assert_eq!(registry.decide(alice), None);
+        let carol = registry.insert("carol", crate::tests::four().add(0));
+        assert_ne!(carol, alice);
+        let names: Vec<_> = registry.iter().map(|(_, name, _)| name).collect();
+        assert_eq!(names, vec!["bob", "carol"]);
+    }
+}
@@ -0,0 +1,141 @@
+//! Serialized deltas for audit and replay.
+//!
+//! When deltas implement `SerializeDelta`, an agent can journal every delta
+//! that is applied and undone while deciding, see `AgentN::journal_deltas`.
+//! `trace::Trace` attaches the journal of each decision to the recorded step,
+//! such that the exact mutation history of a decision can be inspected offline.
+//!
+//! Each journaled delta is serialized with the layer and the probe that applied or undid it,
+//! and can be parsed back with `DeltaRecord::parse`.
+
+use std::fmt;
+
+use crate::delta::DeltaMeta;
+use crate::AgentN;
+
+/// Implemented by deltas that can be serialized.
+pub trait SerializeDelta: Sized {
+    /// Returns the delta as a string.
+    fn serialize(&self) -> String;
+    /// Parses a delta, returning `None` if the string is not a valid delta.
+    fn deserialize(s: &str) -> Option<Self>;
+}
+
+macro_rules! serialize_parse {
+    ($($t:ty),*) => {$(
+        impl SerializeDelta for $t {
+            fn serialize(&self) -> String {self.to_string()}
+            fn deserialize(s: &str) -> Option<Self> {s.parse().ok()}
+        }
+    )*}
+}
+
+serialize_parse!(bool, u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl SerializeDelta for () {
+    fn serialize(&self) -> String {String::new()}
+    fn deserialize(s: &str) -> Option<Self> {if s.is_empty() {Some(())} else {None}}
+}
+
+/// Whether a delta was applied or undone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeltaOp {
+    /// The delta was applied by a mutater.
+    Apply,
+    /// The delta was undone by the undoer of core zero.
+    Undo,
+}
+
+/// Stores a delta that was applied or undone while deciding.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DeltaRecord {
+    /// The safety layer probing, where `1` is the innermost one.
+    pub layer: usize,
+    /// The index of the probe.
+    pub probe: u8,
+    /// Whether the delta was applied or undone.
+    pub op: DeltaOp,
+    /// The serialized delta.
+    pub delta: String,
+    /// The description of the delta, when the agent describes deltas.
+    pub meta: Option<DeltaMeta>,
+}
+
+impl DeltaRecord {
+    /// Parses the delta of the record.
+    pub fn parse<D: SerializeDelta>(&self) -> Option<D> {D::deserialize(&self.delta)}
+}
+
+impl fmt::Display for DeltaRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {DeltaOp::Apply => "apply", DeltaOp::Undo => "undo"};
+        write!(f, "layer {} #{} {} {}", self.layer, self.probe, op, self.delta)?;
+        if let Some(meta) = self.meta {write!(f, " ({})", meta)?}
+        Ok(())
+    }
+}
+
+/// Stores the journal of deltas of an agent.
+pub(crate) struct Journal<D> {
+    pub(crate) serialize: fn(&D) -> String,
+    pub(crate) records: Vec<DeltaRecord>,
+}
+
+impl<D> Clone for Journal<D> {
+    fn clone(&self) -> Self {Journal {serialize: self.serialize, records: self.records.clone()}}
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Journals every delta applied and undone while deciding.
+    pub fn journal_deltas(&mut self) where D: SerializeDelta {
+        self.journal = Some(Journal {serialize: D::serialize, records: vec![]});
+    }
+
+    /// Returns the deltas journaled by the last decide call.
+    ///
+    /// Returns an empty list when deltas are not journaled.
+    pub fn deltas(&self) -> &[DeltaRecord] {
+        self.journal.as_ref().map(|j| &j.records[..]).unwrap_or(&[])
+    }
+
+    /// Takes the deltas journaled by the last decide call.
+    pub fn take_deltas(&mut self) -> Vec<DeltaRecord> {
+        self.journal.as_mut().map(|j| std::mem::take(&mut j.records)).unwrap_or_default()
+    }
+
+    /// Journals a delta, when journaling.
+    pub(crate) fn journal(&mut self, layer: usize, probe: u8, op: DeltaOp, delta: &D) {
+        if let Some(j) = &mut self.journal {
+            let meta = self.describe.map(|describe| describe(delta));
+            let delta = (j.serialize)(delta);
+            j.records.push(DeltaRecord {layer, probe, op, delta, meta});
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agent;
+
+    #[test]
+    fn journal() {
+        let mut s = crate::tests::four().add(1);
+        s.journal_deltas();
+        assert_eq!(s.decide(), crate::Decision::Action(1));
+        assert_eq!(s.deltas(), &[
+            DeltaRecord {layer: 1, probe: 0, op: DeltaOp::Apply, delta: "-1".into(), meta: None},
+            DeltaRecord {layer: 1, probe: 0, op: DeltaOp::Undo, delta: "-1".into(), meta: None},
+        ]);
+        assert_eq!(s.deltas()[0].parse::<i32>(), Some(-1));
+        assert_eq!(s.deltas()[1].to_string(), "layer 1 #0 undo -1");
+        // A clone keeps journaling.
+        let mut t = s.clone();
+        t.decide();
+        assert_eq!(t.take_deltas().len(), 2);
+        assert!(t.deltas().is_empty());
+        t.describe = Some(|d| DeltaMeta::new("decrement", "goal", -*d as f64));
+        t.decide();
+        assert_eq!(t.deltas()[0].to_string(), "layer 1 #0 apply -1 (decrement of goal by 1)");
+    }
+}
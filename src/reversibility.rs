@@ -0,0 +1,90 @@
+//! Classification of irreversible actions.
+//!
+//! Several safety mechanisms treat irreversible actions with more caution,
+//! e.g. `commit::TwoPhase` prepares them before committing.
+//! The `Irreversibility` trait keeps the classification of actions in one place,
+//! such that every mechanism agrees on which actions can be undone.
+//!
+//! For action enums where the class does not depend on the model,
+//! the macro `irreversibility!` implements the trait from a list of patterns:
+//!
+//! ```
+//! use agent_safety_layers::irreversibility;
+//! use agent_safety_layers::reversibility::{Irreversibility, Reversibility};
+//!
+//! enum Action {Move(i32), Wait, Launch}
+//!
+//! irreversibility!(Action {
+//!     Action::Move(_) => Costly,
+//!     Action::Wait => Reversible,
+//!     Action::Launch => Irreversible,
+//! });
+//!
+//! assert_eq!(Action::class(&(), &Action::Launch), Reversibility::Irreversible);
+//! ```
+
+/// The reversibility class of an action.
+///
+/// Classes are ordered from least to most cautious.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Reversibility {
+    /// The action can be undone at no cost.
+    Reversible,
+    /// The action can be undone, but at some cost.
+    Costly,
+    /// The action can not be undone.
+    Irreversible,
+}
+
+/// Implemented by classifiers of the reversibility of actions.
+pub trait Irreversibility<M, A> {
+    /// Returns the reversibility class of an action in some model.
+    fn class(model: &M, action: &A) -> Reversibility;
+}
+
+/// Returns `true` if an action in some model is irreversible, using a classifier.
+///
+/// This can be used as a function pointer, e.g. `irreversible::<M, A, C>`.
+pub fn irreversible<M, A, C: Irreversibility<M, A>>(model: &M, action: &A) -> bool {
+    C::class(model, action) == Reversibility::Irreversible
+}
+
+/// Implements `Irreversibility` for an action type from a list of patterns and classes.
+#[macro_export]
+macro_rules! irreversibility {
+    ($t:ty {$($p:pat => $class:ident),* $(,)?}) => {
+        impl<M> $crate::reversibility::Irreversibility<M, $t> for $t {
+            fn class(_: &M, action: &$t) -> $crate::reversibility::Reversibility {
+                match action {$($p => $crate::reversibility::Reversibility::$class),*}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit::{Phase, TwoPhase};
+    use crate::{Agent, Decision};
+
+    // Moving past the state `2` can not be undone.
+    struct PastTwo;
+
+    impl Irreversibility<(u32, u32), i32> for PastTwo {
+        fn class(m: &(u32, u32), a: &i32) -> Reversibility {
+            if m.1 < 2 && m.1 as i32 + a >= 2 {Reversibility::Irreversible}
+            else if *a == 0 {Reversibility::Reversible}
+            else {Reversibility::Costly}
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let mut s = TwoPhase::classified::<PastTwo>(crate::tests::four().add(1));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.log, vec![Phase::Prepared(1)]);
+        assert!(PastTwo::class(&(4, 0), &0) < PastTwo::class(&(4, 0), &1));
+    }
+}
@@ -0,0 +1,153 @@
+//! Mutations of reward models.
+//!
+//! Utility-based agents often embed the goal as weights of reward terms.
+//! For such models, mutations of goals are mutations of weights:
+//!
+//! - `scale` multiplies the weight of a term by `RewardModel::SCALE`
+//! - `shift` adds `RewardModel::SHIFT` to the weight of a term
+//! - `drop_term` sets the weight of a term to zero
+//! - `permute` swaps the weights of two terms, permuting their priorities
+//!
+//! The terms are constant parameters, such that the mutaters can be used as function pointers,
+//! e.g. `scale::<M, 0>`.
+//! `RewardSampler` samples among these mutations for `Stochastic` mutaters.
+//!
+//! Every mutation returns a `RewardDelta` which is undone exactly by `undo`,
+//! by restoring the old weight instead of applying the inverse operation.
+
+use crate::rng::{MutationDistribution, Rng};
+
+/// Implemented by models that embed weights of reward terms.
+pub trait RewardModel {
+    /// The factor used by `scale`.
+    const SCALE: f64 = 2.0;
+    /// The offset used by `shift`.
+    const SHIFT: f64 = 1.0;
+
+    /// Returns the weights of reward terms.
+    fn weights(&mut self) -> &mut [f64];
+}
+
+/// Stores the change of a mutation of reward weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RewardDelta {
+    /// The weight of a term was changed.
+    Set {
+        /// The index of the term.
+        term: usize,
+        /// The old weight.
+        weight: f64,
+    },
+    /// The weights of two terms were swapped.
+    Swap(usize, usize),
+    /// No term was changed, because the index was out of range.
+    Unchanged,
+}
+
+fn set<M: RewardModel>(model: &mut M, term: usize, f: impl Fn(f64) -> f64) -> RewardDelta {
+    match model.weights().get_mut(term) {
+        Some(w) => {
+            let weight = *w;
+            *w = f(weight);
+            RewardDelta::Set {term, weight}
+        }
+        None => RewardDelta::Unchanged,
+    }
+}
+
+/// Multiplies the weight of a term by `RewardModel::SCALE`.
+pub fn scale<M: RewardModel, const TERM: usize>(model: &mut M) -> RewardDelta {set(model, TERM, |w| w * M::SCALE)}
+
+/// Adds `RewardModel::SHIFT` to the weight of a term.
+pub fn shift<M: RewardModel, const TERM: usize>(model: &mut M) -> RewardDelta {set(model, TERM, |w| w + M::SHIFT)}
+
+/// Sets the weight of a term to zero.
+pub fn drop_term<M: RewardModel, const TERM: usize>(model: &mut M) -> RewardDelta {set(model, TERM, |_| 0.0)}
+
+/// Swaps the weights of two terms.
+pub fn permute<M: RewardModel, const I: usize, const J: usize>(model: &mut M) -> RewardDelta {
+    let weights = model.weights();
+    if I >= weights.len() || J >= weights.len() {return RewardDelta::Unchanged}
+    weights.swap(I, J);
+    RewardDelta::Swap(I, J)
+}
+
+/// Undoes a mutation of reward weights exactly.
+pub fn undo<M: RewardModel>(model: &mut M, delta: RewardDelta) {
+    match delta {
+        RewardDelta::Set {term, weight} => model.weights()[term] = weight,
+        RewardDelta::Swap(i, j) => model.weights().swap(i, j),
+        RewardDelta::Unchanged => {}
+    }
+}
+
+/// Samples one of the mutations of reward weights, and the terms it changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RewardSampler;
+
+impl<M: RewardModel> MutationDistribution<M> for RewardSampler {
+    type Delta = RewardDelta;
+
+    fn mutate(model: &mut M, rng: &mut Rng) -> RewardDelta {
+        let n = model.weights().len() as u64;
+        if n == 0 {return RewardDelta::Unchanged}
+        let term = rng.below(n) as usize;
+        match rng.below(4) {
+            0 => set(model, term, |w| w * M::SCALE),
+            1 => set(model, term, |w| w + M::SHIFT),
+            2 => set(model, term, |_| 0.0),
+            _ => {
+                let other = rng.below(n) as usize;
+                model.weights().swap(term, other);
+                RewardDelta::Swap(term, other)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    /// Rewards progress towards `10` against the cost of moving.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Rewarded {
+        weights: [f64; 2],
+        state: i32,
+    }
+
+    impl RewardModel for Rewarded {
+        const SCALE: f64 = 0.1;
+        fn weights(&mut self) -> &mut [f64] {&mut self.weights}
+    }
+
+    fn decider(m: &Rewarded) -> i32 {
+        let reward = |a: i32| -m.weights[0] * (10 - m.state - a).abs() as f64 - m.weights[1] * a.abs() as f64;
+        if reward(1) > reward(0) {1} else {0}
+    }
+
+    #[test]
+    fn mutate_weights() {
+        let model = Rewarded {weights: [0.3, 0.1], state: 0};
+        let mut m = model.clone();
+        for mutater in [scale::<Rewarded, 0>, shift::<_, 1>, drop_term::<_, 1>, permute::<_, 0, 1>, scale::<_, 2>] {
+            let delta = mutater(&mut m);
+            undo(&mut m, delta);
+            assert_eq!(m, model);
+        }
+        let mut rng = Rng::new(0);
+        for _ in 0..10 {
+            let delta = RewardSampler::mutate(&mut m, &mut rng);
+            undo(&mut m, delta);
+            assert_eq!(m, model);
+        }
+
+        let z = AgentZ {model, decider, actor: |m, a| m.state += a, mutater: drop_term::<_, 1>, undoer: undo};
+        let mut s = z.add(1);
+        assert_eq!(s.decide(), Decision::Action(1));
+        // Caring less about progress would make moving not worth the cost.
+        s.mutaters = vec![scale::<_, 0>];
+        assert_eq!(s.decide(), Decision::RequestModel);
+    }
+}
@@ -0,0 +1,259 @@
+//! Seeded randomness for stochastic mutaters.
+//!
+//! A stochastic mutater receives a random number generator owned by the agent,
+//! seeded at construction.
+//! Runs with the same seed probe the same mutations,
+//! which makes stochastic mutation strategies reproducible.
+//!
+//! Mutations can also be sampled from a distribution implementing `MutationDistribution`,
+//! for example a Gaussian perturbation of some parameter, or a random swap of goals.
+//! Every probe draws a fresh sample,
+//! such that probing covers the mutation space stochastically instead of a fixed cycle.
+//!
+//! By default, the generator continues from one decide call to the next,
+//! so the probed mutations depend on the history of the agent.
+//! With a `Schedule`, the generator is reseeded on every decide call
+//! from a seed plus a fingerprint of the model.
+//! Replays and distributed workers then probe exactly the same mutations in the same order.
+//!
+//! The generator is SplitMix64, which is fast, small and good enough for sampling mutations.
+//! It is not suitable for cryptography.
+
+use std::fmt;
+use std::ptr::fn_addr_eq;
+
+/// Stores a seeded pseudo-random number generator.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a new generator from a seed.
+    pub fn new(seed: u64) -> Self {Rng {state: seed}}
+
+    /// Returns the next random number.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a random number in `[0, n)`, where `n` is greater than zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// Returns a random number from the standard normal distribution.
+    pub fn normal(&mut self) -> f64 {
+        // Box-Muller transform, using `1 - x` to avoid the logarithm of zero.
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Returns a random element of a non-empty slice.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+}
+
+/// Implemented by distributions of mutations.
+///
+/// Parameters of a distribution are associated constants of the implementing type,
+/// such that `mutate` can be used as a function pointer.
+pub trait MutationDistribution<M> {
+    /// The type of deltas.
+    type Delta;
+
+    /// Samples a mutation and applies it, returning the delta.
+    fn mutate(model: &mut M, rng: &mut Rng) -> Self::Delta;
+}
+
+/// Stores a deterministic schedule of mutations.
+pub struct Schedule<M> {
+    /// The seed.
+    pub seed: u64,
+    /// Returns a fingerprint of the model.
+    pub fingerprint: fn(&M) -> u64,
+}
+
+impl<M> Schedule<M> {
+    /// Returns the generator for deciding on a model.
+    pub fn rng(&self, model: &M) -> Rng {
+        Rng::new(Rng::new(self.seed).next_u64() ^ (self.fingerprint)(model))
+    }
+}
+
+impl<M> Clone for Schedule<M> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M> Copy for Schedule<M> {}
+
+impl<M> fmt::Debug for Schedule<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schedule")
+            .field("seed", &self.seed)
+            .field("fingerprint", &self.fingerprint)
+            .finish()
+    }
+}
+
+impl<M> PartialEq for Schedule<M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.seed == other.seed && fn_addr_eq(self.fingerprint, other.fingerprint)
+    }
+}
+
+/// Stores a stochastic mutater with its generator.
+pub struct Stochastic<M, D> {
+    /// The seed of the generator.
+    pub seed: u64,
+    /// The generator.
+    pub rng: Rng,
+    /// Mutates the model using the generator.
+    pub mutater: fn(&mut M, &mut Rng) -> D,
+    /// Reseeds the generator on every decide call when set.
+    pub schedule: Option<Schedule<M>>,
+}
+
+impl<M, D> Stochastic<M, D> {
+    /// Creates a new stochastic mutater seeded with `seed`.
+    pub fn new(seed: u64, mutater: fn(&mut M, &mut Rng) -> D) -> Self {
+        Stochastic {seed, rng: Rng::new(seed), mutater, schedule: None}
+    }
+
+    /// Creates a new stochastic mutater following a schedule.
+    pub fn scheduled(schedule: Schedule<M>, mutater: fn(&mut M, &mut Rng) -> D) -> Self {
+        Stochastic {schedule: Some(schedule), ..Self::new(schedule.seed, mutater)}
+    }
+
+    /// Creates a new stochastic mutater sampling from a distribution.
+    pub fn sampled<T: MutationDistribution<M, Delta = D>>(seed: u64) -> Self {
+        Self::new(seed, T::mutate)
+    }
+
+    /// Resets the generator to its seed.
+    pub fn reseed(&mut self) {self.rng = Rng::new(self.seed)}
+}
+
+impl<M, D> Clone for Stochastic<M, D> {
+    fn clone(&self) -> Self {
+        Stochastic {
+            seed: self.seed,
+            rng: self.rng.clone(),
+            mutater: self.mutater,
+            schedule: self.schedule,
+        }
+    }
+}
+
+impl<M, D> fmt::Debug for Stochastic<M, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stochastic")
+            .field("seed", &self.seed)
+            .field("rng", &self.rng)
+            .field("mutater", &self.mutater)
+            .field("schedule", &self.schedule)
+            .finish()
+    }
+}
+
+impl<M, D> PartialEq for Stochastic<M, D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.seed == other.seed && self.rng == other.rng &&
+        fn_addr_eq(self.mutater, other.mutater) && self.schedule == other.schedule
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn reproducible() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        assert_eq!(a.next_u64(), b.next_u64());
+        assert!(a.below(3) < 3);
+        assert!(a.next_f64() < 1.0);
+
+        // Mutate the goal by a random amount, deciding the same in every run.
+        let run = || {
+            let mut s = crate::tests::four().add(1);
+            s.update_model((4, 2));
+            s.stochastic = Some(Stochastic::new(42, |m: &mut (u32, u32), rng| {
+                let d = 1 + rng.below(2) as u32;
+                m.0 -= d;
+                d as i32
+            }));
+            s.z.undoer = |m, d| m.0 += d as u32;
+            (0..8).map(|_| s.decide()).collect::<Vec<_>>()
+        };
+        let decisions = run();
+        assert_eq!(decisions, run());
+        assert!(decisions.contains(&Decision::Action(1)));
+        assert!(decisions.contains(&Decision::RequestModel));
+    }
+
+    #[test]
+    fn schedule() {
+        let schedule = Schedule {seed: 5, fingerprint: |m: &(u32, u32)| (m.0 as u64) << 32 | m.1 as u64};
+        let sample = |m: &mut (u32, u32), rng: &mut Rng| {
+            let d = rng.below(2) as u32;
+            m.0 -= d;
+            d as i32
+        };
+        let mut s = crate::tests::four().add(1);
+        s.z.undoer = |m, d| m.0 += d as u32;
+        s.update_model((4, 3));
+        s.stochastic = Some(Stochastic::scheduled(schedule, sample));
+        // The same model probes the same mutations, regardless of history.
+        let first = s.decide();
+        for _ in 0..8 {assert_eq!(s.decide(), first)}
+        s.update_model((4, 1));
+        for _ in 0..3 {s.decide();}
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), first);
+    }
+
+    /// Perturbs the goal with Gaussian noise of standard deviation `1`.
+    struct GoalNoise;
+
+    impl MutationDistribution<(u32, u32)> for GoalNoise {
+        type Delta = i32;
+        fn mutate(m: &mut (u32, u32), rng: &mut Rng) -> i32 {
+            let noise = rng.normal().round() as i32;
+            let old = m.0;
+            m.0 = (m.0 as i32 + noise).max(0) as u32;
+            old as i32 - m.0 as i32
+        }
+    }
+
+    #[test]
+    fn distribution() {
+        let mut rng = Rng::new(1);
+        let mean = (0..1000).map(|_| rng.normal()).sum::<f64>() / 1000.0;
+        assert!(mean.abs() < 0.1);
+        assert_eq!(*rng.choose(&[3]), 3);
+
+        let mut s = crate::tests::four().add(1);
+        s.update_model((4, 3));
+        s.stochastic = Some(Stochastic::sampled::<GoalNoise>(3));
+        s.z.undoer = |m, d| m.0 = (m.0 as i32 + d) as u32;
+        let decisions: Vec<_> = (0..16).map(|_| s.decide()).collect();
+        // Noise that keeps the goal agrees, noise that moves it down disagrees.
+        assert!(decisions.contains(&Decision::Action(1)));
+        assert!(decisions.contains(&Decision::RequestModel));
+        assert_eq!(s.z.model, (4, 3));
+    }
+}
@@ -0,0 +1,153 @@
+//! Scheduled perturbations for robustness testing.
+//!
+//! A layered agent should notice when the environment changes under it,
+//! e.g. when the goal flips or the state jumps, and request a model update instead of acting.
+//! The function `inject_perturbations` runs an agent in an environment,
+//! applies a schedule of named perturbations to the environment before some steps,
+//! and measures the detection latency of every perturbation:
+//! the number of steps the agent acted on its stale model before requesting a model update.
+//!
+//! Halting also counts as detection, since the agent does not act.
+//! A model request detects every perturbation injected so far,
+//! even when the agent was uncertain for other reasons.
+//! Steps are counted from `1`.
+
+use std::fmt;
+
+use crate::environment::{step, Environment, StepOutcome};
+use crate::Agent;
+
+/// Stores a perturbation of an environment before some step.
+pub struct Scheduled<E> {
+    /// The name of the perturbation, e.g. `"goal flip"` or `"state jump"`.
+    pub name: &'static str,
+    /// The step before which the perturbation is applied.
+    pub step: usize,
+    /// Perturbs the environment.
+    pub perturb: fn(&mut E),
+}
+
+impl<E> Clone for Scheduled<E> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<E> Copy for Scheduled<E> {}
+
+impl<E> fmt::Debug for Scheduled<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduled").field("name", &self.name).field("step", &self.step).finish()
+    }
+}
+
+/// Stores the detection of a perturbation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Detection {
+    /// The name of the perturbation.
+    pub name: &'static str,
+    /// The step before which the perturbation was applied.
+    pub step: usize,
+    /// The number of actions before the agent requested a model update or halted,
+    /// or `None` if it never did.
+    pub latency: Option<usize>,
+}
+
+/// Stores the result of injecting perturbations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RobustnessReport {
+    /// The number of steps used.
+    pub steps: usize,
+    /// Whether the agent halted.
+    pub halted: bool,
+    /// The detections, in the order of the schedule.
+    ///
+    /// Perturbations scheduled after the last step are not included.
+    pub detections: Vec<Detection>,
+}
+
+impl RobustnessReport {
+    /// Returns the number of perturbations that were never detected.
+    pub fn undetected(&self) -> usize {self.detections.iter().filter(|d| d.latency.is_none()).count()}
+
+    /// Returns the mean detection latency of the detected perturbations with some name.
+    pub fn mean_latency(&self, name: &str) -> Option<f64> {
+        let latencies: Vec<usize> = self.detections.iter()
+            .filter(|d| d.name == name).filter_map(|d| d.latency).collect();
+        if latencies.is_empty() {None}
+        else {Some(latencies.iter().sum::<usize>() as f64 / latencies.len() as f64)}
+    }
+
+    /// Returns the maximum detection latency, or `None` if some perturbation was never detected.
+    pub fn max_latency(&self) -> Option<usize> {
+        self.detections.iter().try_fold(0, |max, d| d.latency.map(|l| max.max(l)))
+    }
+
+    /// Returns `true` if every perturbation was detected within some number of actions.
+    pub fn is_robust(&self, max_latency: usize) -> bool {
+        self.max_latency().map(|l| l <= max_latency).unwrap_or(false)
+    }
+}
+
+/// Runs an agent in an environment for some steps, applying scheduled perturbations.
+///
+/// The run stops early when the agent halts.
+pub fn inject_perturbations<T, E>(
+    agent: &mut T,
+    env: &mut E,
+    schedule: &[Scheduled<E>],
+    max_steps: usize
+) -> RobustnessReport
+    where T: Agent, E: Environment<Model = T::Model, Action = T::Action>, T::Action: Clone
+{
+    let mut report = RobustnessReport::default();
+    let mut pending = vec![];
+    while report.steps < max_steps {
+        report.steps += 1;
+        let k = report.steps;
+        for p in schedule.iter().filter(|p| p.step == k) {
+            (p.perturb)(env);
+            pending.push(report.detections.len());
+            report.detections.push(Detection {name: p.name, step: p.step, latency: None});
+        }
+        let outcome = step(agent, env);
+        if let StepOutcome::Acted(_) = outcome {continue}
+        for i in pending.drain(..) {
+            let d = &mut report.detections[i];
+            d.latency = Some(report.steps - d.step);
+        }
+        if let StepOutcome::Halted = outcome {
+            report.halted = true;
+            break;
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The environment of `crate::tests::four`, storing the goal and the state.
+    struct World(u32, u32);
+
+    impl Environment for World {
+        type Model = (u32, u32);
+        type Action = i32;
+        fn model(&mut self) -> (u32, u32) {(self.0, self.1)}
+        fn act(&mut self, action: &i32) {self.1 = (self.1 as i32 + action) as u32}
+    }
+
+    #[test]
+    fn latency() {
+        let schedule = [
+            Scheduled {name: "goal flip", step: 2, perturb: |w: &mut World| w.0 = 2},
+            Scheduled {name: "state jump", step: 8, perturb: |w: &mut World| w.1 += 3},
+        ];
+        let report = inject_perturbations(&mut crate::tests::four().add(1), &mut World(4, 0), &schedule, 10);
+        assert_eq!(report.detections[0], Detection {name: "goal flip", step: 2, latency: Some(2)});
+        assert_eq!(report.mean_latency("goal flip"), Some(2.0));
+        assert!(report.is_robust(2) && !report.is_robust(1));
+
+        let report = inject_perturbations(&mut crate::tests::four().add(0), &mut World(4, 0), &schedule, 10);
+        assert_eq!((report.undetected(), report.max_latency()), (2, None));
+    }
+}
@@ -0,0 +1,137 @@
+//! Rollback of recent actions.
+//!
+//! When a model update reveals that the last actions were based on a wrong goal,
+//! stopping is not always enough, since the actions might have moved the world in the wrong direction.
+//! A `ReversibleActor` performs an action and returns a record that undoes it.
+//!
+//! A `Reversible` agent keeps the records of the most recent actions, up to a depth,
+//! such that `Reversible::rollback` can actively unwind them on the internal model.
+//! The undone records are returned, such that the caller can unwind them in the environment.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::{Agent, AgentN, Decision, Inspect};
+
+/// Implemented by actors that can undo their actions.
+pub trait ReversibleActor<M, A> {
+    /// The type of records that undo actions.
+    type Record: Clone;
+
+    /// Performs an action, returning a record that undoes it.
+    fn act(model: &mut M, action: A) -> Self::Record;
+    /// Undoes an action using its record.
+    fn undo(model: &mut M, record: Self::Record);
+}
+
+/// Performs an action with a reversible actor, discarding the record.
+///
+/// This can be used as a function pointer, e.g. `act::<M, A, R>`.
+pub fn act<M, A, R: ReversibleActor<M, A>>(model: &mut M, action: A) {R::act(model, action);}
+
+/// Stores an agent that can roll back its most recent actions.
+pub struct Reversible<M, A, D, R: ReversibleActor<M, A>> {
+    /// The inner agent.
+    pub agent: AgentN<M, A, D>,
+    /// The maximum number of actions that can be rolled back.
+    pub depth: usize,
+    /// The records of the most recent actions, the last one being the latest.
+    pub history: VecDeque<R::Record>,
+    actor: PhantomData<fn() -> R>,
+}
+
+impl<M, A, D, R: ReversibleActor<M, A>> Clone for Reversible<M, A, D, R> where M: Clone, A: Clone {
+    fn clone(&self) -> Self {
+        Reversible {agent: self.agent.clone(), depth: self.depth, history: self.history.clone(), actor: PhantomData}
+    }
+}
+
+impl<M, A, D, R: ReversibleActor<M, A>> fmt::Debug for Reversible<M, A, D, R>
+    where M: fmt::Debug, A: fmt::Debug, R::Record: fmt::Debug
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reversible")
+            .field("agent", &self.agent)
+            .field("depth", &self.depth)
+            .field("history", &self.history)
+            .finish()
+    }
+}
+
+impl<M, A, D, R: ReversibleActor<M, A>> Reversible<M, A, D, R> {
+    /// Creates a new agent that can roll back up to `depth` actions.
+    ///
+    /// The actor of core zero is replaced by the reversible actor.
+    pub fn new(mut agent: AgentN<M, A, D>, depth: usize) -> Self {
+        agent.z.actor = act::<M, A, R>;
+        Reversible {agent, depth, history: VecDeque::new(), actor: PhantomData}
+    }
+
+    /// Rolls back up to `k` of the most recent actions on the internal model.
+    ///
+    /// Returns the undone records, the latest action first.
+    pub fn rollback(&mut self, k: usize) -> Vec<R::Record> {
+        let mut undone = vec![];
+        for _ in 0..k {
+            match self.history.pop_back() {
+                Some(record) => {
+                    R::undo(&mut self.agent.z.model, record.clone());
+                    undone.push(record);
+                }
+                None => break,
+            }
+        }
+        undone
+    }
+}
+
+impl<M, A: PartialEq, D, R: ReversibleActor<M, A>> Agent for Reversible<M, A, D, R> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {self.agent.decide()}
+    fn act(&mut self, action: A) {
+        let record = R::act(&mut self.agent.z.model, action);
+        self.history.push_back(record);
+        while self.history.len() > self.depth {self.history.pop_front();}
+    }
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D, R: ReversibleActor<M, A>> Inspect for Reversible<M, A, D, R> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Step;
+
+    impl ReversibleActor<(u32, u32), i32> for Step {
+        type Record = i32;
+        fn act(m: &mut (u32, u32), a: i32) -> i32 {
+            m.1 = (m.1 as i32 + a) as u32;
+            -a
+        }
+        fn undo(m: &mut (u32, u32), a: i32) {m.1 = (m.1 as i32 + a) as u32}
+    }
+
+    #[test]
+    fn unwind() {
+        let mut s: Reversible<_, _, _, Step> = Reversible::new(crate::tests::four().add(1), 2);
+        for _ in 0..3 {
+            let a = match s.decide() {Decision::Action(a) => a, _ => panic!()};
+            s.act(a);
+        }
+        assert_eq!(s.model(), &(4, 3));
+        // The goal was `1` after all.
+        s.update_model((1, 3));
+        assert_eq!(s.rollback(3), vec![-1, -1]);
+        assert_eq!(s.model(), &(1, 1));
+        assert_eq!(s.agent.simulate(1), (1, 2));
+    }
+}
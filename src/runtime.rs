@@ -0,0 +1,103 @@
+//! Running agents on their own thread.
+//!
+//! The function `spawn_agent` moves an agent to a new thread,
+//! which decides, acts on its internal model and sends every decision over a channel.
+//! When the agent requests a model update or halts, the thread waits for a new model.
+//! Models received while acting are applied before the next decide, the latest one winning.
+//!
+//! The decision channel is bounded, such that the agent does not run ahead of the environment.
+
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+use crate::{Agent, Decision};
+
+enum Message<M> {
+    Model(M),
+    Shutdown,
+}
+
+/// Stores a handle to an agent running on its own thread.
+pub struct AgentThread<T: Agent> {
+    models: Sender<Message<T::Model>>,
+    decisions: Receiver<Decision<T::Action>>,
+    thread: JoinHandle<T>,
+}
+
+/// Runs an agent on its own thread.
+///
+/// At most `bound` decisions are buffered before the agent waits for them to be received.
+pub fn spawn_agent<T>(agent: T, bound: usize) -> AgentThread<T>
+    where T: Agent + Send + 'static, T::Model: Send, T::Action: Clone + Send
+{
+    let (models, models_rx) = mpsc::channel();
+    let (decisions_tx, decisions) = mpsc::sync_channel(bound);
+    let thread = thread::spawn(move || run(agent, models_rx, decisions_tx));
+    AgentThread {models, decisions, thread}
+}
+
+fn run<T>(mut agent: T, models: Receiver<Message<T::Model>>, decisions: SyncSender<Decision<T::Action>>) -> T
+    where T: Agent, T::Action: Clone
+{
+    loop {
+        let mut model = None;
+        loop {
+            match models.try_recv() {
+                Ok(Message::Model(m)) => model = Some(m),
+                Ok(Message::Shutdown) | Err(TryRecvError::Disconnected) => return agent,
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+        if let Some(m) = model {agent.update_model(m)}
+
+        let decision = agent.decide();
+        if let Decision::Action(a) = &decision {agent.act(a.clone())}
+        let wait = !matches!(decision, Decision::Action(_));
+        if decisions.send(decision).is_err() {return agent}
+        if wait {
+            match models.recv() {
+                Ok(Message::Model(m)) => agent.update_model(m),
+                Ok(Message::Shutdown) | Err(_) => return agent,
+            }
+        }
+    }
+}
+
+impl<T: Agent> AgentThread<T> {
+    /// Sends a model update.
+    ///
+    /// Returns `false` if the agent is no longer running.
+    pub fn send_model(&self, model: T::Model) -> bool {
+        self.models.send(Message::Model(model)).is_ok()
+    }
+
+    /// Receives the next decision, blocking until one is available.
+    ///
+    /// Returns `None` if the agent is no longer running.
+    pub fn recv_decision(&self) -> Option<Decision<T::Action>> {self.decisions.recv().ok()}
+
+    /// Stops the agent and returns it.
+    ///
+    /// Returns an error if the agent thread panicked.
+    pub fn shutdown(self) -> thread::Result<T> {
+        let _ = self.models.send(Message::Shutdown);
+        // Unblocks the agent when it waits for a decision to be received.
+        drop(self.decisions);
+        self.thread.join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive() {
+        let agent = spawn_agent(crate::tests::four().add(1), 1);
+        for _ in 0..3 {assert_eq!(agent.recv_decision(), Some(Decision::Action(1)))}
+        assert_eq!(agent.recv_decision(), Some(Decision::RequestModel));
+        assert!(agent.send_model((2, 0)));
+        assert_eq!(agent.recv_decision(), Some(Decision::Action(1)));
+        assert_eq!(agent.shutdown().unwrap().z.model.0, 2);
+    }
+}
@@ -0,0 +1,189 @@
+//! Sandboxed external deciders with resource limits.
+//!
+//! A decider running in a subprocess might be untrusted,
+//! and could stall or crash the agent that uses it.
+//! A `Sandbox` runs a program for every decision, writing the encoded model to its standard input
+//! and parsing the action from its standard output.
+//!
+//! The program is killed when it runs longer than the wall-clock limit.
+//! On Unix, the memory limit is enforced with `ulimit -v` in a shell,
+//! such that allocations beyond it fail.
+//! Violations are recorded and turned into model requests by `SandboxAgent`.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores resource limits of a sandboxed decider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The maximum wall-clock time of a decision.
+    pub wall: Duration,
+    /// The maximum virtual memory in bytes, if any.
+    pub memory: Option<u64>,
+}
+
+/// Stores a violation by a sandboxed decider.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// The program could not be started.
+    Spawn(String),
+    /// The program ran longer than the wall-clock limit, and was killed.
+    Timeout,
+    /// The program exited with an error status, or was killed by a signal.
+    Crash(Option<i32>),
+    /// The output of the program was not a valid action.
+    Invalid(String),
+}
+
+/// Stores a decider that runs in a subprocess.
+#[derive(Clone, Debug)]
+pub struct Sandbox<M, A> {
+    /// The program.
+    pub program: String,
+    /// The arguments.
+    pub args: Vec<String>,
+    /// The resource limits.
+    pub limits: Limits,
+    /// Encodes a model for the standard input of the program.
+    pub encode: fn(&M) -> String,
+    /// Parses an action from the standard output of the program.
+    pub parse: fn(&str) -> Option<A>,
+}
+
+impl<M, A> Sandbox<M, A> {
+    fn command(&self) -> Command {
+        match self.limits.memory {
+            Some(bytes) if cfg!(unix) => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(format!("ulimit -v {} && exec \"$0\" \"$@\"", bytes / 1024));
+                cmd.arg(&self.program).args(&self.args);
+                cmd
+            }
+            _ => {
+                let mut cmd = Command::new(&self.program);
+                cmd.args(&self.args);
+                cmd
+            }
+        }
+    }
+
+    /// Runs the program to decide an action for a model.
+    pub fn run(&self, model: &M) -> Result<A, Violation> {
+        let mut child = self.command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| Violation::Spawn(err.to_string()))?;
+        // Input and output are handled on their own threads, such that full pipes do not stall.
+        let input = (self.encode)(model);
+        let mut stdin = child.stdin.take().expect("Piped stdin");
+        let writer = thread::spawn(move || {let _ = stdin.write_all(input.as_bytes());});
+        let mut stdout = child.stdout.take().expect("Piped stdout");
+        let reader = thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stdout.read_to_string(&mut output);
+            output
+        });
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if start.elapsed() >= self.limits.wall => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Violation::Timeout);
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(1)),
+                Err(err) => return Err(Violation::Spawn(err.to_string())),
+            }
+        };
+        let _ = writer.join();
+        let output = reader.join().unwrap_or_default();
+        if !status.success() {return Err(Violation::Crash(status.code()))}
+        (self.parse)(output.trim()).ok_or(Violation::Invalid(output))
+    }
+}
+
+/// Stores an agent that decides using a sandboxed decider.
+#[derive(Clone, Debug)]
+pub struct SandboxAgent<M, A, D> {
+    /// The internal model.
+    pub model: M,
+    /// The sandboxed decider.
+    pub sandbox: Sandbox<M, A>,
+    /// Performs an action on the model.
+    pub actor: fn(&mut M, A),
+    /// Mutates the model.
+    pub mutater: fn(&mut M) -> D,
+    /// Undoes a mutation.
+    pub undoer: fn(&mut M, D),
+    /// The violation of the last decision, if any.
+    pub violation: Option<Violation>,
+}
+
+impl<M, A, D> Agent for SandboxAgent<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.model = model}
+    fn decide(&mut self) -> Decision<A> {
+        match self.sandbox.run(&self.model) {
+            Ok(a) => {
+                self.violation = None;
+                Decision::Action(a)
+            }
+            Err(violation) => {
+                self.violation = Some(violation);
+                Decision::RequestModel
+            }
+        }
+    }
+    fn act(&mut self, action: A) {(self.actor)(&mut self.model, action)}
+    fn mutate(&mut self) -> D {(self.mutater)(&mut self.model)}
+    fn undo(&mut self, delta: D) {(self.undoer)(&mut self.model, delta)}
+}
+
+impl<M, A, D> Inspect for SandboxAgent<M, A, D> {
+    fn model(&self) -> &M {&self.model}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sandbox(script: &str) -> Sandbox<(u32, u32), i32> {
+        Sandbox {
+            program: "sh".into(),
+            args: vec!["-c".into(), script.into()],
+            limits: Limits {wall: Duration::from_millis(200), memory: Some(1 << 30)},
+            encode: |m| format!("{} {}\n", m.0, m.1),
+            parse: |s| s.parse().ok(),
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let run = |script| sandbox(script).run(&(4, 0));
+        assert_eq!(run("read goal state; [ $state -lt $goal ] && echo 1"), Ok(1));
+        assert_eq!(run("sleep 5"), Err(Violation::Timeout));
+        assert_eq!(run("exit 3"), Err(Violation::Crash(Some(3))));
+        assert_eq!(run("echo left"), Err(Violation::Invalid("left\n".into())));
+
+        let z = crate::tests::four();
+        let mut s = SandboxAgent {
+            model: z.model,
+            sandbox: sandbox("sleep 5"),
+            actor: z.actor,
+            mutater: z.mutater,
+            undoer: z.undoer,
+            violation: None,
+        };
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.violation, Some(Violation::Timeout));
+    }
+}
@@ -0,0 +1,106 @@
+//! Named model checkpoints.
+//!
+//! An exploratory phase, e.g. trying out a plan on the internal model,
+//! should be abandoned atomically when it turns out to be a bad idea.
+//! A `Savepoints` agent brackets such phases with named checkpoints of the model,
+//! kept on a stack with a maximum depth, where the oldest checkpoint is dropped first.
+//!
+//! `Savepoints::rollback_to` restores the model of a checkpoint as a model update,
+//! discarding all later checkpoints.
+//! Unlike `checkpoint::Checkpointed`, the checkpoints are kept in memory and are not serialized.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an agent with named checkpoints of its model.
+#[derive(Clone, Debug)]
+pub struct Savepoints<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The maximum number of checkpoints.
+    pub depth: usize,
+    /// The checkpoints, the last one being the latest.
+    pub stack: Vec<(&'static str, T::Model)>,
+}
+
+impl<T: Inspect> Savepoints<T> where T::Model: Clone {
+    /// Creates a new agent keeping up to `depth` checkpoints.
+    pub fn new(agent: T, depth: usize) -> Self {Savepoints {agent, depth, stack: vec![]}}
+
+    /// Saves the current model as a named checkpoint.
+    pub fn checkpoint(&mut self, name: &'static str) {
+        self.stack.push((name, self.agent.model().clone()));
+        if self.stack.len() > self.depth {
+            let n = self.stack.len() - self.depth;
+            self.stack.drain(..n);
+        }
+    }
+
+    /// Restores the model of the latest checkpoint with some name, keeping the checkpoint.
+    ///
+    /// Later checkpoints are discarded.
+    /// Returns `false` if there is no such checkpoint.
+    pub fn rollback_to(&mut self, name: &'static str) -> bool {
+        match self.stack.iter().rposition(|(n, _)| *n == name) {
+            Some(i) => {
+                self.stack.truncate(i + 1);
+                self.agent.update_model(self.stack[i].1.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Discards the latest checkpoint with some name, and all later checkpoints.
+    ///
+    /// Returns `false` if there is no such checkpoint.
+    pub fn release(&mut self, name: &'static str) -> bool {
+        match self.stack.iter().rposition(|(n, _)| *n == name) {
+            Some(i) => {
+                self.stack.truncate(i);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T: Inspect> Agent for Savepoints<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {self.agent.decide()}
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Savepoints<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bracket() {
+        let mut s = Savepoints::new(crate::tests::four().add(1), 2);
+        s.checkpoint("start");
+        s.act(1);
+        s.checkpoint("explore");
+        s.act(1);
+        assert!(s.rollback_to("explore"));
+        assert_eq!(s.model(), &(4, 1));
+        assert!(s.rollback_to("start"));
+        assert_eq!(s.model(), &(4, 0));
+        assert!(!s.rollback_to("explore"));
+
+        s.checkpoint("a");
+        s.checkpoint("b");
+        // The oldest checkpoint is dropped.
+        assert_eq!(s.stack.iter().map(|(n, _)| *n).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(s.release("a"));
+        assert!(s.stack.is_empty());
+    }
+}
@@ -0,0 +1,237 @@
+//! Scenarios described as data files.
+//!
+//! Safety test suites can be authored as data instead of Rust test functions,
+//! such that they can be shared across teams.
+//! A `Scenario` is stored as JSON, where models are encoded as strings by user-supplied codecs:
+//!
+//! ```text
+//! {"name":"goal moves","initial":"4,0","max_steps":10,
+//!  "observations":[{"step":2,"model":"3,1"}],
+//!  "perturbations":[{"step":3,"name":"push"}],
+//!  "goals":["at goal"]}
+//! ```
+//!
+//! - `initial`: the model of the environment and the agent at the start
+//! - `observations`: replace the model of the environment before some step
+//! - `perturbations`: change the model of the environment before some step, by name
+//! - `goals`: the episode ends when all goal predicates hold on the model of the environment
+//!
+//! Steps are counted from `1`.
+//! Goal predicates, perturbations and the actor of the environment are given by a `Library`,
+//! and `run_scenario` runs an agent in the scenario.
+
+use std::fmt::{self, Write};
+
+use crate::environment::{step, Environment, RunReport, StepOutcome};
+use crate::{json, Error, Inspect};
+
+/// Stores a scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario<M> {
+    /// The name of the scenario.
+    pub name: String,
+    /// The initial model.
+    pub initial: M,
+    /// The maximum number of steps.
+    pub max_steps: usize,
+    /// The models that replace the model of the environment before some steps.
+    pub observations: Vec<(usize, M)>,
+    /// The names of perturbations of the model of the environment before some steps.
+    pub perturbations: Vec<(usize, String)>,
+    /// The names of the goal predicates.
+    pub goals: Vec<String>,
+}
+
+fn invalid(msg: &str) -> Error {Error::Protocol(format!("Invalid scenario: {}", msg))}
+
+fn count(value: &json::Value, key: &str) -> Result<usize, Error> {
+    value.get(key).and_then(|v| v.num()).filter(|x| *x >= 0.0 && x.fract() == 0.0)
+        .map(|x| x as usize).ok_or_else(|| invalid(&format!("Expected {}", key)))
+}
+
+impl<M> Scenario<M> {
+    /// Parses a scenario from JSON, decoding models from strings.
+    ///
+    /// Missing lists are empty.
+    pub fn from_json(src: &str, decode: fn(&str) -> Option<M>) -> Result<Scenario<M>, Error> {
+        let value = json::parse(src).ok_or_else(|| invalid("Expected JSON"))?;
+        let model = |v: &json::Value| v.get("model").and_then(|m| m.str()).and_then(decode)
+            .ok_or_else(|| invalid("Expected model"));
+        let list = |key: &str| value.get(key).and_then(|v| v.array()).unwrap_or(&[]);
+        let name = |v: &json::Value| v.get("name").and_then(|n| n.str()).map(String::from)
+            .ok_or_else(|| invalid("Expected name"));
+        Ok(Scenario {
+            name: name(&value)?,
+            initial: value.get("initial").and_then(|v| v.str()).and_then(decode)
+                .ok_or_else(|| invalid("Expected initial"))?,
+            max_steps: count(&value, "max_steps")?,
+            observations: list("observations").iter().map(|v| Ok((count(v, "step")?, model(v)?)))
+                .collect::<Result<_, Error>>()?,
+            perturbations: list("perturbations").iter().map(|v| Ok((count(v, "step")?, name(v)?)))
+                .collect::<Result<_, Error>>()?,
+            goals: list("goals").iter().map(|v| v.str().map(String::from).ok_or_else(|| invalid("Expected goal")))
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+
+    /// Returns the scenario as JSON, encoding models as strings.
+    pub fn to_json(&self, encode: fn(&M) -> String) -> String {
+        let mut out = format!("{{\"name\":{},\"initial\":{},\"max_steps\":{},\"observations\":[",
+                              json::string(&self.name), json::string(&encode(&self.initial)), self.max_steps);
+        for (i, (step, model)) in self.observations.iter().enumerate() {
+            if i > 0 {out.push(',')}
+            let _ = write!(out, "{{\"step\":{},\"model\":{}}}", step, json::string(&encode(model)));
+        }
+        out.push_str("],\"perturbations\":[");
+        for (i, (step, name)) in self.perturbations.iter().enumerate() {
+            if i > 0 {out.push(',')}
+            let _ = write!(out, "{{\"step\":{},\"name\":{}}}", step, json::string(name));
+        }
+        let goals: Vec<String> = self.goals.iter().map(|g| json::string(g)).collect();
+        let _ = write!(out, "],\"goals\":[{}]}}", goals.join(","));
+        out
+    }
+}
+
+/// The name and predicate of a goal.
+pub type Goal<M> = (&'static str, fn(&M) -> bool);
+
+/// The name and function of a perturbation.
+pub type Perturbation<M> = (&'static str, fn(&mut M));
+
+/// Stores the functions that scenarios refer to.
+pub struct Library<M, A> {
+    /// Performs an action on the model of the environment.
+    pub actor: fn(&mut M, &A),
+    /// The named goal predicates.
+    pub goals: Vec<Goal<M>>,
+    /// The named perturbations.
+    pub perturbations: Vec<Perturbation<M>>,
+}
+
+impl<M, A> Clone for Library<M, A> {
+    fn clone(&self) -> Self {
+        Library {actor: self.actor, goals: self.goals.clone(), perturbations: self.perturbations.clone()}
+    }
+}
+
+impl<M, A> fmt::Debug for Library<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Library")
+            .field("actor", &self.actor)
+            .field("goals", &self.goals.iter().map(|g| g.0).collect::<Vec<_>>())
+            .field("perturbations", &self.perturbations.iter().map(|p| p.0).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl<M, A> Library<M, A> {
+    /// Creates a new library with an actor of the environment.
+    pub fn new(actor: fn(&mut M, &A)) -> Self {Library {actor, goals: vec![], perturbations: vec![]}}
+
+    /// Adds a named goal predicate.
+    pub fn goal(mut self, name: &'static str, f: fn(&M) -> bool) -> Self {
+        self.goals.push((name, f));
+        self
+    }
+
+    /// Adds a named perturbation.
+    pub fn perturbation(mut self, name: &'static str, f: fn(&mut M)) -> Self {
+        self.perturbations.push((name, f));
+        self
+    }
+}
+
+/// Stores the environment of a scenario.
+struct Scripted<M, A> {
+    model: M,
+    actor: fn(&mut M, &A),
+}
+
+impl<M: Clone, A> Environment for Scripted<M, A> {
+    type Model = M;
+    type Action = A;
+    fn model(&mut self) -> M {self.model.clone()}
+    fn act(&mut self, action: &A) {(self.actor)(&mut self.model, action)}
+}
+
+/// Stores a report of running a scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioReport {
+    /// The name of the scenario.
+    pub name: String,
+    /// The report of the run, where the goal is that all goal predicates hold.
+    pub run: RunReport,
+    /// Whether each goal predicate held at the end.
+    pub goals: Vec<(String, bool)>,
+}
+
+/// Runs an agent in a scenario, starting from the initial model.
+///
+/// Returns an error if the scenario refers to a name that is not in the library.
+pub fn run_scenario<T>(
+    agent: &mut T,
+    scenario: &Scenario<T::Model>,
+    library: &Library<T::Model, T::Action>
+) -> Result<ScenarioReport, Error>
+    where T: Inspect, T::Model: Clone, T::Action: Clone
+{
+    let goals = scenario.goals.iter().map(|name| library.goals.iter().find(|g| g.0 == name)
+        .map(|g| g.1).ok_or_else(|| invalid(&format!("Unknown goal `{}`", name))))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let perturbations = scenario.perturbations.iter().map(|(step, name)| library.perturbations.iter()
+        .find(|p| p.0 == name).map(|p| (*step, p.1)).ok_or_else(|| invalid(&format!("Unknown perturbation `{}`", name))))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let mut env = Scripted {model: scenario.initial.clone(), actor: library.actor};
+    agent.update_model(scenario.initial.clone());
+    let reached = |m: &T::Model| goals.iter().all(|g| g(m));
+    let mut run = RunReport::default();
+    while run.steps < scenario.max_steps {
+        let next = run.steps + 1;
+        for (_, model) in scenario.observations.iter().filter(|(s, _)| *s == next) {env.model = model.clone()}
+        for (_, f) in perturbations.iter().filter(|(s, _)| *s == next) {f(&mut env.model)}
+        if reached(&env.model) {break}
+        run.steps = next;
+        match step(agent, &mut env) {
+            StepOutcome::Acted(_) => {}
+            StepOutcome::Requested => run.requests += 1,
+            StepOutcome::Halted => {
+                run.halted = true;
+                break;
+            }
+        }
+    }
+    run.goal = !run.halted && reached(&env.model);
+    let goals = scenario.goals.iter().zip(&goals).map(|(name, g)| (name.clone(), g(&env.model))).collect();
+    Ok(ScenarioReport {name: scenario.name.clone(), run, goals})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(m: &(u32, u32)) -> String {format!("{},{}", m.0, m.1)}
+    fn decode(s: &str) -> Option<(u32, u32)> {
+        let (a, b) = s.split_once(',')?;
+        Some((a.parse().ok()?, b.parse().ok()?))
+    }
+
+    #[test]
+    fn run() {
+        let src = r#"{"name":"goal moves","initial":"4,0","max_steps":10,
+            "observations":[{"step":2,"model":"3,1"}],
+            "perturbations":[{"step":3,"name":"push"}],
+            "goals":["at goal"]}"#;
+        let scenario = Scenario::from_json(src, decode).unwrap();
+        assert_eq!(Scenario::from_json(&scenario.to_json(encode), decode).unwrap(), scenario);
+        let library = Library::new(|m: &mut (u32, u32), &a: &i32| m.1 = (m.1 as i32 + a) as u32)
+            .goal("at goal", |m| m.0 == m.1)
+            .perturbation("push", |m| m.1 += 1);
+        let report = run_scenario(&mut crate::tests::four().add(1), &scenario, &library).unwrap();
+        assert_eq!((report.run.goal, report.run.steps), (true, 2));
+        assert_eq!(report.goals, vec![("at goal".to_string(), true)]);
+
+        let unknown = Scenario {goals: vec!["nowhere".into()], ..scenario};
+        assert!(run_scenario(&mut crate::tests::four().add(1), &unknown, &library).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Cross-language schemas of models and actions.
+//!
+//! The wire protocol exchanges models and actions as strings encoded by the processes.
+//! When they are encoded as JSON, a `Schema` describes their shape,
+//! such that environments written in other languages can validate the messages they exchange.
+//!
+//! Types implement `Describe` to return their schema.
+//! `Schema::to_json_schema` writes a JSON Schema (draft 2020-12),
+//! and `wire_schema` describes the messages of `wire::Message`,
+//! with the encoded models and actions as JSON content of the given schemas.
+//! `Schema::validate` checks JSON against a schema in Rust.
+
+use std::fmt::Write;
+
+use crate::json::{self, Value};
+
+/// Describes the shape of JSON values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    /// `null`.
+    Null,
+    /// `true` or `false`.
+    Bool,
+    /// An integral number in a range.
+    Integer {
+        /// The minimum value.
+        min: f64,
+        /// The maximum value.
+        max: f64,
+    },
+    /// A number.
+    Number,
+    /// A string.
+    String,
+    /// One of some strings, used for field-less enums.
+    Enum(Vec<&'static str>),
+    /// An array of items.
+    Array(Box<Schema>),
+    /// An array with one item per schema, used for tuples.
+    Tuple(Vec<Schema>),
+    /// An object with required fields.
+    Object(Vec<(&'static str, Schema)>),
+    /// One of some schemas, used for optional values and enums with fields.
+    OneOf(Vec<Schema>),
+}
+
+/// Implemented by types that describe their JSON encoding.
+pub trait Describe {
+    /// Returns the schema of the type.
+    fn schema() -> Schema;
+}
+
+macro_rules! describe_integer {
+    ($($t:ty),*) => {$(
+        impl Describe for $t {
+            fn schema() -> Schema {Schema::Integer {min: <$t>::MIN as f64, max: <$t>::MAX as f64}}
+        }
+    )*}
+}
+
+describe_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Describe for () {fn schema() -> Schema {Schema::Null}}
+impl Describe for bool {fn schema() -> Schema {Schema::Bool}}
+impl Describe for f32 {fn schema() -> Schema {Schema::Number}}
+impl Describe for f64 {fn schema() -> Schema {Schema::Number}}
+impl Describe for String {fn schema() -> Schema {Schema::String}}
+impl<T: Describe> Describe for Vec<T> {fn schema() -> Schema {Schema::Array(Box::new(T::schema()))}}
+impl<T: Describe> Describe for Option<T> {
+    fn schema() -> Schema {Schema::OneOf(vec![Schema::Null, T::schema()])}
+}
+impl<A: Describe, B: Describe> Describe for (A, B) {
+    fn schema() -> Schema {Schema::Tuple(vec![A::schema(), B::schema()])}
+}
+impl<A: Describe, B: Describe, C: Describe> Describe for (A, B, C) {
+    fn schema() -> Schema {Schema::Tuple(vec![A::schema(), B::schema(), C::schema()])}
+}
+
+fn integral(x: f64) -> bool {x.fract() == 0.0}
+
+impl Schema {
+    /// Returns the schema as a JSON Schema, without the `$schema` keyword.
+    pub fn to_json_schema(&self) -> String {
+        let list = |items: &[Schema]| items.iter().map(|s| s.to_json_schema()).collect::<Vec<_>>().join(",");
+        match self {
+            Schema::Null => "{\"type\":\"null\"}".into(),
+            Schema::Bool => "{\"type\":\"boolean\"}".into(),
+            Schema::Integer {min, max} => format!("{{\"type\":\"integer\",\"minimum\":{},\"maximum\":{}}}", min, max),
+            Schema::Number => "{\"type\":\"number\"}".into(),
+            Schema::String => "{\"type\":\"string\"}".into(),
+            Schema::Enum(names) => format!("{{\"enum\":[{}]}}",
+                names.iter().map(|n| json::string(n)).collect::<Vec<_>>().join(",")),
+            Schema::Array(item) => format!("{{\"type\":\"array\",\"items\":{}}}", item.to_json_schema()),
+            Schema::Tuple(items) => format!("{{\"type\":\"array\",\"prefixItems\":[{}],\"items\":false}}", list(items)),
+            Schema::Object(fields) => {
+                let mut out = String::from("{\"type\":\"object\",\"properties\":{");
+                for (i, (name, schema)) in fields.iter().enumerate() {
+                    if i > 0 {out.push(',')}
+                    let _ = write!(out, "{}:{}", json::string(name), schema.to_json_schema());
+                }
+                let required: Vec<String> = fields.iter().map(|f| json::string(f.0)).collect();
+                let _ = write!(out, "}},\"required\":[{}]}}", required.join(","));
+                out
+            }
+            Schema::OneOf(items) => format!("{{\"oneOf\":[{}]}}", list(items)),
+        }
+    }
+
+    fn check(&self, value: &Value, path: &str) -> Result<(), String> {
+        let fail = || Err(format!("`{}` does not match {}", if path.is_empty() {"$"} else {path},
+                                  self.to_json_schema()));
+        match (self, value) {
+            (Schema::Null, Value::Null) | (Schema::Bool, Value::Bool(_)) |
+            (Schema::Number, Value::Num(_)) | (Schema::String, Value::Str(_)) => Ok(()),
+            (Schema::Integer {min, max}, Value::Num(x)) if integral(*x) && x >= min && x <= max => Ok(()),
+            (Schema::Enum(names), Value::Str(s)) if names.contains(&s.as_str()) => Ok(()),
+            (Schema::Array(item), Value::Arr(items)) => {
+                for (i, v) in items.iter().enumerate() {item.check(v, &format!("{}[{}]", path, i))?}
+                Ok(())
+            }
+            (Schema::Tuple(schemas), Value::Arr(items)) if schemas.len() == items.len() => {
+                for (i, (s, v)) in schemas.iter().zip(items).enumerate() {s.check(v, &format!("{}[{}]", path, i))?}
+                Ok(())
+            }
+            (Schema::Object(fields), Value::Obj(_)) => {
+                for (name, schema) in fields {
+                    let path = format!("{}.{}", path, name);
+                    match value.get(name) {
+                        Some(v) => schema.check(v, &path)?,
+                        None => return Err(format!("`{}` is missing", path)),
+                    }
+                }
+                Ok(())
+            }
+            (Schema::OneOf(schemas), _) if schemas.iter().any(|s| s.check(value, path).is_ok()) => Ok(()),
+            _ => fail(),
+        }
+    }
+
+    /// Checks that JSON matches the schema, returning a description of the first mismatch.
+    pub fn validate(&self, src: &str) -> Result<(), String> {
+        let value = json::parse(src).ok_or_else(|| "invalid JSON".to_string())?;
+        self.check(&value, "")
+    }
+}
+
+/// Returns a JSON Schema of the messages of the wire protocol,
+/// where encoded models and actions are JSON content of some schemas.
+pub fn wire_schema(model: &Schema, action: &Schema) -> String {
+    let content = |schema: &Schema| format!(
+        "{{\"type\":\"string\",\"contentMediaType\":\"application/json\",\"contentSchema\":{}}}",
+        schema.to_json_schema()
+    );
+    let message = |ty: &str, fields: &[(&str, String)]| {
+        let mut properties = format!("\"version\":{{\"type\":\"integer\",\"maximum\":{}}},\"type\":{{\"const\":{}}}",
+                                     crate::wire::VERSION, json::string(ty));
+        for (name, schema) in fields {let _ = write!(properties, ",{}:{}", json::string(name), schema);}
+        let required: Vec<String> = ["version", "type"].iter().copied().chain(fields.iter().map(|f| f.0))
+            .map(json::string).collect();
+        format!("{{\"type\":\"object\",\"properties\":{{{}}},\"required\":[{}]}}", properties, required.join(","))
+    };
+    let decision = |name: &str| format!("{{\"const\":{}}}", json::string(name));
+    let messages = [
+        message("observation", &[("model", content(model))]),
+        message("model_update", &[("model", content(model)), ("generation", "{\"type\":\"string\",\"pattern\":\"^[0-9]+$\"}".into())]),
+        message("decision", &[("decision", decision("action")), ("action", content(action))]),
+        message("decision", &[("decision", decision("request_model"))]),
+        message("decision", &[("decision", decision("halt"))]),
+        message("request_info", &[("reason", Schema::String.to_json_schema()),
+                                  ("target", <Option<String>>::schema().to_json_schema())]),
+    ];
+    format!("{{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\"oneOf\":[{}]}}", messages.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        let model = Schema::Object(vec![("goal", u32::schema()), ("state", <Option<u32>>::schema())]);
+        assert!(model.validate(r#"{"goal":4,"state":null}"#).is_ok());
+        assert_eq!(model.validate(r#"{"goal":-1,"state":3}"#).unwrap_err(),
+                   "`.goal` does not match {\"type\":\"integer\",\"minimum\":0,\"maximum\":4294967295}");
+        assert_eq!(model.validate(r#"{"goal":4}"#).unwrap_err(), "`.state` is missing");
+        assert!(<(i32, bool)>::schema().validate("[-1,true]").is_ok());
+        assert!(Schema::Enum(vec!["Open"]).validate("\"Close\"").is_err());
+    }
+
+    #[test]
+    fn wire() {
+        let schema = wire_schema(&<(u32, u32)>::schema(), &i32::schema());
+        let schema = json::parse(&schema).unwrap();
+        assert_eq!(schema.get("oneOf").and_then(|m| m.array()).map(|m| m.len()), Some(6));
+    }
+}
@@ -0,0 +1,120 @@
+//! Plottable time series of episodes.
+//!
+//! A `Series` exports recorded traces as aligned numeric columns, one row per step,
+//! for plotting the behavior of an agent over time:
+//!
+//! - `episode`: the index of the trace
+//! - `step`: the index of the step in the episode
+//! - `decision`: `0` for actions, `1` for model requests and `2` for halting
+//! - `level`: the number of safety layers that decided
+//! - `disagreement`: the fraction of probes in all layers that disagreed with core zero
+//!
+//! followed by one column per model field, using accessor functions.
+//! `Series::to_csv` writes the columns as CSV with a header row,
+//! which most plotting tools read directly.
+
+use std::fmt::Write;
+
+use crate::rationale::Rationale;
+use crate::trace::Trace;
+use crate::{Decision, ProbeOutcome};
+
+/// The names of the columns that are always exported.
+pub const COLUMNS: &[&str] = &["episode", "step", "decision", "level", "disagreement"];
+
+/// The name and accessor of a model field.
+pub type Field<M> = (&'static str, fn(&M) -> f64);
+
+/// Stores the model fields to export as time series.
+pub struct Series<M> {
+    /// The name and accessor of each model field.
+    pub fields: Vec<Field<M>>,
+}
+
+impl<M> Default for Series<M> {
+    fn default() -> Self {Series {fields: vec![]}}
+}
+
+fn probes(rationale: &Rationale) -> (u32, u32) {
+    rationale.checks.iter().fold((0, 0), |(n, d), check| {
+        let (inner_n, inner_d) = check.inner.as_ref().map(|inner| probes(inner)).unwrap_or((0, 0));
+        let disagree = (check.outcome == ProbeOutcome::Disagree) as u32;
+        (n + 1 + inner_n, d + disagree + inner_d)
+    })
+}
+
+impl<M> Series<M> {
+    /// Creates a new series without model fields.
+    pub fn new() -> Self {Series::default()}
+
+    /// Adds a model field.
+    pub fn field(mut self, name: &'static str, f: fn(&M) -> f64) -> Self {
+        self.fields.push((name, f));
+        self
+    }
+
+    /// Returns the names of all columns.
+    pub fn names(&self) -> Vec<&'static str> {
+        COLUMNS.iter().copied().chain(self.fields.iter().map(|f| f.0)).collect()
+    }
+
+    /// Returns the rows of some episodes, with one value per column.
+    pub fn rows<A>(&self, episodes: &[Trace<M, A>]) -> Vec<Vec<f64>> {
+        let mut rows = vec![];
+        for (episode, trace) in episodes.iter().enumerate() {
+            for (i, step) in trace.steps.iter().enumerate() {
+                let decision = match step.decision {
+                    Decision::Action(_) => 0.0,
+                    Decision::RequestModel => 1.0,
+                    Decision::Halt => 2.0,
+                };
+                let (n, d) = probes(&step.rationale);
+                let disagreement = if n == 0 {0.0} else {d as f64 / n as f64};
+                let mut row = vec![episode as f64, i as f64, decision, step.rationale.layer as f64, disagreement];
+                row.extend(self.fields.iter().map(|f| (f.1)(&step.model)));
+                rows.push(row);
+            }
+        }
+        rows
+    }
+
+    /// Returns the columns of some episodes, with one value per step.
+    pub fn columns<A>(&self, episodes: &[Trace<M, A>]) -> Vec<Vec<f64>> {
+        let rows = self.rows(episodes);
+        (0..self.names().len()).map(|j| rows.iter().map(|row| row[j]).collect()).collect()
+    }
+
+    /// Returns the series of some episodes as CSV, with a header row.
+    pub fn to_csv<A>(&self, episodes: &[Trace<M, A>]) -> String {
+        let mut out = self.names().join(",");
+        out.push('\n');
+        for row in self.rows(episodes) {
+            for (j, x) in row.iter().enumerate() {
+                if j > 0 {out.push(',')}
+                // Missing values are left empty.
+                if x.is_finite() {let _ = write!(out, "{}", x);}
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let mut trace = Trace::new();
+        trace.run(&mut crate::tests::four().add(1), 10);
+        let series = Series::new().field("state", |m: &(u32, u32)| m.1 as f64);
+        let csv = series.to_csv(&[trace.clone(), trace.clone()]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "episode,step,decision,level,disagreement,state");
+        assert_eq!(lines[1], "0,0,0,1,0,0");
+        assert_eq!(lines[4], "0,3,1,1,1,3");
+        assert_eq!(lines.len(), 9);
+        assert_eq!(series.columns(&[trace])[5], vec![0.0, 1.0, 2.0, 3.0]);
+    }
+}
@@ -0,0 +1,163 @@
+//! Shadow mode for evaluating safety levels on live traffic.
+//!
+//! Before raising the safety level of a deployed agent, operators want to know how it would behave.
+//! A `Shadow` agent runs a shadow copy, e.g. with more safety layers, alongside the deployed agent.
+//! Both receive the same model updates, but only the decisions of the deployed agent are returned.
+//! The actions of the deployed agent are also performed on the internal model of the shadow,
+//! such that both keep deciding on the same model.
+//!
+//! A `ShadowReport` accumulates where the decisions diverge:
+//!
+//! - the deployed agent acts, while the shadow requests a model update or halts
+//! - the shadow acts, while the deployed agent requests a model update or halts
+//! - both act, but on different actions
+//!
+//! Divergences in caution are sampled with the model they happened on, up to a limit.
+//! When the shadow is good enough, `Shadow::promote` returns it for deployment.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores a divergence in caution between the deployed agent and the shadow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample<M, A> {
+    /// The index of the decide call.
+    pub decision: usize,
+    /// The model of the decide call.
+    pub model: M,
+    /// The decision of the deployed agent.
+    pub deployed: Decision<A>,
+    /// The decision of the shadow.
+    pub shadow: Decision<A>,
+}
+
+/// Stores a report comparing the deployed agent with the shadow.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowReport<M, A> {
+    /// The number of decide calls.
+    pub decisions: usize,
+    /// The number of decide calls where the deployed agent acted, while the shadow did not.
+    pub more_cautious: usize,
+    /// The number of decide calls where the shadow acted, while the deployed agent did not.
+    pub less_cautious: usize,
+    /// The number of decide calls where both acted on different actions.
+    pub different_actions: usize,
+    /// The sampled divergences in caution.
+    pub samples: Vec<Sample<M, A>>,
+}
+
+impl<M, A> Default for ShadowReport<M, A> {
+    fn default() -> Self {
+        ShadowReport {decisions: 0, more_cautious: 0, less_cautious: 0, different_actions: 0, samples: vec![]}
+    }
+}
+
+impl<M, A> ShadowReport<M, A> {
+    /// Returns the number of decide calls where the decisions diverged.
+    pub fn divergences(&self) -> usize {self.more_cautious + self.less_cautious + self.different_actions}
+
+    /// Returns the fraction of decide calls where the decisions diverged.
+    pub fn divergence_rate(&self) -> f64 {
+        if self.decisions == 0 {0.0} else {self.divergences() as f64 / self.decisions as f64}
+    }
+}
+
+/// Stores a deployed agent together with a shadow that does not act.
+#[derive(Clone, Debug)]
+pub struct Shadow<T: Agent, U> {
+    /// The deployed agent.
+    pub agent: T,
+    /// The shadow agent.
+    pub shadow: U,
+    /// The maximum number of sampled divergences.
+    pub max_samples: usize,
+    /// The report.
+    pub report: ShadowReport<T::Model, T::Action>,
+}
+
+impl<T: Agent, U> Shadow<T, U> {
+    /// Creates a new agent with a shadow, sampling up to some number of divergences.
+    pub fn new(agent: T, shadow: U, max_samples: usize) -> Self {
+        Shadow {agent, shadow, max_samples, report: ShadowReport::default()}
+    }
+
+    /// Returns the shadow for deployment, together with the report.
+    pub fn promote(self) -> (U, ShadowReport<T::Model, T::Action>) {(self.shadow, self.report)}
+}
+
+impl<T, U> Agent for Shadow<T, U>
+    where T: Inspect,
+          U: Agent<Model = T::Model, Action = T::Action>,
+          T::Model: Clone,
+          T::Action: Clone + PartialEq
+{
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        self.shadow.update_model(model.clone());
+        self.agent.update_model(model);
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        let deployed = self.agent.decide();
+        let shadow = self.shadow.decide();
+        let report = &mut self.report;
+        let decision = report.decisions;
+        report.decisions += 1;
+        match (&deployed, &shadow) {
+            (Decision::Action(a), Decision::Action(b)) => {
+                if a != b {report.different_actions += 1}
+                return deployed;
+            }
+            (Decision::Action(_), _) => report.more_cautious += 1,
+            (_, Decision::Action(_)) => report.less_cautious += 1,
+            _ => return deployed,
+        }
+        if report.samples.len() < self.max_samples {
+            report.samples.push(Sample {
+                decision,
+                model: self.agent.model().clone(),
+                deployed: deployed.clone(),
+                shadow,
+            });
+        }
+        deployed
+    }
+    fn act(&mut self, action: T::Action) {
+        self.shadow.act(action.clone());
+        self.agent.act(action);
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T, U> Inspect for Shadow<T, U>
+    where T: Inspect,
+          U: Agent<Model = T::Model, Action = T::Action>,
+          T::Model: Clone,
+          T::Action: Clone + PartialEq
+{
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{run_until, tests::Three};
+
+    #[test]
+    fn shadow() {
+        let mut s = Shadow::new(crate::tests::four().add(0), crate::tests::four().add(1), 1);
+        let report = run_until(&mut s, &mut Three(0), |m| m.1 == 4, 10);
+        assert!(report.goal);
+        assert_eq!((s.report.decisions, s.report.more_cautious, s.report.less_cautious), (4, 1, 0));
+        assert_eq!(s.report.samples, vec![Sample {
+            decision: 3,
+            model: (4, 3),
+            deployed: Decision::Action(1),
+            shadow: Decision::RequestModel,
+        }]);
+        assert_eq!(s.report.divergence_rate(), 0.25);
+        let (shadow, _) = s.promote();
+        assert_eq!(shadow.z.model, (4, 4));
+    }
+}
@@ -0,0 +1,104 @@
+//! Sharing agents between threads.
+//!
+//! The agent types in this library store the model and function pointers.
+//! Function pointers are `Send` and `Sync`,
+//! so agents are `Send` or `Sync` when the model is.
+//!
+//! A `SharedAgent` wraps an agent in `Arc<Mutex<_>>`,
+//! such that it can be driven from one thread and inspected from another.
+//!
+//! When a thread panics while holding the lock,
+//! the model might still be mutated by an unfinished probe.
+//! A poisoned agent requests a model update on every decide,
+//! until a new model is received.
+//! Acting and inspecting still work on a poisoned agent, without clearing the poisoned state.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{Agent, Decision};
+
+/// Stores an agent shared between threads.
+pub struct SharedAgent<T> {
+    inner: Arc<Mutex<T>>,
+}
+
+impl<T> Clone for SharedAgent<T> {
+    fn clone(&self) -> Self {SharedAgent {inner: self.inner.clone()}}
+}
+
+impl<T: Agent> SharedAgent<T> {
+    /// Creates a new shared agent.
+    pub fn new(agent: T) -> Self {
+        SharedAgent {inner: Arc::new(Mutex::new(agent))}
+    }
+
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns `true` if a thread panicked while holding the lock.
+    pub fn is_poisoned(&self) -> bool {self.inner.is_poisoned()}
+
+    /// Update internal model.
+    ///
+    /// This clears the poisoned state.
+    pub fn update_model(&self, model: T::Model) {
+        let mut agent = self.lock();
+        agent.update_model(model);
+        drop(agent);
+        self.inner.clear_poison();
+    }
+
+    /// Decide what to do next.
+    ///
+    /// Requests a model update when the agent is poisoned.
+    pub fn decide(&self) -> Decision<T::Action> {
+        match self.inner.lock() {
+            Ok(mut agent) => agent.decide(),
+            Err(_) => Decision::RequestModel,
+        }
+    }
+
+    /// Perform an action on its internal model.
+    pub fn act(&self, action: T::Action) {self.lock().act(action)}
+
+    /// Inspects the agent while holding the lock.
+    pub fn inspect<U>(&self, f: impl FnOnce(&T) -> U) -> U {f(&self.lock())}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AgentN;
+
+    fn is_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn send_sync() {
+        is_send_sync::<AgentN<(u32, u32), i32, i32>>();
+        is_send_sync::<SharedAgent<AgentN<(u32, u32), i32, i32>>>();
+    }
+
+    #[test]
+    fn drive_and_inspect() {
+        let agent = SharedAgent::new(crate::tests::four().add(1));
+        let driver = agent.clone();
+        std::thread::spawn(move || {
+            if let Decision::Action(a) = driver.decide() {driver.act(a)}
+        }).join().unwrap();
+        assert_eq!(agent.inspect(|agent| agent.z.model), (4, 1));
+
+        let poisoner = agent.clone();
+        let _ = std::thread::spawn(move || {
+            poisoner.inspect(|_| panic!("monitor crashed"))
+        }).join();
+        assert!(agent.is_poisoned());
+        assert_eq!(agent.decide(), Decision::RequestModel);
+        // Monitors can still inspect a poisoned agent.
+        assert_eq!(agent.inspect(|agent| agent.z.model), (4, 1));
+        agent.act(0);
+        assert!(agent.is_poisoned());
+        agent.update_model((4, 1));
+        assert_eq!(agent.decide(), Decision::Action(1));
+    }
+}
@@ -0,0 +1,298 @@
+//! Guardrails written in a small constraint language.
+//!
+//! A `Shield` wraps an agent and forbids actions when conditions on the model hold.
+//! A forbidden action is downgraded to a model request.
+//!
+//! Guardrails are parsed at construction, one rule per line:
+//!
+//! ```text
+//! forbid action delete when state.backup == false
+//! forbid action move when speed > 10 and zone == "school"
+//! forbid action any when battery < 5
+//! ```
+//!
+//! The action name `any` matches all actions.
+//! Lines that are empty or start with `#` are ignored.
+//! Values are booleans, integers or strings.
+//! Operators are `==`, `!=`, `<`, `<=`, `>` and `>=`.
+//!
+//! Actions are named and fields are read using user-supplied functions.
+//! When a field is missing, or has a different type than the value compared with,
+//! the comparison is assumed to hold, which forbids the action.
+
+use crate::{Agent, Decision, Error, Inspect};
+
+/// Stores a value in the constraint language.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd)]
+pub enum Value {
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    Int(i64),
+    /// A string.
+    Str(String),
+}
+
+/// Stores a comparison operator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+/// Stores a comparison between a field and a value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// The path of the field.
+    pub field: String,
+    /// The operator.
+    pub op: Op,
+    /// The value.
+    pub value: Value,
+}
+
+impl Comparison {
+    /// Returns `true` if the comparison holds for some field value.
+    pub fn holds(&self, field: Option<Value>) -> bool {
+        use std::cmp::Ordering::*;
+
+        let ord = match field {
+            Some(field) => match (&field, &self.value) {
+                (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+                (Value::Int(a), Value::Int(b)) => a.cmp(b),
+                (Value::Str(a), Value::Str(b)) => a.cmp(b),
+                _ => return true,
+            },
+            None => return true,
+        };
+        match self.op {
+            Op::Eq => ord == Equal,
+            Op::Ne => ord != Equal,
+            Op::Lt => ord == Less,
+            Op::Le => ord != Greater,
+            Op::Gt => ord == Greater,
+            Op::Ge => ord != Less,
+        }
+    }
+}
+
+/// Stores a guardrail rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    /// The name of the forbidden action, where `None` matches all actions.
+    pub action: Option<String>,
+    /// The conditions that all must hold for the action to be forbidden.
+    pub when: Vec<Comparison>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Int(i64),
+    Str(String),
+    Op(Op),
+}
+
+fn tokenize(line: &str, n: usize) -> Result<Vec<Token>, Error> {
+    let err = |message: &str| Error::Parse {line: n, message: message.into()};
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() || c == '-' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {i += 1}
+            let s: String = chars[start..i].iter().collect();
+            tokens.push(Token::Int(s.parse().map_err(|_| err("Expected integer"))?));
+        } else if c == '"' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '"' {i += 1}
+            if i == chars.len() {return Err(err("Expected `\"`"))}
+            tokens.push(Token::Str(chars[start..i].iter().collect()));
+            i += 1;
+        } else {
+            let next = chars.get(i + 1) == Some(&'=');
+            let op = match (c, next) {
+                ('=', true) => Op::Eq,
+                ('!', true) => Op::Ne,
+                ('<', true) => Op::Le,
+                ('>', true) => Op::Ge,
+                ('<', false) => Op::Lt,
+                ('>', false) => Op::Gt,
+                _ => return Err(err(&format!("Unexpected `{}`", c))),
+            };
+            i += if next {2} else {1};
+            tokens.push(Token::Op(op));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses guardrail rules.
+pub fn parse(src: &str) -> Result<Vec<Rule>, Error> {
+    let mut rules = vec![];
+    for (i, line) in src.lines().enumerate() {
+        let n = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {continue}
+        let err = |message: &str| Error::Parse {line: n, message: message.into()};
+        let word = |t: Option<&Token>, w: &str| matches!(t, Some(Token::Word(x)) if x == w);
+
+        let tokens = tokenize(line, n)?;
+        let mut it = tokens.iter().peekable();
+        if !word(it.next(), "forbid") {return Err(err("Expected `forbid`"))}
+        if !word(it.next(), "action") {return Err(err("Expected `action`"))}
+        let action = match it.next() {
+            Some(Token::Word(x)) if x == "any" => None,
+            Some(Token::Word(x)) => Some(x.clone()),
+            _ => return Err(err("Expected action name")),
+        };
+        let mut when = vec![];
+        if it.peek().is_some() {
+            if !word(it.next(), "when") {return Err(err("Expected `when`"))}
+            loop {
+                let field = match it.next() {
+                    Some(Token::Word(x)) => x.clone(),
+                    _ => return Err(err("Expected field")),
+                };
+                let op = match it.next() {
+                    Some(Token::Op(op)) => *op,
+                    _ => return Err(err("Expected operator")),
+                };
+                let value = match it.next() {
+                    Some(Token::Word(x)) if x == "true" => Value::Bool(true),
+                    Some(Token::Word(x)) if x == "false" => Value::Bool(false),
+                    Some(Token::Int(x)) => Value::Int(*x),
+                    Some(Token::Str(x)) => Value::Str(x.clone()),
+                    _ => return Err(err("Expected value")),
+                };
+                when.push(Comparison {field, op, value});
+                match it.next() {
+                    None => break,
+                    t if word(t, "and") => continue,
+                    _ => return Err(err("Expected `and`")),
+                }
+            }
+        }
+        rules.push(Rule {action, when});
+    }
+    Ok(rules)
+}
+
+/// Stores an agent wrapped in guardrails.
+#[derive(Clone, Debug)]
+pub struct Shield<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The guardrail rules.
+    pub rules: Vec<Rule>,
+    /// Returns the name of an action.
+    pub name: fn(&T::Action) -> &str,
+    /// Returns the value of a field in the model.
+    pub field: fn(&T::Model, &str) -> Option<Value>,
+    /// The index of the rule that forbid the last decision.
+    pub forbidden: Option<usize>,
+}
+
+impl<T: Inspect> Shield<T> {
+    /// Creates a new shield by parsing guardrail rules.
+    pub fn new(
+        agent: T,
+        src: &str,
+        name: fn(&T::Action) -> &str,
+        field: fn(&T::Model, &str) -> Option<Value>,
+    ) -> Result<Self, Error> {
+        Ok(Shield {agent, rules: parse(src)?, name, field, forbidden: None})
+    }
+
+    /// Returns the index of the first rule that forbids an action.
+    pub fn check(&self, action: &T::Action) -> Option<usize> {
+        let name = (self.name)(action);
+        let model = self.agent.model();
+        self.rules.iter().position(|rule| {
+            rule.action.as_ref().map(|a| a == name).unwrap_or(true) &&
+            rule.when.iter().all(|c| c.holds((self.field)(model, &c.field)))
+        })
+    }
+}
+
+impl<T: Inspect> Agent for Shield<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        self.forbidden = None;
+        match self.agent.decide() {
+            Decision::Action(a) => {
+                self.forbidden = self.check(&a);
+                if self.forbidden.is_some() {Decision::RequestModel} else {Decision::Action(a)}
+            }
+            Decision::RequestModel => Decision::RequestModel,
+            Decision::Halt => Decision::Halt,
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Shield<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rules() {
+        let rules = parse("
+            # Comments are ignored.
+            forbid action delete when state.backup == false
+            forbid action any when zone == \"school\" and speed>=-3
+        ").unwrap();
+        assert_eq!(rules[0], Rule {
+            action: Some("delete".into()),
+            when: vec![Comparison {field: "state.backup".into(), op: Op::Eq, value: Value::Bool(false)}],
+        });
+        assert_eq!(rules[1].action, None);
+        assert_eq!(rules[1].when[1].value, Value::Int(-3));
+        assert_eq!(parse("forbid delete"), Err(Error::Parse {line: 1, message: "Expected `action`".into()}));
+    }
+
+    #[test]
+    fn forbid_overshoot() {
+        let name = |a: &i32| if *a > 0 {"inc"} else if *a < 0 {"dec"} else {"stay"};
+        let field = |m: &(u32, u32), f: &str| match f {
+            "state" => Some(Value::Int(m.1 as i64)),
+            _ => None,
+        };
+        let mut s = Shield::new(crate::tests::four(), "forbid action inc when state >= 2", name, field).unwrap();
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.update_model((4, 2));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.forbidden, Some(0));
+    }
+}
@@ -0,0 +1,110 @@
+//! Signed model updates.
+//!
+//! A model update that corrects the goal of an agent is a powerful lever.
+//! When the channel delivering model updates is compromised,
+//! it could feed the agent a malicious goal.
+//!
+//! A `Signed` agent applies a `SignedUpdate` only when its signature is verified
+//! against a registered public key.
+//! The model is encoded to bytes before verifying, using a user-supplied encoder.
+//!
+//! The signature scheme is supplied by the user as a verifier function,
+//! e.g. Ed25519 from a cryptography library, so this library has no dependencies.
+
+use crate::{Agent, Decision, Error, Inspect};
+
+/// Stores a model update with a signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedUpdate<M> {
+    /// The model.
+    pub model: M,
+    /// The index of the public key that signed the update.
+    pub key: usize,
+    /// The signature of the encoded model.
+    pub signature: Vec<u8>,
+}
+
+/// Stores an agent that only applies model updates with verified signatures.
+#[derive(Clone, Debug)]
+pub struct Signed<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// The registered public keys.
+    pub keys: Vec<Vec<u8>>,
+    /// Encodes a model to the bytes that are signed.
+    pub encode: fn(&T::Model) -> Vec<u8>,
+    /// Returns `true` if a signature of a message is valid for a public key.
+    pub verify: fn(key: &[u8], message: &[u8], signature: &[u8]) -> bool,
+}
+
+impl<T: Agent> Signed<T> {
+    /// Creates a new agent that verifies signatures of model updates.
+    pub fn new(
+        agent: T,
+        encode: fn(&T::Model) -> Vec<u8>,
+        verify: fn(key: &[u8], message: &[u8], signature: &[u8]) -> bool,
+    ) -> Self {
+        Signed {agent, keys: vec![], encode, verify}
+    }
+
+    /// Registers a public key, returning its index.
+    pub fn register(&mut self, key: Vec<u8>) -> usize {
+        self.keys.push(key);
+        self.keys.len() - 1
+    }
+
+    /// Applies a signed model update when its signature is verified.
+    pub fn update_signed(&mut self, update: SignedUpdate<T::Model>) -> Result<(), Error> {
+        let key = self.keys.get(update.key)
+            .ok_or_else(|| Error::Signature(format!("Unknown key #{}", update.key)))?;
+        if !(self.verify)(key, &(self.encode)(&update.model), &update.signature) {
+            return Err(Error::Signature(format!("Invalid signature by key #{}", update.key)));
+        }
+        self.agent.update_model(update.model);
+        Ok(())
+    }
+}
+
+/// Rejects unsigned model updates.
+///
+/// Use `Signed::update_signed` to update the model.
+impl<T: Agent> Agent for Signed<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, _: T::Model) {}
+    fn decide(&mut self) -> Decision<T::Action> {self.agent.decide()}
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Signed<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A toy scheme for testing, where the signature is the message xor the key.
+    fn verify(key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        message.len() == signature.len() &&
+        message.iter().zip(signature).enumerate().all(|(i, (m, s))| m ^ key[i % key.len()] == *s)
+    }
+
+    #[test]
+    fn verify_updates() {
+        let encode = |m: &(u32, u32)| vec![m.0 as u8, m.1 as u8];
+        let mut s = Signed::new(crate::tests::four().add(1), encode, verify);
+        let key = s.register(vec![7]);
+        assert_eq!(s.update_signed(SignedUpdate {model: (3, 0), key, signature: vec![3 ^ 7, 7]}), Ok(()));
+        assert_eq!(s.agent.z.model, (3, 0));
+        let forged = SignedUpdate {model: (9, 0), key, signature: vec![3 ^ 7, 7]};
+        assert_eq!(s.update_signed(forged).unwrap_err().to_string(),
+                   "Signature rejected: Invalid signature by key #0");
+        assert!(s.update_signed(SignedUpdate {model: (9, 0), key: 1, signature: vec![]}).is_err());
+        s.update_model((9, 0));
+        assert_eq!(s.agent.z.model, (3, 0));
+    }
+}
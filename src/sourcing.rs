@@ -0,0 +1,133 @@
+//! Event-sourced reconstruction of agent state.
+//!
+//! Incident analysis needs the exact state of an agent at some past decision.
+//! A `Sourced` agent records every change of its state in a log:
+//! model updates, actions and configuration changes, with a marker for every decide call.
+//!
+//! `AgentN::from_events` rebuilds an agent by replaying a log on its initial state,
+//! and `Sourced::state_at` rebuilds the agent as it was before some decide call.
+//! Since deciding undoes every mutation, a log without decide markers rebuilds the same state.
+
+use crate::{Agent, AgentN, Decision, Inspect, LayerConfig};
+
+/// A change of the state of an agent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change<M, A> {
+    /// The model was updated.
+    Update(M),
+    /// An action was performed on the model.
+    Act(A),
+    /// The configuration of a safety layer was replaced, where `1` is the innermost layer.
+    Config {
+        /// The safety layer.
+        layer: usize,
+        /// The new configuration.
+        config: LayerConfig<A>,
+    },
+    /// The number of safety layers was changed,
+    /// adding layers with the default configuration.
+    Layers(usize),
+    /// The agent decided, which marks the position of a decision in the log.
+    Decide,
+}
+
+impl<M, A: Clone + PartialEq, D> AgentN<M, A, D> {
+    /// Applies a change to the agent.
+    pub fn apply_change(&mut self, change: Change<M, A>) {
+        match change {
+            Change::Update(model) => self.update_model(model),
+            Change::Act(action) => self.act(action),
+            Change::Config {layer, config} => {
+                if let Some(c) = layer.checked_sub(1).and_then(|i| self.layers.get_mut(i)) {*c = config}
+            }
+            Change::Layers(n) => self.layers.resize(n, LayerConfig::default()),
+            Change::Decide => {}
+        }
+    }
+
+    /// Rebuilds an agent by replaying a log of changes on its initial state.
+    pub fn from_events(mut initial: AgentN<M, A, D>, log: &[Change<M, A>]) -> AgentN<M, A, D>
+        where M: Clone
+    {
+        for change in log {initial.apply_change(change.clone())}
+        initial
+    }
+}
+
+/// Stores an agent that records every change of its state.
+#[derive(Clone, Debug)]
+pub struct Sourced<M, A, D> {
+    /// The agent.
+    pub agent: AgentN<M, A, D>,
+    /// The initial state of the agent.
+    pub initial: AgentN<M, A, D>,
+    /// The log of changes since the initial state.
+    pub log: Vec<Change<M, A>>,
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Sourced<M, A, D> {
+    /// Creates a new agent recording changes of its state.
+    pub fn new(agent: AgentN<M, A, D>) -> Self {
+        Sourced {initial: agent.clone(), agent, log: vec![]}
+    }
+
+    /// Changes the agent and records the change.
+    pub fn change(&mut self, change: Change<M, A>) {
+        self.log.push(change.clone());
+        self.agent.apply_change(change);
+    }
+
+    /// Rebuilds the agent as it was before a decide call, where `0` is the first one.
+    ///
+    /// Returns `None` if the agent did not decide that many times.
+    pub fn state_at(&self, decision: usize) -> Option<AgentN<M, A, D>> {
+        let mut decides = 0;
+        let end = self.log.iter().position(|change| {
+            if let Change::Decide = change {
+                decides += 1;
+                decides > decision
+            } else {false}
+        })?;
+        Some(AgentN::from_events(self.initial.clone(), &self.log[..end]))
+    }
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Agent for Sourced<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.change(Change::Update(model))}
+    fn decide(&mut self) -> Decision<A> {
+        self.log.push(Change::Decide);
+        self.agent.decide()
+    }
+    fn act(&mut self, action: A) {self.change(Change::Act(action))}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Inspect for Sourced<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuild() {
+        let mut s = Sourced::new(crate::tests::four().add(1));
+        for _ in 0..3 {
+            if let Decision::Action(a) = s.decide() {s.act(a)}
+        }
+        s.change(Change::Layers(2));
+        s.update_model((4, 1));
+        assert_eq!(s.decide(), Decision::Action(1));
+        let before = s.state_at(3).unwrap();
+        assert_eq!((before.z.model, before.layers()), ((4, 1), 2));
+        assert_eq!(s.state_at(1).unwrap().z.model, (4, 1));
+        assert_eq!(s.state_at(4), None);
+        let now = AgentN::from_events(s.initial.clone(), &s.log);
+        assert_eq!((now.z, now.layers), (s.agent.z, s.agent.layers));
+    }
+}
@@ -0,0 +1,76 @@
+//! Programmatic stepping of a live agent.
+//!
+//! A `Stepper` drives an agent one decision at a time, e.g. from a notebook or a debugger,
+//! and allows poking its state between steps:
+//!
+//! - `step` decides, acts on the decision, and records the step
+//! - `peek_model` returns the internal model
+//! - `inject_update` replaces the internal model
+//! - `force_mutation` applies a mutater to the internal model, returning the delta
+//!
+//! The recorded trace can be inspected afterwards using `debugger::Debugger`.
+
+use crate::trace::Trace;
+use crate::{Agent, AgentN, Decision};
+
+/// Stores an agent driven one decision at a time.
+#[derive(Clone, Debug)]
+pub struct Stepper<M, A, D> {
+    /// The agent.
+    pub agent: AgentN<M, A, D>,
+    /// The recorded steps.
+    pub trace: Trace<M, A>,
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> Stepper<M, A, D> {
+    /// Creates a new stepper.
+    pub fn new(agent: AgentN<M, A, D>) -> Self {Stepper {agent, trace: Trace::new()}}
+
+    /// Decides, acting on the internal model when the agent acts, and records the step.
+    pub fn step(&mut self) -> Decision<A> {
+        let decision = self.trace.record(&mut self.agent);
+        if let Decision::Action(a) = &decision {self.agent.act(a.clone())}
+        decision
+    }
+
+    /// Returns the internal model.
+    pub fn peek_model(&self) -> &M {&self.agent.z.model}
+
+    /// Replaces the internal model, as if the environment answered a model request.
+    pub fn inject_update(&mut self, model: M) {self.agent.update_model(model)}
+
+    /// Applies mutater `i` to the internal model, returning the delta.
+    ///
+    /// Index `0` is the mutater of core zero when there are no mutaters.
+    /// Returns `None` if there is no such mutater.
+    /// The delta can be undone using `undo`.
+    pub fn force_mutation(&mut self, i: usize) -> Option<D> {
+        let mutater = match self.agent.mutaters.len() {
+            0 if i == 0 => self.agent.z.mutater,
+            _ => *self.agent.mutaters.get(i)?,
+        };
+        Some(mutater(&mut self.agent.z.model))
+    }
+
+    /// Undoes a delta change to the internal model.
+    pub fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poke() {
+        let mut s = Stepper::new(crate::tests::four().add(1));
+        assert_eq!(s.step(), Decision::Action(1));
+        assert_eq!(s.peek_model(), &(4, 1));
+        let delta = s.force_mutation(0).unwrap();
+        assert_eq!(s.peek_model(), &(3, 1));
+        assert_eq!(s.force_mutation(1), None);
+        s.undo(delta);
+        s.inject_update((4, 3));
+        assert_eq!(s.step(), Decision::RequestModel);
+        assert_eq!((s.trace.steps.len(), s.peek_model()), (2, &(4, 3)));
+    }
+}
@@ -0,0 +1,90 @@
+//! Streams of decisions.
+//!
+//! The function `decision_stream` drives an agent in an environment,
+//! yielding the outcome of every step to async consumers.
+//! The stream ends after the agent halts.
+//!
+//! Stepping is synchronous, so the stream is always ready.
+//! The method `DecisionStream::poll_next` has the same signature as in `futures::Stream`,
+//! such that an adapter can be written without this library depending on `futures`.
+//!
+//! Requires the `async` feature.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::environment::{step, Environment, StepOutcome};
+use crate::Agent;
+
+/// Stores a stream of step outcomes.
+pub struct DecisionStream<'a, T, E> {
+    /// The agent.
+    pub agent: &'a mut T,
+    /// The environment.
+    pub env: &'a mut E,
+    halted: bool,
+}
+
+/// Returns a stream of step outcomes of an agent in an environment.
+pub fn decision_stream<'a, T, E>(agent: &'a mut T, env: &'a mut E) -> DecisionStream<'a, T, E>
+    where T: Agent, E: Environment<Model = T::Model, Action = T::Action>, T::Action: Clone
+{
+    DecisionStream {agent, env, halted: false}
+}
+
+impl<T, E> Unpin for DecisionStream<'_, T, E> {}
+
+impl<'a, T, E> DecisionStream<'a, T, E>
+    where T: Agent, E: Environment<Model = T::Model, Action = T::Action>, T::Action: Clone
+{
+    /// Attempts to pull out the next step outcome.
+    pub fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<StepOutcome<T::Action>>> {
+        if self.halted {return Poll::Ready(None)}
+        let this = &mut *self;
+        let outcome = step(this.agent, this.env);
+        this.halted = matches!(outcome, StepOutcome::Halted);
+        Poll::Ready(Some(outcome))
+    }
+
+    /// Returns the next step outcome.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Next<'_, 'a, T, E> {Next {stream: self}}
+}
+
+/// Stores a future returning the next step outcome.
+pub struct Next<'s, 'a, T, E> {
+    stream: &'s mut DecisionStream<'a, T, E>,
+}
+
+impl<T, E> Future for Next<'_, '_, T, E>
+    where T: Agent, E: Environment<Model = T::Model, Action = T::Action>, T::Action: Clone
+{
+    type Output = Option<StepOutcome<T::Action>>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{Budget, Fallback};
+    use crate::environment::tests::Three;
+    use crate::handle::tests::block_on;
+
+    #[test]
+    fn stream() {
+        let mut agent = Budget::new(crate::tests::four().add(1), 0, Fallback::Halt);
+        let mut env = Three(0);
+        let mut stream = decision_stream(&mut agent, &mut env);
+        let outcomes = block_on(async {
+            let mut outcomes = vec![];
+            while let Some(outcome) = stream.next().await {outcomes.push(outcome)}
+            outcomes
+        });
+        assert_eq!(outcomes, vec![
+            StepOutcome::Acted(1), StepOutcome::Acted(1), StepOutcome::Acted(1), StepOutcome::Halted
+        ]);
+    }
+}
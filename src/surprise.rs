@@ -0,0 +1,89 @@
+//! Surprise-triggered model requests.
+//!
+//! Mutation probes check whether a decision is robust to uncertainty about the model,
+//! but they can all agree on a model that is demonstrably wrong.
+//! A `Surprised` agent remembers the model predicted by acting,
+//! and compares it with the next model update, which is the observed model.
+//! When the prediction error exceeds a threshold,
+//! the next decision requests a model update, even if mutation probes would agree.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores an agent that requests a model update when surprised.
+#[derive(Clone, Debug)]
+pub struct Surprised<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// Returns the prediction error between a predicted and an observed model.
+    pub distance: fn(&T::Model, &T::Model) -> f64,
+    /// The maximum prediction error before requesting a model update.
+    pub threshold: f64,
+    /// The prediction error of the last observation, if any.
+    pub surprise: Option<f64>,
+    /// Whether the next decision requests a model update.
+    pub surprised: bool,
+    predicted: Option<T::Model>,
+}
+
+impl<T: Inspect> Surprised<T> {
+    /// Creates a new agent that requests a model update when surprised.
+    pub fn new(agent: T, distance: fn(&T::Model, &T::Model) -> f64, threshold: f64) -> Self {
+        Surprised {agent, distance, threshold, surprise: None, surprised: false, predicted: None}
+    }
+}
+
+impl<T: Inspect> Agent for Surprised<T> where T::Model: Clone {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {
+        if let Some(predicted) = self.predicted.take() {
+            let surprise = (self.distance)(&predicted, &model);
+            self.surprise = Some(surprise);
+            if surprise > self.threshold {self.surprised = true}
+        }
+        self.agent.update_model(model)
+    }
+    fn decide(&mut self) -> Decision<T::Action> {
+        if self.surprised {
+            // The model is demonstrably wrong, so it is more safe to request a model update.
+            self.surprised = false;
+            return Decision::RequestModel;
+        }
+        self.agent.decide()
+    }
+    fn act(&mut self, action: T::Action) {
+        self.agent.act(action);
+        self.predicted = Some(self.agent.model().clone());
+    }
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Surprised<T> where T::Model: Clone {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: &(u32, u32), b: &(u32, u32)) -> f64 {(a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as f64}
+
+    #[test]
+    fn surprise() {
+        let mut s = Surprised::new(crate::tests::four().add(1), distance, 0.5);
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        s.update_model((4, 1));
+        assert_eq!(s.surprise, Some(0.0));
+        assert_eq!(s.decide(), Decision::Action(1));
+        s.act(1);
+        // The goal turned out to be different, although probes would agree.
+        s.update_model((6, 2));
+        assert_eq!(s.surprise, Some(2.0));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.update_model((6, 2));
+        assert_eq!(s.decide(), Decision::Action(1));
+    }
+}
@@ -0,0 +1,125 @@
+//! Decision diffs for swapping the core decider.
+//!
+//! When the decider of core zero is hot-swapped or retrained,
+//! the new decider might behave differently on models that the old one handled well.
+//! The function `diff_cores` replays a held-out set of recorded models through
+//! the layered agent with the old core and with the new core,
+//! and reports where the deciders and the layered decisions diverge.
+//!
+//! `AgentN::swap_core_gated` only replaces the core when the fraction of diverging
+//! layered decisions is acceptable, and the new core never acts where the old one did not.
+
+use crate::{Agent, AgentN, AgentZ, Decision, Diagnosis};
+
+/// Stores the decisions of the old and new core on a model where they diverge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoreDiff<M, A> {
+    /// The recorded model.
+    pub model: M,
+    /// The action of the old decider.
+    pub old_action: A,
+    /// The action of the new decider.
+    pub new_action: A,
+    /// The layered decision with the old core.
+    pub old: Diagnosis<A>,
+    /// The layered decision with the new core.
+    pub new: Diagnosis<A>,
+}
+
+/// Stores a report of replaying recorded models through the old and new core.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwapReport<M, A> {
+    /// The number of recorded models.
+    pub models: usize,
+    /// The number of models where the deciders chose different actions.
+    pub decider_diffs: usize,
+    /// The number of models where the layered decisions differ.
+    pub decision_diffs: usize,
+    /// The number of models where the new core acts, while the old one does not.
+    pub less_cautious: usize,
+    /// The models where the deciders or the layered decisions differ.
+    pub diffs: Vec<CoreDiff<M, A>>,
+}
+
+impl<M, A> SwapReport<M, A> {
+    /// Returns the fraction of models where the layered decisions differ.
+    pub fn divergence(&self) -> f64 {
+        if self.models == 0 {0.0} else {self.decision_diffs as f64 / self.models as f64}
+    }
+
+    /// Returns `true` if the divergence is at most some fraction,
+    /// and the new core never acts where the old one does not.
+    pub fn is_acceptable(&self, max_divergence: f64) -> bool {
+        self.less_cautious == 0 && self.divergence() <= max_divergence
+    }
+}
+
+/// Replays recorded models through a layered agent with its own core and with a new core.
+///
+/// The agent is cloned, such that it is not changed.
+pub fn diff_cores<M, A, D>(agent: &AgentN<M, A, D>, core: &AgentZ<M, A, D>, models: &[M]) -> SwapReport<M, A>
+    where M: Clone, A: Clone + PartialEq
+{
+    let mut old = agent.clone();
+    let mut new = agent.clone();
+    new.replace_core(core.clone());
+    let mut report = SwapReport {models: models.len(), decider_diffs: 0, decision_diffs: 0, less_cautious: 0, diffs: vec![]};
+    for model in models {
+        old.update_model(model.clone());
+        new.update_model(model.clone());
+        let (old_action, new_action) = ((old.z.decider)(model), (new.z.decider)(model));
+        let (a, b) = (old.diagnose(), new.diagnose());
+        let decider_diff = old_action != new_action;
+        let decision_diff = a.decision != b.decision;
+        if decider_diff {report.decider_diffs += 1}
+        if decision_diff {report.decision_diffs += 1}
+        if let (Decision::RequestModel | Decision::Halt, Decision::Action(_)) = (&a.decision, &b.decision) {
+            report.less_cautious += 1;
+        }
+        if decider_diff || decision_diff {
+            report.diffs.push(CoreDiff {model: model.clone(), old_action, new_action, old: a, new: b});
+        }
+    }
+    report
+}
+
+impl<M: Clone, A: Clone + PartialEq, D> AgentN<M, A, D> {
+    /// Replaces the core zero agent when replaying recorded models shows an acceptable divergence,
+    /// returning the old core.
+    ///
+    /// Returns the report when the swap is rejected.
+    pub fn swap_core_gated(
+        &mut self,
+        core: AgentZ<M, A, D>,
+        models: &[M],
+        max_divergence: f64
+    ) -> Result<AgentZ<M, A, D>, SwapReport<M, A>> {
+        let report = diff_cores(self, &core, models);
+        if !report.is_acceptable(max_divergence) {return Err(report)}
+        Ok(self.replace_core(core))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap() {
+        let models = [(4, 0), (4, 2), (4, 3), (4, 4)];
+        // The new decider stops one short of the goal.
+        let mut core = crate::tests::four();
+        core.decider = |m| if m.1 + 1 < m.0 {1} else {0};
+        let s = crate::tests::four().add(1);
+        let report = diff_cores(&s, &core, &models);
+        assert_eq!((report.decider_diffs, report.decision_diffs, report.less_cautious), (1, 3, 2));
+        assert_eq!(report.diffs[1].model, (4, 3));
+        let reason = crate::Reason::Agree {layer: 1, probe: 0};
+        assert_eq!(report.diffs[1].new, Diagnosis {decision: Decision::Action(0), reason});
+
+        let mut s = s;
+        assert!(s.swap_core_gated(core, &models, 1.0).is_err());
+        assert!(s.swap_core_gated(crate::tests::four(), &models, 0.0).is_ok());
+        assert!(s.handoff);
+    }
+}
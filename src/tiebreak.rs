@@ -0,0 +1,125 @@
+//! Reproducible tie-breaking of equally acceptable actions.
+//!
+//! Ranked or approximate agreement might yield several equally acceptable actions,
+//! e.g. a Pareto-optimal set or actions tied in all lexicographic objectives.
+//! Instead of picking whatever comes first, a `TieBreaker` chooses among them
+//! using a seeded generator, uniformly or weighted by decider scores.
+//!
+//! Every tie that is broken is logged as a `TieBreak`,
+//! such that a choice can be attributed to the seed and the draw, and reproduced.
+
+use crate::pareto::ParetoSet;
+use crate::rng::Rng;
+
+/// Stores how ties are broken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Policy {
+    /// Every candidate is equally likely.
+    Uniform,
+    /// Candidates are chosen with probability proportional to their weights.
+    ///
+    /// When no weight is positive, every candidate is equally likely.
+    Weighted,
+}
+
+/// Stores a logged tie-break.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TieBreak {
+    /// The weight of each candidate, which are all `1` for uniform tie-breaking.
+    pub weights: Vec<f64>,
+    /// The random number in `[0, 1)` that was drawn.
+    pub draw: f64,
+    /// The index of the chosen candidate.
+    pub chosen: usize,
+}
+
+/// Stores a seeded tie-breaking policy with an audit log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TieBreaker {
+    /// The seed of the generator.
+    pub seed: u64,
+    /// How ties are broken.
+    pub policy: Policy,
+    /// The tie-breaks since the last reset.
+    pub log: Vec<TieBreak>,
+    rng: Rng,
+}
+
+impl TieBreaker {
+    /// Creates a new tie-breaker from a seed.
+    pub fn new(seed: u64, policy: Policy) -> Self {
+        TieBreaker {seed, policy, log: vec![], rng: Rng::new(seed)}
+    }
+
+    /// Reseeds the generator and clears the log, reproducing the same choices.
+    pub fn reset(&mut self) {
+        self.rng = Rng::new(self.seed);
+        self.log.clear();
+    }
+
+    /// Chooses the index of a candidate by their weights.
+    ///
+    /// A single candidate is chosen without drawing or logging.
+    /// Returns `None` if there are no candidates.
+    pub fn choose(&mut self, weights: &[f64]) -> Option<usize> {
+        match weights.len() {
+            0 => return None,
+            1 => return Some(0),
+            _ => {}
+        }
+        let weights: Vec<f64> = match self.policy {
+            Policy::Weighted if weights.iter().any(|&w| w > 0.0) =>
+                weights.iter().map(|&w| if w > 0.0 {w} else {0.0}).collect(),
+            _ => vec![1.0; weights.len()],
+        };
+        let draw = self.rng.next_f64();
+        let total: f64 = weights.iter().sum();
+        let mut acc = 0.0;
+        let chosen = weights.iter()
+            .position(|w| {
+                acc += w;
+                draw * total < acc
+            })
+            .unwrap_or(weights.len() - 1);
+        self.log.push(TieBreak {weights, draw, chosen});
+        Some(chosen)
+    }
+
+    /// Chooses a candidate, weighted by a score.
+    pub fn pick<'a, A>(&mut self, candidates: &'a [A], score: impl Fn(&A) -> f64) -> Option<&'a A> {
+        let weights: Vec<f64> = candidates.iter().map(score).collect();
+        self.choose(&weights).map(|i| &candidates[i])
+    }
+}
+
+impl<A> ParetoSet<A> {
+    /// Chooses an action from the set, weighted by a score.
+    pub fn break_tie(&self, breaker: &mut TieBreaker, score: impl Fn(&A) -> f64) -> Option<&A> {
+        breaker.pick(&self.actions, score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible() {
+        let ties = crate::lexicographic::ties(&(), &[1, 2, 3, 4], &[|_, &a| (a % 2) as f64]);
+        assert_eq!(ties, vec![1, 3]);
+
+        let mut t = TieBreaker::new(7, Policy::Uniform);
+        let first: Vec<i32> = (0..8).map(|_| *t.pick(&ties, |_| 1.0).unwrap()).collect();
+        assert_eq!(t.log.len(), 8);
+        t.reset();
+        let second: Vec<i32> = (0..8).map(|_| *t.pick(&ties, |_| 1.0).unwrap()).collect();
+        assert_eq!(first, second);
+        assert_eq!(t.pick(&[5], |_| 1.0), Some(&5));
+        assert_eq!(t.log.len(), 8);
+
+        let mut t = TieBreaker::new(7, Policy::Weighted);
+        let set = ParetoSet {actions: vec!['a', 'b']};
+        assert!((0..8).all(|_| set.break_tie(&mut t, |&c| (c == 'b') as u8 as f64) == Some(&'b')));
+        assert_eq!(t.log[0].weights, vec![0.0, 1.0]);
+    }
+}
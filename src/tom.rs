@@ -0,0 +1,122 @@
+//! Bounded-depth recursive theory of mind.
+//!
+//! When agents model each other, a model might contain what I think you think I will do,
+//! and so on. A `Tom` stores one model per recursion level,
+//! where level `0` is the own model, level `1` is what the agent thinks the other thinks,
+//! level `2` is what the agent thinks the other thinks the agent thinks, etc.
+//!
+//! Nesting is capped at a maximum depth, which prevents runaway nesting
+//! when agents model each other modeling each other.
+//!
+//! Mutations can target each recursion level separately.
+//! `mutate_level::<M, L>` mutates level `L`, and is a mutater of `Tom<M>`
+//! that can be used for probing, while `undo_level` is the corresponding undoer.
+//! The function `mutaters` returns one mutater per level.
+
+/// The maximum depth supported by `mutaters`.
+pub const MAX_DEPTH: usize = 8;
+
+/// Implemented by models that can be mutated at a recursion level.
+pub trait Level {
+    /// The delta of a mutation.
+    type Delta;
+    /// Mutates the model.
+    fn mutate(&mut self) -> Self::Delta;
+    /// Undoes a mutation.
+    fn undo(&mut self, delta: Self::Delta);
+}
+
+/// Stores models at each recursion level.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tom<M> {
+    /// The models, from the own model to the most nested one.
+    pub levels: Vec<M>,
+    /// The maximum number of levels.
+    pub max_depth: usize,
+}
+
+impl<M> Tom<M> {
+    /// Creates a new recursive model with the own model.
+    pub fn new(model: M, max_depth: usize) -> Self {
+        Tom {levels: vec![model], max_depth: max_depth.max(1)}
+    }
+
+    /// Returns the number of levels.
+    pub fn depth(&self) -> usize {self.levels.len()}
+
+    /// Adds a more nested level.
+    ///
+    /// Returns `false` if the maximum depth is reached.
+    pub fn nest(&mut self, model: M) -> bool {
+        if self.depth() >= self.max_depth {return false}
+        self.levels.push(model);
+        true
+    }
+}
+
+/// Stores a mutation at a recursion level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LevelDelta<D> {
+    /// The recursion level.
+    pub level: usize,
+    /// The delta, or `None` if the model is not nested that deep.
+    pub delta: Option<D>,
+}
+
+/// Mutates recursion level `L`.
+pub fn mutate_level<M: Level, const L: usize>(tom: &mut Tom<M>) -> LevelDelta<M::Delta> {
+    LevelDelta {level: L, delta: tom.levels.get_mut(L).map(|m| m.mutate())}
+}
+
+/// Undoes a mutation at a recursion level.
+pub fn undo_level<M: Level>(tom: &mut Tom<M>, delta: LevelDelta<M::Delta>) {
+    if let (Some(m), Some(d)) = (tom.levels.get_mut(delta.level), delta.delta) {m.undo(d)}
+}
+
+/// A mutater of a recursion level.
+pub type LevelMutater<M> = fn(&mut Tom<M>) -> LevelDelta<<M as Level>::Delta>;
+
+/// Returns one mutater per recursion level, up to a depth of at most `MAX_DEPTH`.
+pub fn mutaters<M: Level>(depth: usize) -> Vec<LevelMutater<M>> {
+    let all: [LevelMutater<M>; MAX_DEPTH] = [
+        mutate_level::<M, 0>, mutate_level::<M, 1>, mutate_level::<M, 2>, mutate_level::<M, 3>,
+        mutate_level::<M, 4>, mutate_level::<M, 5>, mutate_level::<M, 6>, mutate_level::<M, 7>,
+    ];
+    all[..depth.min(MAX_DEPTH)].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, AgentZ, Decision};
+
+    impl Level for i32 {
+        type Delta = ();
+        fn mutate(&mut self) {*self += 1}
+        fn undo(&mut self, _: ()) {*self -= 1}
+    }
+
+    #[test]
+    fn target_levels() {
+        let mut tom = Tom::new(0, 2);
+        assert!(tom.nest(0));
+        assert!(!tom.nest(0));
+        assert_eq!(mutaters::<i32>(tom.depth()).len(), 2);
+        let delta = mutate_level::<i32, 1>(&mut tom);
+        assert_eq!(tom.levels, vec![0, 1]);
+        undo_level(&mut tom, delta);
+        assert_eq!(mutate_level::<i32, 2>(&mut tom).delta, None);
+
+        // The agent moves when it thinks the other thinks it should.
+        let mut s = AgentZ {
+            model: tom,
+            decider: |t: &Tom<i32>| t.levels[1].signum(),
+            actor: |_, _| {},
+            mutater: mutate_level::<i32, 1>,
+            undoer: undo_level,
+        }.add(1);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        s.z.mutater = mutate_level::<i32, 0>;
+        assert_eq!(s.decide(), Decision::Action(0));
+    }
+}
@@ -0,0 +1,197 @@
+//! Recorded decision traces with Graphviz DOT export.
+//!
+//! A `Trace` records the model and the decision of every step of an episode,
+//! together with the rationale of the safety layers.
+//!
+//! `Trace::to_dot` renders the trace as a DOT graph:
+//! model states are nodes, actions and requests are edges between consecutive states,
+//! and mutation probes are dashed sub-nodes annotated with their outcome.
+//! Probes in inner safety layers hang off the probe that decided using them.
+//!
+//! With the `cbor` feature, `Trace::to_cbor` encodes the trace compactly for logging,
+//! where models and actions are encoded as strings by user-supplied codecs.
+//!
+//! With the `replay` feature, a step also records the deltas journaled by the agent,
+//! see `AgentN::journal_deltas`.
+
+use std::fmt::{self, Write};
+
+use crate::rationale::Rationale;
+use crate::{Agent, AgentN, Decision};
+
+/// Stores a recorded step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep<M, A> {
+    /// The model before deciding.
+    pub model: M,
+    /// The decision.
+    pub decision: Decision<A>,
+    /// The rationale of the decision.
+    pub rationale: Rationale,
+    /// The deltas applied and undone while deciding, when journaled.
+    #[cfg(feature = "replay")]
+    pub deltas: Vec<crate::replay::DeltaRecord>,
+}
+
+/// Stores a recorded episode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace<M, A> {
+    /// The recorded steps, in order.
+    pub steps: Vec<TraceStep<M, A>>,
+    /// The manifest needed to reproduce the trace, if any.
+    pub manifest: Option<crate::manifest::Manifest>,
+}
+
+impl<M, A> Default for Trace<M, A> {
+    fn default() -> Self {Trace {steps: vec![], manifest: None}}
+}
+
+fn escape(s: &str) -> String {s.replace('\\', "\\\\").replace('"', "\\\"")}
+
+fn write_probes(out: &mut String, parent: &str, rationale: &Rationale) -> fmt::Result {
+    for (k, check) in rationale.checks.iter().enumerate() {
+        let node = format!("{}_{}", parent, k);
+        let probe = match check.second {
+            Some(j) => format!("#{}+#{}", check.probe, j),
+            None => format!("#{}", check.probe),
+        };
+        writeln!(out, "  {} [shape=box, style=dashed, label=\"layer {} {}: {}\"];",
+                 node, rationale.layer, probe, check.outcome)?;
+        writeln!(out, "  {} -> {} [style=dashed, arrowhead=none];", parent, node)?;
+        if let Some(inner) = &check.inner {write_probes(out, &node, inner)?}
+    }
+    Ok(())
+}
+
+impl<M: Clone, A> Trace<M, A> {
+    /// Creates a new empty trace.
+    pub fn new() -> Self {Self::default()}
+
+    /// Decides using the agent and records the step.
+    pub fn record<D>(&mut self, agent: &mut AgentN<M, A, D>) -> Decision<A>
+        where A: Clone + PartialEq
+    {
+        let model = agent.z.model.clone();
+        let (decision, rationale) = agent.decide_rationale();
+        self.steps.push(TraceStep {
+            model,
+            decision: decision.clone(),
+            rationale,
+            #[cfg(feature = "replay")]
+            deltas: agent.take_deltas(),
+        });
+        decision
+    }
+
+    /// Records steps until the agent does not act, or until reaching a maximum number of steps.
+    ///
+    /// Actions are performed on the model of the agent.
+    pub fn run<D>(&mut self, agent: &mut AgentN<M, A, D>, max_steps: usize)
+        where A: Clone + PartialEq
+    {
+        for _ in 0..max_steps {
+            match self.record(agent) {
+                Decision::Action(a) => agent.act(a),
+                Decision::RequestModel | Decision::Halt => break,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<M, A> Trace<M, A> {
+    /// Returns the trace as CBOR, encoding models and actions as strings.
+    pub fn to_cbor(&self, encode_model: fn(&M) -> String, encode_action: fn(&A) -> String) -> Vec<u8> {
+        use crate::json::Value;
+
+        let steps = self.steps.iter().map(|step| {
+            let mut fields = vec![("model".into(), Value::Str(encode_model(&step.model)))];
+            match &step.decision {
+                Decision::Action(a) => {
+                    fields.push(("decision".into(), Value::Str("action".into())));
+                    fields.push(("action".into(), Value::Str(encode_action(a))));
+                }
+                Decision::RequestModel => fields.push(("decision".into(), Value::Str("request_model".into()))),
+                Decision::Halt => fields.push(("decision".into(), Value::Str("halt".into()))),
+            }
+            fields.push(("rationale".into(), step.rationale.to_value()));
+            #[cfg(feature = "replay")]
+            fields.push(("deltas".into(), Value::Arr(step.deltas.iter().map(|d| Value::Str(d.to_string())).collect())));
+            Value::Obj(fields)
+        }).collect();
+        crate::cbor::from_value(&Value::Arr(steps))
+    }
+}
+
+impl<M: fmt::Debug, A: fmt::Debug> Trace<M, A> {
+    /// Renders the trace as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph trace {\n");
+        for (i, step) in self.steps.iter().enumerate() {
+            let node = format!("s{}", i);
+            let _ = writeln!(out, "  {} [label=\"{}\"];", node, escape(&format!("{:?}", step.model)));
+            let _ = write_probes(&mut out, &node, &step.rationale);
+            let label = match &step.decision {
+                Decision::Action(a) => format!("{:?}", a),
+                Decision::RequestModel => "request model".into(),
+                Decision::Halt => "halt".into(),
+            };
+            let next = if i + 1 < self.steps.len() {format!("s{}", i + 1)} else {
+                let _ = writeln!(out, "  end [shape=point];");
+                "end".into()
+            };
+            let _ = writeln!(out, "  {} -> {} [label=\"{}\"];", node, next, escape(&label));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot() {
+        let mut s = crate::tests::four().add(1);
+        let mut trace = Trace::new();
+        trace.run(&mut s, 10);
+        assert_eq!(trace.steps.len(), 4);
+        assert_eq!(trace.steps[3].decision, Decision::RequestModel);
+        let dot = trace.to_dot();
+        assert!(dot.starts_with("digraph trace {\n  s0 [label=\"(4, 0)\"];\n"));
+        assert!(dot.contains("  s0_0 [shape=box, style=dashed, label=\"layer 1 #0: agree\"];\n"));
+        assert!(dot.contains("  s0 -> s1 [label=\"1\"];\n"));
+        assert!(dot.contains("  s3 -> end [label=\"request model\"];\n"));
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor() {
+        let mut s = crate::tests::four().add(1);
+        let mut trace = Trace::new();
+        trace.run(&mut s, 10);
+        let bin = trace.to_cbor(|m| format!("{:?}", m), |a| a.to_string());
+        let json = crate::cbor::to_json(&bin).unwrap();
+        assert!(json.starts_with("[{\"model\":\"(4, 0)\",\"decision\":\"action\",\"action\":\"1\",\"rationale\":{"));
+        assert!(bin.len() < json.len());
+        // Deep rationales are encoded too.
+        let mut s = crate::tests::four().add(32);
+        s.update_model((40, 0));
+        let mut trace = Trace::new();
+        trace.record(&mut s);
+        let json = crate::cbor::to_json(&trace.to_cbor(|m| format!("{:?}", m), |a| a.to_string())).unwrap();
+        assert!(json.starts_with("[{\"model\":\"(40, 0)\",\"decision\":\"action\""));
+    }
+
+    #[cfg(feature = "replay")]
+    #[test]
+    fn deltas() {
+        let mut s = crate::tests::four().add(1);
+        s.journal_deltas();
+        let mut trace = Trace::new();
+        trace.run(&mut s, 10);
+        let deltas: Vec<_> = trace.steps.iter().map(|step| step.deltas.len()).collect();
+        assert_eq!(deltas, vec![2; 4]);
+    }
+}
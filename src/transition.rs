@@ -0,0 +1,179 @@
+//! Transition system export for external model checkers.
+//!
+//! For a specific deployment, the safety claims can be checked by an external model checker
+//! on a finite abstraction of the model space.
+//! The function `export` explores the states reachable from some initial models,
+//! deciding with the layered agent in each state:
+//!
+//! - An action leads to the model after acting
+//! - A model request leads to each possible model update, given by the user
+//! - Halting has no transitions
+//!
+//! The result is a `TransitionSystem`, which can be written as JSON with `to_json`,
+//! or as a TLA+ module with `to_tla`.
+//! In the TLA+ module, the variable `s` is the index of the state,
+//! and `Acting` is the set of states where the agent acts,
+//! such that safety properties can be stated as invariants, e.g. `s \in Acting => s \in Safe`.
+
+use std::fmt::{self, Write};
+
+use crate::{json, Agent, AgentN, Decision};
+
+/// Stores a state of a transition system.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct State {
+    /// The model, formatted for debugging.
+    pub model: String,
+    /// The decision in this state, formatted for debugging.
+    pub decision: String,
+    /// The reason for the decision.
+    pub reason: String,
+    /// Whether the agent acts in this state.
+    pub acts: bool,
+}
+
+/// Stores a transition between states.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Transition {
+    /// The index of the state before.
+    pub from: usize,
+    /// The index of the state after.
+    pub to: usize,
+    /// Describes the transition, either an action or `request`.
+    pub label: String,
+}
+
+/// Stores a finite transition system of an agent.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TransitionSystem {
+    /// The states.
+    pub states: Vec<State>,
+    /// The indices of the initial states.
+    pub initial: Vec<usize>,
+    /// The transitions.
+    pub transitions: Vec<Transition>,
+    /// Whether all reachable states were explored, instead of stopping at the maximum.
+    pub complete: bool,
+}
+
+/// Explores the states reachable from initial models, up to a maximum number of states.
+///
+/// The agent is cloned, such that it is not changed.
+pub fn export<M, A, D>(
+    agent: &AgentN<M, A, D>,
+    initial: Vec<M>,
+    updates: fn(&M) -> Vec<M>,
+    max_states: usize,
+) -> TransitionSystem
+    where M: Clone + PartialEq + fmt::Debug, A: Clone + PartialEq + fmt::Debug
+{
+    let mut ts = TransitionSystem {complete: true, ..Default::default()};
+    let mut models: Vec<M> = vec![];
+    let index = |models: &mut Vec<M>, m: M| -> Option<usize> {
+        if let Some(i) = models.iter().position(|x| *x == m) {return Some(i)}
+        if models.len() >= max_states {return None}
+        models.push(m);
+        Some(models.len() - 1)
+    };
+    for m in initial {
+        match index(&mut models, m) {
+            Some(i) => if !ts.initial.contains(&i) {ts.initial.push(i)},
+            None => ts.complete = false,
+        }
+    }
+    let mut i = 0;
+    while i < models.len() {
+        let mut s = agent.clone();
+        s.update_model(models[i].clone());
+        let diagnosis = s.diagnose();
+        let next: Vec<(M, String)> = match &diagnosis.decision {
+            Decision::Action(a) => {
+                let label = format!("{:?}", a);
+                s.act(a.clone());
+                vec![(s.z.model.clone(), label)]
+            }
+            Decision::RequestModel => updates(&models[i]).into_iter().map(|m| (m, "request".into())).collect(),
+            Decision::Halt => vec![],
+        };
+        ts.states.push(State {
+            model: format!("{:?}", models[i]),
+            decision: format!("{:?}", diagnosis.decision),
+            reason: diagnosis.reason.to_string(),
+            acts: matches!(diagnosis.decision, Decision::Action(_)),
+        });
+        for (m, label) in next {
+            match index(&mut models, m) {
+                Some(to) => ts.transitions.push(Transition {from: i, to, label}),
+                None => ts.complete = false,
+            }
+        }
+        i += 1;
+    }
+    ts
+}
+
+impl TransitionSystem {
+    /// Returns the transition system as JSON.
+    pub fn to_json(&self) -> String {
+        let states: Vec<String> = self.states.iter().enumerate().map(|(i, s)| format!(
+            "{{\"id\":{},\"model\":{},\"decision\":{},\"reason\":{},\"acts\":{}}}",
+            i, json::string(&s.model), json::string(&s.decision), json::string(&s.reason), s.acts
+        )).collect();
+        let initial: Vec<String> = self.initial.iter().map(|i| i.to_string()).collect();
+        let transitions: Vec<String> = self.transitions.iter().map(|t| format!(
+            "{{\"from\":{},\"to\":{},\"label\":{}}}", t.from, t.to, json::string(&t.label)
+        )).collect();
+        format!("{{\"states\":[{}],\"initial\":[{}],\"transitions\":[{}],\"complete\":{}}}",
+                states.join(","), initial.join(","), transitions.join(","), self.complete)
+    }
+
+    /// Returns the transition system as a TLA+ module.
+    pub fn to_tla(&self, name: &str) -> String {
+        let set = |ids: Vec<usize>| {
+            ids.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ")
+        };
+        let mut out = String::new();
+        let _ = writeln!(out, "---- MODULE {} ----", name);
+        let _ = writeln!(out, "VARIABLE s");
+        for (i, state) in self.states.iter().enumerate() {
+            let _ = writeln!(out, "\\* {}: {} -> {} ({})", i, state.model, state.decision, state.reason);
+        }
+        let _ = writeln!(out, "Acting == {{{}}}",
+                         set((0..self.states.len()).filter(|&i| self.states[i].acts).collect()));
+        let _ = writeln!(out, "Init == s \\in {{{}}}", set(self.initial.clone()));
+        let _ = write!(out, "Next ==");
+        if self.transitions.is_empty() {let _ = write!(out, " FALSE");}
+        for t in &self.transitions {
+            let _ = write!(out, "\n    \\/ s = {} /\\ s' = {}", t.from, t.to);
+        }
+        let _ = writeln!(out, "\nSpec == Init /\\ [][Next]_s");
+        let _ = writeln!(out, "====");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn four() {
+        let ts = export(&crate::tests::four().add(1), vec![(4, 2)], |_| vec![(3, 0)], 10);
+        assert!(ts.complete);
+        assert_eq!(ts.states.iter().map(|s| s.acts).collect::<Vec<_>>(), vec![true, false, true, true, false]);
+        assert_eq!(ts.transitions[..2], [
+            Transition {from: 0, to: 1, label: "1".into()},
+            Transition {from: 1, to: 2, label: "request".into()},
+        ]);
+        assert_eq!(ts.transitions[4], Transition {from: 4, to: 2, label: "request".into()});
+        assert!(ts.to_json().starts_with(
+            "{\"states\":[{\"id\":0,\"model\":\"(4, 2)\",\"decision\":\"Action(1)\",\
+             \"reason\":\"mutation #0 of layer 1 agreed\",\"acts\":true}"));
+        let tla = ts.to_tla("Four");
+        assert!(tla.contains("Acting == {0, 2, 3}\nInit == s \\in {0}\nNext ==\n    \\/ s = 0 /\\ s' = 1\n"));
+
+        let ts = export(&crate::tests::four().add(1), vec![(4, 0)], |_| vec![], 2);
+        assert!(!ts.complete);
+        assert_eq!(ts.states.len(), 2);
+    }
+}
@@ -0,0 +1,131 @@
+//! Trust scores for model-update sources.
+//!
+//! When several sources can answer a model request, e.g. sensors, humans or other agents,
+//! some of them might be less reliable than others.
+//! A `Trusted` agent tracks the reliability of every source as a score between `0` and `1`,
+//! and rejects updates from sources with a score below a threshold.
+//!
+//! Reliability is learned from feedback, e.g. when a model update was later found to be wrong.
+//! The score moves towards `1` on reliable feedback and towards `0` on unreliable feedback.
+//!
+//! Calling `update_model` directly bypasses the check, as from a fully trusted source.
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores a source of model updates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Source {
+    /// The name of the source.
+    pub name: &'static str,
+    /// The reliability of the source, between `0` and `1`.
+    pub trust: f64,
+    /// The number of updates accepted from the source.
+    pub accepted: usize,
+    /// The number of updates rejected from the source.
+    pub rejected: usize,
+}
+
+/// Stores an agent that weighs model updates by the trust in their source.
+#[derive(Clone, Debug)]
+pub struct Trusted<T> {
+    /// The inner agent.
+    pub agent: T,
+    /// The sources, indexed by the identifier returned from `Trusted::source`.
+    pub sources: Vec<Source>,
+    /// The minimum trust required for an update to be applied.
+    pub threshold: f64,
+    /// How fast the trust moves on feedback, between `0` and `1`.
+    pub learning_rate: f64,
+}
+
+impl<T: Agent> Trusted<T> {
+    /// Creates a new agent that rejects updates from sources with trust below a threshold.
+    pub fn new(agent: T, threshold: f64) -> Self {
+        Trusted {agent, sources: vec![], threshold, learning_rate: 0.25}
+    }
+
+    /// Registers a source with an initial trust, returning its identifier.
+    pub fn source(&mut self, name: &'static str, trust: f64) -> usize {
+        self.sources.push(Source {name, trust, accepted: 0, rejected: 0});
+        self.sources.len() - 1
+    }
+
+    /// Returns `true` if updates from a source are applied.
+    pub fn is_trusted(&self, source: usize) -> bool {self.sources[source].trust >= self.threshold}
+
+    /// Applies a model update from a source, unless it has low trust.
+    ///
+    /// Returns `true` if the update was applied.
+    pub fn update_from(&mut self, source: usize, model: T::Model) -> bool {
+        let trusted = self.is_trusted(source);
+        let s = &mut self.sources[source];
+        if trusted {s.accepted += 1} else {s.rejected += 1}
+        if trusted {self.agent.update_model(model)}
+        trusted
+    }
+
+    /// Applies the update from the most trusted source among candidate updates.
+    ///
+    /// Returns the source of the applied update,
+    /// or `None` if all sources have low trust.
+    pub fn update_best(&mut self, updates: impl IntoIterator<Item = (usize, T::Model)>) -> Option<usize> {
+        let mut best: Option<(usize, T::Model)> = None;
+        for (source, model) in updates {
+            if !self.is_trusted(source) {
+                self.sources[source].rejected += 1;
+                continue;
+            }
+            match &best {
+                Some((b, _)) if self.sources[*b].trust >= self.sources[source].trust => {}
+                _ => best = Some((source, model)),
+            }
+        }
+        let (source, model) = best?;
+        self.sources[source].accepted += 1;
+        self.agent.update_model(model);
+        Some(source)
+    }
+
+    /// Updates the trust in a source from feedback on whether its update was reliable.
+    pub fn feedback(&mut self, source: usize, reliable: bool) {
+        let target = if reliable {1.0} else {0.0};
+        let s = &mut self.sources[source];
+        s.trust += self.learning_rate * (target - s.trust);
+    }
+}
+
+impl<T: Agent> Agent for Trusted<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {self.agent.decide()}
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Trusted<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_low_trust() {
+        let mut s = Trusted::new(crate::tests::four().add(1), 0.5);
+        let sensor = s.source("sensor", 0.6);
+        let human = s.source("human", 0.9);
+        assert!(s.update_from(sensor, (3, 0)));
+        s.feedback(sensor, false);
+        assert!(s.sources[sensor].trust < 0.5);
+        assert!(!s.update_from(sensor, (9, 0)));
+        assert_eq!(s.agent.z.model, (3, 0));
+        assert_eq!(s.sources[sensor].rejected, 1);
+
+        assert_eq!(s.update_best(vec![(sensor, (9, 0)), (human, (2, 0))]), Some(human));
+        assert_eq!(s.agent.z.model, (2, 0));
+    }
+}
@@ -0,0 +1,150 @@
+//! Automatic safety-level tuning per environment.
+//!
+//! The function `tune` searches over layer counts and mutation limits,
+//! returning the minimum configuration that meets a target on a validation set of episodes.
+//!
+//! Configurations are ordered first by layer count, then by mutation limit.
+//! The chosen configuration can be reused to construct agents with `Config::build`.
+//! `tune_parallel` runs the episodes of each configuration on a number of threads.
+
+use crate::batch::{self, Totals};
+use crate::{AgentN, AgentZ};
+
+/// Stores a safety configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The number of safety layers.
+    pub layers: usize,
+    /// The mutation limit of every safety layer.
+    pub mutation_limit: u8,
+}
+
+impl Config {
+    /// Constructs an agent wrapped in safety layers using this configuration.
+    pub fn build<M, A, D>(&self, z: AgentZ<M, A, D>) -> AgentN<M, A, D> {
+        let mut agent = z.add(self.layers);
+        agent.set_mutation_limit(self.mutation_limit);
+        agent
+    }
+}
+
+/// Stores the maximum rates that a configuration must meet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    /// The maximum fraction of decisions that request a model update.
+    pub request_rate: f64,
+    /// The maximum fraction of episodes where the safety was violated.
+    pub violation_rate: f64,
+}
+
+/// Stores statistics of an episode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of decisions.
+    pub decisions: usize,
+    /// The number of decisions that requested a model update.
+    pub requests: usize,
+    /// Whether the safety was violated.
+    pub violation: bool,
+}
+
+/// Returns the minimum configuration that meets the target on the validation set.
+///
+/// The episode function gets a fresh agent for every episode in the validation set.
+/// Returns `None` if no configuration meets the target.
+pub fn tune<M, A, D, E, F>(
+    z: &AgentZ<M, A, D>,
+    layers: impl IntoIterator<Item = usize>,
+    mutation_limits: impl IntoIterator<Item = u8> + Clone,
+    target: Target,
+    episodes: &[E],
+    mut f: F
+) -> Option<Config>
+    where M: Clone, F: FnMut(&mut AgentN<M, A, D>, &E) -> Stats
+{
+    search(layers, mutation_limits, target, |config| {
+        let mut totals = Totals::default();
+        for e in episodes {totals.add(&f(&mut config.build(z.clone()), e))}
+        totals
+    })
+}
+
+/// Returns the minimum configuration that meets the target on the validation set,
+/// like `tune`, running the episodes of each configuration on a number of threads.
+///
+/// When the number of threads is `0`, the available parallelism is used.
+pub fn tune_parallel<M, A, D, E, F>(
+    z: &AgentZ<M, A, D>,
+    layers: impl IntoIterator<Item = usize>,
+    mutation_limits: impl IntoIterator<Item = u8> + Clone,
+    target: Target,
+    episodes: &[E],
+    threads: usize,
+    f: F
+) -> Option<Config>
+    where M: Clone + Sync, E: Sync, F: Fn(&mut AgentN<M, A, D>, &E) -> Stats + Sync
+{
+    let indices: Vec<u64> = (0..episodes.len() as u64).collect();
+    search(layers, mutation_limits, target, |config| {
+        batch::run(&indices, threads, |i| f(&mut config.build(z.clone()), &episodes[i as usize]))
+            .iter().collect()
+    })
+}
+
+fn search(
+    layers: impl IntoIterator<Item = usize>,
+    mutation_limits: impl IntoIterator<Item = u8> + Clone,
+    target: Target,
+    mut f: impl FnMut(Config) -> Totals
+) -> Option<Config> {
+    for n in layers {
+        for mutation_limit in mutation_limits.clone() {
+            let config = Config {layers: n, mutation_limit};
+            let totals = f(config);
+            if totals.request_rate() <= target.request_rate &&
+               totals.violation_rate() <= target.violation_rate {
+                return Some(config);
+            }
+            // Without safety layers, the mutation limit has no effect.
+            if n == 0 {break}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn tune_increments() {
+        // The true goal is either `3` or `4`, but the agent believes it is `4`.
+        // When requesting a model update, the agent is told the true goal.
+        let run = |agent: &mut AgentN<(u32, u32), i32, i32>, goal: &u32| {
+            let mut stats = Stats::default();
+            for _ in 0..10 {
+                stats.decisions += 1;
+                match agent.decide() {
+                    Decision::Action(0) | Decision::Halt => break,
+                    Decision::Action(a) => agent.act(a),
+                    Decision::RequestModel => {
+                        stats.requests += 1;
+                        let state = agent.z().model.1;
+                        agent.update_model((*goal, state));
+                    }
+                }
+                if agent.z().model.1 > *goal {stats.violation = true}
+            }
+            stats
+        };
+        let z = crate::tests::four();
+        let target = Target {request_rate: 1.0, violation_rate: 0.0};
+        let config = tune(&z, 0..3, 1..5, target, &[3, 4], run);
+        assert_eq!(config, Some(Config {layers: 1, mutation_limit: 1}));
+
+        let target = Target {request_rate: 0.1, violation_rate: 0.0};
+        assert_eq!(tune(&z, 0..3, 1..5, target, &[3, 4], run), None);
+        assert_eq!(tune_parallel(&z, 0..3, 1..5, target, &[3, 4], 2, run), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Utility-based deciders with explicit tie-breaking.
+//!
+//! A `DeciderFromUtility` picks the action with the highest utility among an enumerable action set.
+//! When several actions are within a tolerance of the highest utility,
+//! the tie is broken by a `TiePolicy`.
+//!
+//! Near-ties are exactly where mutation probing should ask for clarification,
+//! so the policy `TiePolicy::Request` returns no action on ties.
+//! The decider of a layered agent then uses `Option<A>` as action type:
+//!
+//! ```text
+//! static UTILITY: DeciderFromUtility<M, A> = DeciderFromUtility::new(utility, actions, TiePolicy::Request);
+//! let z = AgentZ {decider: |m| UTILITY.choose(m), ..};
+//! let agent = Tied::new(z.add(n));
+//! ```
+//!
+//! A mutated decision with a tie disagrees with core zero deciding an action,
+//! and a `Tied` agent requests a model update when core zero has a tie.
+
+use std::fmt;
+
+use crate::rng::Rng;
+use crate::{Agent, AgentN, Decision, Diagnosis, Inspect, Reason};
+
+/// The policy for breaking ties between actions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TiePolicy {
+    /// Choose the first tied action.
+    First,
+    /// Choose a tied action using a generator with some seed.
+    ///
+    /// The same set of tied actions always gives the same choice.
+    Random(u64),
+    /// Choose no action, such that a model update is requested.
+    Request,
+}
+
+/// Stores a decider that maximizes utility over an enumerable action set.
+pub struct DeciderFromUtility<M, A> {
+    /// Returns the utility of an action in some model.
+    pub utility: fn(&M, &A) -> f64,
+    /// Returns the action set of some model.
+    pub actions: fn(&M) -> Vec<A>,
+    /// The policy for breaking ties.
+    pub tie: TiePolicy,
+    /// The maximum difference in utility between tied actions.
+    pub tolerance: f64,
+}
+
+impl<M, A> Clone for DeciderFromUtility<M, A> {
+    fn clone(&self) -> Self {*self}
+}
+
+impl<M, A> Copy for DeciderFromUtility<M, A> {}
+
+impl<M, A> fmt::Debug for DeciderFromUtility<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeciderFromUtility")
+            .field("utility", &self.utility)
+            .field("actions", &self.actions)
+            .field("tie", &self.tie)
+            .field("tolerance", &self.tolerance)
+            .finish()
+    }
+}
+
+impl<M, A> DeciderFromUtility<M, A> {
+    /// Creates a new decider where only actions of equal utility are tied.
+    pub const fn new(utility: fn(&M, &A) -> f64, actions: fn(&M) -> Vec<A>, tie: TiePolicy) -> Self {
+        DeciderFromUtility {utility, actions, tie, tolerance: 0.0}
+    }
+
+    /// Sets the maximum difference in utility between tied actions.
+    pub const fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Chooses an action, returning `None` on ties with `TiePolicy::Request`,
+    /// or when there are no actions.
+    pub fn choose(&self, model: &M) -> Option<A> {
+        let mut actions: Vec<(A, f64)> = (self.actions)(model).into_iter()
+            .map(|a| {let u = (self.utility)(model, &a); (a, u)}).collect();
+        let max = actions.iter().map(|(_, u)| *u).fold(f64::NEG_INFINITY, f64::max);
+        // Subtracting from `max` would be NaN when both are infinite.
+        actions.retain(|(_, u)| *u >= max - self.tolerance);
+        let i = match (self.tie, actions.len()) {
+            (_, 0) => return None,
+            (_, 1) | (TiePolicy::First, _) => 0,
+            (TiePolicy::Random(seed), n) => Rng::new(seed).below(n as u64) as usize,
+            (TiePolicy::Request, _) => return None,
+        };
+        Some(actions.swap_remove(i).0)
+    }
+
+    /// Decides on an action, requesting a model update when no action was chosen.
+    pub fn decide(&self, model: &M) -> Decision<A> {
+        match self.choose(model) {
+            Some(a) => Decision::Action(a),
+            None => Decision::RequestModel,
+        }
+    }
+}
+
+/// Stores an agent that requests a model update when core zero chooses no action.
+#[derive(Clone, Debug)]
+pub struct Tied<M, A, D> {
+    /// The inner agent.
+    pub agent: AgentN<M, Option<A>, D>,
+}
+
+impl<M, A: PartialEq, D> Tied<M, A, D> {
+    /// Creates a new agent that requests a model update on ties.
+    pub fn new(agent: AgentN<M, Option<A>, D>) -> Self {Tied {agent}}
+
+    /// Decide what to do next, together with the reason.
+    pub fn diagnose(&mut self) -> Diagnosis<A> {
+        // Probing is not needed when core zero has a tie.
+        if (self.agent.z.decider)(&self.agent.z.model).is_none() {
+            return Diagnosis {decision: Decision::RequestModel, reason: Reason::Tie};
+        }
+        let Diagnosis {decision, reason} = self.agent.diagnose();
+        match decision {
+            Decision::Action(Some(a)) => Diagnosis {decision: Decision::Action(a), reason},
+            Decision::Action(None) => Diagnosis {decision: Decision::RequestModel, reason: Reason::Tie},
+            Decision::RequestModel => Diagnosis {decision: Decision::RequestModel, reason},
+            Decision::Halt => Diagnosis {decision: Decision::Halt, reason},
+        }
+    }
+}
+
+impl<M, A: PartialEq, D> Agent for Tied<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<A> {self.diagnose().decision}
+    fn act(&mut self, action: A) {self.agent.act(Some(action))}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M, A: PartialEq, D> Inspect for Tied<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AgentZ;
+
+    fn utility(m: &(u32, u32), a: &i32) -> f64 {-((m.1 as i32 + a) - m.0 as i32).abs() as f64}
+
+    static UTILITY: DeciderFromUtility<(u32, u32), i32> =
+        DeciderFromUtility::new(utility, |_| vec![2, 0, -2], TiePolicy::Request);
+
+    #[test]
+    fn tie() {
+        let first = DeciderFromUtility {tie: TiePolicy::First, ..UTILITY};
+        assert_eq!(first.choose(&(4, 3)), Some(2));
+        assert_eq!(DeciderFromUtility {tie: TiePolicy::Random(1), ..UTILITY}.choose(&(4, 3)), Some(0));
+        assert_eq!(UTILITY.decide(&(4, 3)), Decision::RequestModel);
+        assert_eq!(first.tolerance(2.0).choose(&(4, 0)), Some(2));
+        assert_eq!(UTILITY.tolerance(2.0).choose(&(4, 0)), None);
+        let infinite = DeciderFromUtility::new(|_: &(), a: &i32| if *a > 0 {f64::INFINITY} else {0.0},
+                                               |_| vec![0, 1, 2], TiePolicy::Request);
+        assert_eq!(infinite.choose(&()), None);
+        assert_eq!(DeciderFromUtility {tie: TiePolicy::First, ..infinite}.choose(&()), Some(1));
+
+        let z = AgentZ {
+            model: (4, 0),
+            decider: |m| UTILITY.choose(m),
+            actor: |m: &mut (u32, u32), a: Option<i32>| if let Some(a) = a {m.1 = (m.1 as i32 + a) as u32},
+            mutater: |m| {m.0 -= 1; -1},
+            undoer: |m, d| m.0 = (m.0 as i32 - d) as u32,
+        };
+        let mut s = Tied::new(z.add(1));
+        assert_eq!(s.decide(), Decision::Action(2));
+        s.act(2);
+        assert_eq!(s.model(), &(4, 2));
+        // The mutated goal `3` has a tie.
+        assert_eq!(s.diagnose().reason, Reason::Disagree {layer: 1, probe: 0});
+        s.update_model((4, 3));
+        assert_eq!(s.diagnose(), Diagnosis {decision: Decision::RequestModel, reason: Reason::Tie});
+    }
+}
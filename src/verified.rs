@@ -0,0 +1,143 @@
+//! Actions that are only executable after a passing decide.
+//!
+//! A `Guarded` agent returns a `VerifiedAction` from decide,
+//! which is tied to the agent and the current model generation.
+//! The generation increases on every model update and action.
+//!
+//! Since a `VerifiedAction` can only be constructed by a `Guarded` agent,
+//! it is impossible to feed arbitrary actions to `act`.
+//! Acting with a stale action, or an action of another agent, returns an error.
+//!
+//! A model update might arrive between decide and act.
+//! `Guarded::act_checked` decides again at the moment of actuation when the action is stale,
+//! and only acts when the agent still decides the same action on the updated model.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Agent, Decision, Error};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Stores an action that passed a decide.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerifiedAction<A> {
+    action: A,
+    agent: u64,
+    generation: u64,
+}
+
+impl<A> VerifiedAction<A> {
+    /// Returns the action.
+    pub fn action(&self) -> &A {&self.action}
+
+    /// Returns the model generation that the action was decided for.
+    pub fn generation(&self) -> u64 {self.generation}
+}
+
+/// Stores an agent that only performs verified actions.
+#[derive(Debug)]
+pub struct Guarded<T> {
+    agent: T,
+    id: u64,
+    generation: u64,
+}
+
+impl<T: Agent> Guarded<T> {
+    /// Creates a new guarded agent.
+    pub fn new(agent: T) -> Self {
+        Guarded {agent, id: NEXT_ID.fetch_add(1, Ordering::Relaxed), generation: 0}
+    }
+
+    /// Returns the inner agent.
+    pub fn agent(&self) -> &T {&self.agent}
+
+    /// Returns the current model generation.
+    pub fn generation(&self) -> u64 {self.generation}
+
+    /// Update internal model.
+    pub fn update_model(&mut self, model: T::Model) {
+        self.generation += 1;
+        self.agent.update_model(model);
+    }
+
+    /// Decide what to do next.
+    pub fn decide(&mut self) -> Decision<VerifiedAction<T::Action>> {
+        match self.agent.decide() {
+            Decision::Action(action) => Decision::Action(VerifiedAction {
+                action,
+                agent: self.id,
+                generation: self.generation,
+            }),
+            Decision::RequestModel => Decision::RequestModel,
+            Decision::Halt => Decision::Halt,
+        }
+    }
+
+    /// Perform a verified action on its internal model.
+    ///
+    /// Returns an error if the action is stale or belongs to another agent.
+    pub fn act(&mut self, action: VerifiedAction<T::Action>) -> Result<(), Error> {
+        if action.agent != self.id {
+            return Err(Error::Protocol("Action was verified by another agent".into()));
+        }
+        if action.generation != self.generation {
+            return Err(Error::StaleAction {
+                action: action.generation,
+                model: self.generation,
+            });
+        }
+        self.generation += 1;
+        self.agent.act(action.action);
+        Ok(())
+    }
+
+    /// Perform a verified action, deciding again when the action is stale.
+    ///
+    /// Returns an error if the action belongs to another agent,
+    /// or if the agent no longer decides the same action on the current model.
+    pub fn act_checked(&mut self, action: VerifiedAction<T::Action>) -> Result<(), Error>
+        where T::Action: PartialEq
+    {
+        if action.agent != self.id || action.generation == self.generation {return self.act(action)}
+        match self.decide() {
+            Decision::Action(b) if b.action == action.action => self.act(b),
+            _ => Err(Error::StaleAction {action: action.generation, model: self.generation}),
+        }
+    }
+
+    /// Returns the inner agent.
+    pub fn into_inner(self) -> T {self.agent}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_actions() {
+        let mut g = Guarded::new(crate::tests::four().add(1));
+        let a = match g.decide() {Decision::Action(a) => a, _ => panic!()};
+        let b = match g.decide() {Decision::Action(b) => b, _ => panic!()};
+        assert_eq!(g.act(a), Ok(()));
+        assert_eq!(g.act(b), Err(Error::StaleAction {action: 0, model: 1}));
+
+        let mut other = Guarded::new(crate::tests::four());
+        let c = match other.decide() {Decision::Action(c) => c, _ => panic!()};
+        assert!(g.act(c).is_err());
+        assert_eq!(g.agent().z.model, (4, 1));
+    }
+
+    #[test]
+    fn recheck() {
+        let mut g = Guarded::new(crate::tests::four().add(1));
+        let a = match g.decide() {Decision::Action(a) => a, _ => panic!()};
+        g.update_model((4, 1));
+        // The action is still decided after the model update.
+        assert_eq!(g.act_checked(a), Ok(()));
+        assert_eq!(g.generation(), 2);
+        let b = match g.decide() {Decision::Action(b) => b, _ => panic!()};
+        g.update_model((4, 3));
+        assert_eq!(g.act_checked(b), Err(Error::StaleAction {action: 2, model: 3}));
+        assert_eq!(g.agent().z.model, (4, 3));
+    }
+}
@@ -0,0 +1,133 @@
+//! Online-weighted voting across mutations.
+//!
+//! Unanimity treats every mutater as equally trustworthy,
+//! but some mutaters dissent for no good reason while others anticipate real corrections of the model.
+//! A `Weighted` agent lets the outermost safety layer probe every mutater once,
+//! and acts when the weight of the agreeing mutaters is at least a threshold fraction of the total weight.
+//!
+//! Weights are updated online on every model update after a decide call:
+//! When the new model differs from the internal model, the update is a correction,
+//! and the mutaters that dissented were right.
+//! Otherwise, the mutaters that agreed were right.
+//! The weights of mutaters that were right are multiplied by `1 + rate`,
+//! and the weights of the others by `1 - rate`.
+
+use crate::{Agent, AgentN, Decision, Inspect, ProbeOutcome, SafetyReport};
+
+/// Stores an agent that decides by a weighted vote of mutations.
+#[derive(Clone, Debug)]
+pub struct Weighted<M, A, D> {
+    /// The inner agent, where the outermost safety layer votes.
+    pub agent: AgentN<M, A, D>,
+    /// The weight of each mutater.
+    pub weights: Vec<f64>,
+    /// The fraction of the total weight that must agree for acting.
+    pub threshold: f64,
+    /// The rate of weight updates, between `0` and `1`.
+    pub rate: f64,
+    /// Whether each mutater dissented in the last decide call, if not yet scored.
+    pub dissent: Option<Vec<bool>>,
+}
+
+impl<M, A: PartialEq, D> Weighted<M, A, D> {
+    /// Creates a new agent with equal weights.
+    pub fn new(agent: AgentN<M, A, D>, threshold: f64, rate: f64) -> Self {
+        let n = agent.mutaters.len().max(1);
+        Weighted {agent, weights: vec![1.0; n], threshold, rate, dissent: None}
+    }
+
+    /// Returns the fraction of the total weight that agreed in the last decide call.
+    ///
+    /// Returns `None` when the last decide call did not vote.
+    pub fn support(&self) -> Option<f64> {
+        let dissent = self.dissent.as_ref()?;
+        let total: f64 = self.weights.iter().sum();
+        let agree: f64 = self.weights.iter().zip(dissent).filter(|(_, &d)| !d).map(|(w, _)| w).sum();
+        Some(if total > 0.0 {agree / total} else {0.0})
+    }
+
+    fn score(&mut self, corrected: bool) {
+        if let Some(dissent) = self.dissent.take() {
+            for (w, d) in self.weights.iter_mut().zip(dissent) {
+                *w *= if d == corrected {1.0 + self.rate} else {1.0 - self.rate};
+            }
+        }
+    }
+}
+
+impl<M: PartialEq, A: PartialEq, D> Agent for Weighted<M, A, D> {
+    type Model = M;
+    type Action = A;
+    type Delta = D;
+    fn update_model(&mut self, model: M) {
+        let corrected = self.agent.z.model != model;
+        self.score(corrected);
+        self.agent.update_model(model)
+    }
+    fn decide(&mut self) -> Decision<A> {
+        let n = self.agent.layers();
+        if n == 0 {return self.agent.decide()}
+        if let Some(diagnosis) = self.agent.decide_start() {return diagnosis.decision}
+        let a = match self.agent.z.decide() {
+            Decision::Action(a) => a,
+            decision => return decision,
+        };
+        let config = self.agent.layers[n - 1];
+        let mut tally = SafetyReport::default();
+        let mut dissent = vec![false; self.weights.len()];
+        for probe in 0..self.weights.len() {
+            let delta = self.agent.mutate_probe(probe as u8);
+            let b = self.agent.decide_n(n - 1, n, &mut tally).0;
+            self.agent.undo(delta);
+            let outcome = match b {
+                Decision::Action(b) if config.agree(&a, &b) => ProbeOutcome::Agree,
+                Decision::Action(_) => ProbeOutcome::Disagree,
+                Decision::RequestModel | Decision::Halt => ProbeOutcome::RequestModel,
+            };
+            tally.probes += 1;
+            match outcome {
+                ProbeOutcome::Agree => tally.approvals += 1,
+                ProbeOutcome::Disagree => tally.disagreements += 1,
+                ProbeOutcome::RequestModel => tally.requests += 1,
+            }
+            dissent[self.agent.mutater_of(probe as u8)] = outcome != ProbeOutcome::Agree;
+        }
+        self.agent.report = tally;
+        self.dissent = Some(dissent);
+        // Acting with too little support is less safe than requesting a model update.
+        match self.support() {
+            Some(support) if support >= self.threshold => Decision::Action(a),
+            _ => Decision::RequestModel,
+        }
+    }
+    fn act(&mut self, action: A) {self.agent.act(action)}
+    fn mutate(&mut self) -> D {self.agent.mutate()}
+    fn undo(&mut self, delta: D) {self.agent.undo(delta)}
+}
+
+impl<M: PartialEq, A: PartialEq, D> Inspect for Weighted<M, A, D> {
+    fn model(&self) -> &M {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vote() {
+        let mut s = crate::tests::four().add(1);
+        s.mutaters = vec![|m| {m.0 += 1; 1}, |m| {m.0 -= 1; -1}];
+        s.z.model = (4, 3);
+        let mut s = Weighted::new(s, 0.6, 0.5);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.support(), Some(0.5));
+        // The model was right, so lowering the goal dissented for no reason.
+        s.update_model((4, 3));
+        assert_eq!(s.weights, vec![1.5, 0.5]);
+        assert_eq!(s.decide(), Decision::Action(1));
+        // The goal was corrected, so lowering the goal was right to dissent.
+        s.update_model((3, 3));
+        assert_eq!(s.weights, vec![0.75, 0.75]);
+        assert_eq!(s.dissent, None);
+    }
+}
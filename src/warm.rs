@@ -0,0 +1,180 @@
+//! Warm-start probing from previous decide calls.
+//!
+//! Consecutive decide calls often see nearly identical models.
+//! When `AgentN::warm` is set, probing reuses what was learned in previous calls:
+//!
+//! - The mutater that most recently caused a disagreement is probed first,
+//!   swapping places with the mutater that would be probed first otherwise.
+//! - The outcome of a probe is reused when the model before and after the mutation
+//!   have the same fingerprints as a probe of a previous call in the same safety layer.
+//!
+//! The fingerprint is computed by a user-supplied function,
+//! which should cover every part of the model that deciders read, such that unchanged
+//! sub-models give the same outcomes. Reused outcomes are cleared when the fingerprint
+//! of the internal model changes, and stochastic mutaters should not be used with reuse.
+//!
+//! While a rationale is recorded, e.g. for a certificate, outcomes are not reused
+//! and the order of mutaters is not changed, such that every recorded check ran in that call.
+
+use std::fmt;
+use std::ptr::fn_addr_eq;
+
+use crate::{AgentN, ProbeOutcome};
+
+/// Stores the state of warm-start probing.
+pub struct WarmStart<M> {
+    /// Returns the fingerprint of the parts of a model that deciders read.
+    pub fingerprint: fn(&M) -> u64,
+    /// The number of probes whose outcome was reused.
+    pub reused: usize,
+    /// The mutater probed first in the current decide call.
+    pub(crate) first: Option<usize>,
+    /// The mutater that most recently caused a disagreement.
+    pub(crate) disagreed: Option<usize>,
+    /// The fingerprint of the internal model when outcomes were cached.
+    pub(crate) top: Option<u64>,
+    /// The outcomes by layer and fingerprints of the model before and after mutation.
+    pub(crate) outcomes: Vec<((usize, u64, u64), ProbeOutcome)>,
+}
+
+impl<M> Clone for WarmStart<M> {
+    fn clone(&self) -> Self {
+        WarmStart {
+            fingerprint: self.fingerprint,
+            reused: self.reused,
+            first: self.first,
+            disagreed: self.disagreed,
+            top: self.top,
+            outcomes: self.outcomes.clone(),
+        }
+    }
+}
+
+impl<M> fmt::Debug for WarmStart<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WarmStart")
+            .field("fingerprint", &self.fingerprint)
+            .field("reused", &self.reused)
+            .field("disagreed", &self.disagreed)
+            .finish()
+    }
+}
+
+impl<M> PartialEq for WarmStart<M> {
+    fn eq(&self, other: &Self) -> bool {fn_addr_eq(self.fingerprint, other.fingerprint)}
+}
+
+impl<M> WarmStart<M> {
+    /// Creates a new warm start without history.
+    pub fn new(fingerprint: fn(&M) -> u64) -> Self {
+        WarmStart {fingerprint, reused: 0, first: None, disagreed: None, top: None, outcomes: vec![]}
+    }
+
+    /// Returns the mutater that most recently caused a disagreement.
+    pub fn disagreed(&self) -> Option<usize> {self.disagreed}
+}
+
+impl<M, A, D> AgentN<M, A, D> {
+    /// Prepares warm-start probing for a decide call.
+    pub(crate) fn warm_up(&mut self) {
+        let recording = self.rationale.is_some();
+        if let Some(w) = &mut self.warm {
+            w.first = if recording {None} else {w.disagreed};
+            let top = (w.fingerprint)(&self.z.model);
+            if w.top != Some(top) {
+                w.top = Some(top);
+                w.outcomes.clear();
+            }
+        }
+    }
+
+    /// Returns the fingerprint of the internal model, when warm-starting.
+    pub(crate) fn warm_fingerprint(&self) -> Option<u64> {
+        self.warm.as_ref().map(|w| (w.fingerprint)(&self.z.model))
+    }
+
+    /// Returns the mutater of a probe, after moving a recent disagreement first.
+    pub(crate) fn warm_order(&self, probe: usize, mutater: usize, first: usize) -> usize {
+        match self.warm.as_ref().and_then(|w| w.first).filter(|&f| f < self.mutaters.len()) {
+            Some(f) if probe == 0 => f,
+            Some(f) if mutater == f => first,
+            _ => mutater,
+        }
+    }
+
+    /// Returns a reused outcome of a probe in a layer, counting the reuse.
+    pub(crate) fn warm_outcome(&mut self, key: (usize, u64, u64)) -> Option<ProbeOutcome> {
+        if self.rationale.is_some() {return None}
+        let w = self.warm.as_mut()?;
+        let outcome = w.outcomes.iter().find(|(k, _)| *k == key).map(|(_, outcome)| *outcome)?;
+        w.reused += 1;
+        Some(outcome)
+    }
+
+    /// Remembers the outcome of a probe with the mutater that was used.
+    pub(crate) fn warm_record(&mut self, key: Option<(usize, u64, u64)>, mutater: usize, outcome: ProbeOutcome) {
+        let has_mutaters = !self.mutaters.is_empty();
+        if let Some(w) = &mut self.warm {
+            if outcome == ProbeOutcome::Disagree && has_mutaters {w.disagreed = Some(mutater)}
+            if let Some(key) = key {
+                if !w.outcomes.iter().any(|(k, _)| *k == key) {w.outcomes.push((key, outcome))}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Agent, Decision};
+
+    #[test]
+    fn warm() {
+        let mut s = crate::tests::four().add(1);
+        // The first mutater does nothing, the second mutates the goal.
+        s.mutaters = vec![|_| 0, crate::tests::four().mutater];
+        s.layers[0].agreement = crate::Agreement::All;
+        s.layers[0].mutation_limit = 2;
+        s.warm = Some(WarmStart::new(|m: &(u32, u32)| (m.0 as u64) << 32 | m.1 as u64));
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.warm.as_ref().unwrap().disagreed(), Some(1));
+        // The disagreeing mutater is probed first.
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!((s.mutater_of(0), s.mutater_of(1)), (1, 0));
+        assert_eq!((s.report.probes, s.warm.as_ref().unwrap().reused), (1, 1));
+        s.update_model((4, 0));
+        assert_eq!(s.decide(), Decision::Action(1));
+        assert_eq!(s.warm.as_ref().unwrap().reused, 1);
+    }
+
+    #[test]
+    fn rationale() {
+        let mut s = crate::tests::four().add(2);
+        s.warm = Some(WarmStart::new(|m: &(u32, u32)| (m.0 as u64) << 32 | m.1 as u64));
+        s.decide();
+        s.decide();
+        assert!(s.warm.as_ref().unwrap().reused > 0);
+        // Recorded checks are re-run, like in a cold agent.
+        assert_eq!(s.decide_rationale(), crate::tests::four().add(2).decide_rationale());
+    }
+
+    #[test]
+    fn successor() {
+        let mut core = crate::tests::four().add(0);
+        core.mutaters = vec![|_| 0, crate::tests::four().mutater];
+        core.warm = Some(WarmStart::new(|m: &(u32, u32)| (m.0 as u64) << 32 | m.1 as u64));
+        let config = crate::LayerConfig {
+            agreement: crate::Agreement::All,
+            mutation_limit: 2,
+            ..crate::LayerConfig::default()
+        };
+        let mut s = crate::AgentS {core, config};
+        s.update_model((4, 3));
+        assert_eq!(s.decide(), Decision::RequestModel);
+        // A successor layer also probes the disagreeing mutater first.
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.core.mutater_of(0), 1);
+        assert_eq!(s.core.report.probes, 1);
+    }
+}
@@ -0,0 +1,115 @@
+//! Escalation after persistent indecision.
+//!
+//! An agent that keeps requesting model updates might be stuck,
+//! e.g. because the environment can not tell it what it needs to know.
+//! A `Watchdog` invokes an escalation callback when the agent has been requesting
+//! model updates for longer than a number of steps or a duration.
+//!
+//! The callback returns the decision to use instead,
+//! such that it can page a human and keep requesting, switch to a fallback action, or halt.
+//! Since a `Watchdog` is an agent, it works with the runners in `environment` and `runtime`.
+
+use std::time::{Duration, Instant};
+
+use crate::{Agent, Decision, Inspect};
+
+/// Stores how long an agent may request model updates before escalating.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Patience {
+    /// The number of consecutive model requests.
+    Steps(usize),
+    /// The time since the first of consecutive model requests.
+    Duration(Duration),
+}
+
+/// Stores an agent that escalates persistent indecision.
+#[derive(Clone, Debug)]
+pub struct Watchdog<T: Agent> {
+    /// The inner agent.
+    pub agent: T,
+    /// How long the agent may request model updates before escalating.
+    pub patience: Patience,
+    /// Returns the decision to use instead of requesting a model update.
+    pub escalate: fn(&mut T) -> Decision<T::Action>,
+    /// The number of consecutive model requests.
+    pub requests: usize,
+    /// The time of the first of consecutive model requests.
+    pub since: Option<Instant>,
+    /// The number of escalations.
+    pub escalations: usize,
+}
+
+impl<T: Agent> Watchdog<T> {
+    /// Creates a new watchdog.
+    pub fn new(agent: T, patience: Patience, escalate: fn(&mut T) -> Decision<T::Action>) -> Self {
+        Watchdog {agent, patience, escalate, requests: 0, since: None, escalations: 0}
+    }
+
+    /// Returns `true` if the agent has been requesting model updates for too long.
+    pub fn is_overdue(&self) -> bool {
+        match (self.patience, self.since) {
+            (Patience::Steps(n), _) => self.requests >= n,
+            (Patience::Duration(d), Some(since)) => since.elapsed() >= d,
+            (Patience::Duration(_), None) => false,
+        }
+    }
+}
+
+impl<T: Agent> Agent for Watchdog<T> {
+    type Model = T::Model;
+    type Action = T::Action;
+    type Delta = T::Delta;
+    fn update_model(&mut self, model: T::Model) {self.agent.update_model(model)}
+    fn decide(&mut self) -> Decision<T::Action> {
+        match self.agent.decide() {
+            Decision::RequestModel if self.is_overdue() => {
+                self.escalations += 1;
+                (self.escalate)(&mut self.agent)
+            }
+            Decision::RequestModel => {
+                self.requests += 1;
+                if self.since.is_none() {self.since = Some(Instant::now())}
+                Decision::RequestModel
+            }
+            x => {
+                self.requests = 0;
+                self.since = None;
+                x
+            }
+        }
+    }
+    fn act(&mut self, action: T::Action) {self.agent.act(action)}
+    fn mutate(&mut self) -> T::Delta {self.agent.mutate()}
+    fn undo(&mut self, delta: T::Delta) {self.agent.undo(delta)}
+}
+
+impl<T: Inspect> Inspect for Watchdog<T> {
+    fn model(&self) -> &T::Model {self.agent.model()}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::{run_until, tests::Three};
+
+    #[test]
+    fn escalate() {
+        // At the true goal, a mutated goal always disagrees.
+        let mut agent = crate::tests::four().add(1);
+        agent.update_model((3, 3));
+        let mut s = Watchdog::new(agent, Patience::Steps(3), |_| Decision::Halt);
+        let report = run_until(&mut s, &mut Three(3), |_| false, 10);
+        assert!(report.halted);
+        assert_eq!((report.steps, report.requests), (4, 3));
+        assert_eq!(s.escalations, 1);
+
+        // Paging a human keeps requesting.
+        let mut s = Watchdog::new(s.agent, Patience::Duration(Duration::from_secs(3600)), |_| Decision::Halt);
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert!(s.since.is_some() && !s.is_overdue());
+        s.patience = Patience::Duration(Duration::from_secs(0));
+        s.escalate = |_| Decision::RequestModel;
+        assert_eq!(s.decide(), Decision::RequestModel);
+        assert_eq!(s.escalations, 1);
+    }
+}
@@ -0,0 +1,242 @@
+//! Versioned wire protocol for agent/environment messages.
+//!
+//! Processes implementing the environment and the agent exchange `Message`s as JSON objects.
+//! Every message has a `version` and a `type`:
+//!
+//! ```text
+//! {"version":1,"type":"observation","model":"..."}
+//! {"version":1,"type":"model_update","model":"...","generation":"3"}
+//! {"version":1,"type":"decision","decision":"action","action":"..."}
+//! {"version":1,"type":"request_info","reason":"...","target":"goal"}
+//! ```
+//!
+//! Models and actions are strings, encoded by the processes, e.g. as nested JSON.
+//! Generations are decimal strings, since JSON numbers are rounded above 2^53.
+//! Generations sent as numbers by older processes are accepted when they are exact.
+//!
+//! Messages of older or equal versions are accepted,
+//! and unknown fields are ignored, such that new fields can be added without a new version.
+//! Messages of newer versions are rejected.
+//! Messages are flat objects, so received messages nested deeper than `MAX_DEPTH` are rejected as well.
+//!
+//! With the `cbor` feature, messages can also be exchanged in a compact binary encoding,
+//! using `Message::to_cbor` and `Message::from_cbor`, with the same fields as in JSON.
+
+use crate::query::ModelQuery;
+use crate::json::{self, Value};
+use crate::{Decision, Error};
+
+/// The version of the protocol.
+pub const VERSION: u32 = 1;
+
+/// The maximum nesting depth of received messages.
+pub const MAX_DEPTH: usize = 64;
+
+/// Stores a decision message.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DecisionMsg {
+    /// Perform an encoded action.
+    Action(String),
+    /// Request a model update.
+    RequestModel,
+    /// Halt.
+    Halt,
+}
+
+impl DecisionMsg {
+    /// Creates a decision message by encoding the action of a decision.
+    pub fn new<A>(decision: &Decision<A>, encode: fn(&A) -> String) -> DecisionMsg {
+        match decision {
+            Decision::Action(a) => DecisionMsg::Action(encode(a)),
+            Decision::RequestModel => DecisionMsg::RequestModel,
+            Decision::Halt => DecisionMsg::Halt,
+        }
+    }
+
+    /// Returns the decision by decoding the action, if valid.
+    pub fn decision<A>(&self, decode: fn(&str) -> Option<A>) -> Option<Decision<A>> {
+        match self {
+            DecisionMsg::Action(a) => decode(a).map(Decision::Action),
+            DecisionMsg::RequestModel => Some(Decision::RequestModel),
+            DecisionMsg::Halt => Some(Decision::Halt),
+        }
+    }
+}
+
+/// Stores a message between agent and environment.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Message {
+    /// The environment sends an observation of the encoded model.
+    Observation {
+        /// The encoded model.
+        model: String,
+    },
+    /// The environment answers a model request.
+    ModelUpdate {
+        /// The encoded model.
+        model: String,
+        /// The generation of the model, increasing with every update.
+        generation: u64,
+    },
+    /// The agent sends a decision.
+    Decision(DecisionMsg),
+    /// The agent tells why it requests a model update.
+    RequestInfo {
+        /// A description of the reason.
+        reason: String,
+        /// The part of the model the agent is uncertain about, if known.
+        target: Option<String>,
+    },
+}
+
+impl From<&ModelQuery> for Message {
+    fn from(query: &ModelQuery) -> Message {
+        Message::RequestInfo {reason: query.to_string(), target: query.target.map(String::from)}
+    }
+}
+
+impl Message {
+    /// Returns the message as JSON.
+    pub fn to_json(&self) -> String {json::write(&self.to_value())}
+
+    /// Returns the message as CBOR.
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Vec<u8> {crate::cbor::from_value(&self.to_value())}
+
+    fn to_value(&self) -> Value {
+        let s = |x: &str| Value::Str(x.into());
+        let mut fields = vec![("version".into(), Value::Num(VERSION as f64))];
+        let mut field = |key: &str, value: Value| fields.push((key.into(), value));
+        match self {
+            Message::Observation {model} => {
+                field("type", s("observation"));
+                field("model", s(model));
+            }
+            Message::ModelUpdate {model, generation} => {
+                field("type", s("model_update"));
+                field("model", s(model));
+                field("generation", s(&generation.to_string()));
+            }
+            Message::Decision(decision) => {
+                field("type", s("decision"));
+                match decision {
+                    DecisionMsg::Action(a) => {
+                        field("decision", s("action"));
+                        field("action", s(a));
+                    }
+                    DecisionMsg::RequestModel => field("decision", s("request_model")),
+                    DecisionMsg::Halt => field("decision", s("halt")),
+                }
+            }
+            Message::RequestInfo {reason, target} => {
+                field("type", s("request_info"));
+                field("reason", s(reason));
+                field("target", target.as_deref().map(s).unwrap_or(Value::Null));
+            }
+        }
+        Value::Obj(fields)
+    }
+
+    /// Parses a message from CBOR.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(src: &[u8]) -> Result<Message, Error> {
+        Message::from_json(&crate::cbor::to_json_limited(src, MAX_DEPTH).ok_or_else(|| Error::Protocol("Expected CBOR".into()))?)
+    }
+
+    /// Parses a message from JSON.
+    pub fn from_json(src: &str) -> Result<Message, Error> {
+        let err = |msg: &str| Error::Protocol(msg.into());
+        let value = json::parse_limited(src, MAX_DEPTH).ok_or_else(|| err("Expected JSON"))?;
+        let version = value.get("version").and_then(|v| v.num()).ok_or_else(|| err("Expected version"))?;
+        if version > VERSION as f64 {
+            return Err(Error::Protocol(format!("Unsupported protocol version {}", version)));
+        }
+        let field = |key: &str| value.get(key).and_then(|v| v.str()).map(String::from)
+            .ok_or_else(|| Error::Protocol(format!("Expected {}", key)));
+        match field("type")?.as_str() {
+            "observation" => Ok(Message::Observation {model: field("model")?}),
+            "model_update" => {
+                let generation = value.get("generation").and_then(|v| match v {
+                    Value::Str(g) => g.parse().ok(),
+                    Value::Num(g) if *g >= 0.0 && g.fract() == 0.0 && *g <= (1u64 << 53) as f64 => Some(*g as u64),
+                    _ => None,
+                }).ok_or_else(|| err("Expected generation"))?;
+                Ok(Message::ModelUpdate {model: field("model")?, generation})
+            }
+            "decision" => match field("decision")?.as_str() {
+                "action" => Ok(Message::Decision(DecisionMsg::Action(field("action")?))),
+                "request_model" => Ok(Message::Decision(DecisionMsg::RequestModel)),
+                "halt" => Ok(Message::Decision(DecisionMsg::Halt)),
+                x => Err(Error::Protocol(format!("Unknown decision `{}`", x))),
+            },
+            "request_info" => Ok(Message::RequestInfo {
+                reason: field("reason")?,
+                target: value.get("target").and_then(|v| v.str()).map(String::from),
+            }),
+            x => Err(Error::Protocol(format!("Unknown message type `{}`", x))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Messages of version 1, which all later versions must accept.
+    const V1: &[&str] = &[
+        r#"{"version":1,"type":"observation","model":"[4,0]"}"#,
+        r#"{"version":1,"type":"model_update","model":"[3,3]","generation":"2"}"#,
+        r#"{"version":1,"type":"decision","decision":"action","action":"1"}"#,
+        r#"{"version":1,"type":"decision","decision":"request_model"}"#,
+        r#"{"version":1,"type":"decision","decision":"halt"}"#,
+        r#"{"version":1,"type":"request_info","reason":"uncertain about goal","target":"goal"}"#,
+        r#"{"version":1,"type":"request_info","reason":"uncertain about mutater 0","target":null}"#,
+    ];
+
+    #[test]
+    fn compatibility() {
+        for src in V1 {
+            let msg = Message::from_json(src).unwrap();
+            assert_eq!(&msg.to_json(), src);
+        }
+        // Unknown fields are ignored.
+        let src = r#"{"version":1,"type":"observation","model":"x","sent":17}"#;
+        assert_eq!(Message::from_json(src), Ok(Message::Observation {model: "x".into()}));
+        let src = r#"{"version":2,"type":"observation","model":"x"}"#;
+        assert_eq!(Message::from_json(src), Err(Error::Protocol("Unsupported protocol version 2".into())));
+        assert!(Message::from_json(r#"{"version":1,"type":"model_update","model":"x"}"#).is_err());
+        let update = |generation| Message::ModelUpdate {model: "x".into(), generation};
+        assert_eq!(Message::from_json(r#"{"version":1,"type":"model_update","model":"x","generation":2}"#),
+                   Ok(update(2)));
+        assert!(Message::from_json(r#"{"version":1,"type":"model_update","model":"x","generation":1e300}"#).is_err());
+        let src = update(u64::MAX).to_json();
+        assert_eq!(Message::from_json(&src), Ok(update(u64::MAX)));
+        assert!(Message::from_json(&"[".repeat(1_000_000)).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor() {
+        for src in V1 {
+            let msg = Message::from_json(src).unwrap();
+            let bin = msg.to_cbor();
+            assert!(bin.len() < src.len());
+            assert_eq!(Message::from_cbor(&bin), Ok(msg));
+        }
+        assert!(Message::from_cbor(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn decisions() {
+        let msg = DecisionMsg::new(&Decision::Action(-1), |a| a.to_string());
+        assert_eq!(msg, DecisionMsg::Action("-1".into()));
+        assert_eq!(msg.decision(|s| s.parse::<i32>().ok()), Some(Decision::Action(-1)));
+
+        let mut s = crate::tests::four().add(1);
+        s.z.model = (4, 3);
+        let reason = s.diagnose().reason;
+        let query = s.query(reason).unwrap();
+        assert_eq!(Message::from(&query).to_json(),
+                   r#"{"version":1,"type":"request_info","reason":"uncertain about mutater 0 (mutation #0 of layer 1 disagreed)","target":null}"#);
+    }
+}
@@ -0,0 +1,100 @@
+//! Deciding with shared access to an agent.
+//!
+//! Probing mutations requires mutable access to a model,
+//! but not to the agent that owns it.
+//! `AgentN::decide_ref` decides using shared access to the agent,
+//! probing mutations on a copy of its model held by a separate `Workspace`.
+//! Each thread monitoring an agent or evaluating it speculatively uses its own workspace.
+//!
+//! The state of stochastic mutaters and coverage is held by the workspace,
+//! such that a workspace samples the same sequence of mutations as an agent would.
+//! Every `AgentN` is also a `Planner`, deciding with a new workspace on every call.
+
+use crate::{AgentN, Decision, SafetyReport};
+use crate::planner::Planner;
+
+/// Stores the mutation workspace used for deciding with shared access to an agent.
+#[derive(Clone, Debug)]
+pub struct Workspace<M, A, D> {
+    agent: Option<AgentN<M, A, D>>,
+}
+
+impl<M, A, D> Default for Workspace<M, A, D> {
+    fn default() -> Self {Workspace {agent: None}}
+}
+
+impl<M, A, D> Workspace<M, A, D> {
+    /// Creates a new empty workspace.
+    pub fn new() -> Self {Workspace::default()}
+
+    /// Returns the safety report of the last decide call in this workspace.
+    pub fn report(&self) -> SafetyReport {
+        self.agent.as_ref().map(|agent| agent.report).unwrap_or_default()
+    }
+}
+
+impl<M: Clone, A: PartialEq, D> AgentN<M, A, D> {
+    /// Decide what to do next using shared access,
+    /// probing mutations in a workspace.
+    ///
+    /// The agent is not changed, so its safety report is not updated.
+    pub fn decide_ref(&self, workspace: &mut Workspace<M, A, D>) -> Decision<A> {
+        self.decide_in(&self.z.model, workspace)
+    }
+
+    /// Decide what to do next in some model using shared access,
+    /// probing mutations in a workspace.
+    pub fn decide_in(&self, model: &M, workspace: &mut Workspace<M, A, D>) -> Decision<A> {
+        use crate::Agent;
+
+        let agent = match &mut workspace.agent {
+            Some(agent) => {
+                agent.z.decider = self.z.decider;
+                agent.z.actor = self.z.actor;
+                agent.z.mutater = self.z.mutater;
+                agent.z.undoer = self.z.undoer;
+                agent.layers.clone_from(&self.layers);
+                agent.mutaters.clone_from(&self.mutaters);
+                agent.targets.clone_from(&self.targets);
+                agent.observers.clone_from(&self.observers);
+                agent.incremental = self.incremental;
+                agent.dedup = self.dedup;
+                agent.describe = self.describe;
+                agent.voi = self.voi;
+                agent.exhausted = self.exhausted;
+                agent.handoff = self.handoff;
+                agent.latency = self.latency;
+                if agent.stochastic.is_none() {agent.stochastic.clone_from(&self.stochastic)}
+                if agent.coverage.is_none() {agent.coverage.clone_from(&self.coverage)}
+                if agent.warm.is_none() {agent.warm.clone_from(&self.warm)}
+                if agent.provenance.is_none() {agent.provenance.clone_from(&self.provenance)}
+                agent
+            }
+            None => workspace.agent.get_or_insert_with(|| self.clone()),
+        };
+        agent.z.model.clone_from(model);
+        agent.decide()
+    }
+}
+
+impl<M: Clone, A: PartialEq, D> Planner for AgentN<M, A, D> {
+    type Model = M;
+    type Action = A;
+    fn plan(&self, model: &M) -> Decision<A> {self.decide_in(model, &mut Workspace::new())}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared() {
+        let s = crate::tests::four().add(1);
+        let mut w = Workspace::new();
+        assert_eq!(s.decide_ref(&mut w), Decision::Action(1));
+        assert_eq!((w.report().probes, s.report.probes), (1, 0));
+        assert_eq!(s.decide_in(&(4, 3), &mut w), Decision::RequestModel);
+        assert_eq!(s.plan(&(4, 1)), Decision::Action(1));
+        assert_eq!(s.z.model, (4, 0));
+    }
+}